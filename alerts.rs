@@ -0,0 +1,902 @@
+pub mod added;
+mod expr;
+pub mod outage;
+pub mod signal;
+pub mod stale;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::Deserialize;
+
+use self::added::AddedRule;
+use self::expr::Expression;
+use self::outage::Outages;
+use self::signal::Signal;
+use self::stale::{Change, Staleness};
+use crate::analytics::Indicator;
+use crate::config::{parse_duration, Config};
+use crate::http::Client;
+use crate::notify::{self, ConsoleNotifier, Notifier};
+use crate::storage::{AlertEvent, PriceRecord, Storage, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::writer::Health;
+use crate::{PriceError, Pricing};
+
+// Rule names used in alerts about the sources themselves.
+const SOURCE_DOWN: &str = "source_down";
+const SOURCE_RECOVERED: &str = "source_recovered";
+const SOURCE_STALE: &str = "source_stale";
+const SOURCE_FRESH: &str = "source_fresh";
+// And about the sink prices are written to.
+const SINK_FAILING: &str = "sink_failing";
+const SINK_RECOVERED: &str = "sink_recovered";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+    pub asset: String,
+    #[serde(flatten)]
+    pub condition: Condition,
+    // Notifier names; empty means every configured notifier.
+    #[serde(default)]
+    pub notify: Vec<String>,
+    // Minimum time between two notifications from this rule, e.g. "30m".
+    pub cooldown: Option<String>,
+    #[serde(default)]
+    pub repeat: Repeat,
+    // After firing, the value has to move this far back past the limit
+    // before the rule re-arms. Only for threshold and indicator rules.
+    pub hysteresis: Option<f64>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    Threshold {
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    // Fires when the price swings by at least `percent` inside `within`.
+    Move {
+        percent: f64,
+        within: String,
+        #[serde(default)]
+        direction: Direction,
+    },
+    // Compares a computed indicator such as "rsi(14)" against limits.
+    Indicator {
+        indicator: String,
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    // Fires when the price falls `percent` below the highest price seen since
+    // the rule was armed (direction = "down"), or rises that far above the
+    // lowest (direction = "up").
+    TrailingStop {
+        percent: f64,
+        #[serde(default = "default_trailing_direction")]
+        direction: Direction,
+    },
+    // Free-form condition in the alert expression language, see expr.rs.
+    Expression {
+        when: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Any,
+    Up,
+    Down,
+}
+
+fn default_trailing_direction() -> Direction {
+    Direction::Down
+}
+
+// `Once` fires when the condition starts to hold and re-arms after it
+// clears; `Always` fires on every matching record, limited by the cooldown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    #[default]
+    Once,
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: String,
+    pub asset: String,
+    pub value: f64,
+    pub message: String,
+    pub severity: Severity,
+    pub timestamp: NaiveDateTime,
+}
+
+struct Rule {
+    config: RuleConfig,
+    window: Duration,
+    indicator: Option<Indicator>,
+    expression: Option<Expression>,
+    notifiers: Vec<usize>,
+    cooldown: Duration,
+    armed: bool,
+    last_fired: Option<NaiveDateTime>,
+    // Lowest and highest price since a trailing stop was armed.
+    trail: Option<(f64, f64)>,
+}
+
+impl Rule {
+    fn new(config: &RuleConfig, notifiers: &[Box<dyn Notifier>]) -> Result<Self, PriceError> {
+        let invalid = |reason: String| PriceError::ParseError(format!("alert '{}': {}", config.name, reason));
+
+        let mut indicator = None;
+        let mut expression = None;
+        let window = match &config.condition {
+            Condition::Threshold { above: None, below: None }
+            | Condition::Indicator { above: None, below: None, .. } => {
+                return Err(invalid("needs an 'above' or 'below' limit".to_string()))
+            }
+            Condition::Threshold { .. } => Duration::zero(),
+            Condition::Move { percent, within, .. } => {
+                if *percent <= 0.0 {
+                    return Err(invalid(format!("percent must be positive, got {}", percent)));
+                }
+                parse_duration(within).map_err(|e| invalid(e.message().to_string()))?
+            }
+            Condition::Indicator { indicator: spec, .. } => {
+                indicator = Some(spec.parse::<Indicator>().map_err(invalid)?);
+                Duration::zero()
+            }
+            Condition::TrailingStop { percent, .. } => {
+                if *percent <= 0.0 {
+                    return Err(invalid(format!("percent must be positive, got {}", percent)));
+                }
+                Duration::zero()
+            }
+            Condition::Expression { when } => {
+                let parsed = Expression::parse(when).map_err(|e| invalid(format!("in '{}': {}", when, e)))?;
+                let window = parsed.window;
+                expression = Some(parsed);
+                window
+            }
+        };
+
+        let cooldown = match &config.cooldown {
+            Some(cooldown) => parse_duration(cooldown).map_err(|e| invalid(e.message().to_string()))?,
+            None => Duration::zero(),
+        };
+        match (config.hysteresis, &config.condition) {
+            (Some(band), _) if band < 0.0 => {
+                return Err(invalid(format!("hysteresis must not be negative, got {}", band)))
+            }
+            (Some(_), Condition::Move { .. } | Condition::TrailingStop { .. } | Condition::Expression { .. }) => {
+                return Err(invalid("hysteresis only applies to threshold and indicator rules".to_string()))
+            }
+            _ => {}
+        }
+
+        let notifiers = resolve(&config.notify, notifiers).map_err(invalid)?;
+
+        Ok(Rule {
+            config: config.clone(),
+            window,
+            indicator,
+            expression,
+            notifiers,
+            cooldown,
+            armed: true,
+            last_fired: None,
+            trail: None,
+        })
+    }
+
+    fn records_needed(&self) -> usize {
+        let indicator = self.indicator.map(|i| i.records_needed()).unwrap_or(0);
+        let expression = self.expression.as_ref().map(|e| e.records).unwrap_or(0);
+        indicator.max(expression)
+    }
+
+    // `history` holds the asset's recent records, oldest first, ending with
+    // `record` itself. Returns the value that tripped the rule and a message.
+    fn check(&self, name: &str, record: &PriceRecord, history: &VecDeque<PriceRecord>) -> Option<(f64, String)> {
+        match &self.config.condition {
+            Condition::Threshold { above, below } => {
+                if let Some(limit) = above.filter(|limit| record.price > *limit) {
+                    return Some((record.price, format!("{} is above {:.2} at {:.2}", name, limit, record.price)));
+                }
+                if let Some(limit) = below.filter(|limit| record.price < *limit) {
+                    return Some((record.price, format!("{} is below {:.2} at {:.2}", name, limit, record.price)));
+                }
+                None
+            }
+            Condition::Move { percent, within, direction } => {
+                let start = record.timestamp.checked_sub_signed(self.window).unwrap_or(NaiveDateTime::MIN);
+                let window = history.iter().filter(|r| r.timestamp >= start);
+                let (low, high) = window.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), r| {
+                    (low.min(r.price), high.max(r.price))
+                });
+
+                let rise = (record.price - low) / low * 100.0;
+                if *direction != Direction::Down && low > 0.0 && rise >= *percent {
+                    return Some((
+                        rise,
+                        format!("{} rose {:.2}% within {} ({:.2} -> {:.2})", name, rise, within, low, record.price),
+                    ));
+                }
+                let fall = (high - record.price) / high * 100.0;
+                if *direction != Direction::Up && high > 0.0 && fall >= *percent {
+                    return Some((
+                        -fall,
+                        format!("{} fell {:.2}% within {} ({:.2} -> {:.2})", name, fall, within, high, record.price),
+                    ));
+                }
+                None
+            }
+            Condition::Indicator { above, below, .. } => {
+                let indicator = self.indicator?;
+                let value = indicator.compute(&recent(history, indicator.records_needed()))?;
+                if let Some(limit) = above.filter(|limit| value > *limit) {
+                    return Some((value, format!("{} {} is {:.2}, above {:.2}", name, indicator, value, limit)));
+                }
+                if let Some(limit) = below.filter(|limit| value < *limit) {
+                    return Some((value, format!("{} {} is {:.2}, below {:.2}", name, indicator, value, limit)));
+                }
+                None
+            }
+            Condition::TrailingStop { percent, direction } => {
+                let (low, high) = self.trail?;
+                let fall = (high - record.price) / high * 100.0;
+                if *direction != Direction::Up && high > 0.0 && fall >= *percent {
+                    return Some((
+                        -fall,
+                        format!("{} fell {:.2}% from its high of {:.2} to {:.2}", name, fall, high, record.price),
+                    ));
+                }
+                let rise = (record.price - low) / low * 100.0;
+                if *direction != Direction::Down && low > 0.0 && rise >= *percent {
+                    return Some((
+                        rise,
+                        format!("{} rose {:.2}% from its low of {:.2} to {:.2}", name, rise, low, record.price),
+                    ));
+                }
+                None
+            }
+            Condition::Expression { when } => {
+                let expression = self.expression.as_ref()?;
+                expression
+                    .matches(record, history)?
+                    .then(|| (record.price, format!("{} matched '{}' at {:.2}", name, when, record.price)))
+            }
+        }
+    }
+
+    // Whether a fired rule may re-arm: the condition no longer holds and, for
+    // rules with limits, the value is back past them by the hysteresis band.
+    fn cleared(&self, name: &str, record: &PriceRecord, history: &VecDeque<PriceRecord>) -> bool {
+        let (value, above, below) = match &self.config.condition {
+            Condition::Threshold { above, below } => (Some(record.price), above, below),
+            Condition::Indicator { above, below, .. } => {
+                let value = self.indicator.and_then(|i| i.compute(&recent(history, i.records_needed())));
+                (value, above, below)
+            }
+            Condition::Move { .. } | Condition::TrailingStop { .. } | Condition::Expression { .. } => {
+                return self.check(name, record, history).is_none()
+            }
+        };
+        let Some(value) = value else {
+            return false;
+        };
+        let band = self.config.hysteresis.unwrap_or(0.0);
+        above.is_none_or(|limit| value <= limit - band) && below.is_none_or(|limit| value >= limit + band)
+    }
+
+    // Widens a trailing stop's range with the latest price.
+    fn track(&mut self, price: f64) {
+        if let Condition::TrailingStop { .. } = self.config.condition {
+            let (low, high) = self.trail.unwrap_or((price, price));
+            self.trail = Some((low.min(price), high.max(price)));
+        }
+    }
+
+    // Applies the rule's repeat mode and cooldown to a matching record and
+    // reports whether it should be sent.
+    fn should_fire(&mut self, timestamp: NaiveDateTime) -> bool {
+        if !self.armed {
+            return false;
+        }
+        // Still armed, so a match once the cooldown is over fires.
+        if self.last_fired.is_some_and(|last| timestamp - last < self.cooldown) {
+            return false;
+        }
+        if self.config.repeat == Repeat::Once {
+            self.armed = false;
+        }
+        self.last_fired = Some(timestamp);
+        true
+    }
+
+    // Runs the rule over a new record, returning the alert's value and
+    // message if it fires.
+    fn evaluate(&mut self, name: &str, record: &PriceRecord, history: &VecDeque<PriceRecord>) -> Option<(f64, String)> {
+        self.track(record.price);
+        let Some((value, message)) = self.check(name, record, history) else {
+            if !self.armed && self.cleared(name, record, history) {
+                self.armed = true;
+            }
+            return None;
+        };
+        // A trailing stop starts over from the price that tripped it.
+        if self.trail.is_some() {
+            self.trail = Some((record.price, record.price));
+        }
+        self.should_fire(record.timestamp).then_some((value, message))
+    }
+}
+
+// How much history an asset's rules need: everything newer than `window`,
+// and at least the latest `records` records.
+#[derive(Debug, Clone, Copy)]
+struct Horizon {
+    window: Duration,
+    records: usize,
+}
+
+pub struct AlertEngine {
+    rules: Vec<Rule>,
+    // How many of the rules, at the end, came from the added rules file.
+    added: usize,
+    notifiers: Vec<Box<dyn Notifier>>,
+    history: HashMap<String, VecDeque<PriceRecord>>,
+    horizon: HashMap<String, Horizon>,
+    outages: Option<Outages>,
+    staleness: Option<Staleness>,
+    signals: Vec<Signal>,
+    signals_path: PathBuf,
+    // Print what would be sent instead of notifying.
+    dry_run: bool,
+}
+
+impl AlertEngine {
+    pub fn new(config: &Config, assets: &[Box<dyn Pricing>], client: &Client) -> Result<Self, PriceError> {
+        let mut built: Vec<Box<dyn Notifier>> = config
+            .notifiers
+            .iter()
+            .map(|notifier| notify::build(notifier, config, client))
+            .collect::<Result<_, _>>()?;
+        if built.is_empty() {
+            built.push(Box::new(ConsoleNotifier { name: "console".to_string(), theme: Theme::from_config(config)? }));
+        }
+
+        let mut horizon: HashMap<String, Horizon> = HashMap::new();
+        let rules = config
+            .alerts
+            .iter()
+            .map(|config| {
+                if !assets.iter().any(|asset| asset.id() == config.asset) {
+                    return Err(PriceError::ParseError(format!(
+                        "alert '{}' refers to unknown asset '{}'",
+                        config.name, config.asset
+                    )));
+                }
+                let rule = Rule::new(config, &built)?;
+                let needed = horizon
+                    .entry(config.asset.clone())
+                    .or_insert(Horizon { window: Duration::zero(), records: 1 });
+                needed.window = needed.window.max(rule.window);
+                needed.records = needed.records.max(rule.records_needed());
+                Ok(rule)
+            })
+            .collect::<Result<_, _>>()?;
+
+        let outages = match &config.outages {
+            Some(outage) if outage.after == 0 => {
+                return Err(PriceError::ParseError("outages: 'after' must be at least 1".to_string()))
+            }
+            Some(outage) => Some(Outages::new(
+                outage.clone(),
+                resolve(&outage.notify, &built).map_err(|e| PriceError::ParseError(format!("outages: {}", e)))?,
+            )),
+            None => None,
+        };
+
+        let staleness = match &config.staleness {
+            Some(stale) if stale.after == 0 => {
+                return Err(PriceError::ParseError("staleness: 'after' must be at least 1".to_string()))
+            }
+            Some(stale) if stale.repeats.is_some_and(|repeats| repeats < 2) => {
+                return Err(PriceError::ParseError("staleness: 'repeats' must be at least 2".to_string()))
+            }
+            Some(stale) => Some(Staleness::new(
+                stale.clone(),
+                resolve(&stale.notify, &built).map_err(|e| PriceError::ParseError(format!("staleness: {}", e)))?,
+                config.interval()?,
+                Local::now().naive_local(),
+            )),
+            None => None,
+        };
+
+        let strategies = &config.strategies;
+        let signals = config
+            .signals
+            .iter()
+            .map(|config| {
+                if !assets.iter().any(|asset| asset.id() == config.asset) {
+                    return Err(PriceError::ParseError(format!(
+                        "signal '{}' refers to unknown asset '{}'",
+                        config.name, config.asset
+                    )));
+                }
+                let notifiers = match &config.notify {
+                    Some(names) => Some(
+                        resolve(names, &built)
+                            .map_err(|e| PriceError::ParseError(format!("signal '{}': {}", config.name, e)))?,
+                    ),
+                    None => None,
+                };
+                Signal::new(config.clone(), notifiers, strategies)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(AlertEngine {
+            rules,
+            added: 0,
+            notifiers: built,
+            history: HashMap::new(),
+            horizon,
+            outages,
+            staleness,
+            signals,
+            signals_path: signal::path(config.storage.directory()),
+            dry_run: false,
+        })
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    // Replaces the rules from the added rules file, keeping the state of
+    // those already there so a reload does not fire them again. Returns how
+    // many there are.
+    pub fn set_added(&mut self, added: &[AddedRule], assets: &[Box<dyn Pricing>]) -> Result<usize, PriceError> {
+        let mut rules = added
+            .iter()
+            .map(|added| {
+                let config = added.to_rule();
+                if !assets.iter().any(|asset| asset.id() == config.asset) {
+                    return Err(PriceError::ParseError(format!(
+                        "alert '{}' refers to unknown asset '{}'",
+                        config.name, config.asset
+                    )));
+                }
+                Rule::new(&config, &self.notifiers)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let previous = self.rules.split_off(self.rules.len() - self.added);
+        for rule in &mut rules {
+            if let Some(old) = previous.iter().find(|old| old.config.name == rule.config.name) {
+                rule.armed = old.armed;
+                rule.last_fired = old.last_fired;
+            }
+            self.horizon
+                .entry(rule.config.asset.clone())
+                .or_insert(Horizon { window: Duration::zero(), records: 1 });
+        }
+        self.added = rules.len();
+        self.rules.append(&mut rules);
+        Ok(self.added)
+    }
+
+    // Seeds the rolling windows from stored history so rate-of-change rules
+    // work straight after a restart, and restores each rule's cooldown from
+    // the alert log. A fire-once rule that fired before the restart stays
+    // disarmed until its condition is seen to clear.
+    pub fn warm_up(&mut self, storage: &dyn Storage) -> Result<(), PriceError> {
+        for event in storage.alert_log()? {
+            if let Some(rule) =
+                self.rules.iter_mut().find(|rule| rule.config.name == event.rule && rule.config.asset == event.asset)
+            {
+                rule.last_fired = Some(event.timestamp);
+                rule.armed = rule.config.repeat == Repeat::Always;
+            }
+        }
+
+        for (asset, horizon) in &self.horizon {
+            if horizon.window.is_zero() && horizon.records <= 1 {
+                continue;
+            }
+            // Trimmed as it is read, so a long history is never held whole.
+            let mut history = VecDeque::new();
+            for record in storage.scan(asset, None)? {
+                history.push_back(record?);
+                trim(&mut history, *horizon);
+            }
+            self.history.insert(asset.clone(), history);
+        }
+
+        // Only crossings after the restart count, so the replay is silent.
+        for signal in &mut self.signals {
+            let mut records = VecDeque::new();
+            for record in storage.scan(&signal.config.asset, None)? {
+                records.push_back(record?);
+                if records.len() > signal.records_needed() {
+                    records.pop_front();
+                }
+            }
+            for record in &records {
+                signal.push(record);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn process(&mut self, asset: &dyn Pricing, record: &PriceRecord, storage: &mut dyn Storage) {
+        self.check_signals(asset, record, storage);
+        self.check_repeats(asset, record, storage);
+        let Some(horizon) = self.horizon.get(asset.id()) else {
+            return;
+        };
+
+        let history = self.history.entry(asset.id().to_string()).or_default();
+        history.push_back(record.clone());
+        trim(history, *horizon);
+
+        for rule in self.rules.iter_mut().filter(|rule| rule.config.asset == asset.id()) {
+            let Some((value, message)) = rule.evaluate(asset.name(), record, history) else {
+                continue;
+            };
+            let alert = Alert {
+                rule: rule.config.name.clone(),
+                asset: asset.name().to_string(),
+                value,
+                message,
+                severity: rule.config.severity,
+                timestamp: record.timestamp,
+            };
+            dispatch(&self.notifiers, &rule.notifiers, alert, asset.id(), storage, self.dry_run);
+        }
+    }
+
+    fn check_signals(&mut self, asset: &dyn Pricing, record: &PriceRecord, storage: &mut dyn Storage) {
+        for signal in self.signals.iter_mut().filter(|signal| signal.config.asset == asset.id()) {
+            let Some(event) = signal.push(record) else {
+                continue;
+            };
+            let message = signal.describe(&event, asset.name(), asset.precision());
+            if self.dry_run {
+                println!("[dry run] {}", message);
+            } else if let Err(e) = signal::append(&self.signals_path, &event) {
+                eprintln!("Error recording signal '{}': {}", event.signal, e);
+            } else if signal.notifiers.is_none() {
+                println!("{}", message);
+            }
+            let Some(targets) = &signal.notifiers else {
+                continue;
+            };
+            let alert = Alert {
+                rule: signal.config.name.clone(),
+                asset: asset.name().to_string(),
+                value: record.price,
+                message,
+                severity: signal.config.severity,
+                timestamp: record.timestamp,
+            };
+            dispatch(&self.notifiers, targets, alert, asset.id(), storage, self.dry_run);
+        }
+    }
+
+    pub fn source_failed(&mut self, asset: &dyn Pricing, error: &PriceError, storage: &mut dyn Storage) {
+        let Some(outages) = self.outages.as_mut() else {
+            return;
+        };
+        let now = Local::now().naive_local();
+        let Some(since) = outages.failed(asset.id(), now) else {
+            return;
+        };
+        let alert = Alert {
+            rule: SOURCE_DOWN.to_string(),
+            asset: asset.name().to_string(),
+            value: outages.config.after as f64,
+            message: format!(
+                "{} has failed {} times in a row since {}: {}",
+                asset.name(),
+                outages.config.after,
+                since.format(TIMESTAMP_FORMAT),
+                error
+            ),
+            severity: outages.config.severity,
+            timestamp: now,
+        };
+        dispatch(&self.notifiers, &outages.notifiers, alert, asset.id(), storage, self.dry_run);
+    }
+
+    pub fn source_succeeded(&mut self, asset: &dyn Pricing, timestamp: NaiveDateTime, storage: &mut dyn Storage) {
+        let Some(outages) = self.outages.as_mut() else {
+            return;
+        };
+        let Some((failures, downtime)) = outages.succeeded(asset.id(), timestamp) else {
+            return;
+        };
+        let alert = Alert {
+            rule: SOURCE_RECOVERED.to_string(),
+            asset: asset.name().to_string(),
+            value: downtime.num_seconds() as f64,
+            message: format!(
+                "{} is back after {} down ({} failed attempts)",
+                asset.name(),
+                outage::describe(downtime),
+                failures
+            ),
+            severity: outages.config.severity,
+            timestamp,
+        };
+        dispatch(&self.notifiers, &outages.notifiers, alert, asset.id(), storage, self.dry_run);
+    }
+
+    // Reports assets that have gone `after` intervals without a new record.
+    pub fn check_staleness(&mut self, assets: &[Box<dyn Pricing>], storage: &mut dyn Storage) {
+        let Some(staleness) = self.staleness.as_mut() else {
+            return;
+        };
+        let ids: Vec<&str> = assets.iter().map(|asset| asset.id()).collect();
+        staleness.retain(&ids);
+        let now = Local::now().naive_local();
+        for asset in assets {
+            let Some(quiet) = staleness.check(asset.id(), now) else {
+                continue;
+            };
+            let alert = Alert {
+                rule: SOURCE_STALE.to_string(),
+                asset: asset.name().to_string(),
+                value: quiet.num_seconds() as f64,
+                message: format!(
+                    "{} has had no new price for {} ({} intervals)",
+                    asset.name(),
+                    outage::describe(quiet),
+                    staleness.config.after
+                ),
+                severity: staleness.config.severity,
+                timestamp: now,
+            };
+            dispatch(&self.notifiers, &staleness.notifiers, alert, asset.id(), storage, self.dry_run);
+        }
+    }
+
+    // Tells every notifier when the sink stops taking records and when it
+    // takes them again.
+    pub fn sink_changed(&mut self, sink: &str, health: Health, storage: &mut dyn Storage) {
+        let now = Local::now().naive_local();
+        let (rule, value, message) = match health {
+            Health::Failing { error, since } => (
+                SINK_FAILING,
+                0.0,
+                format!(
+                    "Prices cannot be written to {} since {}: {}; new ones are held in memory meanwhile",
+                    sink,
+                    since.format(TIMESTAMP_FORMAT),
+                    error
+                ),
+            ),
+            Health::Recovered { down, written } => (
+                SINK_RECOVERED,
+                down.num_seconds() as f64,
+                format!(
+                    "Prices are written to {} again after {}, including the {} held meanwhile",
+                    sink,
+                    outage::describe(down),
+                    written
+                ),
+            ),
+        };
+        let alert = Alert {
+            rule: rule.to_string(),
+            asset: sink.to_string(),
+            value,
+            message,
+            severity: Severity::Critical,
+            timestamp: now,
+        };
+        let targets: Vec<usize> = (0..self.notifiers.len()).collect();
+        dispatch(&self.notifiers, &targets, alert, sink, storage, self.dry_run);
+    }
+
+    fn check_repeats(&mut self, asset: &dyn Pricing, record: &PriceRecord, storage: &mut dyn Storage) {
+        let Some(staleness) = self.staleness.as_mut() else {
+            return;
+        };
+        let now = Local::now().naive_local();
+        let (rule, value, message) = match staleness.record(asset.id(), record.timestamp, record.price, now) {
+            Some(Change::Repeated(repeats)) => (
+                SOURCE_STALE,
+                repeats as f64,
+                format!(
+                    "{} has returned the same price, {:.*}, {} times in a row",
+                    asset.name(),
+                    asset.precision(),
+                    record.price,
+                    repeats
+                ),
+            ),
+            Some(Change::Fresh(stale)) => (
+                SOURCE_FRESH,
+                stale.num_seconds() as f64,
+                format!("{} has new prices again after {} stale", asset.name(), outage::describe(stale)),
+            ),
+            None => return,
+        };
+        let alert = Alert {
+            rule: rule.to_string(),
+            asset: asset.name().to_string(),
+            value,
+            message,
+            severity: staleness.config.severity,
+            timestamp: now,
+        };
+        dispatch(&self.notifiers, &staleness.notifiers, alert, asset.id(), storage, self.dry_run);
+    }
+}
+
+// Maps notifier names to their positions; no names means every notifier.
+fn resolve(names: &[String], notifiers: &[Box<dyn Notifier>]) -> Result<Vec<usize>, String> {
+    if names.is_empty() {
+        return Ok((0..notifiers.len()).collect());
+    }
+    names
+        .iter()
+        .map(|wanted| {
+            notifiers.iter().position(|n| n.name() == wanted).ok_or_else(|| format!("unknown notifier '{}'", wanted))
+        })
+        .collect()
+}
+
+// Sends an alert to the given notifiers and records the outcome in the
+// alert log.
+fn dispatch(
+    notifiers: &[Box<dyn Notifier>],
+    targets: &[usize],
+    alert: Alert,
+    asset: &str,
+    storage: &mut dyn Storage,
+    dry_run: bool,
+) {
+    let mut outcome = Vec::new();
+    for &index in targets {
+        let notifier = &notifiers[index];
+        if !notifier.accepts(&alert) {
+            continue;
+        }
+        if dry_run {
+            println!("[dry run] would notify {} of '{}': {}", notifier.name(), alert.rule, alert.message);
+            outcome.push(format!("{}: dry run", notifier.name()));
+            continue;
+        }
+        match notifier.notify(&alert) {
+            Ok(()) => outcome.push(format!("{}: ok", notifier.name())),
+            Err(e) => {
+                eprintln!("Error sending alert '{}' via {}: {}", alert.rule, notifier.name(), e);
+                outcome.push(format!("{}: {}", notifier.name(), e));
+            }
+        }
+    }
+
+    let event = AlertEvent {
+        timestamp: alert.timestamp,
+        rule: alert.rule,
+        asset: asset.to_string(),
+        value: alert.value,
+        message: alert.message,
+        outcome: outcome.join("; "),
+    };
+    if let Err(e) = storage.log_alert(&event) {
+        eprintln!("Error recording alert '{}': {}", event.rule, e);
+    }
+}
+
+// The last `count` prices of `history`, oldest first, rather than all it
+// holds for a rule with a long window.
+fn recent(history: &VecDeque<PriceRecord>, count: usize) -> Vec<f64> {
+    history.range(history.len().saturating_sub(count)..).map(|r| r.price).collect()
+}
+
+// Keeps the window and the last record before it, which a change over the
+// window is measured from.
+fn trim(history: &mut VecDeque<PriceRecord>, horizon: Horizon) {
+    let Some(latest) = history.back().map(|r| r.timestamp) else {
+        return;
+    };
+    while history.len() > horizon.records
+        && history
+            .get(1)
+            .is_some_and(|next| latest.checked_sub_signed(horizon.window).is_some_and(|start| next.timestamp <= start))
+    {
+        history.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn rule(condition: &str) -> Rule {
+        let config: RuleConfig = toml::from_str(&format!("name = \"test\"\nasset = \"gold\"\n{}", condition)).unwrap();
+        Rule::new(&config, &[]).unwrap()
+    }
+
+    // Feeds `prices` to the rule, each at its minute past midnight, and
+    // returns whether each fired.
+    fn fired(rule: &mut Rule, prices: &[(i64, f64)]) -> Vec<bool> {
+        let start = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let mut history = VecDeque::new();
+        prices
+            .iter()
+            .map(|&(minute, price)| {
+                let record = PriceRecord::new(start + Duration::minutes(minute), price);
+                history.push_back(record.clone());
+                rule.evaluate("gold", &record, &history).is_some()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn once_fires_again_only_after_the_condition_clears() {
+        let mut rule = rule("type = \"threshold\"\nabove = 100.0\n");
+        let prices = [(0, 101.0), (1, 102.0), (2, 99.0), (3, 103.0), (4, 104.0)];
+        assert_eq!(fired(&mut rule, &prices), [true, false, false, true, false]);
+    }
+
+    #[test]
+    fn always_fires_on_every_match_outside_the_cooldown() {
+        let mut rule = rule("type = \"threshold\"\nabove = 100.0\nrepeat = \"always\"\ncooldown = \"5m\"\n");
+        let prices = [(0, 101.0), (1, 102.0), (4, 103.0), (5, 104.0), (6, 99.0), (11, 105.0)];
+        assert_eq!(fired(&mut rule, &prices), [true, false, false, true, false, true]);
+    }
+
+    #[test]
+    fn matches_during_the_cooldown_leave_once_rules_armed() {
+        let mut rule = rule("type = \"threshold\"\nabove = 100.0\ncooldown = \"10m\"\n");
+        // Re-armed at 99, held back by the cooldown at 101, sent once it is over.
+        let prices = [(0, 101.0), (1, 99.0), (2, 101.0), (12, 102.0), (13, 103.0)];
+        assert_eq!(fired(&mut rule, &prices), [true, false, false, true, false]);
+    }
+
+    #[test]
+    fn hysteresis_holds_off_re_arming_until_the_band_is_crossed() {
+        let mut above = rule("type = \"threshold\"\nabove = 100.0\nhysteresis = 5.0\n");
+        let prices = [(0, 101.0), (1, 98.0), (2, 101.0), (3, 95.0), (4, 101.0)];
+        assert_eq!(fired(&mut above, &prices), [true, false, false, false, true]);
+
+        let mut below = rule("type = \"threshold\"\nbelow = 50.0\nhysteresis = 2.0\n");
+        let prices = [(0, 49.0), (1, 51.0), (2, 49.0), (3, 52.0), (4, 49.0)];
+        assert_eq!(fired(&mut below, &prices), [true, false, false, false, true]);
+    }
+}
@@ -0,0 +1,85 @@
+// Threshold rules added from the `repl`, kept in ALERTS_FILE beside the
+// watchlist rather than in the config so a running tracker picks them up the
+// way it picks up `asset add`:
+//
+//   [[alerts]]
+//   asset = "bitcoin"
+//   above = 70000.0
+//
+// Each is named after its asset and limit, e.g. "bitcoin_above_70000".
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Condition, Repeat, RuleConfig, Severity};
+use crate::config::Config;
+use crate::PriceError;
+
+pub const ALERTS_FILE: &str = "alerts.toml";
+
+pub fn path(config: &Config) -> PathBuf {
+    config.watchlist_path().with_file_name(ALERTS_FILE)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AddedRule {
+    pub asset: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+}
+
+impl AddedRule {
+    pub fn name(&self) -> String {
+        match (self.above, self.below) {
+            (Some(above), _) => format!("{}_above_{}", self.asset, above),
+            (None, Some(below)) => format!("{}_below_{}", self.asset, below),
+            (None, None) => self.asset.clone(),
+        }
+    }
+
+    pub fn to_rule(&self) -> RuleConfig {
+        RuleConfig {
+            name: self.name(),
+            asset: self.asset.clone(),
+            condition: Condition::Threshold { above: self.above, below: self.below },
+            notify: Vec::new(),
+            cooldown: None,
+            repeat: Repeat::Once,
+            hysteresis: None,
+            severity: Severity::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AddedRules {
+    #[serde(default)]
+    pub alerts: Vec<AddedRule>,
+}
+
+impl AddedRules {
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        if !path.exists() {
+            return Ok(AddedRules::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        toml::from_str(&contents).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+    }
+
+    // Through a temporary file, like the watchlist, so the tracker never
+    // reads half of it.
+    pub fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let body = toml::to_string(self).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        let contents = format!("# Managed by `crypto_price_tracker repl`.\n\n{}", body);
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, contents)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+}
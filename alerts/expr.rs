@@ -0,0 +1,513 @@
+// Small expression language for alert conditions, e.g.
+//
+//     price > 65000 && change_24h > 3%
+//     rsi(14) > 75 || sma_distance(50) < -10%
+//
+// Variables: price, volume, market_cap and change_<duration> (percentage
+// change over that duration, e.g. change_1h). Functions: rsi(n), stddev(n),
+// sma(n) and sma_distance(n). A trailing % on a number is only a marker;
+// percentages are compared as plain numbers.
+
+use std::collections::VecDeque;
+
+use chrono::Duration;
+
+use crate::analytics::Indicator;
+use crate::config::parse_duration;
+use crate::storage::PriceRecord;
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    root: Node,
+    pub window: Duration,
+    pub records: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Number(f64),
+    Price,
+    Volume,
+    MarketCap,
+    Change(Duration),
+    Sma(usize),
+    Indicator(Indicator),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Arith(char, Box<Node>, Box<Node>),
+    Compare(&'static str, Box<Node>, Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Number,
+    Bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 17] = [
+    "&&", "||", ">=", "<=", "==", "!=", ">", "<", "!", "+", "-", "*", "/", "(", ")", "%", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_ascii_digit() || c == '.' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            let number = rest[..end].replace('_', "");
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| format!("invalid number '{}'", &rest[..end]))?,
+            ));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_ascii_lowercase()));
+            rest = &rest[end..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(format!("unexpected character '{}'", c));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    window: Duration,
+    records: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", op))
+        }
+    }
+
+    fn or(&mut self) -> Result<(Node, Type), String> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            let right = self.and()?;
+            left = (
+                Node::Or(
+                    Box::new(want(left, Type::Bool, "||")?),
+                    Box::new(want(right, Type::Bool, "||")?),
+                ),
+                Type::Bool,
+            );
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<(Node, Type), String> {
+        let mut left = self.not()?;
+        while self.eat("&&") {
+            let right = self.not()?;
+            left = (
+                Node::And(
+                    Box::new(want(left, Type::Bool, "&&")?),
+                    Box::new(want(right, Type::Bool, "&&")?),
+                ),
+                Type::Bool,
+            );
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<(Node, Type), String> {
+        if self.eat("!") {
+            let inner = self.not()?;
+            return Ok((
+                Node::Not(Box::new(want(inner, Type::Bool, "!")?)),
+                Type::Bool,
+            ));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<(Node, Type), String> {
+        let left = self.sum()?;
+        for op in [">=", "<=", "==", "!=", ">", "<"] {
+            if self.eat(op) {
+                let right = self.sum()?;
+                return Ok((
+                    Node::Compare(
+                        op,
+                        Box::new(want(left, Type::Number, op)?),
+                        Box::new(want(right, Type::Number, op)?),
+                    ),
+                    Type::Bool,
+                ));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<(Node, Type), String> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                '+'
+            } else if self.eat("-") {
+                '-'
+            } else {
+                return Ok(left);
+            };
+            let right = self.product()?;
+            let name = op.to_string();
+            left = (
+                Node::Arith(
+                    op,
+                    Box::new(want(left, Type::Number, &name)?),
+                    Box::new(want(right, Type::Number, &name)?),
+                ),
+                Type::Number,
+            );
+        }
+    }
+
+    fn product(&mut self) -> Result<(Node, Type), String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                '*'
+            } else if self.eat("/") {
+                '/'
+            } else {
+                return Ok(left);
+            };
+            let right = self.unary()?;
+            let name = op.to_string();
+            left = (
+                Node::Arith(
+                    op,
+                    Box::new(want(left, Type::Number, &name)?),
+                    Box::new(want(right, Type::Number, &name)?),
+                ),
+                Type::Number,
+            );
+        }
+    }
+
+    fn unary(&mut self) -> Result<(Node, Type), String> {
+        if self.eat("-") {
+            let inner = self.unary()?;
+            return Ok((
+                Node::Neg(Box::new(want(inner, Type::Number, "-")?)),
+                Type::Number,
+            ));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<(Node, Type), String> {
+        let token = self.peek().cloned().ok_or("unexpected end of expression")?;
+        self.position += 1;
+        match token {
+            Token::Number(value) => {
+                self.eat("%");
+                Ok((Node::Number(value), Type::Number))
+            }
+            Token::Op("(") => {
+                let inner = self.or()?;
+                self.expect(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) if self.eat("(") => {
+                let period = match self.peek() {
+                    Some(Token::Number(n)) if *n >= 2.0 && n.fract() == 0.0 => *n as usize,
+                    _ => {
+                        return Err(format!(
+                            "{}() needs a whole number period of at least 2",
+                            name
+                        ))
+                    }
+                };
+                self.position += 1;
+                self.expect(")")?;
+                let node = match name.as_str() {
+                    "sma" => Node::Sma(period),
+                    "rsi" | "stddev" | "sma_distance" => {
+                        Node::Indicator(format!("{}({})", name, period).parse()?)
+                    }
+                    _ => return Err(format!("unknown function '{}'", name)),
+                };
+                self.records = self.records.max(match &node {
+                    Node::Indicator(indicator) => indicator.records_needed(),
+                    _ => period,
+                });
+                Ok((node, Type::Number))
+            }
+            Token::Ident(name) => match name.as_str() {
+                "price" => Ok((Node::Price, Type::Number)),
+                "volume" => Ok((Node::Volume, Type::Number)),
+                "market_cap" => Ok((Node::MarketCap, Type::Number)),
+                _ => match name.strip_prefix("change_") {
+                    Some(period) => {
+                        let period = parse_duration(period).map_err(|e| e.to_string())?;
+                        self.window = self.window.max(period);
+                        Ok((Node::Change(period), Type::Number))
+                    }
+                    None => Err(format!("unknown variable '{}'", name)),
+                },
+            },
+            Token::Op(op) => Err(format!("unexpected '{}'", op)),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(value) => value.to_string(),
+        Token::Ident(name) => name.clone(),
+        Token::Op(op) => op.to_string(),
+    }
+}
+
+fn want(operand: (Node, Type), expected: Type, op: &str) -> Result<Node, String> {
+    if operand.1 == expected {
+        Ok(operand.0)
+    } else if expected == Type::Bool {
+        Err(format!("'{}' needs a comparison on each side", op))
+    } else {
+        Err(format!("'{}' needs numbers, not a comparison", op))
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            window: Duration::zero(),
+            records: 1,
+        };
+        let (root, kind) = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!(
+                "unexpected '{}' after the end of the expression",
+                describe(token)
+            ));
+        }
+        if kind != Type::Bool {
+            return Err("expression must be a comparison, e.g. price > 65000".to_string());
+        }
+        Ok(Expression {
+            root,
+            window: parser.window,
+            records: parser.records,
+        })
+    }
+
+    // `None` means the expression cannot be decided yet, for example because
+    // not enough history has been collected or a field is missing.
+    pub fn matches(&self, record: &PriceRecord, history: &VecDeque<PriceRecord>) -> Option<bool> {
+        let prices = super::recent(history, self.records);
+        truth(
+            &self.root,
+            &Context {
+                record,
+                history,
+                prices: &prices,
+            },
+        )
+    }
+}
+
+struct Context<'a> {
+    record: &'a PriceRecord,
+    history: &'a VecDeque<PriceRecord>,
+    prices: &'a [f64],
+}
+
+fn number(node: &Node, ctx: &Context) -> Option<f64> {
+    match node {
+        Node::Number(value) => Some(*value),
+        Node::Price => Some(ctx.record.price),
+        Node::Volume => ctx.record.volume,
+        Node::MarketCap => ctx.record.market_cap,
+        Node::Change(period) => {
+            let start = ctx.record.timestamp.checked_sub_signed(*period)?;
+            // None until the history goes back that far.
+            let base = ctx.history.iter().rev().find(|r| r.timestamp <= start)?.price;
+            (base != 0.0).then(|| (ctx.record.price - base) / base * 100.0)
+        }
+        Node::Sma(n) => {
+            let recent = ctx.prices.get(ctx.prices.len().checked_sub(*n)?..)?;
+            Some(recent.iter().sum::<f64>() / *n as f64)
+        }
+        Node::Indicator(indicator) => indicator.compute(ctx.prices),
+        Node::Neg(inner) => number(inner, ctx).map(|v| -v),
+        Node::Arith(op, left, right) => {
+            let (left, right) = (number(left, ctx)?, number(right, ctx)?);
+            match op {
+                '+' => Some(left + right),
+                '-' => Some(left - right),
+                '*' => Some(left * right),
+                _ => (right != 0.0).then(|| left / right),
+            }
+        }
+        Node::Not(_) | Node::Compare(..) | Node::And(..) | Node::Or(..) => None,
+    }
+}
+
+fn truth(node: &Node, ctx: &Context) -> Option<bool> {
+    match node {
+        Node::Compare(op, left, right) => {
+            let (left, right) = (number(left, ctx)?, number(right, ctx)?);
+            Some(match *op {
+                ">" => left > right,
+                ">=" => left >= right,
+                "<" => left < right,
+                "<=" => left <= right,
+                "==" => left == right,
+                _ => left != right,
+            })
+        }
+        Node::Not(inner) => truth(inner, ctx).map(|v| !v),
+        Node::And(left, right) => Some(truth(left, ctx)? && truth(right, ctx)?),
+        Node::Or(left, right) => match (truth(left, ctx), truth(right, ctx)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    // A record per minute with these prices, the last being the one checked.
+    fn history(prices: &[f64]) -> VecDeque<PriceRecord> {
+        let start = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let records = prices.iter().enumerate();
+        records.map(|(minute, price)| PriceRecord::new(start + Duration::minutes(minute as i64), *price)).collect()
+    }
+
+    fn eval(source: &str, prices: &[f64]) -> Option<bool> {
+        let history = history(prices);
+        Expression::parse(source).unwrap().matches(history.back().unwrap(), &history)
+    }
+
+    fn error(source: &str) -> String {
+        Expression::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_comparisons_tighter_than_both() {
+        assert_eq!(eval("price > 1 || price > 100 && price < 5", &[10.0]), Some(true));
+        assert_eq!(eval("(price > 1 || price > 100) && price < 5", &[10.0]), Some(false));
+        assert_eq!(eval("!price > 100 && price == 10", &[10.0]), Some(true));
+        assert_eq!(eval("1 + 2 * 3 == 7 && (1 + 2) * 3 == 9 && -2 * 3 == -6 && 8 - 2 - 1 == 5", &[1.0]), Some(true));
+        assert_eq!(eval("price / 2 / 5 == 1", &[10.0]), Some(true));
+    }
+
+    #[test]
+    fn a_percent_sign_only_marks_the_number() {
+        assert_eq!(eval("change_2m > 3% && change_2m < 4", &[100.0, 90.0, 103.5]), Some(true));
+        assert_eq!(eval("price > 10%", &[11.0]), Some(true));
+        assert_eq!(eval("price > 1_000", &[1001.0]), Some(true));
+    }
+
+    #[test]
+    fn changes_wait_for_enough_history() {
+        let expression = Expression::parse("change_2m > 1%").unwrap();
+        assert_eq!(expression.window, Duration::minutes(2));
+        assert_eq!(eval("change_2m > 1%", &[100.0, 103.0]), None);
+        assert_eq!(eval("change_2m > 1%", &[100.0, 50.0, 103.0]), Some(true));
+        // Measured from the last record at the start of the window.
+        assert_eq!(eval("change_2m < 0", &[200.0, 100.0, 50.0, 90.0]), Some(true));
+        assert_eq!(eval("change_2m > 1% || price > 0", &[100.0]), Some(true));
+        assert_eq!(eval("change_2m > 1% && price > 0", &[100.0]), None);
+    }
+
+    #[test]
+    fn functions_take_a_whole_period_of_at_least_two() {
+        for source in ["rsi(1) > 70", "sma_distance(2.5) < -10%", "rsi() > 70", "stddev(price) > 1"] {
+            let name = &source[..source.find('(').unwrap()];
+            assert_eq!(error(source), format!("{}() needs a whole number period of at least 2", name));
+        }
+        assert_eq!(error("ema(10) > 1"), "unknown function 'ema'");
+        assert_eq!(error("rsi(14 > 70"), "expected ')'");
+        assert_eq!(Expression::parse("rsi(14) > 70 || sma(20) > 1").unwrap().records, 20);
+        assert_eq!(Expression::parse("rsi(14) > 70").unwrap().records, 15);
+    }
+
+    #[test]
+    fn functions_wait_for_their_period() {
+        assert_eq!(eval("sma(3) == 20", &[10.0, 20.0]), None);
+        assert_eq!(eval("sma(3) == 20", &[10.0, 20.0, 30.0]), Some(true));
+        assert_eq!(eval("sma_distance(2) == 50%", &[10.0, 30.0]), Some(true));
+    }
+
+    #[test]
+    fn division_by_zero_is_undecided() {
+        assert_eq!(eval("price / 0 > 1", &[10.0]), None);
+        assert_eq!(eval("price / (price - 10) > 1", &[10.0]), None);
+        assert_eq!(eval("price / 0 > 1 || price > 1", &[10.0]), Some(true));
+        // Missing fields are too.
+        assert_eq!(eval("volume > 1", &[10.0]), None);
+    }
+
+    #[test]
+    fn parse_errors_say_what_is_wrong() {
+        for (source, message) in [
+            ("", "unexpected end of expression"),
+            ("price >", "unexpected end of expression"),
+            ("price > 1 )", "unexpected ')' after the end of the expression"),
+            ("price > 1 price", "unexpected 'price' after the end of the expression"),
+            ("(price > 1", "expected ')'"),
+            ("price", "expression must be a comparison, e.g. price > 65000"),
+            ("price && volume > 1", "'&&' needs a comparison on each side"),
+            ("!price", "'!' needs a comparison on each side"),
+            ("(price > 1) + 2 > 0", "'+' needs numbers, not a comparison"),
+            ("price > 1 > 0", "unexpected '>' after the end of the expression"),
+            ("price $ 3", "unexpected character '$'"),
+            ("prise > 1", "unknown variable 'prise'"),
+            ("1..2 > 0", "invalid number '1..2'"),
+            ("price > * 2", "unexpected '*'"),
+        ] {
+            assert_eq!(error(source), message, "{}", source);
+        }
+        assert!(!error("change_soon > 1").is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+// Tracks consecutive fetch failures per source so the tracker can report
+// when a source goes down and when it comes back.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+
+use super::Severity;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OutageConfig {
+    // Consecutive failed fetches before a source counts as down.
+    #[serde(default = "default_after")]
+    pub after: u32,
+    // Notifier names; empty means every configured notifier.
+    #[serde(default)]
+    pub notify: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn default_after() -> u32 {
+    3
+}
+
+struct Outage {
+    failures: u32,
+    since: NaiveDateTime,
+}
+
+pub struct Outages {
+    pub config: OutageConfig,
+    pub notifiers: Vec<usize>,
+    open: HashMap<String, Outage>,
+}
+
+impl Outages {
+    pub fn new(config: OutageConfig, notifiers: Vec<usize>) -> Self {
+        Outages {
+            config,
+            notifiers,
+            open: HashMap::new(),
+        }
+    }
+
+    // Returns when the failures started if this one marks the source as down.
+    pub fn failed(&mut self, asset: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let outage = self.open.entry(asset.to_string()).or_insert(Outage {
+            failures: 0,
+            since: now,
+        });
+        outage.failures += 1;
+        (outage.failures == self.config.after).then_some(outage.since)
+    }
+
+    // Returns the number of failed attempts and the downtime if the source
+    // had been down.
+    pub fn succeeded(&mut self, asset: &str, now: NaiveDateTime) -> Option<(u32, Duration)> {
+        let outage = self.open.remove(asset)?;
+        (outage.failures >= self.config.after).then(|| (outage.failures, now - outage.since))
+    }
+}
+
+pub fn describe(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds % 60),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
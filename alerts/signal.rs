@@ -0,0 +1,205 @@
+// Events derived from the price series rather than a threshold, like SMA(50)
+// crossing SMA(200):
+//
+//   [[signals]]
+//   name = "trend"
+//   asset = "bitcoin"
+//   fast = 50
+//   slow = 200
+//   notify = []               # optional; leave out to only record them
+//
+// Or from a strategy under `[strategies]`, its buys and sells being the
+// events:
+//
+//   [[signals]]
+//   name = "dips"
+//   asset = "bitcoin"
+//   strategy = "dips"
+//
+// Every event is appended to `signals.csv` in the storage directory.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use super::Severity;
+pub use crate::analytics::Cross;
+use crate::analytics::Crossover;
+use crate::storage::csv::quote;
+use crate::storage::{PriceRecord, TIMESTAMP_FORMAT};
+use crate::strategy::{self, Strategy, StrategyConfig};
+use crate::PriceError;
+
+pub const SIGNALS_FILE: &str = "signals.csv";
+const SIGNALS_HEADER: &str = "timestamp,signal,asset,event,price,fast,slow";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignalConfig {
+    pub name: String,
+    pub asset: String,
+    // Moving average periods, 50 and 200 unless a strategy is named.
+    pub fast: Option<usize>,
+    pub slow: Option<usize>,
+    pub strategy: Option<String>,
+    // Notifier names, empty for every notifier; unset only records events.
+    pub notify: Option<Vec<String>>,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_severity() -> Severity {
+    Severity::Info
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    // With SMA(fast) and SMA(slow) at the cross.
+    Cross(Cross, f64, f64),
+    Trade(strategy::Signal),
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub timestamp: NaiveDateTime,
+    pub signal: String,
+    pub asset: String,
+    pub kind: Kind,
+    pub price: f64,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            Kind::Cross(Cross::Above, ..) => "golden_cross",
+            Kind::Cross(Cross::Below, ..) => "death_cross",
+            Kind::Trade(signal) => signal.name(),
+        }
+    }
+}
+
+enum Detector {
+    Crossover { crossover: Crossover, fast: usize, slow: usize },
+    // With the last signal let through, as repeats of it are dropped.
+    Strategy { strategy: Box<dyn Strategy>, last: Option<strategy::Signal> },
+}
+
+pub struct Signal {
+    pub config: SignalConfig,
+    pub notifiers: Option<Vec<usize>>,
+    detector: Detector,
+}
+
+impl Signal {
+    pub fn new(
+        config: SignalConfig,
+        notifiers: Option<Vec<usize>>,
+        strategies: &BTreeMap<String, StrategyConfig>,
+    ) -> Result<Self, PriceError> {
+        let invalid = |reason: String| PriceError::ParseError(format!("signal '{}': {}", config.name, reason));
+        let detector = match &config.strategy {
+            Some(_) if config.fast.is_some() || config.slow.is_some() => {
+                return Err(invalid("set either a strategy or fast and slow".to_string()));
+            }
+            Some(name) => {
+                let settings = strategies
+                    .get(name)
+                    .ok_or_else(|| invalid(format!("unknown strategy '{}'", name)))?;
+                Detector::Strategy { strategy: strategy::build(name, settings)?, last: None }
+            }
+            None => {
+                let (fast, slow) = (config.fast.unwrap_or(50), config.slow.unwrap_or(200));
+                if fast == 0 || fast >= slow {
+                    return Err(invalid("fast must be a shorter period than slow".to_string()));
+                }
+                Detector::Crossover { crossover: Crossover::new(fast, slow), fast, slow }
+            }
+        };
+        Ok(Signal { config, notifiers, detector })
+    }
+
+    pub fn describe(&self, event: &Event, asset: &str, precision: usize) -> String {
+        match (&self.detector, event.kind) {
+            (Detector::Crossover { fast: fast_period, slow: slow_period, .. }, Kind::Cross(cross, fast, slow)) => {
+                let (cross, direction) = match cross {
+                    Cross::Above => ("golden cross", "above"),
+                    Cross::Below => ("death cross", "below"),
+                };
+                format!(
+                    "{}: {}, SMA({}) {:.*} crossed {} SMA({}) {:.*}",
+                    asset, cross, fast_period, precision, fast, direction, slow_period, precision, slow
+                )
+            }
+            _ => format!(
+                "{}: strategy '{}' signals {} at {:.*}",
+                asset,
+                self.config.strategy.as_deref().unwrap_or_default(),
+                event.name(),
+                precision,
+                event.price
+            ),
+        }
+    }
+
+    // Stored records to replay at startup so events can follow on directly.
+    pub fn records_needed(&self) -> usize {
+        match &self.detector {
+            Detector::Crossover { crossover, .. } => crossover.records_needed(),
+            Detector::Strategy { strategy, .. } => strategy.records_needed(),
+        }
+    }
+
+    pub fn push(&mut self, record: &PriceRecord) -> Option<Event> {
+        let kind = match &mut self.detector {
+            Detector::Crossover { crossover, .. } => {
+                let (cross, fast, slow) = crossover.push(record.price)?;
+                Kind::Cross(cross, fast, slow)
+            }
+            Detector::Strategy { strategy, last } => {
+                let signal = strategy.on_price(record).filter(|signal| *last != Some(*signal))?;
+                *last = Some(signal);
+                Kind::Trade(signal)
+            }
+        };
+        Some(Event {
+            timestamp: record.timestamp,
+            signal: self.config.name.clone(),
+            asset: self.config.asset.clone(),
+            kind,
+            price: record.price,
+        })
+    }
+}
+
+pub fn path(directory: &Path) -> PathBuf {
+    directory.join(SIGNALS_FILE)
+}
+
+pub fn append(path: &Path, event: &Event) -> Result<(), PriceError> {
+    let error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(error)?;
+    let mut data = String::new();
+    if file.metadata().map_err(error)?.len() == 0 {
+        data.push_str(SIGNALS_HEADER);
+        data.push('\n');
+    }
+    let (fast, slow) = match event.kind {
+        Kind::Cross(_, fast, slow) => (fast.to_string(), slow.to_string()),
+        Kind::Trade(_) => (String::new(), String::new()),
+    };
+    data.push_str(&format!(
+        "{},{},{},{},{},{},{}\n",
+        event.timestamp.format(TIMESTAMP_FORMAT),
+        quote(&event.signal),
+        quote(&event.asset),
+        event.name(),
+        event.price,
+        fast,
+        slow
+    ));
+    file.write_all(data.as_bytes()).map_err(error)
+}
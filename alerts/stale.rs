@@ -0,0 +1,123 @@
+// Tracks when each asset last produced a new record so the tracker can report
+// a feed that has gone quiet, such as a provider answering "unchanged" for
+// hours or an exchange repeating the same value, instead of carrying on as if
+// the stored price were current.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+
+use super::Severity;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StalenessConfig {
+    // Intervals without a new record before an asset counts as stale.
+    #[serde(default = "default_after")]
+    pub after: u32,
+    // Identical records in a row that count as stale; unset leaves
+    // repeated values alone, as a pegged price legitimately repeats.
+    pub repeats: Option<u32>,
+    // Notifier names; empty means every configured notifier.
+    #[serde(default)]
+    pub notify: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+fn default_after() -> u32 {
+    5
+}
+
+pub enum Change {
+    // The same value this many times in a row.
+    Repeated(u32),
+    // A new value after being stale for this long.
+    Fresh(Duration),
+}
+
+struct Feed {
+    last_new: NaiveDateTime,
+    last_value: Option<(NaiveDateTime, f64)>,
+    repeats: u32,
+    // When the feed was found stale, until a new value arrives.
+    stale_since: Option<NaiveDateTime>,
+}
+
+pub struct Staleness {
+    pub config: StalenessConfig,
+    pub notifiers: Vec<usize>,
+    limit: Duration,
+    started: NaiveDateTime,
+    feeds: HashMap<String, Feed>,
+}
+
+impl Staleness {
+    pub fn new(config: StalenessConfig, notifiers: Vec<usize>, interval: Duration, now: NaiveDateTime) -> Self {
+        Staleness {
+            limit: interval * config.after as i32,
+            config,
+            notifiers,
+            started: now,
+            feeds: HashMap::new(),
+        }
+    }
+
+    fn feed(&mut self, asset: &str) -> &mut Feed {
+        let started = self.started;
+        self.feeds.entry(asset.to_string()).or_insert(Feed {
+            last_new: started,
+            last_value: None,
+            repeats: 0,
+            stale_since: None,
+        })
+    }
+
+    // Returns whether this record makes the feed stale or fresh again.
+    pub fn record(
+        &mut self,
+        asset: &str,
+        timestamp: NaiveDateTime,
+        price: f64,
+        now: NaiveDateTime,
+    ) -> Option<Change> {
+        let repeats = self.config.repeats;
+        let feed = self.feed(asset);
+        // Sources stamping records with the fetch time repeat only the price.
+        let repeated = feed
+            .last_value
+            .is_some_and(|(last, value)| value == price && (last == timestamp || repeats.is_some()));
+        feed.last_value = Some((timestamp, price));
+        if repeated {
+            feed.repeats += 1;
+            let limit = repeats?;
+            if feed.repeats >= limit && feed.stale_since.is_none() {
+                feed.stale_since = Some(now);
+                return Some(Change::Repeated(feed.repeats));
+            }
+            return None;
+        }
+        feed.repeats = 1;
+        feed.last_new = now;
+        feed.stale_since.take().map(|since| Change::Fresh(now - since))
+    }
+
+    // Returns how long the asset has gone without a new record if that
+    // makes it stale now.
+    pub fn check(&mut self, asset: &str, now: NaiveDateTime) -> Option<Duration> {
+        let limit = self.limit;
+        let feed = self.feed(asset);
+        let quiet = now - feed.last_new;
+        if feed.stale_since.is_some() || quiet < limit {
+            return None;
+        }
+        feed.stale_since = Some(now);
+        Some(quiet)
+    }
+
+    // Forgets assets no longer tracked.
+    pub fn retain(&mut self, tracked: &[&str]) {
+        self.feeds.retain(|asset, _| tracked.contains(&asset.as_str()));
+    }
+}
@@ -0,0 +1,378 @@
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::config;
+use crate::storage::PriceRecord;
+use crate::PriceError;
+
+#[derive(Debug, Clone)]
+pub struct Candle {
+    pub start: NaiveDateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+// Simple moving average, fed one price at a time; None for the first
+// `period - 1`.
+pub struct MovingAverage {
+    period: usize,
+    window: VecDeque<f64>,
+    sum: f64,
+}
+
+impl MovingAverage {
+    pub fn new(period: usize) -> Self {
+        MovingAverage { period, window: VecDeque::with_capacity(period), sum: 0.0 }
+    }
+
+    pub fn push(&mut self, price: f64) -> Option<f64> {
+        self.window.push_back(price);
+        self.sum += price;
+        if self.window.len() > self.period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        (self.period > 0 && self.window.len() == self.period).then(|| self.sum / self.period as f64)
+    }
+}
+
+// When SMA(fast) crosses SMA(slow), fed one price at a time.
+pub struct Crossover {
+    fast: MovingAverage,
+    slow: MovingAverage,
+    above: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cross {
+    // The fast average rose above the slow one, a golden cross for 50/200.
+    Above,
+    // It fell below, a death cross.
+    Below,
+}
+
+impl Crossover {
+    pub fn new(fast: usize, slow: usize) -> Self {
+        Crossover { fast: MovingAverage::new(fast), slow: MovingAverage::new(slow), above: None }
+    }
+
+    // Prices needed before the averages can cross.
+    pub fn records_needed(&self) -> usize {
+        self.slow.period + 1
+    }
+
+    // The cross `price` completes, if any, with both averages.
+    pub fn push(&mut self, price: f64) -> Option<(Cross, f64, f64)> {
+        let (Some(fast), Some(slow)) = (self.fast.push(price), self.slow.push(price)) else {
+            return None;
+        };
+        // A tie is no cross either way, the earlier side stands.
+        let above = match fast.partial_cmp(&slow)? {
+            Ordering::Greater => true,
+            Ordering::Less => false,
+            Ordering::Equal => return None,
+        };
+        let was_above = self.above.replace(above)?;
+        match (was_above, above) {
+            (false, true) => Some((Cross::Above, fast, slow)),
+            (true, false) => Some((Cross::Below, fast, slow)),
+            _ => None,
+        }
+    }
+}
+
+// Groups time-ordered records into fixed-width OHLC buckets: adds a record,
+// newer than any before it, to the last candle or a new one.
+pub fn push_candle(candles: &mut Vec<Candle>, record: &PriceRecord, width: Duration) {
+    let width_secs = width.num_seconds().max(1);
+    let secs = record.timestamp.and_utc().timestamp();
+    let bucket = secs - secs.rem_euclid(width_secs);
+    let start = chrono::DateTime::from_timestamp(bucket, 0)
+        .map(|t| t.naive_utc())
+        .unwrap_or(record.timestamp);
+
+    match candles.last_mut() {
+        Some(candle) if candle.start == start => {
+            candle.high = candle.high.max(record.price);
+            candle.low = candle.low.min(record.price);
+            candle.close = record.price;
+        }
+        _ => candles.push(Candle {
+            start,
+            open: record.price,
+            high: record.price,
+            low: record.price,
+            close: record.price,
+        }),
+    }
+}
+
+// Records whose timestamp falls between `since` ago and `until` ago.
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+}
+
+impl Window {
+    pub fn new(since: Option<Duration>, until: Option<Duration>) -> Result<Self, PriceError> {
+        let now = chrono::Local::now().naive_local();
+        let from = since.map(|d| config::before(now, d)).transpose()?;
+        Ok(Window { from, to: until.map(|d| config::before(now, d)).transpose()? })
+    }
+
+    pub fn start(&self) -> Option<NaiveDateTime> {
+        self.from
+    }
+
+    pub fn contains(&self, record: &PriceRecord) -> bool {
+        self.from.is_none_or(|from| record.timestamp >= from) && self.to.is_none_or(|to| record.timestamp <= to)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub count: usize,
+    pub first: PriceRecord,
+    pub last: PriceRecord,
+    pub high: f64,
+    pub low: f64,
+    pub mean: f64,
+}
+
+impl Summary {
+    pub fn change(&self) -> f64 {
+        self.last.price - self.first.price
+    }
+
+    pub fn change_pct(&self) -> f64 {
+        if self.first.price == 0.0 {
+            0.0
+        } else {
+            self.change() / self.first.price * 100.0
+        }
+    }
+}
+
+// Fed one record at a time, in time order.
+#[derive(Default)]
+pub struct Summarizer {
+    first: Option<PriceRecord>,
+    last: Option<PriceRecord>,
+    high: f64,
+    low: f64,
+    stats: RunningStats,
+}
+
+impl Summarizer {
+    pub fn push(&mut self, record: &PriceRecord) {
+        if self.first.is_none() {
+            self.first = Some(record.clone());
+            self.high = record.price;
+            self.low = record.price;
+        }
+        self.high = self.high.max(record.price);
+        self.low = self.low.min(record.price);
+        self.stats.push(record.price);
+        self.last = Some(record.clone());
+    }
+
+    pub fn finish(self) -> Option<Summary> {
+        Some(Summary {
+            count: self.stats.count(),
+            first: self.first?,
+            last: self.last?,
+            high: self.high,
+            low: self.low,
+            mean: self.stats.mean()?,
+        })
+    }
+}
+
+// Relative strength index over the changes in `prices` (Cutler's variant:
+// plain averages of gains and losses). Needs at least two prices.
+pub fn rsi(prices: &[f64]) -> Option<f64> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let (mut gains, mut losses) = (0.0, 0.0);
+    for pair in prices.windows(2) {
+        let change = pair[1] - pair[0];
+        if change > 0.0 {
+            gains += change;
+        } else {
+            losses -= change;
+        }
+    }
+    if gains + losses == 0.0 {
+        return Some(50.0);
+    }
+    Some(100.0 * gains / (gains + losses))
+}
+
+// Mean and variance fed one value at a time in constant memory, by
+// Welford's method, which stays accurate where summing squares would lose
+// the variance of large prices to rounding.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunningStats {
+    count: usize,
+    mean: f64,
+    // Sum of squared differences from the mean.
+    m2: f64,
+}
+
+impl RunningStats {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    // The sample variance; None below two values.
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 1).then(|| self.m2 / (self.count - 1) as f64)
+    }
+
+    pub fn stddev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+}
+
+pub fn stddev(values: &[f64]) -> Option<f64> {
+    stats(values).stddev()
+}
+
+// Pearson correlation of two series of the same length; None below two
+// pairs or where either never varies.
+pub fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let (a_stats, b_stats) = (stats(a), stats(b));
+    let (a_mean, b_mean) = (a_stats.mean()?, b_stats.mean()?);
+    let (a_sd, b_sd) = (a_stats.stddev()?, b_stats.stddev()?);
+    if a.len() != b.len() || a_sd == 0.0 || b_sd == 0.0 {
+        return None;
+    }
+    let covariance: f64 = a.iter().zip(b).map(|(a, b)| (a - a_mean) * (b - b_mean)).sum::<f64>() / (a.len() - 1) as f64;
+    Some((covariance / (a_sd * b_sd)).clamp(-1.0, 1.0))
+}
+
+fn stats(values: &[f64]) -> RunningStats {
+    let mut stats = RunningStats::default();
+    values.iter().for_each(|value| stats.push(*value));
+    stats
+}
+
+// Percentage change between consecutive prices.
+pub fn returns(prices: &[f64]) -> Vec<f64> {
+    prices
+        .windows(2)
+        .filter(|pair| pair[0] != 0.0)
+        .map(|pair| (pair[1] - pair[0]) / pair[0] * 100.0)
+        .collect()
+}
+
+// Percent change from the last price at or before each of `starts` to the
+// latest price, None where the history doesn't reach back that far.
+pub fn returns_since(
+    records: impl Iterator<Item = Result<PriceRecord, PriceError>>,
+    starts: &[NaiveDateTime],
+) -> Result<Vec<Option<f64>>, PriceError> {
+    let mut base: Vec<Option<f64>> = vec![None; starts.len()];
+    let mut last = None;
+    for record in records {
+        let record = record?;
+        for (base, start) in base.iter_mut().zip(starts) {
+            if record.timestamp <= *start {
+                *base = Some(record.price);
+            }
+        }
+        last = Some(record.price);
+    }
+    let Some(last) = last else {
+        return Ok(base);
+    };
+    Ok(base
+        .into_iter()
+        .map(|base| base.filter(|base| *base != 0.0).map(|base| (last - base) / base * 100.0))
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicator {
+    // RSI over the last n changes.
+    Rsi(usize),
+    // Standard deviation of the last n percentage returns.
+    Stddev(usize),
+    // Percentage distance of the latest price from SMA(n).
+    SmaDistance(usize),
+}
+
+impl Indicator {
+    pub fn records_needed(&self) -> usize {
+        match *self {
+            Indicator::Rsi(n) | Indicator::Stddev(n) => n + 1,
+            Indicator::SmaDistance(n) => n,
+        }
+    }
+
+    // Evaluates the indicator on the most recent prices; `None` until enough
+    // history has accumulated.
+    pub fn compute(&self, prices: &[f64]) -> Option<f64> {
+        let needed = self.records_needed();
+        if prices.len() < needed {
+            return None;
+        }
+        let recent = &prices[prices.len() - needed..];
+        match *self {
+            Indicator::Rsi(_) => rsi(recent),
+            Indicator::Stddev(_) => stddev(&returns(recent)),
+            Indicator::SmaDistance(n) => {
+                let average = recent.iter().sum::<f64>() / n as f64;
+                let last = *recent.last()?;
+                (average != 0.0).then(|| (last - average) / average * 100.0)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Indicator {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid indicator '{}', expected rsi(n), stddev(n) or sma_distance(n)", value);
+        let (name, rest) = value.trim().split_once('(').ok_or_else(invalid)?;
+        let period: usize = rest
+            .strip_suffix(')')
+            .and_then(|n| n.trim().parse().ok())
+            .filter(|n| *n >= 2)
+            .ok_or_else(invalid)?;
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "rsi" => Ok(Indicator::Rsi(period)),
+            "stddev" => Ok(Indicator::Stddev(period)),
+            "sma_distance" => Ok(Indicator::SmaDistance(period)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl std::fmt::Display for Indicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Indicator::Rsi(n) => write!(f, "RSI({})", n),
+            Indicator::Stddev(n) => write!(f, "stddev({})", n),
+            Indicator::SmaDistance(n) => write!(f, "distance from SMA({})", n),
+        }
+    }
+}
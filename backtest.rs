@@ -0,0 +1,99 @@
+// Replays stored history through a trading strategy from strategy.rs, all
+// in or all out of a single asset with no leverage, to see how it would have
+// done.
+
+use chrono::NaiveDateTime;
+
+use crate::storage::PriceRecord;
+use crate::strategy::{self, Signal, StrategyConfig};
+use crate::PriceError;
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub timestamp: NaiveDateTime,
+    pub signal: Signal,
+    pub price: f64,
+    // Units bought or sold.
+    pub amount: f64,
+    // Cash plus holdings right after the trade.
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    pub trades: Vec<Trade>,
+    pub start: f64,
+    // Cash plus the holdings at the last price.
+    pub end: f64,
+    // What buying at the first price and holding would have returned, in
+    // percent.
+    pub hold_return: f64,
+    // The largest fall of equity from a previous high, in percent.
+    pub max_drawdown: f64,
+    // Sells above the price of the buy before them.
+    pub wins: usize,
+}
+
+impl Outcome {
+    pub fn total_return(&self) -> f64 {
+        (self.end - self.start) / self.start * 100.0
+    }
+}
+
+// Trades `records` with `cash` by the strategy, paying `fee` percent of
+// each trade.
+pub fn run(
+    name: &str,
+    config: &StrategyConfig,
+    records: impl Iterator<Item = Result<PriceRecord, PriceError>>,
+    cash: f64,
+    fee: f64,
+) -> Result<Outcome, PriceError> {
+    let mut strategy = strategy::build(name, config)?;
+    let (mut balance, mut held) = (cash, 0.0);
+    let mut trades: Vec<Trade> = Vec::new();
+    let (mut first, mut last) = (None, None);
+    let (mut peak, mut max_drawdown) = (cash, 0.0_f64);
+    let mut wins = 0;
+    for record in records {
+        let record = record?;
+        let price = record.price;
+        first.get_or_insert(price);
+        last = Some(price);
+        // Signals for the side already taken change nothing.
+        let signal = strategy.on_price(&record).filter(|signal| (*signal == Signal::Buy) != (held > 0.0));
+        if let Some(signal) = signal {
+            let amount = match signal {
+                Signal::Buy => {
+                    let amount = balance * (1.0 - fee / 100.0) / price;
+                    (balance, held) = (0.0, amount);
+                    amount
+                }
+                Signal::Sell => {
+                    let amount = held;
+                    (balance, held) = (held * price * (1.0 - fee / 100.0), 0.0);
+                    if trades.last().is_some_and(|buy| price > buy.price) {
+                        wins += 1;
+                    }
+                    amount
+                }
+            };
+            let equity = balance + held * price;
+            trades.push(Trade { timestamp: record.timestamp, signal, price, amount, equity });
+        }
+        let equity = balance + held * price;
+        peak = peak.max(equity);
+        max_drawdown = max_drawdown.max((peak - equity) / peak * 100.0);
+    }
+    let (Some(first), Some(last)) = (first, last) else {
+        return Err(PriceError::ParseError("no history to backtest".to_string()));
+    };
+    Ok(Outcome {
+        trades,
+        start: cash,
+        end: balance + held * last,
+        hold_return: (last - first) / first * 100.0,
+        max_drawdown,
+        wins,
+    })
+}
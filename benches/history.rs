@@ -0,0 +1,109 @@
+// Times `chart` and `report` over generated histories of growing length, in
+// each storage backend, and records the peak memory of each run, which
+// should stay flat as the history grows. Run with `cargo bench --bench history`; peak memory is
+// read from /proc, so it shows as "-" elsewhere than on Linux.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{text, Scratch};
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+
+const COMMANDS: [&[&str]; 3] = [
+    &["chart", "bitcoin", "-o", "chart.svg", "--sma", "50"],
+    &["chart", "bitcoin", "-o", "candles.svg", "--style", "candles", "--candle", "7d"],
+    &["report", "--since", "36500d"],
+];
+
+const SQLITE: &str = r#"
+interval = "1s"
+
+[storage]
+backend = "sqlite"
+path = "prices.db"
+"#;
+
+const BINARY: &str = r#"
+interval = "1s"
+
+[storage]
+backend = "binary"
+"#;
+
+fn main() {
+    println!("backend  command     records    file MB    seconds    peak MB");
+    for size in SIZES {
+        let scratch = Scratch::new(&format!("bench-{}", size));
+        scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+        let history = scratch.dir.join("bitcoin_prices.csv");
+        write_history(&history, size);
+        let megabytes = fs::metadata(&history).unwrap().len() as f64 / 1e6;
+
+        for args in COMMANDS {
+            row("csv", args[0], size, megabytes, measure(scratch.command(args)));
+        }
+
+        for (backend, config, copy, file) in [
+            ("binary", BINARY, ["convert", "--to", "binary"].as_slice(), "bitcoin_prices.bin"),
+            ("sqlite", SQLITE, ["migrate"].as_slice(), "prices.db"),
+        ] {
+            scratch.write("tracker.toml", config);
+            let copied = scratch.run(copy);
+            assert!(copied.status.success(), "{}", text(&copied.stderr));
+            let megabytes = fs::metadata(scratch.dir.join(file)).unwrap().len() as f64 / 1e6;
+            for args in COMMANDS {
+                row(backend, args[0], size, megabytes, measure(scratch.command(args)));
+            }
+        }
+        let _ = fs::remove_dir_all(&scratch.dir);
+    }
+}
+
+fn row(backend: &str, command: &str, size: usize, megabytes: f64, (seconds, peak): (f64, String)) {
+    println!("{:<8} {:<8} {:>10} {:>10.1} {:>10.2} {:>10}", backend, command, size, megabytes, seconds, peak);
+}
+
+// One record a minute, ending now, on a deterministic random walk.
+fn write_history(path: &std::path::Path, size: usize) {
+    let mut file = BufWriter::new(File::create(path).unwrap());
+    writeln!(file, "# schema_version=6\ntimestamp,price,volume,market_cap").unwrap();
+    let start = chrono::Local::now().naive_local() - chrono::Duration::minutes(size as i64);
+    let (mut price, mut state) = (30000.0_f64, 0x2545_f491_4f6c_dd1d_u64);
+    for minute in 0..size {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        price *= 1.0 + ((state % 2001) as f64 - 1000.0) / 1e6;
+        let timestamp = start + chrono::Duration::minutes(minute as i64);
+        writeln!(file, "{},{:.2},{:.2},", timestamp.format("%Y-%m-%d %H:%M:%S"), price, 1000.0 + price).unwrap();
+    }
+    file.flush().unwrap();
+}
+
+// Wall time and peak resident memory of a run.
+fn measure(mut command: Command) -> (f64, String) {
+    let started = Instant::now();
+    let mut child = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn().unwrap();
+    let mut peak = None;
+    loop {
+        let status = fs::read_to_string(format!("/proc/{}/status", child.id())).unwrap_or_default();
+        // The high-water mark only grows, so the last reading is the peak.
+        if let Some(kilobytes) = status.lines().find_map(|line| line.strip_prefix("VmHWM:")) {
+            peak = kilobytes.trim().trim_end_matches("kB").trim().parse::<f64>().ok();
+        }
+        if let Some(status) = child.try_wait().unwrap() {
+            assert!(status.success(), "{:?} failed", command);
+            break;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    let peak = peak.map_or_else(|| "-".to_string(), |kilobytes| format!("{:.1}", kilobytes / 1024.0));
+    (started.elapsed().as_secs_f64(), peak)
+}
@@ -0,0 +1,350 @@
+use std::path::PathBuf;
+
+use chrono::Duration;
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::config::parse_duration;
+use crate::http::mock_server::Scenario;
+
+#[derive(Parser)]
+#[command(
+    name = "crypto_price_tracker",
+    version,
+    about = "Tracks crypto and index prices into CSV files",
+    after_help = "Exit status: 1 on a file or other failure, 2 on invalid arguments, 3 on a config error, \
+                  4 on a network failure and 5 when a response or stored data does not parse"
+)]
+pub struct Cli {
+    /// Configuration file (defaults to tracker.toml when present)
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Merge the config's [profiles.<name>] table over the rest of it
+    #[arg(long, global = true, env = "TRACKER_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Fetch prices but only print what would be written, logged or sent
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Redraw a table of the latest prices after each round instead of printing a line per price
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Save every HTTP response body under this directory
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Answer HTTP requests from bodies saved with --record instead of the network
+    #[arg(long, global = true, value_name = "DIR")]
+    pub replay: Option<PathBuf>,
+
+    /// Serve canned CoinGecko/Yahoo responses from a local server and fetch from it (for development)
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "SCENARIO",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "ok",
+        conflicts_with = "replay"
+    )]
+    pub mock_server: Option<Scenario>,
+
+    /// Show the request, the start of the response and the underlying causes under errors
+    #[arg(long, global = true)]
+    pub verbose: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Merge an external CSV (timestamp,price) into an asset's history
+    Import {
+        /// Asset id from the watchlist, e.g. bitcoin
+        asset: String,
+        /// CSV file to import
+        file: PathBuf,
+    },
+    /// Copy existing CSV history into the configured database backend
+    Migrate {
+        /// Directory holding the <asset>_prices.csv files
+        #[arg(long, default_value = ".")]
+        source: PathBuf,
+    },
+    /// Convert every tracked asset's history between CSV and the binary format
+    Convert {
+        /// The format to convert to; histories are read from the other one
+        #[arg(long, value_enum)]
+        to: HistoryFormat,
+        /// Directory holding the history files
+        #[arg(long, default_value = ".")]
+        directory: PathBuf,
+    },
+    /// Write the prices for beancount, hledger, GnuCash or Excel
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// Only this asset id instead of every tracked one
+        #[arg(long)]
+        asset: Option<String>,
+        /// Currency to price in: usd or one of the configured currencies
+        #[arg(long, default_value = "usd")]
+        currency: String,
+        /// Only include records newer than this, e.g. 30d
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Write a new key for the encrypted storage backend
+    Keygen {
+        /// Where to write it; an existing file is never replaced
+        #[arg(long, default_value = "tracker.key")]
+        output: PathBuf,
+    },
+    /// Check the hash chain of each asset's history for edits and deletions
+    Verify {
+        /// Only this asset id instead of every tracked one
+        asset: Option<String>,
+    },
+    /// Render a price chart to PNG or SVG (chosen by the output extension)
+    Chart {
+        asset: String,
+        /// Output file, e.g. bitcoin.png or bitcoin.svg
+        #[arg(long, short)]
+        output: PathBuf,
+        /// Only include records newer than this, e.g. 24h or 30d
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Only include records older than this, e.g. 7d
+        #[arg(long, value_parser = parse_duration)]
+        until: Option<Duration>,
+        #[arg(long, value_enum, default_value = "line")]
+        style: ChartStyle,
+        /// Overlay a simple moving average over this many records (repeatable)
+        #[arg(long)]
+        sma: Vec<usize>,
+        /// Candle width for --style candles
+        #[arg(long, value_parser = parse_duration, default_value = "1h")]
+        candle: Duration,
+        /// Show prices in constant dollars by the CPI, see [inflation]
+        #[arg(long)]
+        real: bool,
+        #[arg(long, default_value_t = 1200)]
+        width: u32,
+        #[arg(long, default_value_t = 600)]
+        height: u32,
+    },
+    /// Write a self-contained HTML report covering every tracked asset
+    Report {
+        /// Cover the last 24 hours (default)
+        #[arg(long, group = "period")]
+        daily: bool,
+        /// Cover the last 7 days
+        #[arg(long, group = "period")]
+        weekly: bool,
+        /// Cover a custom period, e.g. 30d
+        #[arg(long, group = "period", value_parser = parse_duration)]
+        since: Option<Duration>,
+        #[arg(long, short, default_value = "report.html")]
+        output: PathBuf,
+        /// Show prices in constant dollars by the CPI, see [inflation]
+        #[arg(long)]
+        real: bool,
+    },
+    /// Show the log of fired alerts
+    Alerts {
+        /// Only show alerts from this rule
+        #[arg(long)]
+        rule: Option<String>,
+        /// Only show alerts newer than this, e.g. 24h or 7d
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+    },
+    /// Send the configured email digest right away
+    Digest {
+        /// Print the digest instead of emailing it
+        #[arg(long)]
+        print: bool,
+    },
+    /// List an equity's dividends and its total return with them reinvested
+    Dividends {
+        asset: String,
+        /// Only cover records newer than this, e.g. 365d
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Fetch the dividends again instead of using the cached list
+        #[arg(long)]
+        refresh: bool,
+        /// Write the price and total return series to this CSV file
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Replay an asset's history through a strategy from [strategies.<name>]
+    Backtest {
+        asset: String,
+        #[arg(long)]
+        strategy: String,
+        /// Only replay records newer than this, e.g. 90d
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Starting balance in USD
+        #[arg(long, default_value_t = 10_000.0)]
+        cash: f64,
+        /// Fee per trade in percent
+        #[arg(long, default_value_t = 0.1)]
+        fee: f64,
+    },
+    /// Compare each asset's returns with a benchmark's over several windows
+    Performance {
+        /// Asset to compare against, instead of [benchmark] asset
+        #[arg(long)]
+        benchmark: Option<String>,
+        /// Window to compare over, e.g. 7d (repeatable; default 1d, 7d, 30d and 365d)
+        #[arg(long)]
+        window: Vec<String>,
+    },
+    /// Compare two assets' prices, normalized to 100 at the start, and their correlation
+    Compare {
+        first: String,
+        second: String,
+        /// Period to compare over, e.g. 7d or 365d
+        #[arg(long, value_parser = parse_duration, default_value = "30d")]
+        since: Duration,
+        /// Rows of the normalized series to print
+        #[arg(long, default_value_t = 10)]
+        rows: usize,
+    },
+    /// Show the paper trading fills, open orders and profit and loss
+    Paper,
+    /// Find the ids of coins and tickers to track
+    Search {
+        /// Name, symbol or id, e.g. btc or "s&p"
+        query: String,
+        /// Download the CoinGecko coin list again instead of using the cached copy
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Print each new record as the tracker stores it, like tail -f
+    Follow {
+        /// Asset to follow (repeatable; default every tracked asset)
+        #[arg(long)]
+        asset: Vec<String>,
+        /// Print each record as a line of JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run SQL over the stored history, whatever the storage backend
+    Query {
+        /// SQLite's dialect plus date_trunc, e.g. "SELECT date_trunc('day', ts), avg(price) FROM bitcoin GROUP BY 1"
+        ///
+        /// Each asset is a view of ts, price, volume and market_cap; `prices` has them all with an asset column.
+        sql: String,
+        /// Print each row as a line of JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fetch the current price of any coin or ticker once, without storing it
+    Quote {
+        /// Tracked asset, CoinGecko coin id or Yahoo Finance symbol, e.g. bitcoin or AAPL
+        symbol: String,
+        /// Print the quote as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Serve the stored history to Grafana's JSON datasource plugin
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3003")]
+        listen: String,
+    },
+    /// Show today's HTTP requests per host against the configured quotas
+    Usage,
+    /// Show each provider's recent error rate, latency and last success
+    Status {
+        /// Period to count fetches over, e.g. 1h or 7d
+        #[arg(long, value_parser = parse_duration, default_value = "24h")]
+        since: Duration,
+        /// Print the status, with each asset's last price, as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Explore the stored prices interactively: price btc, stats eth 24h, alert add btc > 70000
+    Repl,
+    /// Manage the watchlist of tracked assets
+    Asset {
+        #[command(subcommand)]
+        action: AssetAction,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print shell completions or the man page
+    Completions {
+        #[arg(value_enum)]
+        target: CompletionTarget,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Report every problem in the config without starting the tracker
+    Check,
+}
+
+#[derive(Subcommand)]
+pub enum AssetAction {
+    /// Start tracking an asset: bitcoin, ethereum, sp500 or any CoinGecko coin id
+    Add {
+        id: String,
+        /// Display name, skipping the CoinGecko lookup
+        #[arg(long)]
+        name: Option<String>,
+        /// Short code used for series priced in this asset, e.g. sol
+        #[arg(long)]
+        symbol: Option<String>,
+    },
+    /// Stop tracking an asset (its history is kept)
+    Remove { id: String },
+    /// List the tracked assets
+    List,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChartStyle {
+    Line,
+    Candles,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Beancount,
+    // Also read by ledger.
+    Hledger,
+    // CSV for GnuCash's price importer.
+    Gnucash,
+    // Every record rather than one per day; needs --output.
+    Xlsx,
+}
+
+// The man page is roff, for e.g. `man -l -`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionTarget {
+    Bash,
+    Zsh,
+    Fish,
+    Man,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HistoryFormat {
+    Csv,
+    Binary,
+}
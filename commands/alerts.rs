@@ -0,0 +1,32 @@
+use chrono::{Duration, Local};
+
+use crate::config;
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::PriceError;
+
+pub fn run(storage: &dyn Storage, rule: Option<&str>, since: Option<Duration>) -> Result<(), PriceError> {
+    let cutoff = since.map(|since| config::before(Local::now().naive_local(), since)).transpose()?;
+    let events: Vec<_> = storage
+        .alert_log()?
+        .into_iter()
+        .filter(|event| rule.is_none_or(|rule| event.rule == rule))
+        .filter(|event| cutoff.is_none_or(|cutoff| event.timestamp >= cutoff))
+        .collect();
+
+    if events.is_empty() {
+        println!("No alerts recorded");
+        return Ok(());
+    }
+    for event in &events {
+        println!(
+            "[{}] {} ({}, value {:.2}): {} [{}]",
+            event.timestamp.format(TIMESTAMP_FORMAT),
+            event.rule,
+            event.asset,
+            event.value,
+            event.message,
+            event.outcome
+        );
+    }
+    Ok(())
+}
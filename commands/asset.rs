@@ -0,0 +1,80 @@
+use crate::cli::AssetAction;
+use crate::config::{env, Config};
+use crate::storage::valid_currency;
+use crate::registry::Registry;
+use crate::watchlist::{WatchedAsset, Watchlist, BUILT_IN};
+use crate::http::Client;
+use crate::sources::coingecko;
+use crate::PriceError;
+
+pub fn run(action: AssetAction, config: &Config, client: &Client) -> Result<(), PriceError> {
+    let path = config.watchlist_path();
+    let mut watchlist = Watchlist::load(path)?;
+    if config.asset_list.is_some() && !matches!(action, AssetAction::List) {
+        eprintln!("Warning: {} is set and overrides the watchlist file", env::ASSETS_VARIABLE);
+    }
+
+    match action {
+        AssetAction::Add { id, name, symbol } => {
+            let id = id.trim().to_ascii_lowercase();
+            if watchlist.contains(&id) {
+                println!("{} is already tracked", id);
+                return Ok(());
+            }
+            let asset = if BUILT_IN.contains(&id.as_str()) {
+                WatchedAsset::bare(&id)
+            } else {
+                let (name, symbol) = match (name, symbol) {
+                    (Some(name), symbol) => (name, symbol.unwrap_or_else(|| id.clone())),
+                    (None, symbol) => {
+                        let (name, found) = coingecko::lookup(client, &id)?;
+                        (name, symbol.unwrap_or(found))
+                    }
+                };
+                let symbol = symbol.to_ascii_lowercase();
+                if !valid_currency(&symbol) {
+                    return Err(PriceError::ParseError(format!("invalid symbol '{}'", symbol)));
+                }
+                WatchedAsset { id: id.clone(), name: Some(name), symbol: Some(symbol) }
+            };
+            watchlist.assets.push(asset);
+            watchlist.save(path)?;
+            println!("Now tracking {} ({})", id, path.display());
+        }
+        AssetAction::Remove { id } => {
+            let id = id.trim().to_ascii_lowercase();
+            if !watchlist.remove(&id) {
+                return Err(PriceError::ParseError(format!("{} is not in the watchlist", id)));
+            }
+            if config.denominations.contains(&id) || config.alerts.iter().any(|rule| rule.asset == id) {
+                eprintln!("Warning: {} is still referenced by denominations or alert rules in the config", id);
+            }
+            watchlist.save(path)?;
+            println!("Stopped tracking {} (its history is kept)", id);
+        }
+        AssetAction::List => {
+            let registry = Registry::load(config)?;
+            if registry.assets.is_empty() {
+                println!("The watchlist is empty");
+            }
+            for (i, info) in registry.assets.iter().enumerate() {
+                if i == registry.watched {
+                    let count = config.top.as_ref().map_or(0, |top| top.count);
+                    println!("\nAlso tracked from the top {} coins by market cap:", count);
+                }
+                println!(
+                    "{:<16} {} ({}), {}, {}:{}, {} to {} decimals",
+                    info.id,
+                    info.name,
+                    info.symbol,
+                    info.category,
+                    info.provider,
+                    info.provider_id,
+                    info.quote_currency.to_uppercase(),
+                    info.precision
+                );
+            }
+        }
+    }
+    Ok(())
+}
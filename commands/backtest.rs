@@ -0,0 +1,55 @@
+use chrono::{Duration, Local};
+
+use crate::backtest;
+use crate::strategy::Signal;
+use crate::config::{self, Config};
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+pub fn run(
+    asset: &dyn Pricing,
+    storage: &dyn Storage,
+    config: &Config,
+    strategy: &str,
+    since: Option<Duration>,
+    cash: f64,
+    fee: f64,
+) -> Result<(), PriceError> {
+    let settings = config.strategies.get(strategy).ok_or_else(|| {
+        let known: Vec<&str> = config.strategies.keys().map(String::as_str).collect();
+        PriceError::ParseError(format!("unknown strategy '{}', expected one of: {}", strategy, known.join(", ")))
+    })?;
+    let from = since.map(|since| config::before(Local::now().naive_local(), since)).transpose()?;
+    let outcome = backtest::run(strategy, settings, storage.scan(asset.id(), from)?, cash, fee)?;
+
+    for trade in &outcome.trades {
+        let verb = if trade.signal == Signal::Buy { "buy " } else { "sell" };
+        println!(
+            "[{}] {} {:.6} at {:.*}, equity {:.2}",
+            trade.timestamp.format(TIMESTAMP_FORMAT),
+            verb,
+            trade.amount,
+            asset.precision(),
+            trade.price,
+            trade.equity
+        );
+    }
+    let sells = outcome.trades.iter().filter(|trade| trade.signal == Signal::Sell).count();
+    println!(
+        "{} on {}: {} trades, {} of {} sells at a profit",
+        strategy,
+        asset.name(),
+        outcome.trades.len(),
+        outcome.wins,
+        sells
+    );
+    println!(
+        "Return {:+.2}% ({:.2} to {:.2}), buy and hold {:+.2}%, max drawdown {:.2}%",
+        outcome.total_return(),
+        outcome.start,
+        outcome.end,
+        outcome.hold_return,
+        outcome.max_drawdown
+    );
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::path::Path;
+
+use chrono::{Duration, NaiveDateTime};
+use plotters::coord::types::RangedDateTime;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::analytics::{self, Candle};
+use crate::cli::ChartStyle;
+use crate::inflation::Deflator;
+use crate::storage::{PriceRecord, Storage};
+use crate::theme::{Rgb, Theme};
+use crate::{PriceError, Pricing};
+
+const SMA_COLORS: [RGBColor; 4] = [RGBColor(230, 120, 20), RGBColor(40, 160, 60), RGBColor(150, 60, 200), RGBColor(200, 40, 40)];
+
+// A couple of points per pixel column keep the shape of any history.
+const POINTS_PER_PIXEL: usize = 2;
+
+pub struct ChartOptions {
+    pub style: ChartStyle,
+    pub sma: Vec<usize>,
+    pub candle: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub theme: Theme,
+}
+
+struct Point {
+    timestamp: NaiveDateTime,
+    price: f64,
+    // One per SMA period, over every record up to this one.
+    sma: Vec<Option<f64>>,
+}
+
+// What a chart draws, built from records streamed past in time order so
+// memory stays flat however long the history is. Of each run of `stride`
+// records only the lowest and highest are kept as points, and the runs
+// double in length whenever the points outgrow the chart's width.
+pub struct Series {
+    points: Vec<Point>,
+    capacity: usize,
+    stride: usize,
+    // The extremes of the run being filled, and how many records it has.
+    run: Vec<Point>,
+    run_length: usize,
+    averages: Vec<analytics::MovingAverage>,
+    candles: Vec<Candle>,
+    candle: Option<Duration>,
+    pub count: usize,
+    range: Option<(NaiveDateTime, NaiveDateTime)>,
+    low: f64,
+    high: f64,
+}
+
+impl Series {
+    pub fn new(options: &ChartOptions) -> Self {
+        Series {
+            points: Vec::new(),
+            capacity: (options.width as usize * POINTS_PER_PIXEL).max(4),
+            stride: 1,
+            run: Vec::new(),
+            run_length: 0,
+            averages: options.sma.iter().map(|period| analytics::MovingAverage::new(*period)).collect(),
+            candles: Vec::new(),
+            candle: (options.style == ChartStyle::Candles).then_some(options.candle),
+            count: 0,
+            range: None,
+            low: f64::INFINITY,
+            high: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn push(&mut self, record: &PriceRecord) {
+        self.count += 1;
+        let start = self.range.map_or(record.timestamp, |(start, _)| start);
+        self.range = Some((start, record.timestamp));
+        self.low = self.low.min(record.price);
+        self.high = self.high.max(record.price);
+        if let Some(width) = self.candle {
+            analytics::push_candle(&mut self.candles, record, width);
+        }
+
+        let sma = self.averages.iter_mut().map(|average| average.push(record.price)).collect();
+        self.run.push(Point { timestamp: record.timestamp, price: record.price, sma });
+        if self.run.len() > 2 {
+            self.run = extremes(std::mem::take(&mut self.run));
+        }
+        self.run_length += 1;
+        if self.run_length == self.stride {
+            self.end_run();
+        }
+    }
+
+    fn end_run(&mut self) {
+        self.points.append(&mut self.run);
+        self.run_length = 0;
+        if self.points.len() > self.capacity {
+            let mut points = Vec::with_capacity(self.capacity);
+            let mut old = std::mem::take(&mut self.points).into_iter().peekable();
+            while old.peek().is_some() {
+                points.extend(extremes(old.by_ref().take(4).collect()));
+            }
+            self.points = points;
+            self.stride *= 2;
+        }
+    }
+}
+
+// The lowest and highest of `points`, in time order.
+fn extremes(points: Vec<Point>) -> Vec<Point> {
+    let by_price = |a: &(usize, &Point), b: &(usize, &Point)| a.1.price.total_cmp(&b.1.price);
+    let low = points.iter().enumerate().min_by(by_price).map_or(0, |(index, _)| index);
+    let high = points.iter().enumerate().max_by(by_price).map_or(0, |(index, _)| index);
+    points.into_iter().enumerate().filter(|(index, _)| *index == low || *index == high).map(|(_, p)| p).collect()
+}
+
+pub fn run(
+    asset: &dyn Pricing,
+    storage: &dyn Storage,
+    since: Option<Duration>,
+    until: Option<Duration>,
+    real: Option<&Deflator>,
+    output: &Path,
+    options: &ChartOptions,
+) -> Result<(), PriceError> {
+    let window = analytics::Window::new(since, until)?;
+    let mut series = Series::new(options);
+    for record in storage.scan(asset.id(), window.start())? {
+        let record = record?;
+        if !window.contains(&record) {
+            continue;
+        }
+        // Records from before the CPI series starts are left out.
+        let record = match real.map(|deflator| deflator.deflate(&record)) {
+            Some(Some(real)) => real,
+            Some(None) => continue,
+            None => record,
+        };
+        series.push(&record);
+    }
+    if series.count < 2 {
+        return Err(PriceError::ParseError(format!(
+            "not enough {} records in the selected range to draw a chart",
+            asset.name()
+        )));
+    }
+
+    let title = match real {
+        Some(deflator) => format!("{} in {}", asset.name(), deflator.label()),
+        None => asset.name().to_string(),
+    };
+    let size = (options.width, options.height);
+    let is_svg = output
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("svg"))
+        .unwrap_or(false);
+    if is_svg {
+        draw(SVGBackend::new(output, size).into_drawing_area(), &title, &series, options)?;
+    } else {
+        draw(BitMapBackend::new(output, size).into_drawing_area(), &title, &series, options)?;
+    }
+
+    println!("Wrote {} chart of {} records to {}", asset.name(), series.count, output.display());
+    Ok(())
+}
+
+pub fn render_svg(title: &str, series: &Series, options: &ChartOptions) -> Result<String, PriceError> {
+    let mut svg = String::new();
+    draw(
+        SVGBackend::with_string(&mut svg, (options.width, options.height)).into_drawing_area(),
+        title,
+        series,
+        options,
+    )?;
+    Ok(svg)
+}
+
+// Needs at least two records in `series`.
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    series: &Series,
+    options: &ChartOptions,
+) -> Result<(), PriceError> {
+    let chart_error = |e: DrawingAreaErrorKind<DB::ErrorType>| PriceError::FileError(e.to_string());
+
+    let (start, end) = series.range.unwrap_or_default();
+    let (min, max) = (series.low, series.high);
+    let pad = ((max - min) * 0.05).max(max * 0.001);
+
+    let color = |rgb: Rgb| RGBColor(rgb.0, rgb.1, rgb.2);
+    let theme = &options.theme;
+    let (background, text) = (color(theme.background), color(theme.text));
+    root.fill(&background).map_err(chart_error)?;
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, ("sans-serif", 24).into_font().color(&text))
+        .margin(12)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(RangedDateTime::from(start..end), (min - pad)..(max + pad))
+        .map_err(chart_error)?;
+
+    chart
+        .configure_mesh()
+        .axis_style(text)
+        .label_style(("sans-serif", 12).into_font().color(&text))
+        .bold_line_style(color(theme.grid))
+        .light_line_style(color(theme.grid).mix(0.4))
+        .x_labels(8)
+        .x_label_formatter(&|t| t.format("%m-%d %H:%M").to_string())
+        .y_label_formatter(&|p| format!("{:.2}", p))
+        .draw()
+        .map_err(chart_error)?;
+
+    // With the run still being filled.
+    let points: Vec<&Point> = series.points.iter().chain(&series.run).collect();
+    match options.style {
+        ChartStyle::Line => {
+            let accent = color(theme.accent);
+            chart
+                .draw_series(LineSeries::new(points.iter().map(|p| (p.timestamp, p.price)), &accent))
+                .map_err(chart_error)?
+                .label("price")
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], accent));
+        }
+        ChartStyle::Candles => {
+            let candles = &series.candles;
+            let plot_width = options.width.saturating_sub(100);
+            let body = (plot_width / candles.len().max(1) as u32).clamp(1, 15);
+            let (up, down) = (color(theme.up), color(theme.down));
+            chart
+                .draw_series(candles.iter().map(|c| {
+                    CandleStick::new(c.start, c.open, c.high, c.low, c.close, up.filled(), down.filled(), body)
+                }))
+                .map_err(chart_error)?;
+        }
+    }
+
+    for (index, period) in options.sma.iter().enumerate() {
+        let color = SMA_COLORS[index % SMA_COLORS.len()];
+        let averages = points.iter().filter_map(|p| p.sma[index].map(|average| (p.timestamp, average)));
+        chart
+            .draw_series(LineSeries::new(averages, &color))
+            .map_err(chart_error)?
+            .label(format!("SMA({})", period))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    if options.style == ChartStyle::Line || !options.sma.is_empty() {
+        chart
+            .configure_series_labels()
+            .background_style(background.mix(0.8))
+            .border_style(text)
+            .label_font(("sans-serif", 12).into_font().color(&text))
+            .draw()
+            .map_err(chart_error)?;
+    }
+
+    root.present().map_err(chart_error)
+}
@@ -0,0 +1,132 @@
+// Two assets over the same period: both priced at the same instants, the
+// last price at or before each, from where both histories start to where
+// both end, and indexed to 100 at the start so they read as one scale.
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::analytics::{self, Window};
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::{PriceError, Pricing};
+
+// Instants sampled between the start and the end, for the correlation of
+// the returns between them; the rows printed are a selection of them.
+const SAMPLES: usize = 200;
+
+pub fn run(
+    first: &dyn Pricing,
+    second: &dyn Pricing,
+    storage: &dyn Storage,
+    since: Duration,
+    rows: usize,
+    config: &Config,
+) -> Result<(), PriceError> {
+    if !(2..=SAMPLES + 1).contains(&rows) {
+        return Err(PriceError::ParseError(format!("--rows must be between 2 and {}", SAMPLES + 1)));
+    }
+    let (format, theme) = (NumberFormat::from_config(config)?, Theme::from_config(config)?);
+    let window = Window::new(Some(since), None)?;
+    let (first_start, first_end) = range(storage, first, window)?;
+    let (second_start, second_end) = range(storage, second, window)?;
+    let (start, end) = (first_start.max(second_start), first_end.min(second_end));
+    if start >= end {
+        return Err(PriceError::ParseError(format!(
+            "{} and {} have no prices over the same part of the period",
+            first.name(),
+            second.name()
+        )));
+    }
+    let span = (end - start).num_milliseconds();
+    let times: Vec<NaiveDateTime> =
+        (0..=SAMPLES).map(|i| start + Duration::milliseconds(span * i as i64 / SAMPLES as i64)).collect();
+    let a = normalized(first, &sample(storage, first, window, &times)?)?;
+    let b = normalized(second, &sample(storage, second, window, &times)?)?;
+
+    println!(
+        "{} and {} from {} to {}, normalized to 100:",
+        first.name(),
+        second.name(),
+        start.format(TIMESTAMP_FORMAT),
+        end.format(TIMESTAMP_FORMAT)
+    );
+    let (a_width, b_width) = (first.name().len().max(8), second.name().len().max(8));
+    println!("{:<19}  {:>a_width$}  {:>b_width$}", "Time", first.name(), second.name());
+    for row in 0..rows {
+        let i = (row * SAMPLES + (rows - 1) / 2) / (rows - 1);
+        println!(
+            "{}  {:>a_width$}  {:>b_width$}",
+            times[i].format(TIMESTAMP_FORMAT),
+            format.number(a[i], 2),
+            format.number(b[i], 2)
+        );
+    }
+
+    let (a_change, b_change) = (a[SAMPLES] - 100.0, b[SAMPLES] - 100.0);
+    let percent = |change: f64| theme.change(change, &format!("{}%", format.signed(change, 2)));
+    println!("Change: {} {}, {} {}", first.name(), percent(a_change), second.name(), percent(b_change));
+    let leader = if a_change >= b_change { first } else { second };
+    println!(
+        "Relative performance of {} to {}: {} ({} ahead by {} percentage points)",
+        first.name(),
+        second.name(),
+        format.number(a[SAMPLES] / b[SAMPLES] * 100.0, 2),
+        leader.name(),
+        format.number((a_change - b_change).abs(), 2)
+    );
+    match analytics::correlation(&analytics::returns(&a), &analytics::returns(&b)) {
+        Some(correlation) => {
+            println!("Correlation of returns: {} over {} intervals", format.number(correlation, 2), SAMPLES)
+        }
+        None => println!("Correlation of returns: n/a, at least one of them never moved"),
+    }
+    Ok(())
+}
+
+// When the asset's first and last records in the window are from.
+fn range(
+    storage: &dyn Storage,
+    asset: &dyn Pricing,
+    window: Window,
+) -> Result<(NaiveDateTime, NaiveDateTime), PriceError> {
+    let mut range = None;
+    for record in storage.scan(asset.id(), window.start())? {
+        let record = record?;
+        if window.contains(&record) {
+            let (first, _) = range.unwrap_or((record.timestamp, record.timestamp));
+            range = Some((first, record.timestamp));
+        }
+    }
+    range.ok_or_else(|| PriceError::ParseError(format!("no prices stored for {} in the period", asset.name())))
+}
+
+// The last price at or before each of `times`, which are in order.
+fn sample(
+    storage: &dyn Storage,
+    asset: &dyn Pricing,
+    window: Window,
+    times: &[NaiveDateTime],
+) -> Result<Vec<Option<f64>>, PriceError> {
+    let mut prices = Vec::with_capacity(times.len());
+    let mut last = None;
+    for record in storage.scan(asset.id(), window.start())? {
+        let record = record?;
+        if !window.contains(&record) {
+            continue;
+        }
+        while prices.len() < times.len() && times[prices.len()] < record.timestamp {
+            prices.push(last);
+        }
+        last = Some(record.price);
+    }
+    prices.resize(times.len(), last);
+    Ok(prices)
+}
+
+fn normalized(asset: &dyn Pricing, prices: &[Option<f64>]) -> Result<Vec<f64>, PriceError> {
+    let base = prices.first().copied().flatten().filter(|base| *base > 0.0);
+    let missing = || PriceError::ParseError(format!("{} has no positive price at the start", asset.name()));
+    let base = base.ok_or_else(missing)?;
+    Ok(prices.iter().map(|price| price.unwrap_or(base) / base * 100.0).collect())
+}
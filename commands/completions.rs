@@ -0,0 +1,277 @@
+// Shell completions and the man page, generated from the clap definition in
+// cli.rs so a new command or flag shows up in them without anything else to
+// update.
+
+use clap::builder::PossibleValue;
+use clap::{Arg, ArgAction, Command, CommandFactory, ValueHint};
+
+use crate::cli::{Cli, CompletionTarget};
+use crate::PriceError;
+
+pub fn run(target: CompletionTarget) -> Result<(), PriceError> {
+    let mut cli = Cli::command();
+    cli.build();
+    let output = match target {
+        CompletionTarget::Bash => bash(&cli),
+        CompletionTarget::Zsh => zsh(&cli),
+        CompletionTarget::Fish => fish(&cli),
+        CompletionTarget::Man => man(&cli),
+    };
+    print!("{}", output);
+    Ok(())
+}
+
+// Each command with the names leading to it, the top level first.
+fn walk(command: &Command) -> Vec<(Vec<&str>, &Command)> {
+    fn visit<'a>(path: Vec<&'a str>, command: &'a Command, out: &mut Vec<(Vec<&'a str>, &'a Command)>) {
+        out.push((path.clone(), command));
+        for sub in subcommands(command) {
+            let mut next = path.clone();
+            next.push(sub.get_name());
+            visit(next, sub, out);
+        }
+    }
+    let mut out = Vec::new();
+    visit(Vec::new(), command, &mut out);
+    out
+}
+
+fn subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    command.get_subcommands().filter(|sub| !sub.is_hide_set() && sub.get_name() != "help")
+}
+
+fn options(command: &Command) -> impl Iterator<Item = &Arg> {
+    command.get_arguments().filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values() && arg.get_num_args().is_none_or(|range| range.max_values() > 0)
+}
+
+fn values(arg: &Arg) -> Vec<String> {
+    let values = arg.get_possible_values();
+    values.iter().filter(|value| !value.is_hide_set()).map(PossibleValue::get_name).map(String::from).collect()
+}
+
+fn is_path(arg: &Arg) -> bool {
+    matches!(arg.get_value_hint(), ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath)
+}
+
+// The first line of the help, which is all the completions show.
+fn help(arg: &Arg) -> String {
+    let help = arg.get_help().map(|help| help.to_string()).unwrap_or_default();
+    help.lines().next().unwrap_or_default().to_string()
+}
+
+fn about(command: &Command) -> String {
+    command.get_about().map(|about| about.to_string()).unwrap_or_default()
+}
+
+fn flags(arg: &Arg) -> Vec<String> {
+    let short = arg.get_short().map(|short| format!("-{}", short));
+    let long = arg.get_long().map(|long| format!("--{}", long));
+    short.into_iter().chain(long).collect()
+}
+
+fn bash(cli: &Command) -> String {
+    let name = cli.get_name();
+    let function = format!("_{}", name);
+    let commands = walk(cli);
+    let paths: Vec<String> = commands.iter().skip(1).map(|(path, _)| format!("/{}", path.join("/"))).collect();
+
+    let mut out = format!("{}() {{\n", function);
+    out.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\" path=\"\" i\n");
+    out.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    out.push_str("        case \"$path/${COMP_WORDS[i]}\" in\n");
+    out.push_str(&format!("            {}) path=\"$path/${{COMP_WORDS[i]}}\" ;;\n", paths.join("|")));
+    out.push_str("        esac\n    done\n    case \"$path\" in\n");
+    for (path, command) in &commands {
+        let pattern = if path.is_empty() { "\"\"".to_string() } else { format!("/{}", path.join("/")) };
+        out.push_str(&format!("        {})\n            case \"$prev\" in\n", pattern));
+        for arg in options(command).filter(|arg| takes_value(arg)) {
+            let reply = match values(arg) {
+                values if !values.is_empty() => format!("($(compgen -W \"{}\" -- \"$cur\"))", values.join(" ")),
+                _ if is_path(arg) => "($(compgen -f -- \"$cur\"))".to_string(),
+                _ => "()".to_string(),
+            };
+            out.push_str(&format!("                {}) COMPREPLY={}; return ;;\n", flags(arg).join("|"), reply));
+        }
+        out.push_str("            esac\n");
+        let words: Vec<String> = options(command)
+            .flat_map(flags)
+            .chain(subcommands(command).map(|sub| sub.get_name().to_string()))
+            .collect();
+        let files = command.get_positionals().any(is_path);
+        out.push_str(&format!("            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n", words.join(" ")));
+        if files {
+            out.push_str("            COMPREPLY+=($(compgen -f -- \"$cur\"))\n");
+        }
+        out.push_str("            ;;\n");
+    }
+    out.push_str("    esac\n}\n");
+    out.push_str(&format!("complete -F {} {}\n", function, name));
+    out
+}
+
+fn zsh(cli: &Command) -> String {
+    // Inside single quotes, with the characters _arguments treats specially
+    // escaped.
+    fn quote(text: &str) -> String {
+        text.replace('\'', "'\\''").replace('[', "\\[").replace(']', "\\]").replace(':', "\\:")
+    }
+    let name = cli.get_name();
+    let mut out = format!("#compdef {}\n", name);
+    for (path, command) in walk(cli) {
+        let function = std::iter::once(name).chain(path.iter().copied()).collect::<Vec<_>>().join("__");
+        out.push_str(&format!("\n_{}() {{\n    local line state\n    _arguments -C -s \\\n", function));
+        for arg in options(command) {
+            let repeat = if matches!(arg.get_action(), ArgAction::Append | ArgAction::Count) { "*" } else { "" };
+            let value = match (takes_value(arg), values(arg)) {
+                (false, _) => String::new(),
+                (true, values) if !values.is_empty() => format!(":value:({})", values.join(" ")),
+                (true, _) if is_path(arg) => ":file:_files".to_string(),
+                (true, _) => ":value: ".to_string(),
+            };
+            for flag in flags(arg) {
+                let separator = match (value.is_empty(), flag.starts_with("--")) {
+                    (true, _) => "",
+                    (false, true) => "=",
+                    (false, false) => "+",
+                };
+                let help = quote(&help(arg));
+                out.push_str(&format!("        '{}{}{}[{}]{}' \\\n", repeat, flag, separator, help, value));
+            }
+        }
+        for (index, arg) in command.get_positionals().enumerate() {
+            let action = match values(arg) {
+                values if !values.is_empty() => format!("({})", values.join(" ")),
+                _ if is_path(arg) => "_files".to_string(),
+                _ => " ".to_string(),
+            };
+            let id = arg.get_id().as_str();
+            out.push_str(&format!("        '{}:{}:{}' \\\n", index + 1, quote(id), action));
+        }
+        let subs: Vec<&Command> = subcommands(command).collect();
+        if subs.is_empty() {
+            out.push_str("        && return\n}\n");
+            continue;
+        }
+        let listed: Vec<String> = subs
+            .iter()
+            .map(|sub| format!("{}\\:\"{}\"", sub.get_name(), quote(&about(sub)).replace('"', "\\\"")))
+            .collect();
+        out.push_str(&format!("        '1: :(({}))' \\\n        '*:: :->args'\n", listed.join(" ")));
+        out.push_str("    case $state in\n        args)\n            case $line[1] in\n");
+        for sub in subs {
+            out.push_str(&format!("                {}) _{}__{} ;;\n", sub.get_name(), function, sub.get_name()));
+        }
+        out.push_str("            esac\n            ;;\n    esac\n}\n");
+    }
+    out.push_str(&format!("\n_{} \"$@\"\n", name));
+    out
+}
+
+fn fish(cli: &Command) -> String {
+    fn quote(text: &str) -> String {
+        format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+    let name = cli.get_name();
+    let mut out = String::new();
+    for (path, command) in walk(cli) {
+        let seen: Vec<String> = path.iter().map(|sub| format!("__fish_seen_subcommand_from {}", sub)).collect();
+        let condition = if path.is_empty() { "__fish_use_subcommand".to_string() } else { seen.join("; and ") };
+        for arg in options(command) {
+            let mut line = format!("complete -c {} -n {}", name, quote(&condition));
+            if let Some(short) = arg.get_short() {
+                line.push_str(&format!(" -s {}", short));
+            }
+            if let Some(long) = arg.get_long() {
+                line.push_str(&format!(" -l {}", long));
+            }
+            if takes_value(arg) {
+                match values(arg) {
+                    values if !values.is_empty() => line.push_str(&format!(" -x -a {}", quote(&values.join(" ")))),
+                    _ if is_path(arg) => line.push_str(" -r -F"),
+                    _ => line.push_str(" -x"),
+                }
+            }
+            line.push_str(&format!(" -d {}\n", quote(&help(arg))));
+            out.push_str(&line);
+        }
+        let subs: Vec<&Command> = subcommands(command).collect();
+        let names: Vec<&str> = subs.iter().map(|sub| sub.get_name()).collect();
+        let listing = match path.is_empty() {
+            true => condition.clone(),
+            false => format!("{}; and not __fish_seen_subcommand_from {}", condition, names.join(" ")),
+        };
+        for sub in subs {
+            out.push_str(&format!(
+                "complete -c {} -n {} -f -a {} -d {}\n",
+                name,
+                quote(&listing),
+                sub.get_name(),
+                quote(&about(sub))
+            ));
+        }
+    }
+    out
+}
+
+fn man(cli: &Command) -> String {
+    // Escaped for roff, with a line that would start with a control
+    // character guarded.
+    fn roff(text: &str) -> String {
+        text.lines()
+            .map(|line| {
+                let line = line.replace('\\', "\\\\").replace('-', "\\-");
+                if line.starts_with('.') || line.starts_with('\'') {
+                    format!("\\&{}", line)
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    fn describe(arg: &Arg) -> String {
+        let mut flags = flags(arg).iter().map(|flag| format!("\\fB{}\\fR", roff(flag))).collect::<Vec<_>>().join(", ");
+        if arg.is_positional() || takes_value(arg) {
+            let value = arg.get_value_names().and_then(|names| names.first()).map(|name| name.to_string());
+            let value = value.unwrap_or_else(|| arg.get_id().as_str().to_uppercase());
+            let value = format!("\\fI<{}>\\fR", roff(&value));
+            flags = if flags.is_empty() { value } else { format!("{} {}", flags, value) };
+        }
+        let mut text = roff(&arg.get_help().map(|help| help.to_string()).unwrap_or_default());
+        let values = values(arg);
+        if !values.is_empty() {
+            text.push_str(&format!("\n.br\nOne of: {}", roff(&values.join(", "))));
+        }
+        format!(".TP\n{}\n{}\n", flags, text)
+    }
+
+    let name = cli.get_name();
+    let version = cli.get_version().unwrap_or_default();
+    let mut out = format!(".TH {} 1 \"\" \"{} {}\"\n", roff(&name.to_uppercase()), roff(name), roff(version));
+    out.push_str(&format!(".SH NAME\n{} \\- {}\n", roff(name), roff(&about(cli))));
+    out.push_str(&format!(".SH SYNOPSIS\n\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fICOMMAND\\fR]\n", roff(name)));
+    out.push_str(".SH OPTIONS\n");
+    for arg in options(cli) {
+        out.push_str(&describe(arg));
+    }
+    out.push_str(".SH COMMANDS\n");
+    for (path, command) in walk(cli).into_iter().skip(1) {
+        let positionals: Vec<String> = command
+            .get_positionals()
+            .map(|arg| arg.get_id().as_str().to_uppercase())
+            .map(|id| format!("<{}>", id))
+            .collect();
+        let usage = path.iter().map(|word| word.to_string()).chain(positionals).collect::<Vec<_>>().join(" ");
+        out.push_str(&format!(".SS \"{} {}\"\n{}\n", roff(name), roff(&usage), roff(&about(command))));
+        // The global options are described once above.
+        let own = command.get_arguments().filter(|arg| !arg.is_global_set() && arg.get_id() != "help");
+        for arg in own.filter(|arg| !arg.is_hide_set()) {
+            out.push_str(&describe(arg));
+        }
+    }
+    out
+}
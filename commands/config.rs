@@ -0,0 +1,17 @@
+use crate::cli::ConfigAction;
+use crate::config::{check, Config};
+use crate::PriceError;
+
+pub fn run(action: ConfigAction, config: &Config) -> Result<(), PriceError> {
+    match action {
+        ConfigAction::Check => {
+            check::validate(config)?;
+            let profile = config.profile.as_ref().map(|name| format!(" (profile {})", name)).unwrap_or_default();
+            match &config.path {
+                Some(path) => println!("{}{}: OK", path.display(), profile),
+                None => println!("No config file found, the defaults{} are OK", profile),
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cli::HistoryFormat;
+use crate::storage::binary::{self, BinaryReader, BinaryStorage, Columns};
+use crate::storage::csv::{self, CsvReader, CsvStorage};
+use crate::{PriceError, Pricing};
+
+// Writes each tracked asset's history in `directory` in the `to` format next
+// to the original, which is left in place. Both are streamed, so histories
+// of any size convert in flat memory.
+pub fn run(assets: &[Box<dyn Pricing>], directory: &Path, to: HistoryFormat) -> Result<(), PriceError> {
+    let csv = CsvStorage::new(directory);
+    let binary = BinaryStorage::new(directory);
+    for asset in assets {
+        let (source, target) = match to {
+            HistoryFormat::Binary => (csv.path_for(asset.id()), binary.path_for(asset.id())),
+            HistoryFormat::Csv => (binary.path_for(asset.id()), csv.path_for(asset.id())),
+        };
+        if !source.exists() {
+            println!("{}: no history at {}, skipping", asset.name(), source.display());
+            continue;
+        }
+        if target.exists() {
+            return Err(PriceError::FileError(format!(
+                "{} already exists; move it away to convert {}",
+                target.display(),
+                source.display()
+            )));
+        }
+
+        let count = match to {
+            HistoryFormat::Binary => {
+                // The binary header needs every column up front.
+                let mut columns = Columns::default();
+                let mut count = 0;
+                for record in CsvReader::open(&source)? {
+                    columns.add(&record?);
+                    count += 1;
+                }
+                binary::write_stream(&target, &columns.into_vec(), CsvReader::open(&source)?)?;
+                count
+            }
+            HistoryFormat::Csv => {
+                let reader = BinaryReader::open(&source)?;
+                let (count, columns) = (reader.len(), reader.extra_columns());
+                csv::write_stream(&target, &columns, reader)?;
+                count
+            }
+        };
+        let size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+        println!(
+            "{}: converted {} records from {} ({} bytes) to {} ({} bytes)",
+            asset.name(),
+            count,
+            source.display(),
+            size(&source),
+            target.display(),
+            size(&target)
+        );
+    }
+    Ok(())
+}
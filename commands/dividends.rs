@@ -0,0 +1,58 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Local};
+
+use crate::config;
+use crate::dividends::TotalReturn;
+use crate::sources::yahoo::Dividend;
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+// Lists the dividends paid over the stored history and compares the price
+// return with the total return; `output` gets the total return series as CSV.
+pub fn run(
+    asset: &dyn Pricing,
+    dividends: &[Dividend],
+    storage: &dyn Storage,
+    since: Option<Duration>,
+    output: Option<&Path>,
+) -> Result<(), PriceError> {
+    let from = since.map(|since| config::before(Local::now().naive_local(), since)).transpose()?;
+    let mut total = TotalReturn::new(dividends);
+    let mut series = output.map(|_| String::from("timestamp,price,total_return\n"));
+    let (mut first, mut last) = (None, None);
+    for record in storage.scan(asset.id(), from)? {
+        let record = record?;
+        let value = total.push(&record);
+        if let Some(series) = &mut series {
+            series.push_str(&format!("{},{},{}\n", record.timestamp.format(TIMESTAMP_FORMAT), record.price, value));
+        }
+        first.get_or_insert((record.timestamp, record.price));
+        last = Some((record.timestamp, record.price, value));
+    }
+    let (Some((start, first_price)), Some((end, last_price, last_value))) = (first, last) else {
+        return Err(PriceError::ParseError(format!("no {} history", asset.name())));
+    };
+
+    let paid: Vec<&Dividend> = dividends
+        .iter()
+        .filter(|dividend| dividend.date > start.date() && dividend.date <= end.date())
+        .collect();
+    for dividend in &paid {
+        println!("{} dividend {:.4}", dividend.date, dividend.amount);
+    }
+    println!(
+        "{} since {}: {} dividends, price return {:+.2}%, total return {:+.2}%",
+        asset.name(),
+        start.format("%Y-%m-%d"),
+        paid.len(),
+        (last_price - first_price) / first_price * 100.0,
+        (last_value - first_price) / first_price * 100.0
+    );
+    if let (Some(output), Some(series)) = (output, series) {
+        fs::write(output, series).map_err(|e| PriceError::FileError(format!("{}: {}", output.display(), e)))?;
+        println!("Wrote the total return series to {}", output.display());
+    }
+    Ok(())
+}
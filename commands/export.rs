@@ -0,0 +1,206 @@
+// Writes the history as prices for accounting software, one per asset and
+// day from the day's last record:
+//
+//   2024-06-01 price BTC 67000.00 USD      beancount
+//   P 2024-06-01 BTC 67000.00 USD          hledger and ledger
+//   2024-06-01,CRYPTO,BTC,67000.00,USD     GnuCash, for File > Import > Import Prices
+//
+// or as an Excel workbook with every record, a worksheet per asset after an
+// overview of the latest values.
+//
+// GnuCash looks commodities up by namespace and symbol, which can be set
+// per asset to match the book:
+//
+//   [gnucash]
+//   namespace = "CRYPTO"
+//   commodities.sp500 = { namespace = "INDEX", symbol = "^GSPC" }
+
+mod xlsx;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::cli::ExportFormat;
+use crate::config;
+use crate::storage::csv::quote;
+use crate::storage::{PriceRecord, Storage};
+use crate::{PriceError, Pricing};
+use xlsx::{Cell, Workbook};
+
+const GNUCASH_HEADER: &str = "date,namespace,symbol,price,currency\n";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GnucashConfig {
+    // Namespace of the assets without an entry in `commodities`, under
+    // their uppercase symbol.
+    pub namespace: String,
+    // Keyed by asset id.
+    pub commodities: BTreeMap<String, Commodity>,
+}
+
+impl Default for GnucashConfig {
+    fn default() -> Self {
+        GnucashConfig { namespace: "CRYPTO".to_string(), commodities: BTreeMap::new() }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Commodity {
+    pub namespace: Option<String>,
+    pub symbol: Option<String>,
+}
+
+pub fn run(
+    assets: &[&dyn Pricing],
+    storage: &dyn Storage,
+    format: ExportFormat,
+    gnucash: Option<&GnucashConfig>,
+    currency: &str,
+    since: Option<Duration>,
+    output: Option<&Path>,
+) -> Result<(), PriceError> {
+    let from = since.map(|since| config::before(Local::now().naive_local(), since)).transpose()?;
+    if format == ExportFormat::Xlsx {
+        let output = output.ok_or_else(|| PriceError::ParseError("--format xlsx needs --output".to_string()))?;
+        return workbook(assets, storage, from).and_then(|workbook| workbook.save(output));
+    }
+    let gnucash = gnucash.cloned().unwrap_or_default();
+    let target = commodity(currency, format);
+    let mut text = match format {
+        ExportFormat::Gnucash => GNUCASH_HEADER.to_string(),
+        _ => String::new(),
+    };
+    for asset in assets {
+        let name = match format {
+            ExportFormat::Gnucash => gnucash_commodity(&gnucash, *asset),
+            _ => commodity(asset.symbol(), format),
+        };
+        let mut days: Vec<(NaiveDate, f64)> = Vec::new();
+        for record in storage.scan(asset.id(), from)? {
+            let record = record?;
+            let price = match currency {
+                "usd" => Some(record.price),
+                code => record.quotes.get(code).copied(),
+            };
+            let Some(price) = price else {
+                continue;
+            };
+            let day = record.timestamp.date();
+            match days.last_mut() {
+                Some((last, at)) if *last == day => *at = price,
+                _ => days.push((day, price)),
+            }
+        }
+        for (day, price) in days {
+            let day = day.format("%Y-%m-%d");
+            let price = format!("{:.*}", asset.precision(), price);
+            let line = match format {
+                ExportFormat::Beancount => format!("{} price {} {} {}\n", day, name, price, target),
+                ExportFormat::Hledger => format!("P {} {} {} {}\n", day, name, price, target),
+                _ => format!("{},{},{},{}\n", day, name, price, target),
+            };
+            text.push_str(&line);
+        }
+    }
+    match output {
+        Some(path) => fs::write(path, text).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e))),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+// Beancount commodities are capitals, digits and `'._-`, starting with a
+// capital; hledger takes anything but needs quotes around names that are
+// not only letters.
+fn commodity(symbol: &str, format: ExportFormat) -> String {
+    let symbol = symbol.to_uppercase();
+    match format {
+        ExportFormat::Beancount => {
+            let name: String = symbol
+                .chars()
+                .filter(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(*c))
+                .collect();
+            match name.chars().next() {
+                Some(first) if first.is_ascii_uppercase() => name,
+                _ => format!("X{}", name),
+            }
+        }
+        ExportFormat::Hledger if symbol.chars().all(|c| c.is_alphabetic()) => symbol,
+        ExportFormat::Hledger => format!("\"{}\"", symbol.replace('"', "")),
+        // Currencies are ISO codes in GnuCash; the rest is quoted as CSV.
+        _ => quote(&symbol),
+    }
+}
+
+fn workbook(
+    assets: &[&dyn Pricing],
+    storage: &dyn Storage,
+    from: Option<NaiveDateTime>,
+) -> Result<Workbook, PriceError> {
+    let mut workbook = Workbook::default();
+    let mut overview = Vec::new();
+    let mut sheets = Vec::new();
+    for asset in assets {
+        let records: Vec<PriceRecord> = storage.scan(asset.id(), from)?.collect::<Result<_, _>>()?;
+        if records.len() >= xlsx::MAX_ROWS {
+            return Err(PriceError::ParseError(format!(
+                "{} has {} records, more than a worksheet holds; export fewer with --since",
+                asset.name(),
+                records.len()
+            )));
+        }
+        let Some(last) = records.last() else {
+            continue;
+        };
+        overview.push(vec![
+            Cell::Text(asset.name().to_string()),
+            Cell::Text(asset.id().to_string()),
+            Cell::Time(last.timestamp),
+            Cell::Number(last.price),
+            last.volume.map_or(Cell::Empty, Cell::Number),
+            last.market_cap.map_or(Cell::Empty, Cell::Number),
+        ]);
+
+        let currencies: BTreeSet<&String> = records.iter().flat_map(|record| record.quotes.keys()).collect();
+        let mut header: Vec<String> = ["Timestamp", "Price (USD)", "Volume", "Market cap"].map(String::from).into();
+        header.extend(currencies.iter().map(|code| format!("Price ({})", code.to_uppercase())));
+        let rows = records
+            .iter()
+            .map(|record| {
+                let mut row = vec![
+                    Cell::Time(record.timestamp),
+                    Cell::Number(record.price),
+                    record.volume.map_or(Cell::Empty, Cell::Number),
+                    record.market_cap.map_or(Cell::Empty, Cell::Number),
+                ];
+                let quotes = currencies.iter().map(|code| record.quotes.get(*code).copied());
+                row.extend(quotes.map(|quote| quote.map_or(Cell::Empty, Cell::Number)));
+                row
+            })
+            .collect();
+        sheets.push((asset.name().to_string(), header, rows));
+    }
+    let header = ["Asset", "Id", "Last updated", "Price (USD)", "Volume", "Market cap"];
+    workbook.add_sheet("Overview", &header, overview);
+    for (name, header, rows) in sheets {
+        let header: Vec<&str> = header.iter().map(String::as_str).collect();
+        workbook.add_sheet(&name, &header, rows);
+    }
+    Ok(workbook)
+}
+
+// The namespace and symbol columns of an asset.
+fn gnucash_commodity(config: &GnucashConfig, asset: &dyn Pricing) -> String {
+    let mapped = config.commodities.get(asset.id());
+    let namespace = mapped.and_then(|commodity| commodity.namespace.clone()).unwrap_or(config.namespace.clone());
+    let symbol = mapped.and_then(|commodity| commodity.symbol.clone()).unwrap_or(asset.symbol().to_uppercase());
+    format!("{},{}", quote(&namespace), quote(&symbol))
+}
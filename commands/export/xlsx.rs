@@ -0,0 +1,281 @@
+// A minimal writer of Excel workbooks: worksheets of text, numbers and
+// timestamps with a bold, frozen header row, zipped as Office Open XML.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::PriceError;
+
+// The most rows a worksheet holds.
+pub const MAX_ROWS: usize = 1_048_576;
+
+// Style indexes into the cellXfs of STYLES.
+const TIME_STYLE: u8 = 1;
+const HEADER_STYLE: u8 = 2;
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">
+<numFmts count="1"><numFmt numFmtId="164" formatCode="yyyy-mm-dd hh:mm:ss"/></numFmts>
+<fonts count="2"><font><sz val="11"/><name val="Calibri"/></font>
+<font><b/><sz val="11"/><name val="Calibri"/></font></fonts>
+<fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills>
+<borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders>
+<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>
+<cellXfs count="3"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/>
+<xf numFmtId="164" fontId="0" fillId="0" borderId="0" xfId="0" applyNumberFormat="1"/>
+<xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs>
+</styleSheet>
+"#;
+
+const ROOT_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Target="xl/workbook.xml"
+ Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument"/>
+</Relationships>
+"#;
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Time(NaiveDateTime),
+    Empty,
+}
+
+#[derive(Default)]
+pub struct Workbook {
+    sheets: Vec<Sheet>,
+}
+
+struct Sheet {
+    name: String,
+    header: Vec<String>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Workbook {
+    // Adds a worksheet, its name made valid and unique as Excel requires.
+    pub fn add_sheet(&mut self, name: &str, header: &[&str], rows: Vec<Vec<Cell>>) {
+        let name: String = name.chars().filter(|c| !"[]:*?/\\".contains(*c)).take(31).collect();
+        let mut unique = name.clone();
+        let mut n = 2;
+        while self.sheets.iter().any(|sheet| sheet.name.eq_ignore_ascii_case(&unique)) {
+            let suffix = format!(" ({})", n);
+            unique = format!("{}{}", name.chars().take(31 - suffix.len()).collect::<String>(), suffix);
+            n += 1;
+        }
+        let header = header.iter().map(|column| column.to_string()).collect();
+        self.sheets.push(Sheet { name: unique, header, rows });
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let mut zip = Zip::default();
+        zip.add("[Content_Types].xml", &self.content_types()).map_err(error)?;
+        zip.add("_rels/.rels", ROOT_RELATIONSHIPS).map_err(error)?;
+        zip.add("xl/workbook.xml", &self.workbook()).map_err(error)?;
+        zip.add("xl/_rels/workbook.xml.rels", &self.relationships()).map_err(error)?;
+        zip.add("xl/styles.xml", STYLES).map_err(error)?;
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            zip.add(&format!("xl/worksheets/sheet{}.xml", index + 1), &sheet.xml()).map_err(error)?;
+        }
+        fs::write(path, zip.finish()).map_err(error)
+    }
+
+    fn content_types(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            "\n",
+            r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+            r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+            r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+            r#"<Override PartName="/xl/workbook.xml" "#,
+            r#"ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+            r#"<Override PartName="/xl/styles.xml" "#,
+            r#"ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+        ));
+        for index in 1..=self.sheets.len() {
+            xml.push_str(&format!(
+                concat!(
+                    r#"<Override PartName="/xl/worksheets/sheet{}.xml" "#,
+                    r#"ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#
+                ),
+                index
+            ));
+        }
+        xml.push_str("</Types>\n");
+        xml
+    }
+
+    fn workbook(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            "\n",
+            r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+            r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+        ));
+        for (index, sheet) in self.sheets.iter().enumerate() {
+            let id = index + 1;
+            xml.push_str(&format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, escape(&sheet.name), id, id));
+        }
+        xml.push_str("</sheets></workbook>\n");
+        xml
+    }
+
+    fn relationships(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            "\n",
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+        ));
+        let worksheet = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+        for index in 1..=self.sheets.len() {
+            xml.push_str(&format!(
+                r#"<Relationship Id="rId{}" Type="{}" Target="worksheets/sheet{}.xml"/>"#,
+                index, worksheet, index
+            ));
+        }
+        let styles = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+        xml.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="{}" Target="styles.xml"/>"#,
+            self.sheets.len() + 1,
+            styles
+        ));
+        xml.push_str("</Relationships>\n");
+        xml
+    }
+}
+
+impl Sheet {
+    fn xml(&self) -> String {
+        let mut xml = String::from(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            "\n",
+            r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+            r#"<sheetViews><sheetView workbookViewId="0">"#,
+            r#"<pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/>"#,
+            r#"</sheetView></sheetViews>"#,
+        ));
+        // Wide enough for a timestamp or a name in every column.
+        xml.push_str(&format!(r#"<cols><col min="1" max="{}" width="20" customWidth="1"/></cols>"#, self.header.len()));
+        xml.push_str("<sheetData>");
+        let header = self.header.iter().map(|column| Cell::Text(column.clone())).collect();
+        for (index, row) in std::iter::once(&header).chain(&self.rows).enumerate() {
+            let number = index + 1;
+            xml.push_str(&format!(r#"<row r="{}">"#, number));
+            for (column, cell) in row.iter().enumerate() {
+                let reference = format!("{}{}", column_name(column), number);
+                let style = if index == 0 { format!(r#" s="{}""#, HEADER_STYLE) } else { String::new() };
+                match cell {
+                    Cell::Text(text) => xml.push_str(&format!(
+                        r#"<c r="{}" t="inlineStr"{}><is><t>{}</t></is></c>"#,
+                        reference,
+                        style,
+                        escape(text)
+                    )),
+                    Cell::Number(value) if value.is_finite() => {
+                        xml.push_str(&format!(r#"<c r="{}"{}><v>{}</v></c>"#, reference, style, value))
+                    }
+                    Cell::Time(time) => xml.push_str(&format!(
+                        r#"<c r="{}" s="{}"><v>{}</v></c>"#,
+                        reference,
+                        TIME_STYLE,
+                        serial(*time)
+                    )),
+                    Cell::Number(_) | Cell::Empty => {}
+                }
+            }
+            xml.push_str("</row>");
+        }
+        xml.push_str("</sheetData></worksheet>\n");
+        xml
+    }
+}
+
+// A, B, ... Z, AA, AB, ...
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+// Excel stores times as days since the end of 1899.
+fn serial(time: NaiveDateTime) -> f64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default().and_hms_opt(0, 0, 0).unwrap_or_default();
+    (time - epoch).num_seconds() as f64 / 86_400.0
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// The parts of a deflated zip archive that a workbook needs.
+#[derive(Default)]
+struct Zip {
+    data: Vec<u8>,
+    directory: Vec<u8>,
+    entries: u16,
+}
+
+impl Zip {
+    fn add(&mut self, name: &str, contents: &str) -> std::io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents.as_bytes())?;
+        let compressed = encoder.finish()?;
+        let crc = crc32fast::hash(contents.as_bytes());
+        let offset = self.data.len() as u32;
+
+        // Version 2.0, UTF-8 names, deflate, 1980-01-01 00:00.
+        let mut fields = Vec::new();
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&0x0800u16.to_le_bytes());
+        fields.extend_from_slice(&8u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0x21u16.to_le_bytes());
+        fields.extend_from_slice(&crc.to_le_bytes());
+        fields.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+
+        self.data.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        self.data.extend_from_slice(&fields);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(&compressed);
+
+        self.directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        self.directory.extend_from_slice(&20u16.to_le_bytes());
+        self.directory.extend_from_slice(&fields);
+        // No comment, disk 0, no attributes.
+        self.directory.extend_from_slice(&[0; 10]);
+        self.directory.extend_from_slice(&offset.to_le_bytes());
+        self.directory.extend_from_slice(name.as_bytes());
+        self.entries += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let offset = self.data.len() as u32;
+        let size = self.directory.len() as u32;
+        self.data.append(&mut self.directory);
+        self.data.extend_from_slice(&0x06054b50u32.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&size.to_le_bytes());
+        self.data.extend_from_slice(&offset.to_le_bytes());
+        self.data.extend_from_slice(&0u16.to_le_bytes());
+        self.data
+    }
+}
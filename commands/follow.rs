@@ -0,0 +1,72 @@
+// Prints records as they are stored, like `tail -f`, by polling the storage
+// for ones newer than the last printed. It needs nothing from the tracker,
+// so it follows any backend, whether the tracker writing it runs here or
+// the history is synced in from elsewhere.
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+
+use crate::commands::quote;
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::{format_quotes, PriceError, Pricing};
+
+// Often enough to look live next to a tracker fetching every few seconds.
+const POLL: Duration = Duration::from_secs(1);
+
+pub fn run(assets: &[&dyn Pricing], storage: &dyn Storage, json: bool, config: &Config) -> Result<(), PriceError> {
+    let (format, theme) = (NumberFormat::from_config(config)?, Theme::from_config(config)?);
+    // Only what arrives from now on is printed.
+    let mut seen: HashMap<&str, NaiveDateTime> = HashMap::new();
+    for asset in assets {
+        if let Some(record) = storage.last(asset.id())? {
+            seen.insert(asset.id(), record.timestamp);
+        }
+    }
+    let names: Vec<&str> = assets.iter().map(|asset| asset.name()).collect();
+    eprintln!("Following {}, press Ctrl+C to stop", names.join(", "));
+    loop {
+        for asset in assets {
+            let last = seen.get(asset.id()).copied();
+            // A failed read is retried on the next poll, as is one that
+            // catches a record half written.
+            let records = match storage.scan(asset.id(), last) {
+                Ok(records) => records,
+                Err(e) => {
+                    eprintln!("Error reading {}: {}", asset.name(), e);
+                    continue;
+                }
+            };
+            for record in records {
+                let record = match record {
+                    Ok(record) => record,
+                    Err(e) => {
+                        eprintln!("Error reading {}: {}", asset.name(), e);
+                        break;
+                    }
+                };
+                if last.is_some_and(|last| record.timestamp <= last) {
+                    continue;
+                }
+                if json {
+                    println!("{}", quote::to_json(*asset, &record));
+                } else {
+                    println!(
+                        "[{}] {}: {}{}",
+                        theme.paint(theme.muted, &record.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+                        asset.name(),
+                        format.money(record.price, asset.precision(), "usd"),
+                        format_quotes(&record, &format, &theme)
+                    );
+                }
+                seen.insert(asset.id(), record.timestamp);
+            }
+        }
+        thread::sleep(POLL);
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use chrono::Local;
+
+use crate::storage::{self, Storage};
+use crate::{PriceError, Pricing};
+
+pub fn run(asset: &dyn Pricing, storage: &mut dyn Storage, file: &Path) -> Result<(), PriceError> {
+    let incoming = storage::csv::read_records(file)?;
+
+    let now = Local::now().naive_local();
+    if let Some(record) = incoming.iter().find(|r| r.timestamp > now) {
+        return Err(PriceError::ParseError(format!(
+            "{}: timestamp {} is in the future",
+            file.display(),
+            record.timestamp
+        )));
+    }
+
+    // Stored records are all kept, even those sharing a timestamp; the
+    // import only fills in the timestamps the history lacks.
+    let mut records = storage.read(asset.id())?;
+    let mut seen: HashSet<_> = records.iter().map(|r| r.timestamp).collect();
+    let mut added = 0;
+    let mut duplicates = 0;
+    for record in incoming {
+        if seen.insert(record.timestamp) {
+            records.push(record);
+            added += 1;
+        } else {
+            duplicates += 1;
+        }
+    }
+    // Stable, so stored records keep their order within a timestamp.
+    records.sort_by_key(|r| r.timestamp);
+
+    storage.replace(asset.id(), &records)?;
+
+    println!(
+        "Imported {} records into {} ({} duplicates skipped, {} total)",
+        added,
+        asset.name(),
+        duplicates,
+        records.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use crate::storage::encrypted;
+use crate::PriceError;
+
+pub fn run(output: &Path) -> Result<(), PriceError> {
+    if output.exists() {
+        return Err(PriceError::FileError(format!(
+            "{} already exists; histories sealed with it could not be read with a new key",
+            output.display()
+        )));
+    }
+    encrypted::write_key(output)?;
+    println!(
+        "Wrote a new key to {}; keep a copy somewhere safe, encrypted histories cannot be read without it",
+        output.display()
+    );
+    Ok(())
+}
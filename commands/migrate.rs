@@ -0,0 +1,85 @@
+// Copies CSV histories into the SQLite backend, the only database one; a
+// Postgres target would need its own Storage first.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::config::StorageConfig;
+use crate::storage::csv::{CsvReader, CsvStorage};
+use crate::storage::sqlite::SqliteStorage;
+use crate::storage::Storage;
+use crate::{PriceError, Pricing};
+
+const BATCH_SIZE: usize = 1000;
+
+pub fn run(assets: &[Box<dyn Pricing>], source: &Path, target: &StorageConfig) -> Result<(), PriceError> {
+    let StorageConfig::Sqlite { path } = target else {
+        return Err(PriceError::ParseError(
+            "migrate needs a database backend; set [storage] backend = \"sqlite\" in the config".to_string(),
+        ));
+    };
+
+    let csv = CsvStorage::new(source);
+    let mut db = SqliteStorage::open(path)?;
+
+    for asset in assets {
+        let csv_path = csv.path_for(asset.id());
+        if !csv_path.exists() {
+            println!("{}: no CSV history at {}, skipping", asset.name(), csv_path.display());
+            continue;
+        }
+
+        let before = db.count(asset.id())?;
+        let mut read = 0;
+        let mut inserted = 0;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+        for record in CsvReader::open(&csv_path)? {
+            batch.push(record?);
+            read += 1;
+            if batch.len() == BATCH_SIZE {
+                inserted += db.insert_batch(asset.id(), &batch)?;
+                batch.clear();
+                print!("\r{}: {} rows read", asset.name(), read);
+                io::stdout().flush().ok();
+            }
+        }
+        inserted += db.insert_batch(asset.id(), &batch)?;
+
+        let after = db.count(asset.id())?;
+        println!(
+            "\r{}: {} rows read, {} inserted, {} already present",
+            asset.name(),
+            read,
+            inserted,
+            read - inserted
+        );
+
+        if after != before + inserted {
+            return Err(PriceError::FileError(format!(
+                "verification failed for {}: expected {} rows in {}, found {}",
+                asset.name(),
+                before + inserted,
+                path.display(),
+                after
+            )));
+        }
+    }
+
+    // The alert log is copied only into a database that has none yet, so
+    // running the migration twice does not duplicate entries.
+    let alerts = csv.alert_log()?;
+    if !alerts.is_empty() {
+        if db.alert_log()?.is_empty() {
+            for event in &alerts {
+                db.log_alert(event)?;
+            }
+            println!("Alert log: {} entries copied", alerts.len());
+        } else {
+            println!("Alert log: database already has entries, skipping");
+        }
+    }
+
+    println!("Migration into {} complete", path.display());
+    Ok(())
+}
@@ -0,0 +1,25 @@
+pub mod alerts;
+pub mod asset;
+pub mod backtest;
+pub mod chart;
+pub mod compare;
+pub mod completions;
+pub mod config;
+pub mod convert;
+pub mod dividends;
+pub mod export;
+pub mod follow;
+pub mod import;
+pub mod keygen;
+pub mod migrate;
+pub mod paper;
+pub mod performance;
+pub mod query;
+pub mod quote;
+pub mod repl;
+pub mod report;
+pub mod search;
+pub mod serve;
+pub mod status;
+pub mod usage;
+pub mod verify;
@@ -0,0 +1,72 @@
+use crate::paper::{PaperTrader, Side};
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+// Prints the paper fills, what is still open, and the profit and loss with
+// holdings valued at their latest stored price.
+pub fn run(trader: &PaperTrader, assets: &[Box<dyn Pricing>], storage: &dyn Storage) -> Result<(), PriceError> {
+    let name = |id: &str| assets.iter().find(|asset| asset.id() == id).map_or(id.to_string(), |a| a.name().to_string());
+
+    if trader.fills().is_empty() {
+        println!("No paper fills yet");
+    }
+    for fill in trader.fills() {
+        let verb = if fill.side == Side::Buy { "bought" } else { "sold" };
+        println!(
+            "[{}] {}: {} {} {} at {:.2}",
+            fill.timestamp.format(TIMESTAMP_FORMAT),
+            fill.order,
+            verb,
+            fill.amount,
+            name(&fill.asset),
+            fill.price
+        );
+    }
+    for order in trader.pending() {
+        let (limit, price) = match (order.below, order.above) {
+            (Some(below), _) => ("at or below", below),
+            (_, above) => ("at or above", above.unwrap_or_default()),
+        };
+        let after = order.after.as_ref().map(|after| format!(" after '{}'", after)).unwrap_or_default();
+        let side = if order.side == Side::Buy { "buy" } else { "sell" };
+        println!(
+            "Open: {}: {} {} {} {} {:.2}{}",
+            order.name,
+            side,
+            order.amount,
+            name(&order.asset),
+            limit,
+            price,
+            after
+        );
+    }
+
+    let book = trader.book();
+    let mut value = book.cash;
+    let mut unrealized = 0.0;
+    for (id, position) in book.positions.iter().filter(|(_, position)| position.amount > 1e-12) {
+        let Some(last) = storage.scan(id, None)?.last().transpose()? else {
+            continue;
+        };
+        let gain = (last.price - position.cost) * position.amount;
+        value += last.price * position.amount;
+        unrealized += gain;
+        println!(
+            "Holding {} {} bought at {:.2} on average, now {:.2} ({:+.2})",
+            position.amount,
+            name(id),
+            position.cost,
+            last.price,
+            gain
+        );
+    }
+    println!(
+        "Cash {:.2}, realized {:+.2}, unrealized {:+.2}, total {:+.2} on {:.2}",
+        book.cash,
+        book.realized,
+        unrealized,
+        value - trader.starting_cash(),
+        trader.starting_cash()
+    );
+    Ok(())
+}
@@ -0,0 +1,65 @@
+// Each asset's return next to a benchmark's over the same windows, since a
+// rise means more once the market it rose with is known:
+//
+//   [benchmark]
+//   asset = "sp500"
+//   windows = ["1d", "7d", "30d", "365d"]
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::analytics;
+use crate::config::{self, parse_duration};
+use crate::storage::Storage;
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkConfig {
+    pub asset: String,
+    #[serde(default = "default_windows")]
+    pub windows: Vec<String>,
+}
+
+pub fn default_windows() -> Vec<String> {
+    ["1d", "7d", "30d", "365d"].iter().map(|window| window.to_string()).collect()
+}
+
+// Prints a row per asset with its return over each window and, in
+// brackets, how many percentage points it beat the benchmark by.
+pub fn run(
+    assets: &[Box<dyn Pricing>],
+    benchmark: &dyn Pricing,
+    storage: &dyn Storage,
+    windows: &[String],
+) -> Result<(), PriceError> {
+    let now = Local::now().naive_local();
+    let starts = windows
+        .iter()
+        .map(|window| parse_duration(window).and_then(|duration| config::before(now, duration)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let base = analytics::returns_since(storage.scan(benchmark.id(), None)?, &starts)?;
+
+    let width = assets.iter().map(|asset| asset.name().len()).max().unwrap_or(0).max("Asset".len());
+    let mut header = format!("{:<width$}", "Asset");
+    for window in windows {
+        header.push_str(&format!("  {:>18}", window));
+    }
+    println!("{}", header.trim_end());
+    for asset in assets {
+        let returns = analytics::returns_since(storage.scan(asset.id(), None)?, &starts)?;
+        let mut row = format!("{:<width$}", asset.name());
+        for (change, base) in returns.iter().zip(&base) {
+            let cell = match (change, base) {
+                (Some(change), _) if asset.id() == benchmark.id() => format!("{:+.2}%", change),
+                (Some(change), Some(base)) => format!("{:+.2}% ({:+.2})", change, change - base),
+                (Some(change), None) => format!("{:+.2}%", change),
+                (None, _) => "n/a".to_string(),
+            };
+            row.push_str(&format!("  {:>18}", cell));
+        }
+        println!("{}", row.trim_end());
+    }
+    println!("Brackets show percentage points above or below {}", benchmark.name());
+    Ok(())
+}
@@ -0,0 +1,174 @@
+// SQL over the stored history, whatever the backend: the histories the
+// query names are loaded into an in-memory SQLite database, so the dialect
+// is SQLite's, with date_trunc added for the usual rollups:
+//
+//   query "SELECT date_trunc('day', ts), avg(price) FROM bitcoin GROUP BY 1"
+//
+// Each tracked asset is a view of ts, price, volume and market_cap; the
+// `prices` table holds them all with an `asset` column.
+
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use serde_json::{Map, Value};
+
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+const UNITS: [&str; 6] = ["minute", "hour", "day", "week", "month", "year"];
+
+fn query_error(e: rusqlite::Error) -> PriceError {
+    PriceError::ParseError(format!("query: {}", e))
+}
+
+pub fn run(assets: &[Box<dyn Pricing>], storage: &dyn Storage, sql: &str, json: bool) -> Result<(), PriceError> {
+    let conn = Connection::open_in_memory().map_err(query_error)?;
+    conn.execute_batch(
+        "CREATE TABLE prices (
+             asset TEXT NOT NULL,
+             ts TEXT NOT NULL,
+             price REAL NOT NULL,
+             volume REAL,
+             market_cap REAL
+         );",
+    )
+    .map_err(query_error)?;
+    conn.create_scalar_function(
+        "date_trunc",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let unit: String = ctx.get(0)?;
+            let ts: Option<String> = ctx.get(1)?;
+            ts.map(|ts| date_trunc(&unit, &ts))
+                .transpose()
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.message().into()))
+        },
+    )
+    .map_err(query_error)?;
+
+    // Loading every history for a query over one would be slow for long
+    // ones, so only those the query mentions are, or all for `prices`.
+    let lower = sql.to_lowercase();
+    let everything = lower.contains("prices");
+    for asset in assets {
+        let name = format!("\"{}\"", asset.id().replace('"', "\"\""));
+        conn.execute_batch(&format!(
+            "CREATE VIEW {} AS SELECT ts, price, volume, market_cap FROM prices WHERE asset = '{}';",
+            name,
+            asset.id().replace('\'', "''")
+        ))
+        .map_err(query_error)?;
+        if everything || lower.contains(&asset.id().to_lowercase()) {
+            load(&conn, storage, asset.id())?;
+        }
+    }
+
+    let mut statement = conn.prepare(sql).map_err(query_error)?;
+    let columns: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+    let mut rows = statement.query([]).map_err(query_error)?;
+    let mut table: Vec<Vec<String>> = Vec::new();
+    while let Some(row) = rows.next().map_err(query_error)? {
+        let values = (0..columns.len()).map(|i| row.get_ref(i)).collect::<Result<Vec<_>, _>>();
+        let values = values.map_err(query_error)?;
+        if json {
+            let object: Map<String, Value> =
+                columns.iter().cloned().zip(values.into_iter().map(json_value)).collect();
+            println!("{}", Value::Object(object));
+        } else {
+            table.push(values.into_iter().map(text).collect());
+        }
+    }
+    if !json {
+        print_table(&columns, &table);
+    }
+    Ok(())
+}
+
+fn load(conn: &Connection, storage: &dyn Storage, asset: &str) -> Result<(), PriceError> {
+    let transaction = conn.unchecked_transaction().map_err(query_error)?;
+    {
+        let mut insert = transaction
+            .prepare("INSERT INTO prices (asset, ts, price, volume, market_cap) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .map_err(query_error)?;
+        for record in storage.scan(asset, None)? {
+            let record = record?;
+            let ts = record.timestamp.format(TIMESTAMP_FORMAT).to_string();
+            insert.execute(params![asset, ts, record.price, record.volume, record.market_cap]).map_err(query_error)?;
+        }
+    }
+    transaction.commit().map_err(query_error)
+}
+
+// The start of the minute, hour, day, week (from Monday), month or year
+// `ts` falls in.
+fn date_trunc(unit: &str, ts: &str) -> Result<String, PriceError> {
+    let time = NaiveDateTime::parse_from_str(ts, TIMESTAMP_FORMAT)
+        .map_err(|e| PriceError::ParseError(format!("date_trunc: invalid timestamp '{}': {}", ts, e)))?;
+    let day = time.date();
+    let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+    let start = match unit.to_lowercase().as_str() {
+        "minute" => time.with_second(0).unwrap_or(time),
+        "hour" => day.and_hms_opt(time.hour(), 0, 0).unwrap_or(time),
+        "day" => day.and_hms_opt(0, 0, 0).unwrap_or(time),
+        "week" => monday.and_hms_opt(0, 0, 0).unwrap_or(time),
+        "month" => day.with_day(1).and_then(|day| day.and_hms_opt(0, 0, 0)).unwrap_or(time),
+        "year" => day.with_ordinal(1).and_then(|day| day.and_hms_opt(0, 0, 0)).unwrap_or(time),
+        _ => {
+            return Err(PriceError::ParseError(format!(
+                "date_trunc: unknown unit '{}', expected one of: {}",
+                unit,
+                UNITS.join(", ")
+            )))
+        }
+    };
+    Ok(start.format(TIMESTAMP_FORMAT).to_string())
+}
+
+fn text(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(value) => value.to_string(),
+        ValueRef::Real(value) => value.to_string(),
+        ValueRef::Text(value) => String::from_utf8_lossy(value).into_owned(),
+        ValueRef::Blob(value) => format!("<{} bytes>", value.len()),
+    }
+}
+
+fn json_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(value) => Value::from(value),
+        ValueRef::Real(value) => Value::from(value),
+        ValueRef::Blob(_) | ValueRef::Text(_) => Value::from(text(value)),
+    }
+}
+
+// Numbers right-aligned, the rest to the left.
+fn print_table(columns: &[String], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| rows.iter().map(|row| row[i].chars().count()).fold(column.chars().count(), usize::max))
+        .collect();
+    let numeric: Vec<bool> = (0..columns.len())
+        .map(|i| rows.iter().all(|row| row[i].is_empty() || row[i].parse::<f64>().is_ok()))
+        .collect();
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| match numeric[i] {
+                true => format!("{:>width$}", cell, width = widths[i]),
+                false => format!("{:<width$}", cell, width = widths[i]),
+            })
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    };
+    line(columns);
+    for row in rows {
+        line(row);
+    }
+    println!("({} row{})", rows.len(), if rows.len() == 1 { "" } else { "s" });
+}
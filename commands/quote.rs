@@ -0,0 +1,109 @@
+// Fetches one price, for scripts and quick checks; nothing is stored. A
+// tracked asset is fetched as the tracker would, anything else is looked up:
+// lowercase names as CoinGecko coin ids, falling back to Yahoo Finance, and
+// names with capitals or ^ = . in them as Yahoo Finance symbols.
+
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::http::Client;
+use crate::registry::{self, AssetInfo, Category, Provider};
+use crate::sources::{self, coingecko};
+use crate::storage::{PriceRecord, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::watchlist::{WatchedAsset, BUILT_IN};
+use crate::{format_quotes, PriceError, Pricing};
+
+pub fn run(
+    name: &str,
+    json: bool,
+    assets: &[Box<dyn Pricing>],
+    config: &Config,
+    client: &Client,
+) -> Result<(), PriceError> {
+    let name = name.trim();
+    let tracked = assets
+        .iter()
+        .find(|asset| asset.id().eq_ignore_ascii_case(name) || asset.symbol().eq_ignore_ascii_case(name));
+    let built;
+    let (asset, fallback) = match tracked {
+        Some(asset) => (asset.as_ref(), false),
+        None => {
+            let (info, fallback) = resolve(name, client)?;
+            built = sources::build(&info, config, client)?;
+            (built.as_ref(), fallback)
+        }
+    };
+    let record = sources::guarded(asset, || asset.fetch_record()).map_err(|e| match fallback && e.is_parse() {
+        true => PriceError::ParseError(format!("'{}' is neither a CoinGecko coin id nor a Yahoo Finance symbol", name)),
+        false => e,
+    })?;
+
+    if json {
+        println!("{}", to_json(asset, &record));
+        return Ok(());
+    }
+    let (format, theme) = (NumberFormat::from_config(config)?, Theme::from_config(config)?);
+    // Tickers looked up here are named by their symbol already.
+    let symbol = asset.symbol().to_uppercase();
+    let label = if asset.name() == symbol { symbol } else { format!("{} ({})", asset.name(), symbol) };
+    println!(
+        "{}: {}{}",
+        label,
+        format.money(record.price, asset.precision(), "usd"),
+        format_quotes(&record, &format, &theme)
+    );
+    Ok(())
+}
+
+// A record as a line of JSON, for `quote --json` and `follow --json`.
+pub fn to_json(asset: &dyn Pricing, record: &PriceRecord) -> Value {
+    json!({
+        "id": asset.id(),
+        "name": asset.name(),
+        "symbol": asset.symbol(),
+        "price": record.price,
+        "currency": "usd",
+        "timestamp": record.timestamp.format(TIMESTAMP_FORMAT).to_string(),
+        "volume": record.volume,
+        "market_cap": record.market_cap,
+        "quotes": record.quotes,
+        "changes": record.changes,
+    })
+}
+
+// What to fetch `name` from, and whether it is the Yahoo Finance fallback
+// for a name CoinGecko does not know.
+fn resolve(name: &str, client: &Client) -> Result<(AssetInfo, bool), PriceError> {
+    let id = name.to_ascii_lowercase();
+    // By id, or symbol as in `quote btc`.
+    let built_in = BUILT_IN.iter().filter_map(|id| registry::built_in(id)).find(|info| info.symbol == id);
+    if let Some(info) = registry::built_in(&id).or(built_in) {
+        return Ok((info, false));
+    }
+    if name.chars().any(|c| c.is_ascii_uppercase() || matches!(c, '^' | '=' | '.')) {
+        return Ok((ticker(name), false));
+    }
+    match coingecko::lookup(client, &id) {
+        Ok((coin_name, symbol)) => {
+            let asset = WatchedAsset { id: id.clone(), name: Some(coin_name), symbol: Some(symbol) };
+            Ok((registry::coin(&asset), false))
+        }
+        Err(e) if e.is_parse() => Ok((ticker(&name.to_ascii_uppercase()), true)),
+        Err(e) => Err(e),
+    }
+}
+
+fn ticker(symbol: &str) -> AssetInfo {
+    AssetInfo {
+        id: symbol.to_ascii_lowercase(),
+        name: symbol.to_string(),
+        symbol: symbol.to_ascii_lowercase(),
+        provider: Provider::Yahoo,
+        provider_id: symbol.to_string(),
+        quote_currency: "usd".to_string(),
+        precision: 2,
+        category: if symbol.starts_with('^') { Category::Index } else { Category::Stock },
+    }
+}
@@ -0,0 +1,197 @@
+// Reads commands from stdin against the stored history the tracker writes,
+// one per line, until `quit` or the end of input. Assets are named by id or
+// symbol. Alert rules go to the added rules file, which a running tracker
+// reloads on its next round.
+
+use std::io::{self, BufRead, IsTerminal, Write as _};
+
+use crate::alerts::added::{self, AddedRule, AddedRules};
+use crate::analytics::{RunningStats, Summarizer, Window};
+use crate::config::{parse_duration, Config};
+use crate::display::NumberFormat;
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::{find_asset, format_quotes, PriceError, Pricing};
+
+const HELP: &str = "\
+Commands:
+  price <asset>                    the last stored price
+  stats <asset> [period]           change, range and spread over the period, 24h by default
+  alert add <asset> > <price>      alert once the price is above (or with <, below) the limit
+  alert list                       the configured and added alert rules
+  alert remove <name>              drop an added rule
+  help                             this list
+  quit";
+
+pub fn run(assets: &[Box<dyn Pricing>], storage: &dyn Storage, config: &Config) -> Result<(), PriceError> {
+    let format = NumberFormat::from_config(config)?;
+    let theme = Theme::from_config(config)?;
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        println!("Type help for the commands, quit to leave");
+    }
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            let _ = io::stdout().flush();
+        }
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line.map_err(|e| PriceError::FileError(format!("stdin: {}", e)))?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["quit" | "exit"] => return Ok(()),
+            ["help"] => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            ["price", asset] => resolve(assets, asset).and_then(|asset| price(asset, storage, &format, &theme)),
+            ["stats", asset] => resolve(assets, asset).and_then(|asset| stats(asset, storage, "24h", &format, &theme)),
+            ["stats", asset, period] => {
+                resolve(assets, asset).and_then(|asset| stats(asset, storage, period, &format, &theme))
+            }
+            ["alert", "add", asset, limit @ ..] => {
+                resolve(assets, asset).and_then(|asset| add_alert(asset, &limit.concat(), config))
+            }
+            ["alert", "list"] => list_alerts(config),
+            ["alert", "remove", name] => remove_alert(name, config),
+            _ => Err(PriceError::ParseError(format!("unknown command '{}', type help for the list", line.trim()))),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+// By symbol, e.g. btc, or id.
+fn resolve<'a>(assets: &'a [Box<dyn Pricing>], name: &str) -> Result<&'a dyn Pricing, PriceError> {
+    match assets.iter().find(|asset| asset.symbol().eq_ignore_ascii_case(name)) {
+        Some(asset) => Ok(asset.as_ref()),
+        None => find_asset(assets, name),
+    }
+}
+
+fn price(asset: &dyn Pricing, storage: &dyn Storage, format: &NumberFormat, theme: &Theme) -> Result<(), PriceError> {
+    match storage.last(asset.id())? {
+        Some(record) => println!(
+            "[{}] {}: {}{}",
+            theme.paint(theme.muted, &record.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            asset.name(),
+            format.money(record.price, asset.precision(), "usd"),
+            format_quotes(&record, format, theme)
+        ),
+        None => println!("No prices stored for {}", asset.name()),
+    }
+    Ok(())
+}
+
+fn stats(
+    asset: &dyn Pricing,
+    storage: &dyn Storage,
+    period: &str,
+    format: &NumberFormat,
+    theme: &Theme,
+) -> Result<(), PriceError> {
+    let window = Window::new(Some(parse_duration(period)?), None)?;
+    let mut summarizer = Summarizer::default();
+    // Of the changes from one record to the next.
+    let mut changes = RunningStats::default();
+    let mut previous: Option<f64> = None;
+    for record in storage.scan(asset.id(), window.start())? {
+        let record = record?;
+        if !window.contains(&record) {
+            continue;
+        }
+        summarizer.push(&record);
+        if let Some(previous) = previous.filter(|previous| *previous > 0.0) {
+            changes.push((record.price - previous) / previous * 100.0);
+        }
+        previous = Some(record.price);
+    }
+    let Some(summary) = summarizer.finish() else {
+        println!("No prices stored for {} in the last {}", asset.name(), period);
+        return Ok(());
+    };
+    let precision = asset.precision();
+    let money = |value: f64| format.money(value, precision, "usd");
+    println!(
+        "{} over {}: {} records, last {} ({}), high {}, low {}, mean {}",
+        asset.name(),
+        period,
+        summary.count,
+        money(summary.last.price),
+        theme.change(summary.change_pct(), &format!("{}%", format.signed(summary.change_pct(), 2))),
+        money(summary.high),
+        money(summary.low),
+        money(summary.mean)
+    );
+    if let Some(volatility) = changes.stddev() {
+        println!("Standard deviation of the changes between records: {}%", format.number(volatility, 3));
+    }
+    Ok(())
+}
+
+// `limit` is the rest of the command run together, e.g. ">70000".
+fn add_alert(asset: &dyn Pricing, limit: &str, config: &Config) -> Result<(), PriceError> {
+    let parse = |value: &str| {
+        let message = format!("expected a price after > or <, e.g. alert add btc > 70000, got '{}'", value);
+        value.parse::<f64>().ok().filter(|value| value.is_finite()).ok_or(PriceError::ParseError(message))
+    };
+    let (above, below) = match (limit.strip_prefix('>'), limit.strip_prefix('<')) {
+        (Some(value), _) => (Some(parse(value)?), None),
+        (None, Some(value)) => (None, Some(parse(value)?)),
+        (None, None) => {
+            return Err(PriceError::ParseError(format!(
+                "expected > or < and a price, e.g. alert add btc > 70000, got '{}'",
+                limit
+            )))
+        }
+    };
+    let rule = AddedRule { asset: asset.id().to_string(), above, below };
+    let path = added::path(config);
+    let mut rules = AddedRules::load(&path)?;
+    if rules.alerts.contains(&rule) {
+        println!("{} is already set", rule.name());
+        return Ok(());
+    }
+    rules.alerts.push(rule.clone());
+    rules.save(&path)?;
+    println!("Added {} ({}); a running tracker picks it up on its next round", rule.name(), path.display());
+    Ok(())
+}
+
+fn list_alerts(config: &Config) -> Result<(), PriceError> {
+    let path = added::path(config);
+    let added = AddedRules::load(&path)?;
+    if config.alerts.is_empty() && added.alerts.is_empty() {
+        println!("No alert rules");
+    }
+    for rule in &config.alerts {
+        println!("{:<32} {} (config)", rule.name, rule.asset);
+    }
+    for rule in &added.alerts {
+        println!("{:<32} {} ({})", rule.name(), rule.asset, path.display());
+    }
+    Ok(())
+}
+
+fn remove_alert(name: &str, config: &Config) -> Result<(), PriceError> {
+    let path = added::path(config);
+    let mut rules = AddedRules::load(&path)?;
+    let count = rules.alerts.len();
+    rules.alerts.retain(|rule| rule.name() != name);
+    if rules.alerts.len() == count {
+        let message = if config.alerts.iter().any(|rule| rule.name == name) {
+            format!("'{}' is set in the config, not added here", name)
+        } else {
+            format!("no added alert rule '{}'", name)
+        };
+        return Err(PriceError::ParseError(message));
+    }
+    rules.save(&path)?;
+    println!("Removed {}", name);
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, Local};
+
+use crate::analytics::{self, Summarizer, Summary};
+use crate::cli::ChartStyle;
+use crate::commands::chart::{self, ChartOptions, Series};
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::inflation::Deflator;
+use crate::storage::{PriceRecord, Storage, TIMESTAMP_FORMAT};
+use crate::theme::Theme;
+use crate::{PriceError, Pricing};
+
+pub fn run(
+    assets: &[Box<dyn Pricing>],
+    storage: &dyn Storage,
+    period: Duration,
+    benchmark: Option<&dyn Pricing>,
+    real: Option<&Deflator>,
+    config: &Config,
+    output: &Path,
+) -> Result<(), PriceError> {
+    let (format, theme) = (&NumberFormat::from_config(config)?, Theme::from_config(config)?);
+    let options = ChartOptions {
+        style: ChartStyle::Line,
+        sma: Vec::new(),
+        candle: Duration::hours(1),
+        width: 900,
+        height: 320,
+        theme,
+    };
+
+    let window = analytics::Window::new(Some(period), None)?;
+    // The benchmark's change over the period, for a column comparing each
+    // asset's with it.
+    let base = match benchmark {
+        Some(benchmark) => {
+            let mut summarizer = Summarizer::default();
+            each_record(storage, benchmark.id(), window, real, |record| summarizer.push(record))?;
+            Some(summarizer.finish().map(|summary| summary.change_pct()))
+        }
+        None => None,
+    };
+
+    let mut rows = String::new();
+    let mut sections = String::new();
+    for asset in assets {
+        let mut summarizer = Summarizer::default();
+        let mut series = Series::new(&options);
+        each_record(storage, asset.id(), window, real, |record| {
+            summarizer.push(record);
+            series.push(record);
+        })?;
+        let Some(summary) = summarizer.finish() else {
+            rows.push_str(&format!(
+                "<tr><td>{}</td><td colspan=\"{}\" class=\"muted\">no data in period</td></tr>\n",
+                escape(asset.name()),
+                if base.is_some() { 8 } else { 7 }
+            ));
+            continue;
+        };
+
+        let relative = base.map(|base| match base {
+            Some(base) if benchmark.is_some_and(|b| b.id() != asset.id()) => {
+                let difference = summary.change_pct() - base;
+                let class = if difference >= 0.0 { "up" } else { "down" };
+                format!("<td class=\"{}\">{} pp</td>", class, format.signed(difference, 2))
+            }
+            _ => "<td class=\"muted\">—</td>".to_string(),
+        });
+        rows.push_str(&summary_row(asset.name(), &summary, relative.as_deref(), format));
+        if series.count >= 2 {
+            let svg = chart::render_svg(asset.name(), &series, &options)?;
+            sections.push_str(&format!(
+                "<section><h2>{}</h2>\n{}\n</section>\n",
+                escape(asset.name()),
+                svg
+            ));
+        }
+    }
+
+    let mut title = format!("Price report — last {}", describe(period));
+    if let Some(deflator) = real {
+        title.push_str(&format!(", in {}", deflator.label()));
+    }
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em auto; max-width: 960px; color: {text}; background: {background}; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 2em; }}
+th, td {{ padding: 6px 10px; border-bottom: 1px solid {grid}; text-align: right; }}
+th:first-child, td:first-child {{ text-align: left; }}
+.up {{ color: {up}; }}
+.down {{ color: {down}; }}
+.muted {{ color: {muted}; text-align: left; }}
+section svg {{ width: 100%; height: auto; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class="muted">Generated {generated}</p>
+<table>
+<tr><th>Asset</th><th>Last</th><th>Change</th><th>Change %</th><th>High</th><th>Low</th><th>Average</th><th>Records</th>{vs}</tr>
+{rows}</table>
+{sections}</body>
+</html>
+"#,
+        title = escape(&title),
+        text = theme.text.hex(),
+        background = theme.background.hex(),
+        grid = theme.grid.hex(),
+        up = theme.up.hex(),
+        down = theme.down.hex(),
+        muted = theme.muted.hex(),
+        generated = Local::now().format(TIMESTAMP_FORMAT),
+        vs = benchmark.map(|b| format!("<th>vs {}</th>", escape(b.name()))).unwrap_or_default(),
+        rows = rows,
+        sections = sections,
+    );
+
+    fs::write(output, html).map_err(|e| PriceError::FileError(format!("{}: {}", output.display(), e)))?;
+    println!("Wrote report for {} assets to {}", assets.len(), output.display());
+    Ok(())
+}
+
+// Calls `f` with each record in `window`, in real terms when a deflator is
+// given.
+fn each_record(
+    storage: &dyn Storage,
+    asset: &str,
+    window: analytics::Window,
+    real: Option<&Deflator>,
+    mut f: impl FnMut(&PriceRecord),
+) -> Result<(), PriceError> {
+    for record in storage.scan(asset, window.start())? {
+        let record = record?;
+        if !window.contains(&record) {
+            continue;
+        }
+        match real {
+            Some(deflator) => {
+                if let Some(real) = deflator.deflate(&record) {
+                    f(&real);
+                }
+            }
+            None => f(&record),
+        }
+    }
+    Ok(())
+}
+
+// `relative` is the cell comparing the change with the benchmark's.
+fn summary_row(name: &str, summary: &Summary, relative: Option<&str>, format: &NumberFormat) -> String {
+    let class = if summary.change() >= 0.0 { "up" } else { "down" };
+    format!(
+        "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td class=\"{}\">{}%</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>{}</tr>\n",
+        escape(name),
+        format.number(summary.last.price, 2),
+        class,
+        format.signed(summary.change(), 2),
+        class,
+        format.signed(summary.change_pct(), 2),
+        format.number(summary.high, 2),
+        format.number(summary.low, 2),
+        format.number(summary.mean, 2),
+        summary.count,
+        relative.unwrap_or_default()
+    )
+}
+
+fn describe(period: Duration) -> String {
+    if period.num_days() > 1 && period.num_hours() % 24 == 0 {
+        format!("{} days", period.num_days())
+    } else {
+        format!("{} hours", period.num_hours())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
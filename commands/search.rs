@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::http::Client;
+use crate::watchlist::BUILT_IN;
+use crate::PriceError;
+
+const COIN_LIST_CACHE: &str = "coin_list.json";
+// The coin list only changes when coins are listed or delisted.
+const COIN_LIST_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_RESULTS: usize = 15;
+
+#[derive(Serialize, Deserialize)]
+struct Coin {
+    id: String,
+    symbol: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct YahooSearch {
+    #[serde(default)]
+    quotes: Vec<YahooQuote>,
+}
+
+#[derive(Deserialize)]
+struct YahooQuote {
+    symbol: String,
+    #[serde(default)]
+    shortname: Option<String>,
+    #[serde(default, rename = "quoteType")]
+    quote_type: Option<String>,
+}
+
+pub fn run(query: &str, refresh: bool, client: &Client) -> Result<(), PriceError> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Err(PriceError::ParseError("empty search query".to_string()));
+    }
+
+    let coins = coin_list(client, Path::new(COIN_LIST_CACHE), refresh)?;
+    let mut matches: Vec<(u8, &Coin)> = coins.iter().filter_map(|coin| rank(coin, &query).map(|r| (r, coin))).collect();
+    matches.sort_by_key(|(rank, coin)| (*rank, coin.name.len()));
+
+    println!("CoinGecko (use the id with `asset add`):");
+    if matches.is_empty() {
+        println!("  no matches");
+    }
+    for (_, coin) in matches.iter().take(MAX_RESULTS) {
+        let note = if BUILT_IN.contains(&coin.id.as_str()) { " [built in]" } else { "" };
+        println!("  {:<28} {} ({}){}", coin.id, coin.name, coin.symbol, note);
+    }
+    if matches.len() > MAX_RESULTS {
+        println!("  ... and {} more", matches.len() - MAX_RESULTS);
+    }
+
+    println!("Yahoo Finance:");
+    match yahoo_lookup(client, &query) {
+        Ok(quotes) if quotes.is_empty() => println!("  no matches"),
+        Ok(quotes) => {
+            for quote in quotes {
+                let note = if quote.symbol == "^GSPC" { " [built in as sp500]" } else { "" };
+                println!(
+                    "  {:<28} {} ({}){}",
+                    quote.symbol,
+                    quote.shortname.unwrap_or_default(),
+                    quote.quote_type.unwrap_or_default().to_lowercase(),
+                    note
+                );
+            }
+        }
+        Err(e) => eprintln!("  lookup failed: {}", e),
+    }
+    Ok(())
+}
+
+// Lower is better: exact symbol or id, exact name, prefix, then substring.
+fn rank(coin: &Coin, query: &str) -> Option<u8> {
+    let name = coin.name.to_lowercase();
+    if coin.symbol == query || coin.id == query {
+        Some(0)
+    } else if name == query {
+        Some(1)
+    } else if coin.id.starts_with(query) || name.starts_with(query) || coin.symbol.starts_with(query) {
+        Some(2)
+    } else if coin.id.contains(query) || name.contains(query) {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+// Downloads the full CoinGecko coin list at most once a day; a stale cache is
+// still used when the download fails.
+fn coin_list(client: &Client, cache: &Path, refresh: bool) -> Result<Vec<Coin>, PriceError> {
+    let age = fs::metadata(cache)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    let fresh = age.is_some_and(|age| age < COIN_LIST_MAX_AGE);
+
+    if refresh || !fresh {
+        match download_coin_list(client) {
+            Ok(body) => {
+                if let Err(e) = fs::write(cache, &body) {
+                    eprintln!("Error caching coin list in {}: {}", cache.display(), e);
+                }
+                return parse_coin_list(&body, "coin list");
+            }
+            Err(e) if age.is_some() => eprintln!("{} (using cached coin list)", e),
+            Err(e) => return Err(e),
+        }
+    }
+    let body = fs::read_to_string(cache).map_err(|e| PriceError::FileError(format!("{}: {}", cache.display(), e)))?;
+    parse_coin_list(&body, &cache.display().to_string())
+}
+
+fn download_coin_list(client: &Client) -> Result<String, PriceError> {
+    client
+        .get("https://api.coingecko.com/api/v3/coins/list", &[])
+        .map_err(|e| PriceError::NetworkError(format!("coin list: {}", e)))
+}
+
+fn parse_coin_list(body: &str, source: &str) -> Result<Vec<Coin>, PriceError> {
+    let mut coins: Vec<Coin> = serde_json::from_str(body).map_err(|e| PriceError::ParseError(format!("{}: {}", source, e)))?;
+    for coin in &mut coins {
+        coin.symbol = coin.symbol.to_lowercase();
+    }
+    Ok(coins)
+}
+
+fn yahoo_lookup(client: &Client, query: &str) -> Result<Vec<YahooQuote>, PriceError> {
+    let body = client.get(
+        "https://query1.finance.yahoo.com/v1/finance/search",
+        &[("q", query), ("quotesCount", "10"), ("newsCount", "0")],
+    )?;
+    let search: YahooSearch = serde_json::from_str(&body)?;
+    Ok(search.quotes)
+}
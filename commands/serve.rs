@@ -0,0 +1,211 @@
+// Serves the stored history over the API of Grafana's JSON datasource
+// plugin, so dashboards can chart it straight from the files or database:
+//
+//   GET  /             health check
+//   POST /search       the target names, as a list
+//   POST /metrics      the same, as label/value pairs
+//   POST /query        a time series per target over the dashboard's range
+//   POST /annotations  the alert log over the range
+//
+// A target is an asset id for its USD price, or the id followed by
+// `.volume`, `.market_cap` or one of the configured currencies, e.g.
+// `bitcoin.eur`. Requests are answered one at a time.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde_json::{json, Value};
+
+use crate::storage::{PriceRecord, Storage};
+use crate::{PriceError, Pricing};
+
+const FIELDS: [&str; 2] = ["volume", "market_cap"];
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn run(
+    assets: &[Box<dyn Pricing>],
+    currencies: &[String],
+    storage: &dyn Storage,
+    listen: &str,
+) -> Result<(), PriceError> {
+    let listener = TcpListener::bind(listen).map_err(|e| PriceError::NetworkError(format!("{}: {}", listen, e)))?;
+    let address = listener.local_addr().map_err(|e| PriceError::NetworkError(format!("{}: {}", listen, e)))?;
+    println!("Serving the Grafana JSON API on http://{}", address);
+    let api = Api { assets, currencies, storage };
+    for stream in listener.incoming().flatten() {
+        if let Err(e) = api.handle(stream) {
+            eprintln!("Grafana API: {}", e);
+        }
+    }
+    Ok(())
+}
+
+struct Api<'a> {
+    assets: &'a [Box<dyn Pricing>],
+    currencies: &'a [String],
+    storage: &'a dyn Storage,
+}
+
+impl Api<'_> {
+    fn handle(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut length = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+            line.clear();
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+        let path = target.split('?').next().unwrap_or(target);
+        let (status, body) = match (method, path) {
+            ("GET", "/") => (200, "\"OK\"".to_string()),
+            ("POST", "/search") => (200, json!(self.targets()).to_string()),
+            ("POST", "/metrics") => {
+                let metrics: Vec<Value> =
+                    self.targets().iter().map(|target| json!({ "label": target, "value": target })).collect();
+                (200, Value::Array(metrics).to_string())
+            }
+            ("POST", "/query" | "/annotations") => {
+                let answer = serde_json::from_slice(&body)
+                    .map_err(|e| PriceError::ParseError(format!("invalid request: {}", e)))
+                    .and_then(|request| match path {
+                        "/query" => self.query(&request),
+                        _ => self.annotations(&request),
+                    });
+                match answer {
+                    Ok(answer) => (200, answer.to_string()),
+                    Err(e) => (400, json!({ "error": e.to_string() }).to_string()),
+                }
+            }
+            _ => (404, json!({ "error": "Not Found" }).to_string()),
+        };
+
+        let reason = match status {
+            200 => "OK",
+            400 => "Bad Request",
+            _ => "Not Found",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+
+    fn targets(&self) -> Vec<String> {
+        let mut targets = Vec::new();
+        for asset in self.assets {
+            targets.push(asset.id().to_string());
+            let fields = FIELDS.iter().map(|field| field.to_string()).chain(self.currencies.iter().cloned());
+            targets.extend(fields.map(|field| format!("{}.{}", asset.id(), field)));
+        }
+        targets
+    }
+
+    fn query(&self, request: &Value) -> Result<Value, PriceError> {
+        let (from, to) = range(request)?;
+        let max_points = request["maxDataPoints"].as_u64().map_or(usize::MAX, |max| max.max(1) as usize);
+        let mut series = Vec::new();
+        for target in request["targets"].as_array().into_iter().flatten() {
+            if target["hide"].as_bool() == Some(true) {
+                continue;
+            }
+            let Some(name) = target["target"].as_str().filter(|name| !name.is_empty()) else {
+                continue;
+            };
+            let (id, field) = self.resolve(name)?;
+            let mut points = Vec::new();
+            for record in self.storage.scan(id, Some(from))? {
+                let record = record?;
+                if record.timestamp > to {
+                    break;
+                }
+                if let (Some(value), Some(time)) = (value(&record, field), millis(record.timestamp)) {
+                    points.push(json!([value, time]));
+                }
+            }
+            // Evenly spaced points down to what the panel has room for.
+            let step = points.len().div_ceil(max_points).max(1);
+            let points: Vec<Value> = points.into_iter().step_by(step).collect();
+            series.push(json!({ "target": name, "datapoints": points }));
+        }
+        Ok(Value::Array(series))
+    }
+
+    fn annotations(&self, request: &Value) -> Result<Value, PriceError> {
+        let (from, to) = range(request)?;
+        // The annotation's query, if any, names the rule to show.
+        let rule = request["annotation"]["query"].as_str().filter(|rule| !rule.is_empty());
+        let events = self
+            .storage
+            .alert_log()?
+            .into_iter()
+            .filter(|event| event.timestamp >= from && event.timestamp <= to)
+            .filter(|event| rule.is_none_or(|rule| event.rule == rule))
+            .filter_map(|event| {
+                let time = millis(event.timestamp)?;
+                Some(json!({
+                    "time": time,
+                    "title": event.rule,
+                    "text": event.message,
+                    "tags": [event.asset],
+                }))
+            })
+            .collect();
+        Ok(Value::Array(events))
+    }
+
+    // The asset id and field a target names.
+    fn resolve<'a>(&self, target: &'a str) -> Result<(&str, &'a str), PriceError> {
+        let find = |id: &str| self.assets.iter().find(|asset| asset.id() == id).map(|asset| asset.id());
+        if let Some(id) = find(target) {
+            return Ok((id, "price"));
+        }
+        target
+            .rsplit_once('.')
+            .filter(|(_, field)| FIELDS.contains(field) || self.currencies.iter().any(|code| code == field))
+            .and_then(|(id, field)| Some((find(id)?, field)))
+            .ok_or_else(|| PriceError::ParseError(format!("unknown target '{}'", target)))
+    }
+}
+
+fn value(record: &PriceRecord, field: &str) -> Option<f64> {
+    match field {
+        "price" => Some(record.price),
+        "volume" => record.volume,
+        "market_cap" => record.market_cap,
+        currency => record.quotes.get(currency).copied(),
+    }
+}
+
+// Stored timestamps are local time; Grafana's are UTC, as RFC 3339.
+fn range(request: &Value) -> Result<(NaiveDateTime, NaiveDateTime), PriceError> {
+    let time = |key: &str| {
+        let text = request["range"][key].as_str().unwrap_or_default();
+        DateTime::parse_from_rfc3339(text)
+            .map(|time| time.with_timezone(&Local).naive_local())
+            .map_err(|e| PriceError::ParseError(format!("range.{}: '{}': {}", key, text, e)))
+    };
+    Ok((time("from")?, time("to")?))
+}
+
+fn millis(timestamp: NaiveDateTime) -> Option<i64> {
+    Local.from_local_datetime(&timestamp).earliest().map(|time| time.timestamp_millis())
+}
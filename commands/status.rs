@@ -0,0 +1,162 @@
+use std::path::Path;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde_json::{json, Value};
+
+use crate::alerts::outage::describe;
+use crate::config::{self, Config};
+use crate::disk::{self, format_size, Outlook, Samples, DISK_FILE};
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::uptime::{Stats, STATS_FILE};
+use crate::{PriceError, Pricing};
+
+pub fn run(
+    assets: &[Box<dyn Pricing>],
+    storage: &dyn Storage,
+    config: &Config,
+    since: Duration,
+    json: bool,
+) -> Result<(), PriceError> {
+    if json {
+        println!("{}", to_json(assets, storage, config, since)?);
+        return Ok(());
+    }
+    providers(since)?;
+    disk_usage(config)
+}
+
+// Everything the text shows plus each asset's last stored price, with ages
+// in seconds so scripts need not parse the times.
+fn to_json(
+    assets: &[Box<dyn Pricing>],
+    storage: &dyn Storage,
+    config: &Config,
+    since: Duration,
+) -> Result<Value, PriceError> {
+    let now = Local::now().naive_local();
+    let age = |time: &str| {
+        NaiveDateTime::parse_from_str(time, TIMESTAMP_FORMAT).ok().map(|time| (now - time).num_seconds())
+    };
+
+    let mut prices = Vec::new();
+    for asset in assets {
+        let last = storage.last(asset.id())?;
+        prices.push(json!({
+            "id": asset.id(),
+            "name": asset.name(),
+            "price": last.as_ref().map(|record| record.price),
+            "timestamp": last.as_ref().map(|record| record.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            "age_seconds": last.as_ref().map(|record| (now - record.timestamp).num_seconds()),
+        }));
+    }
+
+    let stats = Stats::load(Path::new(STATS_FILE))?;
+    let from = config::before(now, since)?;
+    let totals = stats.since(from);
+    let providers: Vec<Value> = stats
+        .providers
+        .iter()
+        .map(|(provider, stats)| {
+            let total = totals.get(provider.as_str()).copied().unwrap_or_default();
+            json!({
+                "provider": provider,
+                "fetches": total.fetches,
+                "errors": total.failures,
+                "average_latency_ms": (total.fetches > 0).then(|| total.latency_ms / total.fetches),
+                "last_success": stats.last_success,
+                "last_success_age_seconds": stats.last_success.as_deref().and_then(age),
+                "last_failure": stats.last_failure,
+                "last_error": stats.last_error,
+                "failing": stats.failing,
+            })
+        })
+        .collect();
+
+    let directory = config.storage.directory();
+    let budget = config.disk.as_ref().map(|disk| disk.budget()).transpose()?;
+    Ok(json!({
+        "since": from.format(TIMESTAMP_FORMAT).to_string(),
+        "assets": prices,
+        "providers": providers,
+        "disk": {
+            "directory": directory.display().to_string(),
+            "used_bytes": disk::measure(directory),
+            "daily_growth_bytes": Samples::load(Path::new(DISK_FILE))?.growth(),
+            "budget_bytes": budget,
+        },
+    }))
+}
+
+// Each provider's error rate and average latency over `since`, with its last
+// success and error overall.
+fn providers(since: Duration) -> Result<(), PriceError> {
+    let stats = Stats::load(Path::new(STATS_FILE))?;
+    if stats.providers.is_empty() {
+        println!("No fetches recorded yet; the tracker counts them as it runs");
+        return Ok(());
+    }
+
+    let from = config::before(Local::now().naive_local(), since)?;
+    let totals = stats.since(from);
+    println!("Fetches since {}:", from.format("%Y-%m-%d %H:00"));
+    println!(
+        "{:<12} {:>8} {:>8} {:>12}  {:<20} Last error",
+        "Provider", "Fetches", "Errors", "Avg latency", "Last success"
+    );
+    for (provider, stats) in &stats.providers {
+        let total = totals.get(provider.as_str()).copied().unwrap_or_default();
+        let (errors, latency) = match total.fetches {
+            0 => ("n/a".to_string(), "n/a".to_string()),
+            fetches => (
+                format!("{:.1}%", total.failures as f64 / fetches as f64 * 100.0),
+                format!("{} ms", total.latency_ms / fetches),
+            ),
+        };
+        // Only an error since the last success is still current.
+        let error = match &stats.last_failure {
+            Some(failure) if stats.failing => {
+                format!("{} at {}", stats.last_error.as_deref().unwrap_or_default(), failure)
+            }
+            _ => "-".to_string(),
+        };
+        println!(
+            "{:<12} {:>8} {:>8} {:>12}  {:<20} {}",
+            provider,
+            total.fetches,
+            errors,
+            latency,
+            stats.last_success.as_deref().unwrap_or("never"),
+            error
+        );
+    }
+
+    if stats.providers.len() > 1 && stats.providers.values().all(|stats| stats.failing) {
+        println!("Every provider is failing, so the problem is likely on this end: the network, DNS or a proxy");
+    }
+    Ok(())
+}
+
+// What the data directory holds now, against the budget at the rate the
+// tracker has seen it grow.
+fn disk_usage(config: &Config) -> Result<(), PriceError> {
+    let directory = config.storage.directory();
+    let used = disk::measure(directory);
+    println!("Data in {}: {}", directory.display(), format_size(used as f64));
+    let growth = Samples::load(Path::new(DISK_FILE))?.growth();
+    if let Some(daily) = growth {
+        println!("Growing by {} a day", format_size(daily));
+    }
+    let Some(disk) = &config.disk else {
+        return Ok(());
+    };
+    let budget = disk.budget()?;
+    let share = used as f64 / budget as f64 * 100.0;
+    match disk::outlook(used, budget, growth) {
+        Outlook::Over => println!("Over the {} budget ({:.1}%)", format_size(budget as f64), share),
+        Outlook::FullIn(left) => {
+            println!("{:.1}% of the {} budget, full in {}", share, format_size(budget as f64), describe(left))
+        }
+        Outlook::Steady => println!("{:.1}% of the {} budget", share, format_size(budget as f64)),
+    }
+    Ok(())
+}
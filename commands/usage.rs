@@ -0,0 +1,31 @@
+use std::collections::BTreeSet;
+
+use crate::config::Config;
+use crate::http::quota::{Usage, USAGE_FILE};
+use crate::PriceError;
+
+pub fn run(config: &Config) -> Result<(), PriceError> {
+    let usage = Usage::load(&config.storage.directory().join(USAGE_FILE))?;
+    let http = config.http.clone().unwrap_or_default();
+    // Hosts with a quota are listed even before their first request.
+    let hosts: BTreeSet<&String> = usage.calls.keys().chain(http.quotas.keys()).collect();
+    if hosts.is_empty() {
+        println!("No requests made on {} (UTC)", usage.date);
+        return Ok(());
+    }
+
+    let width = hosts.iter().map(|host| host.len()).max().unwrap_or_default();
+    println!("Requests made on {} (UTC):", usage.date);
+    for host in hosts {
+        let calls = usage.calls.get(host).copied().unwrap_or_default();
+        match http.quotas.get(host) {
+            Some(&quota) => {
+                let used = calls as f64 / quota as f64 * 100.0;
+                let left = quota.saturating_sub(calls);
+                println!("  {:width$}  {} of {} ({:.1}%, {} left)", host, calls, quota, used, left);
+            }
+            None => println!("  {:width$}  {}", host, calls),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use crate::storage::chain::{self, Verdict};
+use crate::storage::Storage;
+use crate::{PriceError, Pricing};
+
+// Prints the head of each intact chain, to be noted down or published, and
+// fails if any history does not match its chain.
+pub fn run(assets: &[&dyn Pricing], storage: &dyn Storage, directory: &Path) -> Result<(), PriceError> {
+    let mut broken = 0;
+    for asset in assets {
+        match chain::verify(storage, directory, asset.id())? {
+            Verdict::Intact { records, head } => {
+                println!("{}: {} records verified, head {}", asset.name(), records, head)
+            }
+            Verdict::Broken(problem) => {
+                println!("{}: {}", asset.name(), problem);
+                broken += 1;
+            }
+            Verdict::Unchained => println!("{}: no hash chain (set hash_chain = true to start one)", asset.name()),
+        }
+    }
+    if broken > 0 {
+        return Err(PriceError::ParseError(format!(
+            "{} of {} histories do not match their hash chain",
+            broken,
+            assets.len()
+        )));
+    }
+    Ok(())
+}
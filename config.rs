@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::alerts::outage::OutageConfig;
+use crate::alerts::stale::StalenessConfig;
+use crate::alerts::signal::SignalConfig;
+use crate::alerts::RuleConfig;
+use crate::commands::export::GnucashConfig;
+use crate::commands::performance::BenchmarkConfig;
+use crate::digest::DigestConfig;
+use crate::disk::DiskConfig;
+use crate::display::DisplayConfig;
+use crate::email::SmtpConfig;
+use crate::fx::FxConfig;
+use crate::http::HttpConfig;
+use crate::inflation::InflationConfig;
+use crate::sources::mock::MockConfig;
+use crate::sources::orderbook::OrderBookConfig;
+use crate::sources::csv_http::CsvSourceConfig;
+use crate::sources::futures::FuturesConfig;
+use crate::sources::global::GlobalConfig;
+use crate::sources::json::JsonSourceConfig;
+use crate::sources::plugin::PluginConfig;
+use crate::sources::scrape::ScrapeConfig;
+use crate::sources::script::ScriptConfig;
+use crate::sources::yahoo::YahooConfig;
+use crate::notify::NotifierConfig;
+use crate::paper::PaperConfig;
+use crate::registry::AssetOverride;
+use crate::s3::S3Config;
+use crate::sftp::SftpConfig;
+use crate::sheets::SheetsConfig;
+use crate::storage::valid_currency;
+use crate::strategy::StrategyConfig;
+use crate::theme::ThemeConfig;
+use crate::watchlist::{TopConfig, DEFAULT_WATCHLIST_FILE};
+use crate::writer::QueueConfig;
+use crate::PriceError;
+
+pub mod check;
+pub mod env;
+pub mod secrets;
+
+pub const DEFAULT_CONFIG_FILE: &str = "tracker.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub storage: StorageConfig,
+    // Quote currencies fetched alongside USD, e.g. ["eur", "gbp", "jpy"].
+    pub currencies: Vec<String>,
+    // Assets every other price is also expressed in, e.g. ["bitcoin"].
+    pub denominations: Vec<String>,
+    // Time between two rounds of fetching, "10s" unless set.
+    pub interval: Option<String>,
+    // Most assets fetched at the same time, 4 unless set.
+    pub concurrency: Option<usize>,
+    // File listing the tracked assets, managed with `asset add/remove`.
+    pub watchlist: Option<PathBuf>,
+    pub top: Option<TopConfig>,
+    // Offline sources keyed by asset id, for testing.
+    pub mock: BTreeMap<String, MockConfig>,
+    // WASM price sources keyed by asset id.
+    pub plugins: BTreeMap<String, PluginConfig>,
+    // Rhai price sources keyed by asset id.
+    pub scripts: BTreeMap<String, ScriptConfig>,
+    // Generic JSON API sources keyed by asset id.
+    pub json: BTreeMap<String, JsonSourceConfig>,
+    // CSV-over-HTTP sources keyed by asset id.
+    pub csv: BTreeMap<String, CsvSourceConfig>,
+    // Web page scraping sources keyed by asset id.
+    pub scrape: BTreeMap<String, ScrapeConfig>,
+    // Yahoo Finance tickers keyed by asset id.
+    pub yahoo: BTreeMap<String, YahooConfig>,
+    // Perpetual futures mark prices and funding rates keyed by asset id.
+    pub futures: BTreeMap<String, FuturesConfig>,
+    // CoinGecko's market wide figures keyed by asset id.
+    pub global: BTreeMap<String, GlobalConfig>,
+    // Order books sampled with the prices of tracked assets, keyed by id.
+    pub orderbook: BTreeMap<String, OrderBookConfig>,
+    // File holding the values referred to as `{ secret = "<name>" }`.
+    pub secrets: Option<PathBuf>,
+    // Per-asset metadata overrides, keyed by asset id.
+    pub assets: BTreeMap<String, AssetOverride>,
+    pub smtp: Option<SmtpConfig>,
+    pub digest: Option<DigestConfig>,
+    pub alerts: Vec<RuleConfig>,
+    pub notifiers: Vec<NotifierConfig>,
+    pub outages: Option<OutageConfig>,
+    pub staleness: Option<StalenessConfig>,
+    pub signals: Vec<SignalConfig>,
+    pub fx: Option<FxConfig>,
+    pub http: Option<HttpConfig>,
+    // Write queue settings keyed by sink, the storage backend's name.
+    pub queues: BTreeMap<String, QueueConfig>,
+    pub s3: Option<S3Config>,
+    pub sheets: Option<SheetsConfig>,
+    pub sftp: Option<SftpConfig>,
+    pub gnucash: Option<GnucashConfig>,
+    pub paper: Option<PaperConfig>,
+    // Keyed by the name `backtest --strategy` takes.
+    pub strategies: BTreeMap<String, StrategyConfig>,
+    pub benchmark: Option<BenchmarkConfig>,
+    pub inflation: Option<InflationConfig>,
+    pub disk: Option<DiskConfig>,
+    pub display: Option<DisplayConfig>,
+    pub theme: Option<ThemeConfig>,
+    // Chains every stored record to the one before by a hash, for `verify`.
+    pub hash_chain: bool,
+    // File the config was read from, if any.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    // Top-level keys serde ignored, most likely misspelled sections.
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+    // The `[profiles.<name>]` table merged over the rest of the file.
+    #[serde(skip)]
+    pub profile: Option<String>,
+    // Set from TRACKER_ASSETS, replacing the watchlist file.
+    #[serde(skip)]
+    pub asset_list: Option<Vec<String>>,
+}
+
+pub const KNOWN_KEYS: [&str; 42] = [
+    "storage",
+    "interval",
+    "concurrency",
+    "currencies",
+    "denominations",
+    "watchlist",
+    "top",
+    "assets",
+    "smtp",
+    "digest",
+    "alerts",
+    "notifiers",
+    "outages",
+    "staleness",
+    "signals",
+    "fx",
+    "profiles",
+    "secrets",
+    "mock",
+    "http",
+    "plugins",
+    "scripts",
+    "json",
+    "csv",
+    "scrape",
+    "yahoo",
+    "futures",
+    "global",
+    "orderbook",
+    "queues",
+    "hash_chain",
+    "s3",
+    "sheets",
+    "sftp",
+    "gnucash",
+    "paper",
+    "strategies",
+    "benchmark",
+    "inflation",
+    "disk",
+    "display",
+    "theme",
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    Csv {
+        #[serde(default = "default_directory")]
+        directory: PathBuf,
+    },
+    // Fixed-width binary records, see storage/binary.rs.
+    Binary {
+        #[serde(default = "default_directory")]
+        directory: PathBuf,
+    },
+    Sqlite {
+        path: PathBuf,
+    },
+    // Sealed with the key in `key_file`, see storage/encrypted.rs.
+    Encrypted {
+        #[serde(default = "default_directory")]
+        directory: PathBuf,
+        key_file: PathBuf,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::Csv { directory: default_directory() }
+    }
+}
+
+impl StorageConfig {
+    pub fn backend(&self) -> &'static str {
+        match self {
+            StorageConfig::Csv { .. } => "csv",
+            StorageConfig::Binary { .. } => "binary",
+            StorageConfig::Sqlite { .. } => "sqlite",
+            StorageConfig::Encrypted { .. } => "encrypted",
+        }
+    }
+
+    // Where files kept alongside the history go.
+    pub fn directory(&self) -> &Path {
+        match self {
+            StorageConfig::Csv { directory }
+            | StorageConfig::Binary { directory }
+            | StorageConfig::Encrypted { directory, .. } => directory,
+            StorageConfig::Sqlite { path } => {
+                path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."))
+            }
+        }
+    }
+}
+
+fn default_directory() -> PathBuf {
+    PathBuf::from(".")
+}
+
+impl Config {
+    // An explicitly requested file must exist; the default one is optional so
+    // the tracker keeps working with no configuration at all. The selected
+    // profile is merged over the file, and TRACKER_* environment variables
+    // override both.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, PriceError> {
+        let path = match path {
+            Some(path) => Some(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Some(Path::new(DEFAULT_CONFIG_FILE)),
+            None => None,
+        };
+        let source = path.map_or("environment".to_string(), |path| path.display().to_string());
+
+        let contents = match path {
+            Some(path) => fs::read_to_string(path)
+                .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?,
+            None => String::new(),
+        };
+        let mut table: toml::Table = toml::from_str(&contents)
+            .map_err(|e| PriceError::ParseError(format!("{}: {}", source, e)))?;
+        let profiles = table.remove("profiles");
+        let mut layers = Vec::new();
+        if let Some(name) = profile {
+            if path.is_none() {
+                return Err(PriceError::FileError(format!("no config file to read profile '{}' from", name)));
+            }
+            let selected = profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(name))
+                .and_then(|profile| profile.as_table())
+                .ok_or_else(|| {
+                    let known: Vec<&str> = profiles
+                        .as_ref()
+                        .and_then(|profiles| profiles.as_table())
+                        .map(|profiles| profiles.keys().map(String::as_str).collect())
+                        .unwrap_or_default();
+                    PriceError::ParseError(format!(
+                        "{}: no profile '{}' (defined: {})",
+                        source,
+                        name,
+                        if known.is_empty() { "none".to_string() } else { known.join(", ") }
+                    ))
+                })?;
+            merge(&mut table, selected.clone());
+            layers.push(format!("profile {}", name));
+        }
+        layers.extend(env::apply(&mut table, std::env::vars())?);
+        secrets::resolve(&mut table)?;
+
+        // Parsing the file itself keeps line numbers in the errors; the
+        // merged table only has to be used when something was layered on top.
+        let parsed = if layers.is_empty() && profiles.is_none() && !table.contains_key("secrets") {
+            toml::from_str(&contents)
+        } else {
+            toml::Value::Table(table.clone()).try_into()
+        };
+        let mut config: Config = parsed.map_err(|e| match layers.as_slice() {
+            [] => PriceError::ParseError(format!("{}: {}", source, e)),
+            layers => PriceError::ParseError(format!("{} with {}: {}", source, layers.join(", "), e)),
+        })?;
+        config.profile = profile.map(str::to_string);
+        config.unknown_keys = table.keys().filter(|key| !KNOWN_KEYS.contains(&key.as_str())).cloned().collect();
+        config.path = path.map(Path::to_path_buf);
+        config.asset_list = std::env::var(env::ASSETS_VARIABLE).ok().map(|value| env::asset_list(&value));
+
+        for currency in &mut config.currencies {
+            *currency = currency.trim().to_ascii_lowercase();
+            if !valid_currency(currency) || currency == "usd" {
+                return Err(PriceError::ParseError(format!(
+                    "{}: invalid currency '{}' in currencies (USD is always included)",
+                    source,
+                    currency
+                )));
+            }
+        }
+        config.currencies.sort();
+        config.currencies.dedup();
+        Ok(config)
+    }
+
+    pub fn interval(&self) -> Result<chrono::Duration, PriceError> {
+        parse_duration(self.interval.as_deref().unwrap_or("10s"))
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(4)
+    }
+
+    pub fn watchlist_path(&self) -> &Path {
+        self.watchlist.as_deref().unwrap_or(Path::new(DEFAULT_WATCHLIST_FILE))
+    }
+}
+
+// Tables are merged key by key; anything else, arrays of tables such as
+// [[alerts]] included, replaces the base value.
+fn merge(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// Accepts compact durations such as "30s", "5m", "1h", "24h" or "30d".
+pub fn parse_duration(value: &str) -> Result<chrono::Duration, PriceError> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| PriceError::ParseError(format!("invalid duration '{}'", value)))?;
+
+    let duration = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => {
+            return Err(PriceError::ParseError(format!(
+                "invalid duration '{}', expected a number followed by s, m, h, d or w",
+                value
+            )))
+        }
+    };
+    duration.ok_or_else(|| PriceError::ParseError(format!("duration '{}' is out of range", value)))
+}
+
+// The time `duration` before `now`, for --since and --until; durations that
+// reach back past the earliest representable date are turned away.
+pub fn before(now: chrono::NaiveDateTime, duration: chrono::Duration) -> Result<chrono::NaiveDateTime, PriceError> {
+    now.checked_sub_signed(duration)
+        .ok_or_else(|| PriceError::ParseError(format!("{} days back is out of range", duration.num_days())))
+}
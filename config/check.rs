@@ -0,0 +1,248 @@
+// Collects every problem with a loaded config, each prefixed with the key it
+// concerns, so they can all be fixed in one go instead of one per restart.
+
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+use crate::alerts::AlertEngine;
+use crate::config::{parse_duration, Config, StorageConfig, KNOWN_KEYS};
+use crate::digest::Digest;
+use crate::display::NumberFormat;
+use crate::http::{self, headers, limit, quota, HttpConfig};
+use crate::inflation;
+use crate::notify;
+use crate::paper::PaperTrader;
+use crate::pipeline::Pipeline;
+use crate::s3::S3Sink;
+use crate::sftp::SftpSink;
+use crate::sheets::SheetsSink;
+use crate::sources;
+use crate::strategy;
+use crate::theme::Theme;
+use crate::watchlist::TopCoins;
+use crate::PriceError;
+
+pub fn problems(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    let client = http::default_client();
+    for key in &config.unknown_keys {
+        problems.push(format!("{}: unknown key, expected one of: {}", key, KNOWN_KEYS.join(", ")));
+    }
+
+    check_storage(&config.storage, &mut problems);
+    if config.interval.is_some() {
+        match config.interval() {
+            Ok(interval) if interval.num_seconds() < 1 => problems.push("interval: must be at least 1s".to_string()),
+            Ok(_) => {}
+            Err(e) => problems.push(format!("interval: {}", e.message())),
+        }
+    }
+    if config.concurrency == Some(0) {
+        problems.push("concurrency: must be at least 1".to_string());
+    }
+    for (sink, queue) in &config.queues {
+        if sink != config.storage.backend() {
+            problems.push(format!("queues.{}: no such sink, prices are written to {}", sink, config.storage.backend()));
+        }
+        if queue.capacity == 0 {
+            problems.push(format!("queues.{}.capacity: must be at least 1", sink));
+        }
+        if queue.hold == 0 {
+            problems.push(format!("queues.{}.hold: must be at least 1", sink));
+        }
+    }
+    if let Some(fx) = &config.fx {
+        check_duration("fx.refresh", &fx.refresh, &mut problems);
+    }
+    if let Some(inflation) = &config.inflation {
+        check_duration("inflation.refresh", &inflation.refresh, &mut problems);
+        if let Some(Err(e)) = inflation.base.as_deref().map(inflation::parse_month) {
+            problems.push(e.message().to_string());
+        }
+    }
+    if let Some(disk) = &config.disk {
+        for result in [disk.budget().map(|_| ()), disk.warn_within().map(|_| ())] {
+            if let Err(e) = result {
+                problems.push(e.message().to_string());
+            }
+        }
+    }
+    if let Err(e) = NumberFormat::from_config(config) {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = Theme::from_config(config) {
+        problems.push(e.message().to_string());
+    }
+    if let Some(http) = &config.http {
+        check_http(http, &mut problems);
+    }
+    if let Some(top) = &config.top {
+        if check_duration("top.refresh", &top.refresh, &mut problems) {
+            if let Err(e) = TopCoins::from_config(config, client.clone()) {
+                problems.push(e.message().to_string());
+            }
+        }
+    }
+    if let Err(e) = Digest::from_config(config) {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = S3Sink::from_config(config, &client) {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = SheetsSink::from_config(config, &client) {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = SftpSink::from_config(config) {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = Pipeline::from_config(config) {
+        problems.push(e.message().to_string());
+    }
+    if let Some(benchmark) = &config.benchmark {
+        for window in &benchmark.windows {
+            check_duration("benchmark.windows", window, &mut problems);
+        }
+    }
+    for (name, strategy) in &config.strategies {
+        if let Err(e) = strategy::build(name, strategy) {
+            problems.push(e.message().to_string());
+        }
+    }
+
+    let mut notifiers_ok = true;
+    for notifier in &config.notifiers {
+        if let Err(e) = notify::build(notifier, config, &client) {
+            problems.push(e.message().to_string());
+            notifiers_ok = false;
+        }
+    }
+
+    match sources::tracked(config, &client) {
+        Ok(assets) => {
+            let tracked = |id: &str| assets.iter().any(|asset| asset.id() == id);
+            for id in config.denominations.iter().filter(|id| !tracked(id)) {
+                problems.push(format!("denominations: '{}' is not a tracked asset", id));
+            }
+            if let Some(benchmark) = config.benchmark.as_ref().filter(|benchmark| !tracked(&benchmark.asset)) {
+                problems.push(format!("benchmark.asset: '{}' is not a tracked asset", benchmark.asset));
+            }
+            for id in config.orderbook.keys().filter(|id| !tracked(id)) {
+                problems.push(format!("orderbook.{}: '{}' is not a tracked asset", id, id));
+            }
+            for id in config.assets.keys().filter(|id| !tracked(id)) {
+                problems.push(format!("assets.{}: not a tracked asset (add it with `asset add {}`)", id, id));
+            }
+            // Rules are only checked once their notifiers build, so a broken
+            // notifier is not reported twice.
+            if notifiers_ok {
+                if let Err(e) = AlertEngine::new(config, &assets, &client) {
+                    problems.push(e.message().to_string());
+                }
+            }
+            if let Err(e) = PaperTrader::from_config(config, &assets) {
+                problems.push(e.message().to_string());
+            }
+        }
+        Err(e) => problems.push(e.message().to_string()),
+    }
+    problems
+}
+
+// Fails with every problem at once, for the tracker to refuse to start.
+pub fn validate(config: &Config) -> Result<(), PriceError> {
+    let problems = problems(config);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let source = config.path.as_ref().map_or("the config".to_string(), |path| path.display().to_string());
+    Err(PriceError::ParseError(format!(
+        "{} has {} problem{}:\n  {}",
+        source,
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" },
+        problems.join("\n  ")
+    )))
+}
+
+fn check_duration(key: &str, value: &str, problems: &mut Vec<String>) -> bool {
+    match parse_duration(value) {
+        Ok(_) => true,
+        Err(e) => {
+            problems.push(format!("{}: {}", key, e.message()));
+            false
+        }
+    }
+}
+
+fn check_http(http: &HttpConfig, problems: &mut Vec<String>) {
+    if let Err(e) = http.timeout() {
+        problems.push(e.message().to_string());
+    }
+    if let Err(e) = http.max_clock_skew() {
+        problems.push(e.message().to_string());
+    }
+    if !(http.quota_warning > 0.0 && http.quota_warning <= 1.0) {
+        problems.push("http.quota_warning: must be above 0 and at most 1".to_string());
+    }
+    for (host, rate) in &http.rate_limits {
+        if let Err(e) = limit::parse_rate(host, rate) {
+            problems.push(e.message().to_string());
+        }
+    }
+    for (host, quota) in &http.quotas {
+        if quota::host(host) != host {
+            problems.push(format!("http.quotas: '{}' is not a host name, e.g. api.coingecko.com", host));
+        }
+        if *quota == 0 {
+            problems.push(format!("http.quotas.\"{}\": must be at least 1", host));
+        }
+    }
+    if let Err(e) = headers::check(http.user_agent.as_deref(), &http.headers) {
+        problems.push(e.message().to_string());
+    }
+    for host in http.headers.keys().filter(|host| quota::host(host) != host.as_str()) {
+        problems.push(format!("http.headers: '{}' is not a host name, e.g. api.coingecko.com", host));
+    }
+    for host in http.tls.pins.keys().filter(|host| quota::host(host) != host.as_str()) {
+        problems.push(format!("http.tls.pins: '{}' is not a host name, e.g. api.coingecko.com", host));
+    }
+    if let Err(e) = http.tls.client_config() {
+        problems.push(e.message().to_string());
+    }
+}
+
+fn check_storage(storage: &StorageConfig, problems: &mut Vec<String>) {
+    let (key, directory) = match storage {
+        StorageConfig::Csv { directory } | StorageConfig::Binary { directory } => {
+            ("storage.directory", directory.as_path())
+        }
+        StorageConfig::Encrypted { directory, key_file } => {
+            if !key_file.exists() {
+                problems.push(format!(
+                    "storage.key_file: {} does not exist; create it with `keygen`",
+                    key_file.display()
+                ));
+            }
+            ("storage.directory", directory.as_path())
+        }
+        StorageConfig::Sqlite { path } if path.exists() => {
+            if let Err(e) = OpenOptions::new().append(true).open(path) {
+                problems.push(format!("storage.path: {} is not writable: {}", path.display(), e));
+            }
+            return;
+        }
+        StorageConfig::Sqlite { path } => (
+            "storage.path",
+            path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new(".")),
+        ),
+    };
+
+    if !directory.is_dir() {
+        problems.push(format!("{}: directory {} does not exist", key, directory.display()));
+        return;
+    }
+    let probe = directory.join(".tracker_write_test");
+    if let Err(e) = fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe)) {
+        problems.push(format!("{}: cannot write to {}: {}", key, directory.display(), e));
+    }
+}
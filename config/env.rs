@@ -0,0 +1,87 @@
+// Layers TRACKER_* environment variables over the config file, so a
+// container can be configured without mounting one. `__` separates nested
+// keys and array positions: TRACKER_STORAGE__BACKEND=sqlite,
+// TRACKER_SMTP__PASSWORD=..., TRACKER_ALERTS__0__ABOVE=70000.
+
+use toml::{Table, Value};
+
+use crate::PriceError;
+
+pub const PREFIX: &str = "TRACKER_";
+
+// Replaces the watchlist rather than setting a key, since `assets` holds the
+// per-asset metadata.
+pub const ASSETS_VARIABLE: &str = "TRACKER_ASSETS";
+// Read by the command line parser for --profile.
+pub const PROFILE_VARIABLE: &str = "TRACKER_PROFILE";
+
+// Keys whose values are lists, so "eur,gbp" is split even when the file does
+// not set them.
+const LIST_KEYS: [&str; 2] = ["currencies", "denominations"];
+
+// Applies every override and returns the variables used.
+pub fn apply(table: &mut Table, vars: impl Iterator<Item = (String, String)>) -> Result<Vec<String>, PriceError> {
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(PREFIX) else {
+            continue;
+        };
+        if name == ASSETS_VARIABLE || name == PROFILE_VARIABLE || key.is_empty() {
+            continue;
+        }
+        let path: Vec<String> = key.split("__").map(|part| part.to_ascii_lowercase()).collect();
+        set(table, &path, &raw).map_err(|reason| PriceError::ParseError(format!("{}: {}", name, reason)))?;
+        applied.push(name);
+    }
+    Ok(applied)
+}
+
+pub fn asset_list(value: &str) -> Vec<String> {
+    split(value).into_iter().map(|id| id.to_ascii_lowercase()).collect()
+}
+
+fn split(value: &str) -> Vec<String> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect()
+}
+
+fn set(table: &mut Table, path: &[String], raw: &str) -> Result<(), String> {
+    let [key, rest @ ..] = path else {
+        return Err("empty key".to_string());
+    };
+    if rest.is_empty() {
+        let value = parse(raw, key, table.get(key));
+        table.insert(key.clone(), value);
+        return Ok(());
+    }
+    match table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new())) {
+        Value::Table(table) => set(table, rest, raw),
+        Value::Array(items) => {
+            let [index, rest @ ..] = rest else {
+                unreachable!("rest is not empty");
+            };
+            let index: usize = index.parse().map_err(|_| format!("'{}' is not an index into {}", index, key))?;
+            match items.get_mut(index) {
+                Some(Value::Table(table)) if !rest.is_empty() => set(table, rest, raw),
+                Some(_) => Err(format!("expected {}__<index>__<key>", key.to_uppercase())),
+                None => Err(format!("{} has no entry {}", key, index)),
+            }
+        }
+        _ => Err(format!("'{}' is not a table", key)),
+    }
+}
+
+// Values are read as TOML when they parse as such (numbers, booleans,
+// arrays), except where the file already has a string; quote the value to
+// force a string anywhere else.
+fn parse(raw: &str, key: &str, existing: Option<&Value>) -> Value {
+    let parsed = toml::from_str::<Table>(&format!("value = {}", raw)).ok().and_then(|mut t| t.remove("value"));
+    let list = LIST_KEYS.contains(&key) || matches!(existing, Some(Value::Array(_)));
+    match parsed {
+        Some(value @ Value::Array(_)) => value,
+        _ if list => Value::Array(split(raw).into_iter().map(Value::String).collect()),
+        Some(Value::String(value)) => Value::String(value),
+        _ if matches!(existing, Some(Value::String(_))) => Value::String(raw.to_string()),
+        Some(value) => value,
+        None => Value::String(raw.to_string()),
+    }
+}
@@ -0,0 +1,93 @@
+// Keeps passwords and API tokens out of the main config: it names a secrets
+// file with `secrets = "secrets.toml"` and refers to its entries as
+// `password = { secret = "smtp_password" }`.
+
+use std::fs;
+use std::path::Path;
+
+use toml::{Table, Value};
+
+use crate::PriceError;
+
+// Replaces every `{ secret = "<name>" }` in the table with that entry of the
+// secrets file.
+pub fn resolve(table: &mut Table) -> Result<(), PriceError> {
+    let secrets = match table.get("secrets") {
+        Some(Value::String(path)) => Some(load(Path::new(path))?),
+        Some(_) => return Err(PriceError::ParseError("secrets: expected the path of the secrets file".to_string())),
+        None => None,
+    };
+    replace(table, "", secrets.as_ref())
+}
+
+fn load(path: &Path) -> Result<Table, PriceError> {
+    check_permissions(path)?;
+    let contents =
+        fs::read_to_string(path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    toml::from_str(&contents).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+// Secrets and keys must not be readable by other users.
+#[cfg(unix)]
+pub fn check_permissions(path: &Path) -> Result<(), PriceError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path)
+        .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?
+        .permissions()
+        .mode();
+    if mode & 0o004 != 0 {
+        return Err(PriceError::FileError(format!(
+            "{} is readable by everyone (mode {:o}), refusing to use it; run `chmod 600 {}`",
+            path.display(),
+            mode & 0o777,
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn check_permissions(_path: &Path) -> Result<(), PriceError> {
+    Ok(())
+}
+
+fn replace(table: &mut Table, prefix: &str, secrets: Option<&Table>) -> Result<(), PriceError> {
+    for (key, value) in table.iter_mut() {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        replace_value(value, &key, secrets)?;
+    }
+    Ok(())
+}
+
+fn replace_value(value: &mut Value, key: &str, secrets: Option<&Table>) -> Result<(), PriceError> {
+    match value {
+        Value::Table(table) => match reference(table) {
+            Some(name) => {
+                let secrets = secrets.ok_or_else(|| {
+                    PriceError::ParseError(format!("{}: refers to secret '{}' but no secrets file is set", key, name))
+                })?;
+                let secret = secrets
+                    .get(name)
+                    .ok_or_else(|| PriceError::ParseError(format!("{}: no secret '{}' in the secrets file", key, name)))?;
+                *value = secret.clone();
+                Ok(())
+            }
+            None => replace(table, key, secrets),
+        },
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                replace_value(item, &format!("{}[{}]", key, i), secrets)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn reference(table: &Table) -> Option<&str> {
+    match (table.len(), table.get("secret")) {
+        (1, Some(Value::String(name))) => Some(name),
+        _ => None,
+    }
+}
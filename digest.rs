@@ -0,0 +1,179 @@
+use chrono::{Datelike, Duration, Local, NaiveDateTime, NaiveTime, Weekday};
+use serde::Deserialize;
+
+use crate::analytics::{self, Summarizer};
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::email::{Mailer, SmtpConfig};
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DigestConfig {
+    #[serde(default)]
+    pub schedule: Schedule,
+    // Local time of day, "HH:MM".
+    #[serde(default = "default_time")]
+    pub time: String,
+    // Only used by weekly digests.
+    #[serde(default = "default_weekday")]
+    pub weekday: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+fn default_time() -> String {
+    "08:00".to_string()
+}
+
+fn default_weekday() -> String {
+    "mon".to_string()
+}
+
+impl Schedule {
+    pub fn period(self) -> Duration {
+        match self {
+            Schedule::Daily => Duration::hours(24),
+            Schedule::Weekly => Duration::days(7),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Schedule::Daily => "24h",
+            Schedule::Weekly => "7d",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Schedule::Daily => "Daily",
+            Schedule::Weekly => "Weekly",
+        }
+    }
+}
+
+pub struct Digest {
+    config: DigestConfig,
+    time: NaiveTime,
+    weekday: Weekday,
+    mailer: Mailer,
+    next: NaiveDateTime,
+    format: NumberFormat,
+}
+
+impl Digest {
+    pub fn from_config(config: &Config) -> Result<Option<Self>, PriceError> {
+        match (&config.digest, &config.smtp) {
+            (Some(digest), Some(smtp)) => Digest::new(digest, smtp, NumberFormat::from_config(config)?).map(Some),
+            (Some(_), None) => Err(PriceError::ParseError("[digest] requires an [smtp] section".to_string())),
+            (None, _) => Ok(None),
+        }
+    }
+
+    pub fn new(config: &DigestConfig, smtp: &SmtpConfig, format: NumberFormat) -> Result<Self, PriceError> {
+        let time = NaiveTime::parse_from_str(&config.time, "%H:%M")
+            .map_err(|_| PriceError::ParseError(format!("invalid digest.time '{}', expected HH:MM", config.time)))?;
+        let weekday = config
+            .weekday
+            .parse::<Weekday>()
+            .map_err(|_| PriceError::ParseError(format!("invalid digest.weekday '{}'", config.weekday)))?;
+
+        let mut digest = Digest {
+            config: config.clone(),
+            time,
+            weekday,
+            mailer: Mailer::new(smtp)?,
+            next: NaiveDateTime::MIN,
+            format,
+        };
+        digest.next = digest.next_after(Local::now().naive_local());
+        Ok(digest)
+    }
+
+    fn next_after(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let mut candidate = now.date().and_time(self.time);
+        loop {
+            let weekday_ok = self.config.schedule == Schedule::Daily || candidate.weekday() == self.weekday;
+            if candidate > now && weekday_ok {
+                return candidate;
+            }
+            candidate += Duration::days(1);
+        }
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        now >= self.next
+    }
+
+    pub fn send(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage) -> Result<(), PriceError> {
+        let (subject, body) = self.prepare(assets, storage)?;
+        self.mailer.send(&self.config.to, &subject, &body)?;
+        println!("Sent {} digest to {}", self.config.schedule.label(), self.config.to.join(", "));
+        Ok(())
+    }
+
+    // Prints the digest that is due instead of mailing it.
+    pub fn preview(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage) -> Result<(), PriceError> {
+        let (subject, body) = self.prepare(assets, storage)?;
+        println!("[dry run] would send \"{}\" to {}:\n{}", subject, self.config.to.join(", "), body);
+        Ok(())
+    }
+
+    fn prepare(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage) -> Result<(String, String), PriceError> {
+        let now = Local::now().naive_local();
+        self.next = self.next_after(now);
+
+        let body = compose(assets, storage, self.config.schedule, &self.format)?;
+        let subject = format!("{} price digest — {}", self.config.schedule.title(), now.format("%Y-%m-%d"));
+        Ok((subject, body))
+    }
+}
+
+pub fn compose(
+    assets: &[Box<dyn Pricing>],
+    storage: &dyn Storage,
+    schedule: Schedule,
+    format: &NumberFormat,
+) -> Result<String, PriceError> {
+    let mut body = format!(
+        "{} price digest — {}\n\n{:<12} {:>14} {:>10} {:>14} {:>14}\n",
+        schedule.title(),
+        Local::now().format(TIMESTAMP_FORMAT),
+        "Asset",
+        "Last",
+        format!("{} chg", schedule.label()),
+        "High",
+        "Low"
+    );
+
+    for asset in assets {
+        let window = analytics::Window::new(Some(schedule.period()), None)?;
+        let mut summarizer = Summarizer::default();
+        for record in storage.scan(asset.id(), window.start())? {
+            let record = record?;
+            if window.contains(&record) {
+                summarizer.push(&record);
+            }
+        }
+        match summarizer.finish() {
+            Some(summary) => body.push_str(&format!(
+                "{:<12} {:>14} {:>9}% {:>14} {:>14}\n",
+                asset.name(),
+                format.number(summary.last.price, 2),
+                format.number(summary.change_pct(), 2),
+                format.number(summary.high, 2),
+                format.number(summary.low, 2)
+            )),
+            None => body.push_str(&format!("{:<12} {:>14}\n", asset.name(), "no data")),
+        }
+    }
+    Ok(body)
+}
@@ -0,0 +1,224 @@
+// How much the data directory holds, sampled every hour into DISK_FILE for
+// `status`, and when it will reach the budget set in [disk] at the rate it
+// has grown over the samples kept:
+//
+//   [disk]
+//   budget = "2GB"        # or "500MiB" and so on
+//   warn_within = "30d"   # warn once the projection is this close
+//
+// Nothing here deletes records; the warning is the cue to archive or prune
+// the history before the disk fills.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::outage::describe;
+use crate::config::{parse_duration, Config};
+use crate::storage::TIMESTAMP_FORMAT;
+use crate::uptime::hour_of;
+use crate::PriceError;
+
+pub const DISK_FILE: &str = "disk_usage.json";
+
+const RETENTION: Duration = Duration::days(30);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskConfig {
+    pub budget: String,
+    #[serde(default = "default_warn_within")]
+    pub warn_within: String,
+}
+
+fn default_warn_within() -> String {
+    "30d".to_string()
+}
+
+impl DiskConfig {
+    pub fn budget(&self) -> Result<u64, PriceError> {
+        parse_size(&self.budget).map_err(|e| PriceError::ParseError(format!("disk.budget: {}", e.message())))
+    }
+
+    pub fn warn_within(&self) -> Result<Duration, PriceError> {
+        parse_duration(&self.warn_within)
+            .map_err(|e| PriceError::ParseError(format!("disk.warn_within: {}", e.message())))
+    }
+}
+
+// Bytes held when first sampled in each hour, keyed like the hours of
+// provider_stats.json.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Samples {
+    pub hours: BTreeMap<String, u64>,
+}
+
+impl Samples {
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        match fs::read_to_string(path) {
+            Ok(body) => {
+                serde_json::from_str(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+            }
+            Err(_) => Ok(Samples::default()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let body = serde_json::to_string_pretty(self).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        fs::write(path, body).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+
+    // Bytes a day from the oldest sample to the newest, once they are an
+    // hour apart; None while the directory is not growing.
+    pub fn growth(&self) -> Option<f64> {
+        let parse = |hour: &str| NaiveDateTime::parse_from_str(hour, TIMESTAMP_FORMAT).ok();
+        let ((first, from), (last, to)) = (self.hours.first_key_value()?, self.hours.last_key_value()?);
+        let hours = (parse(last)? - parse(first)?).num_hours();
+        (hours > 0 && to > from).then(|| (to - from) as f64 / hours as f64 * 24.0)
+    }
+}
+
+// Where a directory stands against its budget.
+pub enum Outlook {
+    Over,
+    // Full in this long at the current rate.
+    FullIn(Duration),
+    Steady,
+}
+
+pub fn outlook(used: u64, budget: u64, growth: Option<f64>) -> Outlook {
+    if used >= budget {
+        return Outlook::Over;
+    }
+    match growth {
+        Some(daily) => Outlook::FullIn(Duration::seconds(((budget - used) as f64 / daily * 86400.0) as i64)),
+        None => Outlook::Steady,
+    }
+}
+
+// Every file under `dir`, without following links.
+pub fn measure(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+        .map(|(path, metadata)| if metadata.is_dir() { measure(&path) } else { metadata.len() })
+        .sum()
+}
+
+// Accepts "750KB", "1.5GB", "512MiB" and so on; KB and up count in
+// thousands, KiB and up in 1024s.
+pub fn parse_size(value: &str) -> Result<u64, PriceError> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let invalid = || PriceError::ParseError(format!("invalid size '{}', expected e.g. 500MB or 2GiB", value));
+    let amount: f64 = amount.parse().map_err(|_| invalid())?;
+    let unit: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    Ok((amount * unit as f64) as u64)
+}
+
+pub fn format_size(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let (mut size, mut unit) = (bytes, "B");
+    for next in UNITS {
+        if size < 1000.0 {
+            break;
+        }
+        (size, unit) = (size / 1000.0, next);
+    }
+    if unit == "B" {
+        format!("{:.0} B", size)
+    } else {
+        format!("{:.1} {}", size, unit)
+    }
+}
+
+// Samples the directory for the tracker, warning when the budget is near
+// and once more when it no longer is, rather than every hour.
+pub struct DiskMonitor {
+    directory: PathBuf,
+    path: PathBuf,
+    samples: Samples,
+    budget: Option<(u64, Duration)>,
+    sampled: Option<String>,
+    warned: bool,
+}
+
+impl DiskMonitor {
+    pub fn new(config: &Config) -> Result<Self, PriceError> {
+        let budget = match &config.disk {
+            Some(disk) => Some((disk.budget()?, disk.warn_within()?)),
+            None => None,
+        };
+        let path = PathBuf::from(DISK_FILE);
+        let samples = Samples::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error reading disk usage: {}", e);
+            Samples::default()
+        });
+        let directory = config.storage.directory().to_path_buf();
+        Ok(DiskMonitor { directory, path, samples, budget, sampled: None, warned: false })
+    }
+
+    // Samples the directory the first time in each hour.
+    pub fn check(&mut self, now: NaiveDateTime, save: bool) {
+        let hour = hour_of(now);
+        if self.sampled.as_ref() == Some(&hour) {
+            return;
+        }
+        let used = measure(&self.directory);
+        self.samples.hours.insert(hour.clone(), used);
+        self.samples.hours = self.samples.hours.split_off(&hour_of(now - RETENTION));
+        self.sampled = Some(hour);
+        if save {
+            if let Err(e) = self.samples.save(&self.path) {
+                eprintln!("Error saving disk usage: {}", e);
+            }
+        }
+
+        let Some((budget, warn_within)) = self.budget else {
+            return;
+        };
+        let directory = self.directory.display();
+        let warning = match outlook(used, budget, self.samples.growth()) {
+            Outlook::Over => Some(format!(
+                "Warning: {} holds {}, over its {} budget",
+                directory,
+                format_size(used as f64),
+                format_size(budget as f64)
+            )),
+            Outlook::FullIn(left) if left <= warn_within => Some(format!(
+                "Warning: {} holds {} of its {} budget and grows by {} a day; at that rate it is full in {}",
+                directory,
+                format_size(used as f64),
+                format_size(budget as f64),
+                format_size(self.samples.growth().unwrap_or_default()),
+                describe(left)
+            )),
+            _ => None,
+        };
+        let warned = warning.is_some();
+        match warning {
+            Some(warning) if !self.warned => eprintln!("{}", warning),
+            None if self.warned => println!("{} is within its disk budget again", directory),
+            _ => {}
+        }
+        self.warned = warned;
+    }
+}
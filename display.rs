@@ -0,0 +1,142 @@
+// How numbers are shown on the console, in the repl and in reports, set in
+// [display]:
+//
+//   [display]
+//   locale = "de"              # 1.234,56; or "system" for LC_ALL, LC_NUMERIC or LANG
+//   currency_symbols = true    # 1.234,56 € rather than EUR 1234.56
+//
+// Without the section prices print as they always have, 60123.45 with no
+// grouping. Stored records, exports and the alert log are never affected.
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::PriceError;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    pub locale: Option<String>,
+    pub currency_symbols: bool,
+}
+
+// Languages by the separators they use, thousands then decimal, and whether
+// the currency goes after the amount.
+const LOCALES: [(&[&str], char, char, bool); 4] = [
+    (&["en", "ja", "ko", "zh", "he", "th"], ',', '.', false),
+    (&["de", "es", "it", "nl", "pt", "da", "id", "tr", "el", "ro", "hr", "sl"], '.', ',', true),
+    (&["fr", "ru", "sv", "nb", "nn", "no", "fi", "pl", "cs", "sk", "uk", "hu", "bg"], '\u{a0}', ',', true),
+    (&["de-ch", "fr-ch", "it-ch", "rm"], '’', '.', false),
+];
+
+const SYMBOLS: [(&str, &str); 8] = [
+    ("usd", "$"),
+    ("eur", "€"),
+    ("gbp", "£"),
+    ("jpy", "¥"),
+    ("cny", "¥"),
+    ("inr", "₹"),
+    ("krw", "₩"),
+    ("btc", "₿"),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct NumberFormat {
+    grouping: Option<char>,
+    decimal: char,
+    symbols: bool,
+    symbol_after: bool,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat { grouping: None, decimal: '.', symbols: false, symbol_after: false }
+    }
+}
+
+impl NumberFormat {
+    pub fn from_config(config: &Config) -> Result<Self, PriceError> {
+        let Some(display) = &config.display else {
+            return Ok(NumberFormat::default());
+        };
+        let mut format = match display.locale.as_deref() {
+            // An unknown system locale is not worth refusing to start over.
+            Some("system") => system_locale().and_then(|locale| locale_format(&locale).ok()).unwrap_or_default(),
+            Some(locale) => locale_format(locale)?,
+            None => NumberFormat::default(),
+        };
+        format.symbols = display.currency_symbols;
+        Ok(format)
+    }
+
+    pub fn number(&self, value: f64, precision: usize) -> String {
+        let fixed = format!("{:.*}", precision, value.abs());
+        let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+        let mut out = String::new();
+        if value.is_sign_negative() && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            out.push('-');
+        }
+        for (i, digit) in whole.chars().enumerate() {
+            if let Some(separator) = self.grouping.filter(|_| i > 0 && (whole.len() - i) % 3 == 0) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        if !fraction.is_empty() {
+            out.push(self.decimal);
+            out.push_str(fraction);
+        }
+        out
+    }
+
+    // With a sign either way, as for changes.
+    pub fn signed(&self, value: f64, precision: usize) -> String {
+        let number = self.number(value, precision);
+        if number.starts_with('-') {
+            number
+        } else {
+            format!("+{}", number)
+        }
+    }
+
+    // "$60123.45" and "EUR 60123.45" unless currency symbols are on.
+    pub fn money(&self, value: f64, precision: usize, currency: &str) -> String {
+        let number = self.number(value, precision);
+        let symbol = SYMBOLS.iter().find(|(code, _)| code.eq_ignore_ascii_case(currency)).map(|(_, symbol)| *symbol);
+        match symbol {
+            Some("$") if !self.symbols && currency.eq_ignore_ascii_case("usd") => format!("${}", number),
+            Some(symbol) if self.symbols && self.symbol_after => format!("{} {}", number, symbol),
+            Some(symbol) if self.symbols => format!("{}{}", symbol, number),
+            _ if self.symbols && self.symbol_after => format!("{} {}", number, currency.to_uppercase()),
+            _ => format!("{} {}", currency.to_uppercase(), number),
+        }
+    }
+}
+
+// "de_DE.UTF-8" becomes "de-de"; C and POSIX mean no locale.
+fn system_locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())?;
+    let locale = value.split(['.', '@']).next().unwrap_or_default().replace('_', "-").to_lowercase();
+    (locale != "c" && locale != "posix").then_some(locale)
+}
+
+fn locale_format(locale: &str) -> Result<NumberFormat, PriceError> {
+    let locale = locale.trim().replace('_', "-").to_lowercase();
+    let language = locale.split('-').next().unwrap_or_default();
+    let found = LOCALES
+        .iter()
+        .find(|(names, ..)| names.contains(&locale.as_str()))
+        .or_else(|| LOCALES.iter().find(|(names, ..)| names.contains(&language)));
+    let Some((_, grouping, decimal, symbol_after)) = found else {
+        let known: Vec<&str> = LOCALES.iter().flat_map(|(names, ..)| names.iter().copied()).collect();
+        return Err(PriceError::ParseError(format!(
+            "display.locale: unknown locale '{}', expected one of: {}, or system",
+            locale,
+            known.join(", ")
+        )));
+    };
+    Ok(NumberFormat { grouping: Some(*grouping), decimal: *decimal, symbols: false, symbol_after: *symbol_after })
+}
@@ -0,0 +1,107 @@
+// Dividends of Yahoo Finance tickers and the total return they add to the
+// price. The events are cached next to the history as
+// `dividends_<asset>.csv` and fetched again once a day.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveDate;
+
+use crate::config::Config;
+use crate::http::Client;
+use crate::registry::{AssetInfo, Provider};
+use crate::sources::yahoo::{self, Dividend};
+use crate::storage::PriceRecord;
+use crate::PriceError;
+
+const HEADER: &str = "date,amount";
+// Dividends are announced weeks ahead, so a day old list is current enough.
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub fn cache_path(config: &Config, asset: &str) -> PathBuf {
+    config.storage.directory().join(format!("dividends_{}.csv", asset))
+}
+
+// The asset's dividends, from the cache unless it is missing or stale or
+// `refresh` is set; a stale cache is still used when the download fails.
+pub fn load(config: &Config, info: &AssetInfo, client: &Client, refresh: bool) -> Result<Vec<Dividend>, PriceError> {
+    if info.provider != Provider::Yahoo {
+        return Err(PriceError::ParseError(format!(
+            "{}: dividends are only available for Yahoo Finance tickers",
+            info.id
+        )));
+    }
+    let cache = cache_path(config, &info.id);
+    let age = fs::metadata(&cache)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if refresh || age.is_none_or(|age| age >= MAX_AGE) {
+        match yahoo::fetch_dividends(client, &info.provider_id) {
+            Ok(dividends) => {
+                if let Err(e) = write(&cache, &dividends) {
+                    eprintln!("Error caching dividends: {}", e);
+                }
+                return Ok(dividends);
+            }
+            Err(e) if age.is_some() => eprintln!("{}: {} (using cached dividends)", info.id, e.message()),
+            Err(e) => return Err(PriceError::NetworkError(format!("{} dividends: {}", info.id, e.message()))),
+        }
+    }
+    read(&cache)
+}
+
+fn write(path: &Path, dividends: &[Dividend]) -> Result<(), PriceError> {
+    let mut data = format!("{}\n", HEADER);
+    for dividend in dividends {
+        data.push_str(&format!("{},{}\n", dividend.date, dividend.amount));
+    }
+    fs::write(path, data).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+}
+
+fn read(path: &Path) -> Result<Vec<Dividend>, PriceError> {
+    let text = fs::read_to_string(path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    let mut dividends = Vec::new();
+    for (index, line) in text.lines().enumerate().skip(1).filter(|(_, line)| !line.is_empty()) {
+        let invalid = || {
+            PriceError::ParseError(format!("{} line {}: expected a date and an amount", path.display(), index + 1))
+        };
+        let (date, amount) = line.split_once(',').ok_or_else(invalid)?;
+        dividends.push(Dividend {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| invalid())?,
+            amount: amount.parse().map_err(|_| invalid())?,
+        });
+    }
+    Ok(dividends)
+}
+
+// The price with every dividend reinvested at the close before its ex-day,
+// fed the records in time order. Starts out equal to the first price.
+pub struct TotalReturn<'a> {
+    dividends: &'a [Dividend],
+    // Dividends already reinvested.
+    paid: usize,
+    // Shares held per share at the start.
+    shares: f64,
+    last_price: Option<f64>,
+}
+
+impl<'a> TotalReturn<'a> {
+    pub fn new(dividends: &'a [Dividend]) -> Self {
+        TotalReturn { dividends, paid: 0, shares: 1.0, last_price: None }
+    }
+
+    pub fn push(&mut self, record: &PriceRecord) -> f64 {
+        let day = record.timestamp.date();
+        while let Some(dividend) = self.dividends.get(self.paid).filter(|dividend| dividend.date <= day) {
+            // Ones before the history starts were never earned.
+            if let Some(price) = self.last_price.filter(|price| *price > 0.0) {
+                self.shares *= 1.0 + dividend.amount / price;
+            }
+            self.paid += 1;
+        }
+        self.last_price = Some(record.price);
+        record.price * self.shares
+    }
+}
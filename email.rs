@@ -0,0 +1,79 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::Deserialize;
+
+use crate::PriceError;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    #[serde(default)]
+    pub tls: TlsMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TlsMode {
+    // Plain connection upgraded with STARTTLS, usually port 587.
+    #[default]
+    Starttls,
+    // TLS from the first byte, usually port 465.
+    Wrapper,
+    // No encryption; only for local relays.
+    None,
+}
+
+pub struct Mailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn new(config: &SmtpConfig) -> Result<Self, PriceError> {
+        let smtp_error = |e: lettre::transport::smtp::Error| PriceError::NetworkError(format!("smtp: {}", e));
+        let builder = match config.tls {
+            TlsMode::Starttls => SmtpTransport::starttls_relay(&config.host).map_err(smtp_error)?,
+            TlsMode::Wrapper => SmtpTransport::relay(&config.host).map_err(smtp_error)?,
+            TlsMode::None => SmtpTransport::builder_dangerous(&config.host),
+        };
+        let builder = match config.port {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        let builder = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        let from = config
+            .from
+            .parse()
+            .map_err(|e| PriceError::ParseError(format!("invalid smtp.from '{}': {}", config.from, e)))?;
+        Ok(Mailer { transport: builder.build(), from })
+    }
+
+    pub fn send(&self, to: &[String], subject: &str, body: &str) -> Result<(), PriceError> {
+        let mut message = Message::builder().from(self.from.clone()).subject(subject);
+        for address in to {
+            let mailbox: Mailbox = address
+                .parse()
+                .map_err(|e| PriceError::ParseError(format!("invalid recipient '{}': {}", address, e)))?;
+            message = message.to(mailbox);
+        }
+        let message = message
+            .body(body.to_string())
+            .map_err(|e| PriceError::ParseError(format!("email: {}", e)))?;
+
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| PriceError::NetworkError(format!("smtp: {}", e)))
+    }
+}
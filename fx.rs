@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::http::Client;
+use crate::storage::{PriceRecord, TIMESTAMP_FORMAT};
+use crate::PriceError;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FxConfig {
+    // Endpoint answering `?from=USD&to=EUR,GBP` with {"rates": {"EUR": ...}},
+    // such as the ECB-based Frankfurter API.
+    pub url: String,
+    // How long fetched rates are reused before asking again.
+    pub refresh: String,
+}
+
+impl Default for FxConfig {
+    fn default() -> Self {
+        FxConfig { url: "https://api.frankfurter.app/latest".to_string(), refresh: "1h".to_string() }
+    }
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    rates: HashMap<String, f64>,
+}
+
+// Fills in the configured currencies for records whose source only quotes
+// USD, keeping the rate used next to each converted price.
+pub struct FxConverter {
+    url: String,
+    refresh: Duration,
+    currencies: Vec<String>,
+    rates: HashMap<String, f64>,
+    fetched: Option<NaiveDateTime>,
+    client: Client,
+}
+
+impl FxConverter {
+    pub fn from_config(config: &Config, client: Client) -> Result<Option<Self>, PriceError> {
+        if config.currencies.is_empty() {
+            return Ok(None);
+        }
+        let fx = config.fx.clone().unwrap_or_default();
+        Ok(Some(FxConverter {
+            url: fx.url,
+            refresh: parse_duration(&fx.refresh)?,
+            currencies: config.currencies.clone(),
+            rates: HashMap::new(),
+            fetched: None,
+            client,
+        }))
+    }
+
+    fn refresh(&mut self, now: NaiveDateTime) -> Result<(), PriceError> {
+        if self.fetched.is_some_and(|fetched| now - fetched < self.refresh) {
+            return Ok(());
+        }
+        let to: Vec<String> = self.currencies.iter().map(|c| c.to_uppercase()).collect();
+        let body = self
+            .client
+            .get(&self.url, &[("from", "USD"), ("to", &to.join(","))])
+            .map_err(|e| PriceError::NetworkError(format!("fx rates: {}", e)))?;
+        let response: RatesResponse = serde_json::from_str(&body)
+            .map_err(|e| PriceError::ParseError(format!("fx rates: {}", e)))?;
+
+        self.rates = response.rates.into_iter().map(|(currency, rate)| (currency.to_lowercase(), rate)).collect();
+        self.fetched = Some(now);
+        Ok(())
+    }
+
+    // Converts the currencies the source did not quote itself. When rates
+    // cannot be refreshed the previous ones are used, and the error is only
+    // returned if there are none at all.
+    pub fn convert(&mut self, record: &mut PriceRecord) -> Result<(), PriceError> {
+        if self.currencies.iter().all(|c| record.quotes.contains_key(c)) {
+            return Ok(());
+        }
+        if let Err(e) = self.refresh(Local::now().naive_local()) {
+            if self.rates.is_empty() {
+                return Err(e);
+            }
+            let fetched = self.fetched.map(|t| t.format(TIMESTAMP_FORMAT).to_string()).unwrap_or_default();
+            eprintln!("{} (using rates from {})", e, fetched);
+        }
+
+        for currency in &self.currencies {
+            if record.quotes.contains_key(currency) {
+                continue;
+            }
+            if let Some(rate) = self.rates.get(currency) {
+                record.quotes.insert(currency.clone(), record.price * rate);
+                record.fx_rates.insert(currency.clone(), *rate);
+            }
+        }
+        Ok(())
+    }
+}
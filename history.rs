@@ -0,0 +1,173 @@
+// The stored history for other Rust programs, read through the tracker's own
+// config and storage backends so nothing has to parse their files:
+//
+//   let history = HistoryReader::open(Some(Path::new("tracker.toml")))?;
+//   for record in history.records("bitcoin")? {
+//       let record = record?;
+//       println!("{} {}", record.timestamp, record.price);
+//   }
+//
+// PriceRecord here is kept apart from the one the backends store, so fields
+// can be added to that without breaking programs built on this.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::registry::Registry;
+use crate::storage::{self, Storage};
+use crate::PriceError;
+
+// Non-exhaustive so fields can be added; records come from HistoryReader or
+// deserializing, not from struct literals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PriceRecord {
+    pub asset: String,
+    // Local time, as the tracker stores it, serialized like "2024-03-01 09:00:00".
+    #[serde(with = "timestamp")]
+    pub timestamp: NaiveDateTime,
+    // USD.
+    pub price: f64,
+    #[serde(default)]
+    pub volume: Option<f64>,
+    #[serde(default)]
+    pub market_cap: Option<f64>,
+    // Prices in the configured extra currencies, keyed by lowercase code.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quotes: BTreeMap<String, f64>,
+    // The USD rate each converted quote was worked out at, for currencies
+    // the source did not quote itself.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fx_rates: BTreeMap<String, f64>,
+    // Price in units of another tracked asset, keyed by its symbol.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub relative: BTreeMap<String, f64>,
+    // Percent change over a period, keyed by its label, e.g. "24h".
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub changes: BTreeMap<String, f64>,
+    // The source's "high", "low" and "vwap", as far as it reports them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub day: BTreeMap<String, f64>,
+    // Order book metrics keyed by name, e.g. "spread".
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub book: BTreeMap<String, f64>,
+    // Counts the asset's records from 1, where the tracker numbers them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    // When the source says the price is from, for sources that say,
+    // serialized like "2024-03-01T08:59:30Z".
+    #[serde(default, with = "source_time", skip_serializing_if = "Option::is_none")]
+    pub source_time: Option<DateTime<Utc>>,
+}
+
+impl PriceRecord {
+    fn from_stored(asset: &str, record: storage::PriceRecord) -> Self {
+        PriceRecord {
+            asset: asset.to_string(),
+            timestamp: record.timestamp,
+            price: record.price,
+            volume: record.volume,
+            market_cap: record.market_cap,
+            quotes: record.quotes,
+            fx_rates: record.fx_rates,
+            relative: record.relative,
+            changes: record.changes,
+            day: record.day,
+            book: record.book,
+            sequence: record.sequence,
+            source_time: record.source_time,
+        }
+    }
+}
+
+mod timestamp {
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::storage::TIMESTAMP_FORMAT;
+
+    pub fn serialize<S: Serializer>(timestamp: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&timestamp.format(TIMESTAMP_FORMAT))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDateTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NaiveDateTime::parse_from_str(&text, TIMESTAMP_FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+mod source_time {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(time: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.collect_str(&time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+        let Some(text) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        let time = DateTime::parse_from_rfc3339(&text).map_err(serde::de::Error::custom)?;
+        Ok(Some(time.with_timezone(&Utc)))
+    }
+}
+
+pub struct HistoryReader {
+    storage: Box<dyn Storage>,
+    assets: Vec<String>,
+}
+
+impl HistoryReader {
+    // Loads the config as the tracker does: the given file, else tracker.toml
+    // if there is one, with TRACKER_* variables on top. Relative paths in it
+    // are taken from the working directory, as they are by the tracker.
+    pub fn open(config: Option<&Path>) -> Result<Self, PriceError> {
+        let config = Config::load(config, None)?;
+        let assets = Registry::load(&config)?.assets.into_iter().map(|asset| asset.id).collect();
+        Ok(HistoryReader { storage: storage::open(&config.storage)?, assets })
+    }
+
+    // The ids of the tracked assets, in watchlist order.
+    pub fn assets(&self) -> &[String] {
+        &self.assets
+    }
+
+    // Oldest first, read as it is iterated rather than all at once.
+    pub fn records(
+        &self,
+        asset: &str,
+    ) -> Result<impl Iterator<Item = Result<PriceRecord, PriceError>> + '_, PriceError> {
+        self.scan(asset, None)
+    }
+
+    // Like records, from `from` on.
+    pub fn records_since(
+        &self,
+        asset: &str,
+        from: NaiveDateTime,
+    ) -> Result<impl Iterator<Item = Result<PriceRecord, PriceError>> + '_, PriceError> {
+        self.scan(asset, Some(from))
+    }
+
+    pub fn last(&self, asset: &str) -> Result<Option<PriceRecord>, PriceError> {
+        Ok(self.storage.last(asset)?.map(|record| PriceRecord::from_stored(asset, record)))
+    }
+
+    fn scan(
+        &self,
+        asset: &str,
+        from: Option<NaiveDateTime>,
+    ) -> Result<impl Iterator<Item = Result<PriceRecord, PriceError>> + '_, PriceError> {
+        let name = asset.to_string();
+        let records = self.storage.scan(asset, from)?;
+        Ok(records.map(move |record| record.map(|record| PriceRecord::from_stored(&name, record))))
+    }
+}
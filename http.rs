@@ -0,0 +1,278 @@
+mod cache;
+pub mod clock;
+pub mod fixtures;
+pub mod headers;
+pub mod limit;
+pub mod mock_server;
+pub mod quota;
+#[cfg(test)]
+pub mod stub;
+pub mod tls;
+#[cfg(feature = "reqwest")]
+mod reqwest_client;
+#[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+mod ureq_client;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::{Cause, HttpFailure, PriceError};
+
+use mock_server::Scenario;
+use tls::TlsConfig;
+
+// reqwest wins when both features are enabled, since ureq is the default.
+#[cfg(feature = "reqwest")]
+pub use reqwest_client::ReqwestClient as Backend;
+#[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+pub use ureq_client::UreqClient as Backend;
+
+#[cfg(not(any(feature = "ureq", feature = "reqwest")))]
+compile_error!("enable the `ureq` or `reqwest` feature to choose an HTTP backend");
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Sent unless [http] sets `user_agent`, so providers see who is asking
+// rather than the HTTP library.
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+// Connections are kept open between rounds and reused for the same host, so
+// most fetches skip the TCP and TLS handshakes.
+const MAX_IDLE_PER_HOST: usize = 4;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    // Longest a request may take, connecting included.
+    pub timeout: String,
+    // Requests allowed per UTC day, keyed by host.
+    pub quotas: BTreeMap<String, u64>,
+    // Fraction of a quota after which a warning is printed.
+    pub quota_warning: f64,
+    // Requests allowed per period, keyed by host, e.g. "30/min".
+    pub rate_limits: BTreeMap<String, String>,
+    // Largest difference from the servers' clocks before a warning.
+    pub max_clock_skew: String,
+    // Replaces USER_AGENT.
+    pub user_agent: Option<String>,
+    // Extra request headers, keyed by host.
+    pub headers: BTreeMap<String, BTreeMap<String, String>>,
+    pub tls: TlsConfig,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            timeout: format!("{}s", DEFAULT_TIMEOUT.as_secs()),
+            quotas: BTreeMap::new(),
+            quota_warning: 0.8,
+            rate_limits: BTreeMap::new(),
+            max_clock_skew: "30s".to_string(),
+            user_agent: None,
+            headers: BTreeMap::new(),
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn timeout(&self) -> Result<Duration, PriceError> {
+        let timeout = parse_duration(&self.timeout)
+            .map_err(|e| PriceError::ParseError(format!("http.timeout: {}", e.message())))?;
+        timeout
+            .to_std()
+            .ok()
+            .filter(|timeout| !timeout.is_zero())
+            .ok_or_else(|| PriceError::ParseError("http.timeout: must be at least 1s".to_string()))
+    }
+
+    pub fn max_clock_skew(&self) -> Result<chrono::Duration, PriceError> {
+        parse_duration(&self.max_clock_skew)
+            .map_err(|e| PriceError::ParseError(format!("http.max_clock_skew: {}", e.message())))
+    }
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    // The server answered with a non-success status.
+    Status { url: String, code: u16, message: String },
+    Transport { url: String, message: String, source: Option<Box<dyn Error + Send + Sync>> },
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HttpError::Status { message, .. } | HttpError::Transport { message, .. } => f.write_str(message),
+        }
+    }
+}
+
+impl From<HttpError> for PriceError {
+    fn from(e: HttpError) -> Self {
+        let (url, status, message, source) = match e {
+            HttpError::Status { url, code, message } => (url, Some(code), message, None),
+            HttpError::Transport { url, message, source } => (url, None, message, source.map(Cause::from)),
+        };
+        PriceError::Http(Box::new(HttpFailure { provider: None, url, status, message, source }))
+    }
+}
+
+// Validators of an earlier response, sent back so the server can answer 304
+// Not Modified instead of repeating the body.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum Conditional {
+    Modified(String, Validators),
+    NotModified,
+}
+
+pub enum Body<'a> {
+    Text(&'a str),
+    // Sent URL-encoded, as `application/x-www-form-urlencoded`.
+    Form(&'a [(&'a str, &'a str)]),
+}
+
+// Everything that talks HTTP goes through this, so a stub returning canned
+// bodies can stand in for the network and the backend is a build option. One
+// client is shared by the whole run so its connection pool is too.
+pub trait HttpClient: Send + Sync {
+    // Returns the response body; `query` pairs are URL-encoded.
+    fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<String, HttpError> {
+        self.get_with_headers(url, &[], query)
+    }
+
+    // `get` sending the extra `headers`, such as an API key.
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError>;
+
+    // `get_with_headers` sending `validators` as If-None-Match and
+    // If-Modified-Since. Without backend support it always fetches in full.
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        _validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        self.get_with_headers(url, headers, query).map(|body| Conditional::Modified(body, Validators::default()))
+    }
+
+    // Sends `body` with the extra `headers` and returns the response body.
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError>;
+
+    // Uploads `body` as is, such as a file to object storage.
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError>;
+
+    // Called by the tracker before each round; responses a client keeps
+    // around must not outlive it.
+    fn next_cycle(&self) {}
+
+    // How far the local clock is ahead of the servers answering this round,
+    // if any said.
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        None
+    }
+}
+
+pub type Client = Arc<dyn HttpClient>;
+
+// Keeps the validators of the last response from each URL a source fetches,
+// so unchanged data costs a 304 and no parsing.
+#[derive(Default)]
+pub struct Revalidator {
+    validators: Mutex<HashMap<String, Validators>>,
+}
+
+impl Revalidator {
+    // The body, or None if it is the same as last time.
+    pub fn get(
+        &self,
+        client: &Client,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<Option<String>, HttpError> {
+        let key = fixtures::full_url(url, query);
+        let validators = self.validators.lock().unwrap().get(&key).cloned().unwrap_or_default();
+        match client.get_conditional(url, headers, query, &validators)? {
+            Conditional::Modified(body, validators) => {
+                self.validators.lock().unwrap().insert(key, validators);
+                Ok(Some(body))
+            }
+            Conditional::NotModified => Ok(None),
+        }
+    }
+
+    // Drops what was kept, e.g. after the body turned out not to parse, so
+    // the next request fetches it again.
+    pub fn forget(&self) {
+        self.validators.lock().unwrap().clear();
+    }
+}
+
+// Percent-encodes all but unreserved characters and those in `keep`, for
+// URL paths.
+pub fn percent_encode(text: &str, keep: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) || keep.as_bytes().contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+pub fn default_client() -> Client {
+    Arc::new(Backend::new(DEFAULT_TIMEOUT, None))
+}
+
+// The client for the config's [http] section and the --record, --replay and
+// --mock-server flags. Within a round, repeated requests are answered once.
+pub fn client(
+    config: &Config,
+    record: Option<&Path>,
+    replay: Option<&Path>,
+    mock_server: Option<Scenario>,
+    dry_run: bool,
+) -> Result<Client, PriceError> {
+    if let Some(dir) = replay {
+        return Ok(Arc::new(cache::CycleCache::new(Arc::new(fixtures::Replayer::new(dir)?))));
+    }
+    let http = config.http.clone().unwrap_or_default();
+    let mut client: Client = Arc::new(Backend::new(http.timeout()?, http.tls.client_config()?));
+    if let Some(scenario) = mock_server {
+        let base = mock_server::start(scenario)?;
+        eprintln!("Mock API server ({}) listening on {}", scenario, base);
+        client = Arc::new(mock_server::Redirect::new(client, base));
+    }
+    if http.user_agent.is_some() || !http.headers.is_empty() {
+        client = Arc::new(headers::ExtraHeaders::new(client, http.user_agent, http.headers)?);
+    }
+    if !http.rate_limits.is_empty() {
+        client = Arc::new(limit::RateLimiter::new(client, &http.rate_limits)?);
+    }
+    // Dry runs still warn about the quotas, but leave the counts as they are.
+    let usage = (!dry_run).then(|| config.storage.directory().join(quota::USAGE_FILE));
+    client = Arc::new(quota::QuotaTracker::new(client, usage.as_deref(), http.quotas, http.quota_warning));
+    if let Some(dir) = record {
+        client = Arc::new(fixtures::Recorder::new(client, dir)?);
+    }
+    Ok(Arc::new(cache::CycleCache::new(client)))
+}
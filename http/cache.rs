@@ -0,0 +1,94 @@
+// Responses kept for the rest of a tracker round, so assets served by the
+// same URL, such as two columns of one CSV download, cost one request.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::{fixtures, Body, Client, Conditional, HttpClient, HttpError, Validators};
+
+pub struct CycleCache {
+    inner: Client,
+    // Keyed by the full URL, headers and validators sent. A request holds
+    // its slot's lock until answered, so workers asking for the same one
+    // wait for it instead of sending their own.
+    entries: Mutex<HashMap<String, Arc<Mutex<Option<Entry>>>>>,
+}
+
+#[derive(Clone)]
+enum Entry {
+    Body(String, Validators),
+    NotModified,
+}
+
+impl CycleCache {
+    pub fn new(inner: Client) -> Self {
+        CycleCache { inner, entries: Mutex::new(HashMap::new()) }
+    }
+
+    fn fetch(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Entry, HttpError> {
+        let key = format!("{} {:?} {:?}", fixtures::full_url(url, query), headers, validators);
+        let slot = self.entries.lock().unwrap().entry(key).or_default().clone();
+        let mut slot = slot.lock().unwrap();
+        if let Some(entry) = &*slot {
+            return Ok(entry.clone());
+        }
+        // Errors are not kept, the next asset may have better luck.
+        let entry = match self.inner.get_conditional(url, headers, query, validators)? {
+            Conditional::Modified(body, validators) => Entry::Body(body, validators),
+            Conditional::NotModified => Entry::NotModified,
+        };
+        *slot = Some(entry.clone());
+        Ok(entry)
+    }
+}
+
+impl HttpClient for CycleCache {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        match self.fetch(url, headers, query, &Validators::default())? {
+            Entry::Body(body, _) => Ok(body),
+            // Only a request with validators should get a 304.
+            Entry::NotModified => self.inner.get_with_headers(url, headers, query),
+        }
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        match self.fetch(url, headers, query, validators)? {
+            Entry::Body(body, validators) => Ok(Conditional::Modified(body, validators)),
+            Entry::NotModified => Ok(Conditional::NotModified),
+        }
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.inner.post(url, headers, body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.inner.put(url, headers, body)
+    }
+
+    fn next_cycle(&self) {
+        self.entries.lock().unwrap().clear();
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
@@ -0,0 +1,69 @@
+// How far the local clock is from the servers', judged by the Date header of
+// each response. Every record is stamped with the local time, so a clock
+// that is off skews the whole series without anything failing.
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::alerts::outage::describe;
+
+// Samples of the current round, in seconds the local clock is ahead.
+#[derive(Default)]
+pub struct Samples(Mutex<Vec<i64>>);
+
+impl Samples {
+    pub fn observe(&self, date: Option<&str>) {
+        let Some(date) = date.and_then(|date| DateTime::parse_from_rfc2822(date).ok()) else {
+            return;
+        };
+        // The header is truncated to the second, so the server's clock was
+        // half a second past it on average.
+        let ahead = Utc::now().signed_duration_since(date).num_milliseconds() - 500;
+        let ahead = (ahead as f64 / 1000.0).round() as i64;
+        self.0.lock().unwrap().push(ahead);
+    }
+
+    // The median, so one server with a bad clock does not count.
+    pub fn skew(&self) -> Option<Duration> {
+        let mut samples = self.0.lock().unwrap().clone();
+        samples.sort_unstable();
+        samples.get(samples.len() / 2).map(|ahead| Duration::seconds(*ahead))
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+// Warns when the skew first exceeds the limit and again once it is back
+// within it, rather than every round.
+pub struct SkewCheck {
+    limit: Duration,
+    skewed: bool,
+}
+
+impl SkewCheck {
+    pub fn new(limit: Duration) -> Self {
+        SkewCheck { limit, skewed: false }
+    }
+
+    pub fn check(&mut self, skew: Option<Duration>) {
+        let Some(skew) = skew else {
+            return;
+        };
+        let skewed = skew.abs() > self.limit;
+        if skewed && !self.skewed {
+            let direction = if skew > Duration::zero() { "ahead of" } else { "behind" };
+            eprintln!(
+                "Warning: the local clock is {} {} the servers' Date headers; \
+                 the timestamps of new records are off by as much",
+                describe(skew.abs()),
+                direction
+            );
+        } else if !skewed && self.skewed {
+            eprintln!("The local clock agrees with the servers' again");
+        }
+        self.skewed = skewed;
+    }
+}
@@ -0,0 +1,121 @@
+// Saves response bodies under a directory with --record and serves them back
+// with --replay, so a provider's JSON can be kept as a regression fixture and
+// a parse error reproduced offline.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Body, Client, HttpClient, HttpError};
+use crate::PriceError;
+
+pub struct Recorder {
+    inner: Client,
+    dir: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(inner: Client, dir: &Path) -> Result<Self, PriceError> {
+        fs::create_dir_all(dir).map_err(|e| PriceError::FileError(format!("{}: {}", dir.display(), e)))?;
+        Ok(Recorder { inner, dir: dir.to_path_buf() })
+    }
+}
+
+impl HttpClient for Recorder {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        let body = self.inner.get_with_headers(url, headers, query)?;
+        let path = fixture_path(&self.dir, url, query);
+        if let Err(e) = fs::write(&path, &body) {
+            eprintln!("Error recording {}: {}", path.display(), e);
+        }
+        Ok(body)
+    }
+
+    // Only responses to reads make useful fixtures.
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.inner.post(url, headers, body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.inner.put(url, headers, body)
+    }
+
+    fn next_cycle(&self) {
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
+
+pub struct Replayer {
+    dir: PathBuf,
+}
+
+impl Replayer {
+    pub fn new(dir: &Path) -> Result<Self, PriceError> {
+        if !dir.is_dir() {
+            return Err(PriceError::FileError(format!("{}: no such fixture directory", dir.display())));
+        }
+        Ok(Replayer { dir: dir.to_path_buf() })
+    }
+}
+
+impl HttpClient for Replayer {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        let path = fixture_path(&self.dir, url, query);
+        fs::read_to_string(&path).map_err(|_| {
+            let url = full_url(url, query);
+            let message = format!("no recorded response for {} (expected {})", url, path.display());
+            HttpError::Transport { url, message, source: None }
+        })
+    }
+
+    fn post(&self, url: &str, _headers: &[(&str, &str)], _body: Body) -> Result<String, HttpError> {
+        let message = format!("{}: nothing is sent while replaying", url);
+        Err(HttpError::Transport { url: url.to_string(), message, source: None })
+    }
+
+    fn put(&self, url: &str, _headers: &[(&str, &str)], _body: &[u8]) -> Result<String, HttpError> {
+        let message = format!("{}: nothing is sent while replaying", url);
+        Err(HttpError::Transport { url: url.to_string(), message, source: None })
+    }
+}
+
+// A readable prefix from the host and path plus a hash of the whole request,
+// e.g. api.coingecko.com_api_v3_simple_price-1f0c8a2b9d3e4f56.body.
+fn fixture_path(dir: &Path, url: &str, query: &[(&str, &str)]) -> PathBuf {
+    let slug: String = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .take(80)
+        .collect();
+    dir.join(format!("{}-{:016x}.body", slug.trim_end_matches('_'), fnv1a(&full_url(url, query))))
+}
+
+pub fn full_url(url: &str, query: &[(&str, &str)]) -> String {
+    let pairs: Vec<String> = query.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    if pairs.is_empty() {
+        url.to_string()
+    } else {
+        format!("{}?{}", url, pairs.join("&"))
+    }
+}
+
+// Stable across builds and platforms, unlike the std hasher, so fixtures stay
+// valid.
+pub fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
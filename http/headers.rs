@@ -0,0 +1,114 @@
+// Adds the headers configured per host, and the User-Agent, to every
+// request, for providers that want an API key or an identifying agent
+// even from the built-in sources:
+//
+//   [http]
+//   user_agent = "price-tracker (ops@example.com)"
+//
+//   [http.headers."api.coingecko.com"]
+//   x-cg-demo-api-key = { secret = "coingecko_key" }
+//
+// A header a source sets itself wins over the configured one.
+
+use std::collections::BTreeMap;
+
+use super::quota::host;
+use super::{Body, Client, Conditional, HttpClient, HttpError, Validators};
+use crate::PriceError;
+
+pub struct ExtraHeaders {
+    inner: Client,
+    // Sent to every host.
+    common: Vec<(String, String)>,
+    hosts: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ExtraHeaders {
+    pub fn new(
+        inner: Client,
+        user_agent: Option<String>,
+        hosts: BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Result<Self, PriceError> {
+        check(user_agent.as_deref(), &hosts)?;
+        let common = user_agent.map(|agent| ("User-Agent".to_string(), agent)).into_iter().collect();
+        Ok(ExtraHeaders { inner, common, hosts })
+    }
+
+    // `headers` followed by those configured for the URL's host.
+    fn with<'a>(&'a self, url: &str, headers: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut all = headers.to_vec();
+        let common = self.common.iter().map(|(name, value)| (name, value));
+        let configured = self.hosts.get(host(url)).into_iter().flatten().chain(common);
+        for (name, value) in configured {
+            if !all.iter().any(|(set, _)| set.eq_ignore_ascii_case(name)) {
+                all.push((name, value));
+            }
+        }
+        all
+    }
+}
+
+pub fn check(user_agent: Option<&str>, hosts: &BTreeMap<String, BTreeMap<String, String>>) -> Result<(), PriceError> {
+    if let Some(agent) = user_agent {
+        check_value("http.user_agent", agent)?;
+    }
+    for (host, headers) in hosts {
+        for (name, value) in headers {
+            let key = format!("http.headers.\"{}\".{}", host, name);
+            if !valid_name(name) {
+                return Err(PriceError::ParseError(format!("{}: invalid header name", key)));
+            }
+            check_value(&key, value)?;
+        }
+    }
+    Ok(())
+}
+
+// RFC 9110 token characters.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+fn check_value(key: &str, value: &str) -> Result<(), PriceError> {
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(PriceError::ParseError(format!("{}: header values cannot hold line breaks", key)));
+    }
+    Ok(())
+}
+
+impl HttpClient for ExtraHeaders {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.inner.get_with_headers(url, &self.with(url, headers), query)
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        self.inner.get_conditional(url, &self.with(url, headers), query, validators)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.inner.post(url, &self.with(url, headers), body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.inner.put(url, &self.with(url, headers), body)
+    }
+
+    fn next_cycle(&self) {
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
@@ -0,0 +1,130 @@
+// Spaces out the requests to each host configured under
+// `[http] rate_limits = { "api.coingecko.com" = "30/min" }`. Every asset
+// shares a host's budget, so a longer watchlist makes rounds slower instead
+// of getting the tracker banned. Each host allows a burst of its full count,
+// then one request per period / count.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::quota::host;
+use super::{Body, Client, Conditional, HttpClient, HttpError, Validators};
+use crate::config::parse_duration;
+use crate::PriceError;
+
+// Parses "<count>/<period>", e.g. "30/min", "5/s" or "100/10m".
+pub fn parse_rate(host: &str, text: &str) -> Result<(u32, Duration), PriceError> {
+    let invalid = |reason: String| PriceError::ParseError(format!("http.rate_limits.\"{}\": {}", host, reason));
+    let expected = || invalid(format!("expected <count>/<period> such as 30/min, got '{}'", text));
+    let (count, period) = text.split_once('/').ok_or_else(expected)?;
+    let count: u32 = count.trim().parse().map_err(|_| expected())?;
+    let period = match period.trim() {
+        "min" => "1m".to_string(),
+        period if period.starts_with(|c: char| c.is_ascii_digit()) => period.to_string(),
+        period => format!("1{}", period),
+    };
+    let period = parse_duration(&period).map_err(|e| invalid(e.message().to_string()))?;
+    let period = period.to_std().ok().filter(|period| !period.is_zero());
+    match period {
+        Some(period) if count > 0 => Ok((count, period)),
+        _ => Err(invalid("count and period must be positive".to_string())),
+    }
+}
+
+struct Bucket {
+    capacity: f64,
+    // Requests allowed per second.
+    rate: f64,
+    // Below zero once requests are waiting for their turn.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    // How long the caller has to wait before sending its request.
+    fn take(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+pub struct RateLimiter {
+    inner: Client,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(inner: Client, limits: &BTreeMap<String, String>) -> Result<Self, PriceError> {
+        let mut buckets = HashMap::new();
+        for (host, text) in limits {
+            let (count, period) = parse_rate(host, text)?;
+            let bucket = Bucket {
+                capacity: count as f64,
+                rate: count as f64 / period.as_secs_f64(),
+                tokens: count as f64,
+                refilled: Instant::now(),
+            };
+            buckets.insert(host.clone(), bucket);
+        }
+        Ok(RateLimiter { inner, buckets: Mutex::new(buckets) })
+    }
+
+    fn wait(&self, url: &str) {
+        // The lock is released before sleeping so other hosts are not held up.
+        let wait = self.buckets.lock().unwrap().get_mut(host(url)).map_or(Duration::ZERO, Bucket::take);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+impl HttpClient for RateLimiter {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.wait(url);
+        self.inner.get_with_headers(url, headers, query)
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        self.wait(url);
+        self.inner.get_conditional(url, headers, query, validators)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.wait(url);
+        self.inner.post(url, headers, body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.wait(url);
+        self.inner.put(url, headers, body)
+    }
+
+    fn next_cycle(&self) {
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
@@ -0,0 +1,521 @@
+// A local stand-in for the CoinGecko, Yahoo Finance, Frankfurter, Binance and
+// Bybit APIs, started with --mock-server so the tracker and the integration
+// tests can run end to end without the network. The scenario decides how it
+// misbehaves.
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use super::fixtures::fnv1a;
+use super::{Body, Client, Conditional, HttpClient, HttpError, Validators};
+use crate::PriceError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scenario {
+    // Well-formed answers.
+    Ok,
+    // 429 Too Many Requests for everything, as when the free tier is used up.
+    RateLimit,
+    // Bodies cut off halfway through.
+    Malformed,
+    // Never answers within any sensible timeout.
+    Timeout,
+    // Well-formed answers dated ten minutes ahead, as a client whose clock
+    // is behind would see them.
+    Skewed,
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        f.write_str(&name)
+    }
+}
+
+// Requests to these hosts are sent to the mock server instead.
+const HOSTS: [&str; 7] = [
+    "https://api.coingecko.com",
+    "https://query1.finance.yahoo.com",
+    "https://finance.yahoo.com",
+    "https://api.frankfurter.app",
+    "https://api.binance.com",
+    "https://fapi.binance.com",
+    "https://api.bybit.com",
+];
+
+const TIMEOUT_DELAY: Duration = Duration::from_secs(300);
+
+const SKEW: i64 = 600;
+
+// How long /mock/slow/* takes to serve the download, for a provider that is
+// slow but not failing.
+const SLOW_DELAY: Duration = Duration::from_millis(1500);
+
+// The data never changes, so neither does its date.
+const LAST_MODIFIED: &str = "Wed, 01 May 2024 00:00:00 GMT";
+// The same instant in seconds since 1970, for APIs that date their quotes.
+// Yahoo's chart has no such field here, so plugins can take the last
+// number in it as the price.
+const QUOTED_AT: i64 = 1_714_521_600;
+
+// id, symbol, name, USD price
+const COINS: [(&str, &str, &str, f64); 5] = [
+    ("bitcoin", "btc", "Bitcoin", 65000.0),
+    ("ethereum", "eth", "Ethereum", 3200.0),
+    ("tether", "usdt", "Tether", 1.0),
+    ("solana", "sol", "Solana", 150.0),
+    ("dogecoin", "doge", "Dogecoin", 0.125),
+];
+
+// Perpetual contract, mark price, funding rate
+const PERPETUALS: [(&str, f64, f64); 2] = [("BTCUSDT", 65032.5, 0.0001), ("ETHUSDT", 3201.6, -0.00005)];
+
+// Order book levels around the mid price, as an offset from it and a size,
+// best first. The outer ones are more than 1% away.
+const BIDS: [(f64, f64); 3] = [(-0.0001, 0.5), (-0.005, 1.0), (-0.02, 2.0)];
+const ASKS: [(f64, f64); 3] = [(0.0001, 0.4), (0.005, 1.2), (0.02, 3.0)];
+
+// Yahoo Finance symbol, currency, price
+const TICKERS: [(&str, &str, f64); 6] = [
+    ("^GSPC", "USD", 5100.25),
+    ("^IXIC", "USD", 16000.5),
+    ("SPY", "USD", 510.75),
+    ("QQQ", "USD", 440.1),
+    ("AAPL", "USD", 189.5),
+    ("VOD.L", "GBp", 70.2),
+];
+
+// Daily history as Yahoo Finance's download endpoint serves it, ending on
+// the ^GSPC price in TICKERS.
+const SP500_HISTORY: &str = "Date,Open,High,Low,Close,Adj Close,Volume
+2024-05-01,5029.03,5096.12,5013.45,5018.39,5018.39,3814750000
+2024-05-02,5049.32,5073.21,5011.05,5064.20,5064.20,3249470000
+2024-05-03,5122.78,5139.12,5101.22,5100.25,5100.25,3635860000
+";
+
+// The quote page, for scraping. Like the real one, it turns away requests
+// without a browser-like User-Agent.
+const SP500_PAGE: &str = r#"<!DOCTYPE html>
+<html><head><title>S&amp;P 500 (^GSPC)</title></head>
+<body>
+<h1>S&amp;P 500 (^GSPC)</h1>
+<div class="quote"><fin-streamer data-field="regularMarketPrice" data-value="5100.25">5,100.25</fin-streamer>
+<span class="change">+24.61 (+0.48%)</span></div>
+</body></html>
+"#;
+
+// Units of each currency per USD.
+const RATES: [(&str, f64); 4] = [("eur", 0.92), ("gbp", 0.79), ("jpy", 150.0), ("chf", 0.88)];
+
+// Serves `scenario` on a free local port from a background thread and returns
+// its base URL.
+pub fn start(scenario: Scenario) -> Result<String, PriceError> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| PriceError::NetworkError(format!("mock server: {}", e)))?;
+    let address = listener.local_addr().map_err(|e| PriceError::NetworkError(format!("mock server: {}", e)))?;
+    let stats = Arc::new(Stats::default());
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let stats = stats.clone();
+            thread::spawn(move || {
+                if let Err(e) = handle(stream, scenario, &stats) {
+                    eprintln!("Mock server: {}", e);
+                }
+            });
+        }
+    });
+    Ok(format!("http://{}", address))
+}
+
+// Rewrites provider URLs to the mock server.
+pub struct Redirect {
+    inner: Client,
+    base: String,
+}
+
+impl Redirect {
+    pub fn new(inner: Client, base: String) -> Self {
+        Redirect { inner, base }
+    }
+
+    fn rewrite(&self, url: &str) -> String {
+        match HOSTS.iter().find_map(|host| url.strip_prefix(host)) {
+            Some(path) => format!("{}{}", self.base, path),
+            None => url.to_string(),
+        }
+    }
+}
+
+impl HttpClient for Redirect {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.inner.get_with_headers(&self.rewrite(url), headers, query)
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        self.inner.get_conditional(&self.rewrite(url), headers, query, validators)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.inner.post(url, headers, body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.inner.put(url, headers, body)
+    }
+
+    fn next_cycle(&self) {
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
+
+// Counted over the server's lifetime and served at /mock/stats, to show how
+// well the client reuses connections.
+#[derive(Default)]
+struct Stats {
+    connections: AtomicUsize,
+    requests: AtomicUsize,
+}
+
+// Answers requests on the connection until the client closes it.
+fn handle(mut stream: TcpStream, scenario: Scenario, stats: &Stats) -> std::io::Result<()> {
+    stats.connections.fetch_add(1, Ordering::Relaxed);
+    let mut reader = BufReader::new(stream.try_clone()?);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line)? == 0 {
+            return Ok(());
+        }
+        // The requests have no body.
+        let mut user_agent = String::new();
+        let mut if_none_match = None;
+        let mut if_modified_since = None;
+        let mut close = false;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            match line.split_once(':') {
+                Some((name, value)) if name.eq_ignore_ascii_case("user-agent") => user_agent = value.trim().to_string(),
+                Some((name, value)) if name.eq_ignore_ascii_case("connection") => {
+                    close = value.trim().eq_ignore_ascii_case("close")
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("if-none-match") => {
+                    if_none_match = Some(value.trim().to_string())
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("if-modified-since") => {
+                    if_modified_since = Some(value.trim().to_string())
+                }
+                _ => {}
+            }
+            line.clear();
+        }
+        let requests = stats.requests.fetch_add(1, Ordering::Relaxed) + 1;
+
+        let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query: Vec<(String, String)> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (decode(name), decode(value)))
+            .collect();
+
+        let (status, body) = match scenario {
+            _ if path == "/mock/stats" => {
+                let connections = stats.connections.load(Ordering::Relaxed);
+                (200, json!({"connections": connections, "requests": requests}).to_string())
+            }
+            Scenario::RateLimit => (
+                429,
+                json!({"status": {"error_code": 429, "error_message": "You've exceeded the Rate Limit."}}).to_string(),
+            ),
+            Scenario::Timeout => {
+                thread::sleep(TIMEOUT_DELAY);
+                return Ok(());
+            }
+            Scenario::Ok | Scenario::Malformed | Scenario::Skewed => {
+                let path = decode(path);
+                let (status, body) = if path.starts_with("/v7/finance/download/") {
+                    (200, SP500_HISTORY.to_string())
+                } else if path.starts_with("/mock/slow/") {
+                    thread::sleep(SLOW_DELAY);
+                    (200, SP500_HISTORY.to_string())
+                } else if path.starts_with("/quote/") {
+                    let product = user_agent.split('/').next().unwrap_or_default();
+                    match product {
+                        "" | "ureq" | "reqwest" => (403, "<html><body>Forbidden</body></html>".to_string()),
+                        _ => (200, SP500_PAGE.to_string()),
+                    }
+                } else {
+                    let (status, body) = route(&path, &query);
+                    (status, body.to_string())
+                };
+                match scenario {
+                    Scenario::Malformed if status == 200 => (status, body[..body.len() / 2].to_string()),
+                    _ => (status, body),
+                }
+            }
+        };
+
+        // Every body is served with validators, and a request repeating them
+        // gets 304 and no body, If-None-Match taking precedence.
+        let mut validators = String::new();
+        let (status, body) = if status == 200 && path != "/mock/stats" {
+            let etag = format!("\"{:016x}\"", fnv1a(&body));
+            let unchanged = match (&if_none_match, &if_modified_since) {
+                (Some(tag), _) => *tag == etag,
+                (None, Some(since)) => since == LAST_MODIFIED,
+                (None, None) => false,
+            };
+            validators = format!("ETag: {}\r\nLast-Modified: {}\r\n", etag, LAST_MODIFIED);
+            if unchanged { (304, String::new()) } else { (status, body) }
+        } else {
+            (status, body)
+        };
+
+        let reason = match status {
+            200 => "OK",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            _ => "Too Many Requests",
+        };
+        let retry_after = if status == 429 { "Retry-After: 60\r\n" } else { "" };
+        let content_type = match body.chars().next() {
+            Some('{' | '[') => "application/json",
+            Some('<') => "text/html",
+            _ => "text/csv",
+        };
+        let skew = if scenario == Scenario::Skewed { SKEW } else { 0 };
+        let date = Utc::now() + chrono::Duration::seconds(skew);
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nDate: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}{}{}\r\n{}",
+            status,
+            reason,
+            date.format("%a, %d %b %Y %H:%M:%S GMT"),
+            content_type,
+            body.len(),
+            validators,
+            retry_after,
+            if close { "Connection: close\r\n" } else { "" },
+            body
+        )?;
+        stream.flush()?;
+        if close {
+            return Ok(());
+        }
+    }
+}
+
+fn route(path: &str, query: &[(String, String)]) -> (u16, Value) {
+    let param = |name: &str| query.iter().find(|(key, _)| key == name).map_or("", |(_, value)| value.as_str());
+    let list = |name: &str| -> Vec<String> {
+        param(name).split(',').filter(|item| !item.is_empty()).map(str::to_lowercase).collect()
+    };
+
+    match path {
+        "/api/v3/simple/price" => {
+            let currencies = list("vs_currencies");
+            let mut prices = serde_json::Map::new();
+            // Unknown ids are left out, as CoinGecko does.
+            for (id, _, _, usd) in COINS.iter().filter(|coin| list("ids").iter().any(|id| id == coin.0)) {
+                let mut quote = serde_json::Map::new();
+                for currency in &currencies {
+                    let Some(rate) = rate(currency) else {
+                        continue;
+                    };
+                    quote.insert(currency.clone(), json!(usd * rate));
+                    if param("include_market_cap") == "true" {
+                        quote.insert(format!("{}_market_cap", currency), json!(usd * rate * 19_000_000.0));
+                    }
+                    if param("include_24hr_vol") == "true" {
+                        quote.insert(format!("{}_24h_vol", currency), json!(usd * rate * 400_000.0));
+                    }
+                }
+                if param("include_last_updated_at") == "true" {
+                    quote.insert("last_updated_at".to_string(), json!(QUOTED_AT));
+                }
+                prices.insert(id.to_string(), Value::Object(quote));
+            }
+            (200, Value::Object(prices))
+        }
+        "/api/v3/coins/markets" => {
+            let count = param("per_page").parse().unwrap_or(100);
+            (200, Value::Array(COINS.iter().take(count).map(coin).collect()))
+        }
+        "/api/v3/global" => {
+            // Caps as /simple/price reports them.
+            let total: f64 = COINS.iter().map(|(_, _, _, usd)| usd * 19_000_000.0).sum();
+            let shares: serde_json::Map<String, Value> = COINS
+                .iter()
+                .map(|(_, symbol, _, usd)| (symbol.to_string(), json!(usd * 19_000_000.0 / total * 100.0)))
+                .collect();
+            let data = json!({
+                "active_cryptocurrencies": COINS.len(),
+                "total_market_cap": {"usd": total},
+                "total_volume": {"usd": total / 50.0},
+                "market_cap_percentage": shares,
+            });
+            (200, json!({"data": data}))
+        }
+        "/api/v3/coins/list" => (200, Value::Array(COINS.iter().map(coin).collect())),
+        "/v1/finance/search" => {
+            let q = param("q").to_lowercase();
+            let quotes = if "^gspc s&p 500".contains(&q) {
+                vec![json!({"symbol": "^GSPC", "shortname": "S&P 500", "quoteType": "INDEX"})]
+            } else {
+                Vec::new()
+            };
+            (200, json!({"quotes": quotes}))
+        }
+        "/latest" => {
+            let rates: serde_json::Map<String, Value> = list("to")
+                .iter()
+                .filter_map(|currency| rate(currency).map(|rate| (currency.to_uppercase(), json!(rate))))
+                .collect();
+            (200, json!({"base": "USD", "rates": rates}))
+        }
+        "/fapi/v1/premiumIndex" => match PERPETUALS.iter().find(|perp| perp.0 == param("symbol")) {
+            Some((symbol, mark, funding)) => (
+                200,
+                json!({"symbol": symbol, "markPrice": mark.to_string(), "lastFundingRate": funding.to_string(),
+                    "time": QUOTED_AT * 1000}),
+            ),
+            None => (400, json!({"code": -1121, "msg": "Invalid symbol."})),
+        },
+        "/api/v3/depth" | "/fapi/v1/depth" => match PERPETUALS.iter().find(|perp| perp.0 == param("symbol")) {
+            Some((_, mid, _)) => {
+                (200, json!({"lastUpdateId": 1, "bids": levels(*mid, &BIDS), "asks": levels(*mid, &ASKS)}))
+            }
+            None => (400, json!({"code": -1121, "msg": "Invalid symbol."})),
+        },
+        "/v5/market/orderbook" => {
+            let (bids, asks) = match PERPETUALS.iter().find(|perp| perp.0 == param("symbol")) {
+                Some((_, mid, _)) => (levels(*mid, &BIDS), levels(*mid, &ASKS)),
+                None => (Vec::new(), Vec::new()),
+            };
+            (200, json!({"retCode": 0, "retMsg": "OK", "result": {"s": param("symbol"), "b": bids, "a": asks}}))
+        }
+        "/fapi/v1/ticker/24hr" => match PERPETUALS.iter().find(|perp| perp.0 == param("symbol")) {
+            Some((symbol, mark, _)) => {
+                let (high, low, vwap, base) = day(*mark);
+                let quote = vwap * base;
+                (
+                    200,
+                    json!({"symbol": symbol, "highPrice": high.to_string(), "lowPrice": low.to_string(),
+                        "weightedAvgPrice": vwap.to_string(), "volume": base.to_string(),
+                        "quoteVolume": quote.to_string()}),
+                )
+            }
+            None => (400, json!({"code": -1121, "msg": "Invalid symbol."})),
+        },
+        "/v5/market/tickers" => {
+            let list: Vec<Value> = PERPETUALS
+                .iter()
+                .filter(|perp| perp.0 == param("symbol"))
+                .map(|(symbol, mark, funding)| {
+                    let (high, low, vwap, base) = day(*mark);
+                    json!({"symbol": symbol, "markPrice": mark.to_string(), "fundingRate": funding.to_string(),
+                        "highPrice24h": high.to_string(), "lowPrice24h": low.to_string(),
+                        "volume24h": base.to_string(), "turnover24h": (vwap * base).to_string()})
+                })
+                .collect();
+            let result = json!({"category": param("category"), "list": list});
+            (200, json!({"retCode": 0, "retMsg": "OK", "result": result, "time": QUOTED_AT * 1000}))
+        }
+        _ if path.starts_with("/v8/finance/chart/") => chart(&path["/v8/finance/chart/".len()..]),
+        _ => match path.strip_prefix("/api/v3/coins/").and_then(|id| COINS.iter().find(|coin| coin.0 == id)) {
+            Some(entry) => (200, coin(entry)),
+            None => (404, json!({"error": "Not Found"})),
+        },
+    }
+}
+
+// The 24 hour high, low, VWAP and base volume reported with `price`.
+fn day(price: f64) -> (f64, f64, f64, f64) {
+    (price * 1.02, price * 0.97, price * 0.995, 1000.0)
+}
+
+// `[["price", "size"], ...]` as both exchanges send them.
+fn levels(mid: f64, offsets: &[(f64, f64)]) -> Vec<Value> {
+    offsets.iter().map(|(offset, size)| json!([(mid * (1.0 + offset)).to_string(), size.to_string()])).collect()
+}
+
+fn chart(symbol: &str) -> (u16, Value) {
+    let Some((symbol, currency, price)) = TICKERS.iter().find(|ticker| ticker.0 == symbol) else {
+        let error = json!({"code": "Not Found", "description": "No data found, symbol may be delisted"});
+        return (404, json!({"chart": {"result": null, "error": error}}));
+    };
+    let meta = json!({
+        "symbol": symbol,
+        "currency": currency,
+        "regularMarketDayHigh": price * 1.01,
+        "regularMarketDayLow": price * 0.99,
+        "regularMarketPrice": price,
+    });
+    (
+        200,
+        json!({
+            "chart": {"result": [{"meta": meta}], "error": null},
+            // An AlphaVantage style quote too, for testing `[json]` sources
+            // with quoted keys and string numbers.
+            "Global Quote": {"05. price": price.to_string()},
+        }),
+    )
+}
+
+fn coin((id, symbol, name, _): &(&str, &str, &str, f64)) -> Value {
+    json!({"id": id, "symbol": symbol, "name": name})
+}
+
+fn rate(currency: &str) -> Option<f64> {
+    if currency == "usd" {
+        return Some(1.0);
+    }
+    RATES.iter().find(|(code, _)| *code == currency).map(|(_, rate)| *rate)
+}
+
+// Undoes the URL encoding of a path or query component.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,202 @@
+// Counts the requests sent to each host per UTC day, against the daily
+// quotas in `[http] quotas = { "api.coingecko.com" = 10000 }`, and warns as
+// one is running out. Counts are kept in USAGE_FILE in the storage directory
+// so every run of the binary adds to the same day's total.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Body, Client, Conditional, HttpClient, HttpError, Validators};
+use crate::PriceError;
+
+pub const USAGE_FILE: &str = "http_usage.json";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Usage {
+    pub date: String,
+    // Requests per host.
+    pub calls: BTreeMap<String, u64>,
+}
+
+impl Usage {
+    // Today's counts; those of an earlier day are dropped.
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        let today = today();
+        let usage = match fs::read_to_string(path) {
+            Ok(body) => serde_json::from_str(&body)
+                .map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))?,
+            Err(_) => Usage::default(),
+        };
+        if usage.date == today {
+            Ok(usage)
+        } else {
+            Ok(Usage { date: today, calls: BTreeMap::new() })
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let body = serde_json::to_string_pretty(self).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, body).map_err(file_error)?;
+        fs::rename(&tmp_path, path).map_err(file_error)
+    }
+}
+
+fn today() -> String {
+    chrono::Utc::now().format(DATE_FORMAT).to_string()
+}
+
+pub fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    authority.split(':').next().unwrap_or(authority)
+}
+
+pub struct QuotaTracker {
+    inner: Client,
+    // None for dry runs, which count without saving.
+    path: Option<PathBuf>,
+    quotas: BTreeMap<String, u64>,
+    // Fraction of a quota after which it is reported as running out.
+    warning: f64,
+    state: Mutex<State>,
+}
+
+struct State {
+    usage: Usage,
+    // Requests not yet added to the file.
+    unsaved: BTreeMap<String, u64>,
+    // Per host, 1 once warned about the quota running out, 2 once used up.
+    warned: BTreeMap<String, u8>,
+}
+
+impl QuotaTracker {
+    pub fn new(inner: Client, path: Option<&Path>, quotas: BTreeMap<String, u64>, warning: f64) -> Self {
+        let usage = match path {
+            Some(path) => Usage::load(path).unwrap_or_else(|e| {
+                eprintln!("Error reading request counts: {}", e);
+                Usage { date: today(), calls: BTreeMap::new() }
+            }),
+            None => Usage { date: today(), calls: BTreeMap::new() },
+        };
+        let state = State { usage, unsaved: BTreeMap::new(), warned: BTreeMap::new() };
+        QuotaTracker { inner, path: path.map(Path::to_path_buf), quotas, warning, state: Mutex::new(state) }
+    }
+
+    fn count(&self, url: &str) {
+        let host = host(url);
+        let mut state = self.state.lock().unwrap();
+        if state.usage.date != today() {
+            state.usage = Usage { date: today(), calls: BTreeMap::new() };
+            state.unsaved.clear();
+            state.warned.clear();
+        }
+        *state.unsaved.entry(host.to_string()).or_default() += 1;
+        let calls = state.usage.calls.entry(host.to_string()).or_default();
+        *calls += 1;
+        let calls = *calls;
+
+        let Some(&quota) = self.quotas.get(host) else {
+            return;
+        };
+        let level = if calls >= quota {
+            2
+        } else if calls as f64 >= quota as f64 * self.warning {
+            1
+        } else {
+            return;
+        };
+        let warned = state.warned.entry(host.to_string()).or_default();
+        if *warned >= level {
+            return;
+        }
+        *warned = level;
+        if level == 2 {
+            eprintln!("Warning: the daily quota of {} requests to {} is used up", quota, host);
+        } else {
+            eprintln!("Warning: {} of the {} daily requests to {} are used", calls, quota, host);
+        }
+    }
+
+    // Adds the unsaved counts to the file, which other runs may have
+    // updated since it was read.
+    fn save(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(path) = self.path.as_deref().filter(|_| !state.unsaved.is_empty()) else {
+            return;
+        };
+        let result = Usage::load(path).and_then(|mut usage| {
+            if usage.date == state.usage.date {
+                for (host, calls) in &state.unsaved {
+                    *usage.calls.entry(host.clone()).or_default() += calls;
+                }
+            }
+            usage.save(path)?;
+            Ok(usage)
+        });
+        match result {
+            Ok(usage) => {
+                state.usage = usage;
+                state.unsaved.clear();
+            }
+            Err(e) => eprintln!("Error saving request counts: {}", e),
+        }
+    }
+}
+
+impl HttpClient for QuotaTracker {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.count(url);
+        self.inner.get_with_headers(url, headers, query)
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        self.count(url);
+        self.inner.get_conditional(url, headers, query, validators)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        self.count(url);
+        self.inner.post(url, headers, body)
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        self.count(url);
+        self.inner.put(url, headers, body)
+    }
+
+    fn next_cycle(&self) {
+        self.save();
+        self.inner.next_cycle();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.inner.clock_skew()
+    }
+}
+
+impl Drop for QuotaTracker {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
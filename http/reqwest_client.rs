@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{DATE, ETAG, LAST_MODIFIED};
+use reqwest::StatusCode;
+use rustls::ClientConfig;
+
+use super::clock::Samples;
+use super::{Body, Conditional, HttpClient, HttpError, Validators, MAX_IDLE_PER_HOST, USER_AGENT};
+
+// Shorter than most servers wait before closing an idle connection.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(50);
+
+pub struct ReqwestClient {
+    client: Client,
+    clock: Samples,
+}
+
+impl ReqwestClient {
+    pub fn new(timeout: Duration, tls: Option<Arc<ClientConfig>>) -> Self {
+        let mut builder = Client::builder()
+            .timeout(timeout)
+            .pool_max_idle_per_host(MAX_IDLE_PER_HOST)
+            .pool_idle_timeout(IDLE_TIMEOUT)
+            .user_agent(USER_AGENT);
+        if let Some(tls) = tls {
+            builder = builder.use_preconfigured_tls((*tls).clone());
+        }
+        let client = builder.build().expect("the TLS backend failed to initialize");
+        ReqwestClient { client, clock: Samples::default() }
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.send(self.request(url, headers, query)).and_then(|response| response.text().map_err(transport))
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        let mut request = self.request(url, headers, query);
+        if let Some(etag) = &validators.etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+        let response = self.send(request)?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Conditional::NotModified);
+        }
+        let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        let validators = Validators { etag: header(ETAG), last_modified: header(LAST_MODIFIED) };
+        response.text().map(|body| Conditional::Modified(body, validators)).map_err(transport)
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        let mut request = self.client.post(url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        self.send(match body {
+            Body::Text(text) => request.body(text.to_string()),
+            Body::Form(form) => request.form(form),
+        })
+        .and_then(|response| response.text().map_err(transport))
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        let mut request = self.client.put(url);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        self.send(request.body(body.to_vec())).and_then(|response| response.text().map_err(transport))
+    }
+
+    fn next_cycle(&self) {
+        self.clock.clear();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.clock.skew()
+    }
+}
+
+impl ReqwestClient {
+    fn request(&self, url: &str, headers: &[(&str, &str)], query: &[(&str, &str)]) -> RequestBuilder {
+        let mut request = self.client.get(url).query(query);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request
+    }
+
+    // The response, unless its status is an error. 304 passes, for
+    // `get_conditional`.
+    fn send(&self, request: RequestBuilder) -> Result<Response, HttpError> {
+        let response = request.send().map_err(transport)?;
+        let date = response.headers().get(DATE).and_then(|value| value.to_str().ok());
+        self.clock.observe(date);
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            let url = response.url().to_string();
+            let message = format!("{}: status code {}", url, status.as_u16());
+            return Err(HttpError::Status { url, code: status.as_u16(), message });
+        }
+        Ok(response)
+    }
+}
+
+// reqwest keeps the cause, such as a timeout, out of its own message.
+fn transport(e: reqwest::Error) -> HttpError {
+    let url = e.url().map(|url| url.to_string()).unwrap_or_default();
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    HttpError::Transport { url, message, source: Some(Box::new(e)) }
+}
@@ -0,0 +1,72 @@
+// Canned responses standing in for the network in unit tests, keyed by the
+// URL without its query:
+//
+//   let stub = Arc::new(StubClient::default().respond(url, r#"{"bitcoin": {"usd": 1.0}}"#));
+//   let coin = Coin::new(info, Vec::new(), stub.clone());
+//
+// URLs given no response fail as a connection would.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::fixtures::full_url;
+use super::{Body, HttpClient, HttpError};
+
+#[derive(Default)]
+pub struct StubClient {
+    // A body, or the status to fail with.
+    responses: HashMap<String, Result<String, u16>>,
+    // Every URL asked for, with its query.
+    requests: Mutex<Vec<String>>,
+}
+
+impl StubClient {
+    pub fn respond(mut self, url: &str, body: &str) -> Self {
+        self.responses.insert(url.to_string(), Ok(body.to_string()));
+        self
+    }
+
+    pub fn fail(mut self, url: &str, status: u16) -> Self {
+        self.responses.insert(url.to_string(), Err(status));
+        self
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn answer(&self, url: &str, query: &[(&str, &str)]) -> Result<String, HttpError> {
+        let full = full_url(url, query);
+        self.requests.lock().unwrap().push(full.clone());
+        match self.responses.get(url) {
+            Some(Ok(body)) => Ok(body.clone()),
+            Some(Err(code)) => {
+                let message = format!("{}: status code {}", full, code);
+                Err(HttpError::Status { url: full, code: *code, message })
+            }
+            None => {
+                let message = format!("{}: no canned response", full);
+                Err(HttpError::Transport { url: full, message, source: None })
+            }
+        }
+    }
+}
+
+impl HttpClient for StubClient {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.answer(url, query)
+    }
+
+    fn post(&self, url: &str, _headers: &[(&str, &str)], _body: Body) -> Result<String, HttpError> {
+        self.answer(url, &[])
+    }
+
+    fn put(&self, url: &str, _headers: &[(&str, &str)], _body: &[u8]) -> Result<String, HttpError> {
+        self.answer(url, &[])
+    }
+}
@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::ClientConfig;
+
+use super::clock::Samples;
+use super::{Body, Conditional, HttpClient, HttpError, Validators, MAX_IDLE_PER_HOST, USER_AGENT};
+
+pub struct UreqClient {
+    agent: ureq::Agent,
+    clock: Samples,
+}
+
+impl UreqClient {
+    // ureq resends a GET whose pooled connection turns out to be closed, so
+    // idle connections need no expiry.
+    pub fn new(timeout: Duration, tls: Option<Arc<ClientConfig>>) -> Self {
+        let mut agent = ureq::AgentBuilder::new()
+            .timeout(timeout)
+            .max_idle_connections_per_host(MAX_IDLE_PER_HOST)
+            .user_agent(USER_AGENT);
+        if let Some(tls) = tls {
+            agent = agent.tls_config(tls);
+        }
+        let agent = agent.build();
+        UreqClient { agent, clock: Samples::default() }
+    }
+
+    fn request(&self, url: &str, headers: &[(&str, &str)], query: &[(&str, &str)]) -> ureq::Request {
+        let mut request = self.agent.get(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        for (name, value) in query {
+            request = request.query(name, value);
+        }
+        request
+    }
+
+    fn read(&self, url: &str, result: Result<ureq::Response, ureq::Error>) -> Result<String, HttpError> {
+        let response = result.map_err(|e| error(url, e))?;
+        self.clock.observe(response.header("Date"));
+        response.into_string().map_err(|e| transport(url, e))
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+    ) -> Result<String, HttpError> {
+        self.read(url, self.request(url, headers, query).call())
+    }
+
+    fn get_conditional(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        query: &[(&str, &str)],
+        validators: &Validators,
+    ) -> Result<Conditional, HttpError> {
+        let mut request = self.request(url, headers, query);
+        if let Some(etag) = &validators.etag {
+            request = request.set("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.set("If-Modified-Since", last_modified);
+        }
+        let response = request.call().map_err(|e| error(url, e))?;
+        self.clock.observe(response.header("Date"));
+        if response.status() == 304 {
+            return Ok(Conditional::NotModified);
+        }
+        let validators = Validators {
+            etag: response.header("ETag").map(str::to_string),
+            last_modified: response.header("Last-Modified").map(str::to_string),
+        };
+        response
+            .into_string()
+            .map_err(|e| transport(url, e))
+            .map(|body| Conditional::Modified(body, validators))
+    }
+
+    fn post(&self, url: &str, headers: &[(&str, &str)], body: Body) -> Result<String, HttpError> {
+        let mut request = self.agent.post(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        self.read(url, match body {
+            Body::Text(text) => request.send_string(text),
+            Body::Form(form) => request.send_form(form),
+        })
+    }
+
+    fn put(&self, url: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<String, HttpError> {
+        let mut request = self.agent.put(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        self.read(url, request.send_bytes(body))
+    }
+
+    fn next_cycle(&self) {
+        self.clock.clear();
+    }
+
+    fn clock_skew(&self) -> Option<chrono::Duration> {
+        self.clock.skew()
+    }
+}
+
+fn error(url: &str, e: ureq::Error) -> HttpError {
+    match e {
+        ureq::Error::Status(code, response) => {
+            let url = response.get_url().to_string();
+            let message = format!("{}: status code {}", url, code);
+            // Reading the body to the end hands the connection back to the pool.
+            let _ = response.into_string();
+            HttpError::Status { url, code, message }
+        }
+        e => transport(url, e),
+    }
+}
+
+fn transport(url: &str, e: impl Error + Send + Sync + 'static) -> HttpError {
+    HttpError::Transport { url: url.to_string(), message: e.to_string(), source: Some(Box::new(e)) }
+}
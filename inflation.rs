@@ -0,0 +1,141 @@
+// Expresses USD prices in constant dollars by the consumer price index from
+// FRED, for `chart --real` and `report --real`:
+//
+//   [inflation]
+//   series = "CPIAUCSL"       # FRED series id, monthly CPI for all urban consumers
+//   base = "2020-01"          # the month whose dollars to show; the latest by default
+//   refresh = "1d"
+//
+// The series is cached next to the history as `cpi_<series>.csv` and only
+// downloaded again once older than `refresh`; a stale copy is still used
+// when the download fails. A record takes the index of the latest month
+// starting at or before it, so the weeks FRED lags behind use the last
+// published value.
+
+use std::fs;
+use std::time::SystemTime;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::http::Client;
+use crate::storage::PriceRecord;
+use crate::PriceError;
+
+const FRED_URL: &str = "https://fred.stlouisfed.org/graph/fredgraph.csv";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InflationConfig {
+    pub series: String,
+    // "YYYY-MM".
+    pub base: Option<String>,
+    pub refresh: String,
+}
+
+impl Default for InflationConfig {
+    fn default() -> Self {
+        InflationConfig { series: "CPIAUCSL".to_string(), base: None, refresh: "1d".to_string() }
+    }
+}
+
+pub struct Deflator {
+    // Oldest first.
+    observations: Vec<(NaiveDate, f64)>,
+    base: (NaiveDate, f64),
+}
+
+impl Deflator {
+    // Reads the cached series, downloading it first if it is missing or
+    // stale.
+    pub fn load(config: &Config, client: &Client) -> Result<Self, PriceError> {
+        let inflation = config.inflation.clone().unwrap_or_default();
+        let max_age = parse_duration(&inflation.refresh)?.to_std().unwrap_or_default();
+        let cache = config.storage.directory().join(format!("cpi_{}.csv", inflation.series));
+        let age = fs::metadata(&cache)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+        if age.is_none_or(|age| age >= max_age) {
+            match client.get(FRED_URL, &[("id", &inflation.series)]) {
+                Ok(body) => {
+                    let observations = parse(&body, &inflation.series)?;
+                    if let Err(e) = fs::write(&cache, &body) {
+                        eprintln!("Error caching {} in {}: {}", inflation.series, cache.display(), e);
+                    }
+                    return Deflator::new(observations, inflation.base.as_deref());
+                }
+                Err(e) if age.is_some() => eprintln!("{}: {} (using the cached series)", inflation.series, e),
+                Err(e) => return Err(PriceError::NetworkError(format!("{}: {}", inflation.series, e))),
+            }
+        }
+        let body =
+            fs::read_to_string(&cache).map_err(|e| PriceError::FileError(format!("{}: {}", cache.display(), e)))?;
+        Deflator::new(parse(&body, &cache.display().to_string())?, inflation.base.as_deref())
+    }
+
+    fn new(observations: Vec<(NaiveDate, f64)>, base: Option<&str>) -> Result<Self, PriceError> {
+        let base = match base {
+            Some(month) => {
+                let date = parse_month(month)?;
+                observations.iter().find(|(observed, _)| *observed == date).copied().ok_or_else(|| {
+                    PriceError::ParseError(format!("inflation.base: the series has no value for {}", month))
+                })?
+            }
+            None => *observations
+                .last()
+                .ok_or_else(|| PriceError::ParseError("the inflation series is empty".to_string()))?,
+        };
+        Ok(Deflator { observations, base })
+    }
+
+    // "2020-01 dollars", for titles.
+    pub fn label(&self) -> String {
+        format!("{} dollars", self.base.0.format("%Y-%m"))
+    }
+
+    fn factor(&self, at: NaiveDateTime) -> Option<f64> {
+        let month = self.observations.partition_point(|(observed, _)| *observed <= at.date());
+        let (_, index) = self.observations.get(month.checked_sub(1)?)?;
+        Some(self.base.1 / index)
+    }
+
+    // The record with its USD values in base-month dollars, or None if it
+    // predates the series.
+    pub fn deflate(&self, record: &PriceRecord) -> Option<PriceRecord> {
+        let factor = self.factor(record.timestamp)?;
+        let mut real = record.clone();
+        real.price *= factor;
+        real.volume = record.volume.map(|volume| volume * factor);
+        real.market_cap = record.market_cap.map(|market_cap| market_cap * factor);
+        Some(real)
+    }
+}
+
+pub fn parse_month(month: &str) -> Result<NaiveDate, PriceError> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| PriceError::ParseError(format!("inflation.base: '{}' is not a month like 2020-01", month)))
+}
+
+// FRED's CSV: a header, then a date and a value per line, "." marking a
+// month without one.
+fn parse(body: &str, source: &str) -> Result<Vec<(NaiveDate, f64)>, PriceError> {
+    let mut observations = Vec::new();
+    for (index, line) in body.lines().enumerate().skip(1).filter(|(_, line)| !line.trim().is_empty()) {
+        let invalid = || PriceError::ParseError(format!("{} line {}: expected a date and a value", source, index + 1));
+        let (date, value) = line.split_once(',').ok_or_else(invalid)?;
+        if value.trim() == "." {
+            continue;
+        }
+        let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| invalid())?;
+        let value: f64 = value.trim().parse().map_err(|_| invalid())?;
+        if value.is_nan() || value <= 0.0 {
+            return Err(invalid());
+        }
+        observations.push((date, value));
+    }
+    observations.sort_by_key(|(date, _)| *date);
+    Ok(observations)
+}
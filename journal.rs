@@ -0,0 +1,153 @@
+// A write-ahead journal in front of each sink, `<sink>_journal.jsonl` in
+// the storage directory unless [queues.<sink>] sets `journal_file`. Every record is written and
+// synced to it before it is queued, and acknowledged once the sink has it,
+// so records a crash caught in the queue are written when the tracker is
+// next started. The file is emptied whenever nothing is outstanding.
+//
+// Each line is either an entry, a numbered queued write, or an
+// acknowledgement of one: {"ack": <number>}.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Storage;
+use crate::writer::{Spilled, Write};
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Ack {
+        ack: u64,
+    },
+    Entry {
+        id: u64,
+        #[serde(flatten)]
+        write: Spilled,
+    },
+}
+
+pub struct Journal {
+    path: PathBuf,
+    file: Option<File>,
+    next: u64,
+    // Entries not yet acknowledged.
+    outstanding: usize,
+    // Whether the last entry could not be written, to say so only once.
+    failing: bool,
+}
+
+impl Journal {
+    // Opens the journal, returning the writes a previous run left
+    // unacknowledged, oldest first.
+    pub fn open(path: &Path) -> (Self, Vec<Write>) {
+        let mut entries = BTreeMap::new();
+        for line in fs::read_to_string(path).unwrap_or_default().lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<Line>(line) {
+                Ok(Line::Entry { id, write }) => {
+                    entries.insert(id, write);
+                }
+                Ok(Line::Ack { ack }) => {
+                    entries.remove(&ack);
+                }
+                // Most likely the last line, cut short by the crash before
+                // its record was queued.
+                Err(e) => eprintln!("Error reading {}: {}", path.display(), e),
+            }
+        }
+        let unwritten = entries
+            .into_values()
+            .filter_map(|entry| {
+                entry.into_write().inspect_err(|e| eprintln!("Error reading {}: {}", path.display(), e)).ok()
+            })
+            .collect();
+        let journal = Journal { path: path.to_path_buf(), file: None, next: 1, outstanding: 0, failing: false };
+        (journal, unwritten)
+    }
+
+    // Starts the journal over once what it held has been replayed.
+    pub fn clear(&mut self) {
+        if let Err(e) = self.file().and_then(|file| file.set_len(0).map_err(|e| e.to_string())) {
+            eprintln!("Error clearing {}: {}", self.path.display(), e);
+        }
+        self.outstanding = 0;
+    }
+
+    // Journals `write` and returns its number, or None if the journal could
+    // not be written, in which case the record is still queued.
+    pub fn record(&mut self, write: &Write) -> Option<u64> {
+        let id = self.next;
+        let line = serde_json::to_string(&Line::Entry { id, write: Spilled::from_write(write) });
+        let result = line.map_err(|e| e.to_string()).and_then(|line| {
+            let file = self.file()?;
+            writeln!(file, "{}", line).and_then(|_| file.sync_data()).map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            if !self.failing {
+                eprintln!("Error journaling price for {}: {}: {}", write.name, self.path.display(), e);
+            }
+            self.failing = true;
+            return None;
+        }
+        if self.failing {
+            println!("Journaling records to {} again", self.path.display());
+            self.failing = false;
+        }
+        self.next += 1;
+        self.outstanding += 1;
+        Some(id)
+    }
+
+    // Marks the write as in the sink, or otherwise dealt with.
+    pub fn acknowledge(&mut self, id: u64) {
+        self.outstanding = self.outstanding.saturating_sub(1);
+        if self.outstanding == 0 {
+            self.clear();
+            return;
+        }
+        let line = serde_json::to_string(&Line::Ack { ack: id }).map_err(|e| e.to_string());
+        if let Err(e) = line.and_then(|line| writeln!(self.file()?, "{}", line).map_err(|e| e.to_string())) {
+            eprintln!("Error updating {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn file(&mut self) -> Result<&mut File, String> {
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| format!("{}: {}", self.path.display(), e))?;
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+}
+
+// Writes what the journal held to the sink before anything new, leaving
+// out records the sink already has by their sequence number, in case the
+// crash came between writing a record and acknowledging it. Returns the
+// writes that failed again.
+pub fn replay(storage: &mut dyn Storage, writes: Vec<Write>) -> Vec<Write> {
+    let mut last = HashMap::new();
+    let mut failed = Vec::new();
+    for write in writes {
+        let stored = last.entry(write.asset.clone()).or_insert_with(|| {
+            storage.last(&write.asset).ok().flatten().and_then(|record| record.sequence).unwrap_or(0)
+        });
+        if write.record.sequence.is_some_and(|sequence| sequence <= *stored) {
+            continue;
+        }
+        match storage.append(&write.asset, &write.record) {
+            Ok(()) => println!("Wrote the journaled price of {} from {}", write.name, write.record.timestamp),
+            Err(e) => {
+                eprintln!("Error saving journaled price for {}: {}", write.name, e);
+                failed.push(write);
+            }
+        }
+    }
+    failed
+}
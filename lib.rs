@@ -0,0 +1,790 @@
+
+mod alerts;
+mod analytics;
+mod backtest;
+mod cli;
+mod commands;
+mod config;
+mod digest;
+mod disk;
+mod display;
+mod dividends;
+mod email;
+mod fx;
+pub mod history;
+mod http;
+mod inflation;
+mod journal;
+mod notify;
+mod paper;
+mod pipeline;
+mod registry;
+mod s3;
+mod sheets;
+mod sftp;
+mod sources;
+mod storage;
+mod strategy;
+mod supervisor;
+mod theme;
+mod uploads;
+mod uptime;
+mod watch;
+mod watchlist;
+mod writer;
+
+use std::thread;
+use std::time::Duration;
+use chrono::Local;
+use std::error::Error;
+use std::fmt;
+use std::process;
+use std::sync::Arc;
+use clap::Parser;
+use cli::{Cli, Command};
+use alerts::added::AddedRules;
+use alerts::AlertEngine;
+use config::Config;
+use digest::Digest;
+use disk::DiskMonitor;
+use display::NumberFormat;
+use fx::FxConverter;
+use http::clock::SkewCheck;
+use http::Client;
+use inflation::Deflator;
+use paper::PaperTrader;
+use pipeline::Pipeline;
+use s3::S3Sink;
+use sheets::SheetsSink;
+use sftp::SftpSink;
+use storage::sequence::Sequencer;
+use storage::{PriceRecord, Storage, TIMESTAMP_FORMAT};
+use supervisor::Supervisor;
+use theme::Theme;
+use uptime::Uptime;
+use watch::WatchScreen;
+use watchlist::TopCoins;
+use writer::{Write, Writer};
+
+
+#[derive(Debug)]
+pub enum PriceError {
+    NetworkError(String),
+    ParseError(String),
+    FileError(String),
+    // A bug rather than bad input, such as a source panicking.
+    Internal(String),
+    // A failed HTTP request, shown as a network error but with the request
+    // kept apart so callers need not pick it out of the message.
+    Http(Box<HttpFailure>),
+    // A parse error keeping its cause and, for a response, what was fetched,
+    // shown as a ParseError.
+    Parse(Box<ParseFailure>),
+}
+
+// What an error came from, kept whole for Error::source.
+pub type Cause = Arc<dyn Error + Send + Sync>;
+
+// The most of a response body kept with an error parsing it.
+const RESPONSE_EXCERPT: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct HttpFailure {
+    // The provider of the source that made the request, once the error has
+    // come back out of it; None for requests made elsewhere.
+    pub provider: Option<String>,
+    pub url: String,
+    // None when nothing was answered, e.g. on a timeout.
+    pub status: Option<u16>,
+    pub message: String,
+    // The HTTP library's error, when it gave one.
+    pub source: Option<Cause>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseFailure {
+    // As for HttpFailure.
+    pub provider: Option<String>,
+    pub message: String,
+    // The request whose response did not parse, if it came from one, with
+    // the start of the response and its whole length in bytes.
+    pub url: Option<String>,
+    pub body: Option<String>,
+    pub body_length: usize,
+    // The parser's error, such as serde_json's.
+    pub source: Option<Cause>,
+}
+
+impl fmt::Display for PriceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (kind, provider) = match self {
+            PriceError::NetworkError(_) => ("Network Error", None),
+            PriceError::Http(failure) => ("Network Error", failure.provider.as_ref()),
+            PriceError::ParseError(_) => ("Parse Error", None),
+            PriceError::Parse(failure) => ("Parse Error", failure.provider.as_ref()),
+            PriceError::FileError(_) => ("File Error", None),
+            PriceError::Internal(_) => ("Internal Error", None),
+        };
+        write!(f, "{} [{}]: ", kind, self.code())?;
+        if let Some(provider) = provider {
+            write!(f, "{}: ", provider)?;
+        }
+        f.write_str(self.message())
+    }
+}
+
+impl Error for PriceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let cause = match self {
+            PriceError::Http(failure) => failure.source.as_ref(),
+            PriceError::Parse(failure) => failure.source.as_ref(),
+            _ => None,
+        };
+        cause.map(|cause| cause.as_ref() as &(dyn Error + 'static))
+    }
+}
+
+impl From<serde_json::Error> for PriceError {
+    fn from(e: serde_json::Error) -> Self {
+        PriceError::Parse(Box::new(ParseFailure {
+            provider: None,
+            message: e.to_string(),
+            url: None,
+            body: None,
+            body_length: 0,
+            source: Some(Arc::new(e)),
+        }))
+    }
+}
+
+impl PriceError {
+    // The description without its kind, for wrapping in another message.
+    pub fn message(&self) -> &str {
+        match self {
+            PriceError::NetworkError(msg)
+            | PriceError::ParseError(msg)
+            | PriceError::FileError(msg)
+            | PriceError::Internal(msg) => msg,
+            PriceError::Http(failure) => &failure.message,
+            PriceError::Parse(failure) => &failure.message,
+        }
+    }
+
+    // Shown in brackets after the kind, and never reused for another kind of
+    // error, so logs can be searched and alerted on by it.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PriceError::NetworkError(_) => "E100",
+            PriceError::Http(failure) if failure.status.is_none() => "E101",
+            PriceError::Http(_) => "E102",
+            PriceError::ParseError(_) | PriceError::Parse(_) => "E200",
+            PriceError::FileError(_) => "E300",
+            PriceError::Internal(_) => "E500",
+        }
+    }
+
+    // Whether the same call may succeed if made again later: network trouble,
+    // timeouts, rate limiting and server errors, but not a request the
+    // server refused, data that did not parse or a missing file.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PriceError::NetworkError(_) => true,
+            PriceError::Http(failure) => {
+                failure.status.is_none_or(|status| status == 408 || status == 429 || status >= 500)
+            }
+            PriceError::ParseError(_) | PriceError::Parse(_) | PriceError::FileError(_) | PriceError::Internal(_) => {
+                false
+            }
+        }
+    }
+
+    pub fn is_parse(&self) -> bool {
+        matches!(self, PriceError::ParseError(_) | PriceError::Parse(_))
+    }
+
+    pub fn http(&self) -> Option<&HttpFailure> {
+        match self {
+            PriceError::Http(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self) -> Option<&ParseFailure> {
+        match self {
+            PriceError::Parse(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    // Records which provider's source a request failed in; other errors
+    // are returned as they are.
+    pub fn with_provider(mut self, provider: &str) -> Self {
+        match &mut self {
+            PriceError::Http(failure) => {
+                failure.provider.get_or_insert_with(|| provider.to_string());
+            }
+            PriceError::Parse(failure) => {
+                failure.provider.get_or_insert_with(|| provider.to_string());
+            }
+            _ => {}
+        }
+        self
+    }
+
+    // Keeps the request and the start of the response with a parse error
+    // from it, for --verbose; other errors are returned as they are.
+    pub fn with_response(self, url: &str, body: &str) -> Self {
+        let mut failure = match self {
+            PriceError::ParseError(message) => Box::new(ParseFailure {
+                provider: None,
+                message,
+                url: None,
+                body: None,
+                body_length: 0,
+                source: None,
+            }),
+            PriceError::Parse(failure) if failure.url.is_none() => failure,
+            e => return e,
+        };
+        failure.url = Some(url.to_string());
+        failure.body = Some(body.chars().take(RESPONSE_EXCERPT).collect());
+        failure.body_length = body.len();
+        PriceError::Parse(failure)
+    }
+
+    // The lines --verbose adds under the error: the request and response
+    // a parse error came from and the chain of causes.
+    pub fn diagnostic(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(failure) = self.parse() {
+            if let (Some(url), Some(body)) = (&failure.url, &failure.body) {
+                lines.push(format!("  request: {}", url));
+                let shown = match body.len() < failure.body_length {
+                    true => format!("{} bytes, the first {} characters", failure.body_length, RESPONSE_EXCERPT),
+                    false => format!("{} bytes", failure.body_length),
+                };
+                lines.push(format!("  response ({}): {:?}", shown, body));
+            }
+        }
+        let mut source = self.source();
+        while let Some(cause) = source {
+            lines.push(format!("  caused by: {}", cause));
+            source = cause.source();
+        }
+        lines
+    }
+}
+
+
+// Shared by the fetch workers, so sources keep their state behind locks.
+pub trait Pricing: Send + Sync {
+    fn fetch_price(&self) -> Result<f64, PriceError>;
+    fn name(&self) -> &str;
+    fn id(&self) -> &str;
+
+    // Short lowercase code used for series priced in this asset.
+    fn symbol(&self) -> &str {
+        self.id()
+    }
+
+    // Decimal places when showing prices.
+    fn precision(&self) -> usize {
+        2
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.fetch_price()
+            .map(|price| PriceRecord::new(Local::now().naive_local(), price))
+    }
+
+    // What the tracker loop calls: like fetch_record, but None when the
+    // provider says nothing changed since the last call.
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        self.fetch_record().map(Some)
+    }
+
+    // Called once when tracking starts, failing only for an asset that can
+    // never be fetched, such as an unknown ticker. Passing trouble is left
+    // for the fetches to report.
+    fn validate(&self) -> Result<(), PriceError> {
+        Ok(())
+    }
+}
+
+
+// Extra currencies and changes for the console line, e.g.
+// " (EUR 60123.45, 0.05 BTC, 24h +1.20%)".
+fn format_quotes(record: &PriceRecord, format: &NumberFormat, theme: &Theme) -> String {
+    let quotes: Vec<String> = record.quotes
+        .iter()
+        .map(|(currency, price)| format.money(*price, 2, currency))
+        .chain(record.relative.iter().map(|(symbol, price)| {
+            format!("{} {}", format.number(*price, 8), symbol.to_uppercase())
+        }))
+        .chain(record.changes.iter().map(|(period, change)| {
+            format!("{} {}", period, theme.change(*change, &format!("{}%", format.signed(*change, 2))))
+        }))
+        .collect();
+    if quotes.is_empty() {
+        return String::new();
+    }
+    format!(" ({})", quotes.join(", "))
+}
+
+// Expresses each fetched price in terms of every denomination asset fetched
+// in the same cycle.
+fn denominate(fetched: &mut [(&dyn Pricing, PriceRecord)], denominations: &[String]) {
+    for id in denominations {
+        let Some((symbol, base)) = fetched
+            .iter()
+            .find(|(asset, _)| asset.id() == id)
+            .map(|(asset, record)| (asset.symbol().to_string(), record.price))
+            .filter(|(_, price)| *price > 0.0)
+        else {
+            continue;
+        };
+        for (_, record) in fetched.iter_mut().filter(|(asset, _)| asset.id() != id) {
+            record.relative.insert(symbol.clone(), record.price / base);
+        }
+    }
+}
+
+fn find_asset<'a>(assets: &'a [Box<dyn Pricing>], id: &str) -> Result<&'a dyn Pricing, PriceError> {
+    assets
+        .iter()
+        .find(|asset| asset.id().eq_ignore_ascii_case(id))
+        .map(|asset| asset.as_ref())
+        .ok_or_else(|| {
+            let known: Vec<&str> = assets.iter().map(|asset| asset.id()).collect();
+            PriceError::ParseError(format!("unknown asset '{}', expected one of: {}", id, known.join(", ")))
+        })
+}
+
+// The whole command line, with main.rs only calling it, so the modules
+// behind it are the library's as well. Not part of the library's API, which
+// is `history` and the error types.
+#[doc(hidden)]
+pub fn run() {
+    let cli = Cli::parse();
+    // Needs no config, so it works wherever the binary is installed.
+    if let Some(Command::Completions { target }) = cli.command {
+        if let Err(e) = commands::completions::run(target) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // Until the config is loaded and the assets built from it, a parse or
+    // file error is a problem with the config.
+    let mut configured = false;
+    let result = Config::load(cli.config.as_deref(), cli.profile.as_deref()).and_then(|config| {
+        // Checked before the assets are built so every problem is reported.
+        if let Some(Command::Config { action }) = cli.command {
+            return commands::config::run(action, &config);
+        }
+        let client = http::client(&config, cli.record.as_deref(), cli.replay.as_deref(), cli.mock_server, cli.dry_run)?;
+        let assets = sources::tracked(&config, &client)?;
+        if cli.command.is_none() {
+            config::check::validate(&config)?;
+        }
+        configured = true;
+        match cli.command {
+            None => {
+                let open = || {
+                    if cli.dry_run {
+                        storage::open_dry_run(&config.storage)
+                    } else {
+                        storage::open_chained(&config)
+                    }
+                };
+                // The writer appends prices through a handle of its own.
+                let mut storage = open()?;
+                for asset in &assets {
+                    if let Err(e) = storage.recover(asset.id()) {
+                        eprintln!("Error checking the history of {}: {}", asset.name(), e);
+                    }
+                }
+                let sink = config.storage.backend();
+                let mut queue = config.queues.get(sink).cloned().unwrap_or_default();
+                // Nothing is written for real to be recovered.
+                queue.journal &= !cli.dry_run;
+                let (format, theme) = (NumberFormat::from_config(&config)?, Theme::from_config(&config)?);
+                let console = (!cli.watch).then_some((format, theme));
+                let writer = Writer::start(sink, open()?, &queue, config.storage.directory(), console)?;
+                let modes = Modes { dry_run: cli.dry_run, verbose: cli.verbose, watch: cli.watch };
+                run_tracker(assets, storage.as_mut(), writer, &config, client, modes)
+            }
+            Some(Command::Import { asset, file }) => {
+                let asset = find_asset(&assets, &asset)?;
+                let mut storage = storage::open_chained(&config)?;
+                commands::import::run(asset, storage.as_mut(), &file)
+            }
+            Some(Command::Migrate { source }) => commands::migrate::run(&assets, &source, &config.storage),
+            Some(Command::Convert { to, directory }) => commands::convert::run(&assets, &directory, to),
+            Some(Command::Export { format, asset, currency, since, output }) => {
+                let assets = match asset {
+                    Some(asset) => vec![find_asset(&assets, &asset)?],
+                    None => assets.iter().map(|asset| asset.as_ref()).collect(),
+                };
+                let storage = storage::open(&config.storage)?;
+                let currency = currency.to_lowercase();
+                if currency != "usd" && !config.currencies.contains(&currency) {
+                    let message = format!("'{}' is not one of the configured currencies", currency);
+                    return Err(PriceError::ParseError(message));
+                }
+                let gnucash = config.gnucash.as_ref();
+                commands::export::run(&assets, storage.as_ref(), format, gnucash, &currency, since, output.as_deref())
+            }
+            Some(Command::Keygen { output }) => commands::keygen::run(&output),
+            Some(Command::Verify { asset }) => {
+                let assets = match asset {
+                    Some(asset) => vec![find_asset(&assets, &asset)?],
+                    None => assets.iter().map(|asset| asset.as_ref()).collect(),
+                };
+                let storage = storage::open(&config.storage)?;
+                commands::verify::run(&assets, storage.as_ref(), config.storage.directory())
+            }
+            Some(Command::Chart { asset, output, since, until, style, sma, candle, real, width, height }) => {
+                let asset = find_asset(&assets, &asset)?;
+                let storage = storage::open(&config.storage)?;
+                let theme = Theme::from_config(&config)?;
+                let options = commands::chart::ChartOptions { style, sma, candle, width, height, theme };
+                let deflator = real.then(|| Deflator::load(&config, &client)).transpose()?;
+                commands::chart::run(asset, storage.as_ref(), since, until, deflator.as_ref(), &output, &options)
+            }
+            Some(Command::Report { daily: _, weekly, since, output, real }) => {
+                let period = since.unwrap_or(if weekly { chrono::Duration::days(7) } else { chrono::Duration::hours(24) });
+                let benchmark = config.benchmark.as_ref().map(|b| find_asset(&assets, &b.asset)).transpose()?;
+                let storage = storage::open(&config.storage)?;
+                let deflator = real.then(|| Deflator::load(&config, &client)).transpose()?;
+                commands::report::run(&assets, storage.as_ref(), period, benchmark, deflator.as_ref(), &config, &output)
+            }
+            Some(Command::Alerts { rule, since }) => {
+                let storage = storage::open(&config.storage)?;
+                commands::alerts::run(storage.as_ref(), rule.as_deref(), since)
+            }
+            Some(Command::Digest { print }) => {
+                let storage = storage::open(&config.storage)?;
+                if print {
+                    let schedule = config.digest.as_ref().map(|d| d.schedule).unwrap_or_default();
+                    let format = NumberFormat::from_config(&config)?;
+                    digest::compose(&assets, storage.as_ref(), schedule, &format).map(|body| print!("{}", body))
+                } else {
+                    match Digest::from_config(&config)? {
+                        Some(mut digest) => digest.send(&assets, storage.as_ref()),
+                        None => Err(PriceError::ParseError("no [digest] section in the config".to_string())),
+                    }
+                }
+            }
+            Some(Command::Dividends { asset, since, refresh, output }) => {
+                let asset = find_asset(&assets, &asset)?;
+                let registry = registry::Registry::load(&config)?;
+                let info = registry.assets.iter().find(|info| info.id == asset.id());
+                let info = info.ok_or_else(|| PriceError::ParseError(format!("{} is not tracked", asset.id())))?;
+                let dividends = dividends::load(&config, info, &client, refresh)?;
+                let storage = storage::open(&config.storage)?;
+                commands::dividends::run(asset, &dividends, storage.as_ref(), since, output.as_deref())
+            }
+            Some(Command::Backtest { asset, strategy, since, cash, fee }) => {
+                let asset = find_asset(&assets, &asset)?;
+                let storage = storage::open(&config.storage)?;
+                commands::backtest::run(asset, storage.as_ref(), &config, &strategy, since, cash, fee)
+            }
+            Some(Command::Paper) => {
+                let storage = storage::open(&config.storage)?;
+                match PaperTrader::from_config(&config, &assets)? {
+                    Some(trader) => commands::paper::run(&trader, &assets, storage.as_ref()),
+                    None => Err(PriceError::ParseError("no [paper] section in the config".to_string())),
+                }
+            }
+            Some(Command::Compare { first, second, since, rows }) => {
+                let (first, second) = (find_asset(&assets, &first)?, find_asset(&assets, &second)?);
+                let storage = storage::open(&config.storage)?;
+                commands::compare::run(first, second, storage.as_ref(), since, rows, &config)
+            }
+            Some(Command::Performance { benchmark, window }) => {
+                let benchmark = benchmark.or_else(|| config.benchmark.as_ref().map(|b| b.asset.clone())).ok_or_else(|| {
+                    PriceError::ParseError("no benchmark, pass --benchmark or set [benchmark] asset".to_string())
+                })?;
+                let windows = match (window.is_empty(), &config.benchmark) {
+                    (false, _) => window,
+                    (true, Some(config)) => config.windows.clone(),
+                    (true, None) => commands::performance::default_windows(),
+                };
+                let benchmark = find_asset(&assets, &benchmark)?;
+                let storage = storage::open(&config.storage)?;
+                commands::performance::run(&assets, benchmark, storage.as_ref(), &windows)
+            }
+            Some(Command::Search { query, refresh }) => commands::search::run(&query, refresh, &client),
+            Some(Command::Follow { asset, json }) => {
+                let assets = match asset.is_empty() {
+                    true => assets.iter().map(|asset| asset.as_ref()).collect(),
+                    false => asset.iter().map(|id| find_asset(&assets, id)).collect::<Result<Vec<_>, _>>()?,
+                };
+                let storage = storage::open(&config.storage)?;
+                commands::follow::run(&assets, storage.as_ref(), json, &config)
+            }
+            Some(Command::Query { sql, json }) => {
+                let storage = storage::open(&config.storage)?;
+                commands::query::run(&assets, storage.as_ref(), &sql, json)
+            }
+            Some(Command::Quote { symbol, json }) => commands::quote::run(&symbol, json, &assets, &config, &client),
+            Some(Command::Serve { listen }) => {
+                let storage = storage::open(&config.storage)?;
+                commands::serve::run(&assets, &config.currencies, storage.as_ref(), &listen)
+            }
+            Some(Command::Usage) => commands::usage::run(&config),
+            Some(Command::Status { since, json }) => {
+                let storage = storage::open(&config.storage)?;
+                commands::status::run(&assets, storage.as_ref(), &config, since, json)
+            }
+            Some(Command::Repl) => {
+                let storage = storage::open(&config.storage)?;
+                commands::repl::run(&assets, storage.as_ref(), &config)
+            }
+            Some(Command::Asset { action }) => commands::asset::run(action, &config, &client),
+            Some(Command::Config { .. } | Command::Completions { .. }) => unreachable!(),
+        }
+    });
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        if cli.verbose {
+            e.diagnostic().iter().for_each(|line| eprintln!("{}", line));
+        }
+        process::exit(exit_code(&e, configured));
+    }
+}
+
+// Listed in the --help output; 2 is clap's, for invalid arguments.
+const EXIT_FAILURE: i32 = 1;
+const EXIT_CONFIG: i32 = 3;
+const EXIT_NETWORK: i32 = 4;
+const EXIT_PARSE: i32 = 5;
+
+fn exit_code(e: &PriceError, configured: bool) -> i32 {
+    match e {
+        PriceError::NetworkError(_) | PriceError::Http(_) => EXIT_NETWORK,
+        _ if !configured => EXIT_CONFIG,
+        e if e.is_parse() => EXIT_PARSE,
+        _ => EXIT_FAILURE,
+    }
+}
+
+// What the command line changes about how the tracker runs.
+#[derive(Clone, Copy)]
+struct Modes {
+    dry_run: bool,
+    verbose: bool,
+    watch: bool,
+}
+
+// A dry run fetches as usual but leaves files, the database and notifiers
+// alone, printing what it would have done.
+fn run_tracker(
+    mut assets: Vec<Box<dyn Pricing>>,
+    storage: &mut dyn Storage,
+    writer: Writer,
+    config: &Config,
+    client: Client,
+    modes: Modes,
+) -> Result<(), PriceError> {
+    let Modes { dry_run, verbose, watch } = modes;
+    let mut screen = watch.then(|| WatchScreen::from_config(config)).transpose()?;
+    let mut digest = Digest::from_config(config)?;
+    let mut fx = FxConverter::from_config(config, client.clone())?;
+    for id in &config.denominations {
+        find_asset(&assets, id)?;
+    }
+    for asset in &assets {
+        asset.validate()?;
+    }
+    let mut alerts = AlertEngine::new(config, &assets, &client)?;
+    alerts.set_dry_run(dry_run);
+    // Rules added from the repl, reloaded whenever the file changes.
+    let alerts_path = alerts::added::path(config);
+    let mut alerts_modified = watchlist::modified(&alerts_path);
+    alerts.set_added(&AddedRules::load(&alerts_path)?.alerts, &assets)?;
+    let mut top = TopCoins::from_config(config, client.clone())?;
+    let mut pipeline = Pipeline::from_config(config)?;
+    let mut s3 = S3Sink::from_config(config, &client)?;
+    let mut sheets = SheetsSink::from_config(config, &client)?;
+    let mut sftp = SftpSink::from_config(config)?;
+    let mut paper = PaperTrader::from_config(config, &assets)?;
+    if let Some(paper) = paper.as_mut() {
+        paper.set_dry_run(dry_run);
+    }
+    let mut supervisor = Supervisor::default();
+    let mut uptime = Uptime::new(config);
+    let mut disk = DiskMonitor::new(config)?;
+    let mut sequencer = Sequencer::default();
+    let mut clock = SkewCheck::new(config.http.clone().unwrap_or_default().max_clock_skew()?);
+    let interval = config.interval()?.to_std().unwrap_or(Duration::from_secs(10));
+    let mut watchlist_modified = watchlist::modified(config.watchlist_path());
+    if let Err(e) = alerts.warm_up(&*storage) {
+        eprintln!("Error loading history for alerts: {}", e);
+    }
+    if let Err(e) = pipeline.warm_up(&*storage) {
+        eprintln!("Error loading history for pipelines: {}", e);
+    }
+
+    println!("Starting price tracker{}...", if dry_run { " (dry run)" } else { "" });
+    println!("Press Ctrl+C to stop the program");
+
+    
+    loop {
+        client.next_cycle();
+        // `asset add/remove` rewrites the watchlist; pick up the change
+        // without a restart.
+        let modified = watchlist::modified(config.watchlist_path());
+        let mut reload = modified != watchlist_modified;
+        watchlist_modified = modified;
+
+        let now = Local::now().naive_local();
+        if let Some(top) = top.as_mut().filter(|top| top.is_due(now)) {
+            match top.refresh(now, !dry_run) {
+                Ok((entered, left)) => {
+                    let prefix = if dry_run { "[dry run] " } else { "" };
+                    for id in &entered {
+                        println!("{}{} entered the top coins", prefix, id);
+                    }
+                    for id in &left {
+                        println!("{}{} dropped out of the top coins", prefix, id);
+                    }
+                    reload |= !entered.is_empty() || !left.is_empty();
+                }
+                Err(e) => eprintln!("Error fetching top coins: {}", e),
+            }
+        }
+
+        if reload {
+            match sources::tracked(config, &client) {
+                Ok(updated) => {
+                    assets = updated;
+                    uptime.reload(config);
+                    let ids: Vec<&str> = assets.iter().map(|asset| asset.id()).collect();
+                    println!("Now tracking: {}", ids.join(", "));
+                }
+                Err(e) => eprintln!("Error reloading watchlist: {}", e),
+            }
+        }
+        let modified = watchlist::modified(&alerts_path);
+        if modified != alerts_modified {
+            alerts_modified = modified;
+            match AddedRules::load(&alerts_path).and_then(|added| alerts.set_added(&added.alerts, &assets)) {
+                Ok(count) => println!("Loaded {} alert rules from {}", count, alerts_path.display()),
+                Err(e) => eprintln!("Error reloading {}: {}", alerts_path.display(), e),
+            }
+        }
+
+        supervisor.restart_due(&mut assets, config, &client);
+        let due: Vec<&dyn Pricing> =
+            assets.iter().map(|asset| asset.as_ref()).filter(|asset| !supervisor.is_waiting(*asset)).collect();
+        let mut fetched = Vec::new();
+        let panicked = sources::fetch_all(&due, config.concurrency(), |asset, result, elapsed| {
+            uptime.record(asset.id(), elapsed, result.as_ref().err());
+            match result {
+                // The provider says the stored price is still current.
+                Ok(None) => {
+                    let now = Local::now().naive_local();
+                    match screen.as_mut() {
+                        Some(screen) => screen.unchanged(asset, now),
+                        None => println!("[{}] {}: unchanged", now.format(TIMESTAMP_FORMAT), asset.name()),
+                    }
+                    alerts.source_succeeded(asset, now, &mut *storage);
+                }
+                Ok(Some(record)) => {
+                    // A stage rejecting a record is not a failure of the source.
+                    let mut record = match pipeline.apply(asset.id(), record) {
+                        Ok(record) => record,
+                        Err(e) => {
+                            eprintln!("Dropped the price of {}: {}", asset.name(), e);
+                            return;
+                        }
+                    };
+                    if let Some(fx) = fx.as_mut() {
+                        if let Err(e) = fx.convert(&mut record) {
+                            eprintln!("Error converting price for {}: {}", asset.name(), e);
+                        }
+                    }
+                    fetched.push((asset, record));
+                }
+                Err(e) => {
+                    match screen.as_mut() {
+                        Some(screen) => screen.failed(asset, &e),
+                        None => eprintln!("Error fetching price for {}: {}", asset.name(), e),
+                    }
+                    if verbose {
+                        e.diagnostic().iter().for_each(|line| eprintln!("{}", line));
+                    }
+                    alerts.source_failed(asset, &e, &mut *storage);
+                }
+            }
+        });
+        supervisor.finish_round(&due, &panicked);
+        clock.check(client.clock_skew());
+        if !dry_run {
+            uptime.save();
+        }
+        disk.check(Local::now().naive_local(), !dry_run);
+        // Results arrive as they are ready; keep the watchlist's order.
+        fetched.sort_by_key(|(asset, _)| assets.iter().position(|other| other.id() == asset.id()));
+        denominate(&mut fetched, &config.denominations);
+        if let Some(screen) = screen.as_mut() {
+            for (asset, record) in &fetched {
+                screen.fetched(*asset, record);
+            }
+            screen.draw(&assets);
+        }
+
+        for (asset, mut record) in fetched {
+            alerts.source_succeeded(asset, record.timestamp, &mut *storage);
+            if !sequencer.assign(asset.id(), &mut record, &*storage) {
+                if screen.is_some() {
+                    continue;
+                }
+                println!(
+                    "[{}] {}: same as the last stored price, not written again",
+                    record.timestamp.format(TIMESTAMP_FORMAT),
+                    asset.name()
+                );
+                continue;
+            }
+            alerts.process(asset, &record, &mut *storage);
+            if let Some(paper) = paper.as_mut() {
+                paper.on_price(asset, &record);
+            }
+            writer.send(Write::new(asset, record));
+        }
+        alerts.check_staleness(&assets, &mut *storage);
+        if let Some(health) = writer.report() {
+            alerts.sink_changed(writer.name(), health, &mut *storage);
+        }
+
+        if let Some(digest) = digest.as_mut() {
+            if digest.is_due(Local::now().naive_local()) {
+                let result = if dry_run {
+                    digest.preview(&assets, &*storage)
+                } else {
+                    digest.send(&assets, &*storage)
+                };
+                if let Err(e) = result {
+                    eprintln!("Error sending digest: {}", e);
+                }
+            }
+        }
+        if let Some(s3) = s3.as_mut().filter(|s3| s3.is_due(Local::now().naive_local())) {
+            s3.upload(&assets, &*storage, dry_run);
+        }
+        if let Some(sheets) = sheets.as_mut().filter(|sheets| sheets.is_due(Local::now().naive_local())) {
+            sheets.upload(&assets, &*storage, dry_run);
+        }
+        if let Some(sftp) = sftp.as_mut().filter(|sftp| sftp.is_due(Local::now().naive_local())) {
+            sftp.upload(&assets, &*storage, dry_run);
+        }
+
+        
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{Alert, Severity};
+use crate::config::Config;
+use crate::email::{Mailer, SmtpConfig};
+use crate::http::{Body, Client};
+use crate::storage::TIMESTAMP_FORMAT;
+use crate::theme::Theme;
+use crate::PriceError;
+
+pub trait Notifier {
+    fn name(&self) -> &str;
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError>;
+
+    // Lets a notifier skip alerts it is not meant for.
+    fn accepts(&self, _alert: &Alert) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NotifierConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: NotifierKind,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifierKind {
+    Console,
+    Email {
+        to: Vec<String>,
+        #[serde(default = "default_subject")]
+        subject: String,
+        #[serde(default = "default_body")]
+        body: String,
+        // Overrides the top-level [smtp] section for this notifier only.
+        smtp: Option<SmtpConfig>,
+    },
+    // https://ntfy.sh or a self-hosted server.
+    Ntfy {
+        topic: String,
+        #[serde(default = "default_ntfy_server")]
+        server: String,
+        // Access token for protected topics.
+        token: Option<String>,
+        // 1 (min) to 5 (max); ntfy's default is 3.
+        priority: Option<u8>,
+    },
+    Pushover {
+        // Application API token.
+        token: String,
+        // User or group key to deliver to.
+        user: String,
+        // -2 (lowest) to 1 (high).
+        priority: Option<i8>,
+    },
+    // Native notification through the desktop's notification service.
+    Desktop {
+        #[serde(default)]
+        urgency: Urgency,
+        // Seconds before the notification closes; the desktop decides if unset.
+        timeout: Option<u32>,
+    },
+    // SMS through Twilio, sent only for rules with severity = "critical".
+    Twilio {
+        account_sid: String,
+        auth_token: String,
+        from: String,
+        to: Vec<String>,
+        // Messages allowed per calendar day, across all recipients.
+        #[serde(default = "default_daily_limit")]
+        daily_limit: u32,
+    },
+    // Rings the terminal bell, or plays `sound` when set, for when the
+    // tracker runs in a terminal in the corner of the screen.
+    Bell {
+        sound: Option<PathBuf>,
+        // Command used to play `sound`; afplay on macOS, paplay elsewhere.
+        player: Option<String>,
+    },
+}
+
+fn default_daily_limit() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+fn default_subject() -> String {
+    "[{asset}] {rule}".to_string()
+}
+
+fn default_body() -> String {
+    "{message}\n\nRule: {rule}\nAsset: {asset}\nValue: {value}\nTime: {timestamp}\n".to_string()
+}
+
+pub fn build(notifier: &NotifierConfig, config: &Config, client: &Client) -> Result<Box<dyn Notifier>, PriceError> {
+    let invalid = |reason: String| PriceError::ParseError(format!("notifier '{}': {}", notifier.name, reason));
+
+    match &notifier.kind {
+        NotifierKind::Console => {
+            Ok(Box::new(ConsoleNotifier { name: notifier.name.clone(), theme: Theme::from_config(config)? }))
+        }
+        NotifierKind::Email { to, subject, body, smtp } => {
+            if to.is_empty() {
+                return Err(invalid("needs at least one recipient in 'to'".to_string()));
+            }
+            check_template(subject).map_err(invalid)?;
+            check_template(body).map_err(invalid)?;
+            let smtp = smtp
+                .as_ref()
+                .or(config.smtp.as_ref())
+                .ok_or_else(|| invalid("needs an [smtp] section or its own smtp table".to_string()))?;
+            Ok(Box::new(EmailNotifier {
+                name: notifier.name.clone(),
+                mailer: Mailer::new(smtp)?,
+                to: to.clone(),
+                subject: subject.clone(),
+                body: body.clone(),
+            }))
+        }
+        NotifierKind::Ntfy { topic, server, token, priority } => {
+            if topic.is_empty() || topic.contains('/') {
+                return Err(invalid(format!("invalid ntfy topic '{}'", topic)));
+            }
+            if priority.is_some_and(|p| !(1..=5).contains(&p)) {
+                return Err(invalid("ntfy priority must be between 1 and 5".to_string()));
+            }
+            Ok(Box::new(NtfyNotifier {
+                name: notifier.name.clone(),
+                url: format!("{}/{}", server.trim_end_matches('/'), topic),
+                token: token.clone(),
+                priority: *priority,
+                client: client.clone(),
+            }))
+        }
+        NotifierKind::Pushover { token, user, priority } => {
+            if priority.is_some_and(|p| !(-2..=1).contains(&p)) {
+                return Err(invalid("pushover priority must be between -2 and 1".to_string()));
+            }
+            Ok(Box::new(PushoverNotifier {
+                name: notifier.name.clone(),
+                token: token.clone(),
+                user: user.clone(),
+                priority: *priority,
+                client: client.clone(),
+            }))
+        }
+        NotifierKind::Desktop { urgency, timeout } => Ok(Box::new(DesktopNotifier {
+            name: notifier.name.clone(),
+            urgency: *urgency,
+            timeout: *timeout,
+        })),
+        NotifierKind::Twilio { account_sid, auth_token, from, to, daily_limit } => {
+            if to.is_empty() {
+                return Err(invalid("needs at least one phone number in 'to'".to_string()));
+            }
+            Ok(Box::new(TwilioNotifier {
+                name: notifier.name.clone(),
+                url: format!("https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json", account_sid),
+                authorization: format!("Basic {}", BASE64.encode(format!("{}:{}", account_sid, auth_token))),
+                from: from.clone(),
+                to: to.clone(),
+                daily_limit: *daily_limit,
+                sent_path: config.storage.directory().join(TWILIO_SENT_FILE),
+                client: client.clone(),
+            }))
+        }
+        NotifierKind::Bell { sound, player } => {
+            if let Some(sound) = sound.as_ref().filter(|sound| !sound.exists()) {
+                return Err(invalid(format!("sound file {} does not exist", sound.display())));
+            }
+            let default_player = if cfg!(target_os = "macos") { "afplay" } else { "paplay" };
+            Ok(Box::new(BellNotifier {
+                name: notifier.name.clone(),
+                sound: sound.clone(),
+                player: player.clone().unwrap_or_else(|| default_player.to_string()),
+            }))
+        }
+    }
+}
+
+const PLACEHOLDERS: [&str; 6] = ["rule", "asset", "value", "severity", "message", "timestamp"];
+
+// Templates use {rule}, {asset}, {value}, {severity}, {message} and {timestamp}.
+fn check_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in template '{}'", template))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "unknown placeholder '{{{}}}' in template, expected one of: {}",
+                name,
+                PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+fn render(template: &str, alert: &Alert) -> String {
+    template
+        .replace("{rule}", &alert.rule)
+        .replace("{asset}", &alert.asset)
+        .replace("{value}", &format!("{:.2}", alert.value))
+        .replace("{severity}", &alert.severity.to_string())
+        .replace("{timestamp}", &alert.timestamp.format(TIMESTAMP_FORMAT).to_string())
+        .replace("{message}", &alert.message)
+}
+
+pub struct ConsoleNotifier {
+    pub name: String,
+    pub theme: Theme,
+}
+
+impl Notifier for ConsoleNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        // Info alerts in the accent color, warnings and critical ones in the down color.
+        let color = if alert.severity == Severity::Info { self.theme.accent } else { self.theme.down };
+        println!(
+            "[{}] {} {}: {}",
+            self.theme.paint(self.theme.muted, &alert.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+            self.theme.paint(color, "ALERT"),
+            alert.rule,
+            alert.message
+        );
+        Ok(())
+    }
+}
+
+pub struct EmailNotifier {
+    name: String,
+    mailer: Mailer,
+    to: Vec<String>,
+    subject: String,
+    body: String,
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        self.mailer
+            .send(&self.to, &render(&self.subject, alert), &render(&self.body, alert))
+    }
+}
+
+pub struct NtfyNotifier {
+    name: String,
+    url: String,
+    token: Option<String>,
+    priority: Option<u8>,
+    client: Client,
+}
+
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        let title = format!("{}: {}", alert.asset, alert.rule);
+        let authorization = self.token.as_ref().map(|token| format!("Bearer {}", token));
+        let priority = self.priority.map(|p| p.to_string());
+        let mut headers = vec![("Title", title.as_str())];
+        if let Some(authorization) = &authorization {
+            headers.push(("Authorization", authorization));
+        }
+        if let Some(priority) = &priority {
+            headers.push(("Priority", priority));
+        }
+        self.client
+            .post(&self.url, &headers, Body::Text(&alert.message))
+            .map(|_| ())
+            .map_err(|e| PriceError::NetworkError(format!("ntfy: {}", e)))
+    }
+}
+
+pub struct PushoverNotifier {
+    name: String,
+    token: String,
+    user: String,
+    priority: Option<i8>,
+    client: Client,
+}
+
+impl Notifier for PushoverNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        let title = format!("{}: {}", alert.asset, alert.rule);
+        let timestamp = alert.timestamp.and_local_timezone(Local).single().map(|t| t.timestamp().to_string());
+        let priority = self.priority.map(|p| p.to_string());
+
+        let mut form = vec![
+            ("token", self.token.as_str()),
+            ("user", self.user.as_str()),
+            ("title", title.as_str()),
+            ("message", alert.message.as_str()),
+        ];
+        if let Some(timestamp) = &timestamp {
+            form.push(("timestamp", timestamp));
+        }
+        if let Some(priority) = &priority {
+            form.push(("priority", priority));
+        }
+
+        self.client
+            .post("https://api.pushover.net/1/messages.json", &[], Body::Form(&form))
+            .map(|_| ())
+            .map_err(|e| PriceError::NetworkError(format!("pushover: {}", e)))
+    }
+}
+
+pub struct DesktopNotifier {
+    name: String,
+    urgency: Urgency,
+    timeout: Option<u32>,
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        let mut notification = notify_rust::Notification::new();
+        notification
+            .appname("crypto_price_tracker")
+            .summary(&format!("{}: {}", alert.asset, alert.rule))
+            .body(&alert.message);
+        if let Some(seconds) = self.timeout {
+            notification.timeout(notify_rust::Timeout::Milliseconds(seconds.saturating_mul(1000)));
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        notification.urgency(match self.urgency {
+            Urgency::Low => notify_rust::Urgency::Low,
+            Urgency::Normal => notify_rust::Urgency::Normal,
+            Urgency::Critical => notify_rust::Urgency::Critical,
+        });
+        notification
+            .show()
+            .map(|_| ())
+            .map_err(|e| PriceError::NetworkError(format!("desktop notification: {}", e)))
+    }
+}
+
+// Messages sent per Twilio notifier today, so the daily limit holds across
+// restarts of the tracker.
+const TWILIO_SENT_FILE: &str = "twilio_sent.json";
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+struct Sent {
+    date: String,
+    count: u32,
+}
+
+fn load_sent(path: &Path) -> Result<BTreeMap<String, Sent>, PriceError> {
+    match fs::read_to_string(path) {
+        Ok(body) => {
+            serde_json::from_str(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+        }
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn save_sent(path: &Path, sent: &BTreeMap<String, Sent>) -> Result<(), PriceError> {
+    let file_error = |e: io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let body = serde_json::to_string_pretty(sent).map_err(|e| PriceError::Internal(e.to_string()))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, body).map_err(file_error)?;
+    fs::rename(&tmp_path, path).map_err(file_error)
+}
+
+pub struct TwilioNotifier {
+    name: String,
+    url: String,
+    authorization: String,
+    from: String,
+    to: Vec<String>,
+    daily_limit: u32,
+    sent_path: PathBuf,
+    client: Client,
+}
+
+impl Notifier for TwilioNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accepts(&self, alert: &Alert) -> bool {
+        alert.severity == Severity::Critical
+    }
+
+    fn notify(&self, alert: &Alert) -> Result<(), PriceError> {
+        let today = Local::now().date_naive().to_string();
+        let mut sent = load_sent(&self.sent_path)?;
+        let mut count = sent.get(&self.name).filter(|sent| sent.date == today).map_or(0, |sent| sent.count);
+        let before = count;
+
+        let body = format!("{}: {}", alert.rule, alert.message);
+        let mut result = Ok(());
+        for to in &self.to {
+            if count >= self.daily_limit {
+                result = Err(PriceError::NetworkError(format!(
+                    "twilio: daily limit of {} messages reached",
+                    self.daily_limit
+                )));
+                break;
+            }
+            let form = [("From", self.from.as_str()), ("To", to.as_str()), ("Body", body.as_str())];
+            result = self
+                .client
+                .post(&self.url, &[("Authorization", &self.authorization)], Body::Form(&form))
+                .map(|_| ())
+                .map_err(|e| PriceError::NetworkError(format!("twilio: {}", e)));
+            if result.is_err() {
+                break;
+            }
+            count += 1;
+        }
+        if count != before {
+            sent.insert(self.name.clone(), Sent { date: today, count });
+            save_sent(&self.sent_path, &sent)?;
+        }
+        result
+    }
+}
+
+pub struct BellNotifier {
+    name: String,
+    sound: Option<PathBuf>,
+    player: String,
+}
+
+impl Notifier for BellNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn notify(&self, _alert: &Alert) -> Result<(), PriceError> {
+        let Some(sound) = &self.sound else {
+            let mut stdout = io::stdout();
+            return stdout
+                .write_all(b"\x07")
+                .and_then(|_| stdout.flush())
+                .map_err(|e| PriceError::FileError(format!("bell: {}", e)));
+        };
+
+        // Played in the background so a long sound does not hold up the
+        // next fetch; the thread only reaps the player when it exits.
+        let mut child = Command::new(&self.player)
+            .arg(sound)
+            .spawn()
+            .map_err(|e| PriceError::FileError(format!("bell: could not run {}: {}", self.player, e)))?;
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::http::stub::StubClient;
+
+    const MESSAGES_URL: &str = "https://api.twilio.com/2010-04-01/Accounts/AC1/Messages.json";
+
+    fn twilio(directory: &Path, stub: &Arc<StubClient>) -> Box<dyn Notifier> {
+        let notifier: NotifierConfig = toml::from_str(
+            "name = \"sms\"\ntype = \"twilio\"\naccount_sid = \"AC1\"\nauth_token = \"secret\"\n\
+             from = \"+15550000\"\nto = [\"+15550001\", \"+15550002\"]\ndaily_limit = 3\n",
+        )
+        .unwrap();
+        let config: Config =
+            toml::from_str(&format!("[storage]\nbackend = \"csv\"\ndirectory = {:?}\n", directory)).unwrap();
+        build(&notifier, &config, &(stub.clone() as Client)).unwrap()
+    }
+
+    fn alert() -> Alert {
+        Alert {
+            rule: "crash".to_string(),
+            asset: "bitcoin".to_string(),
+            value: 20000.0,
+            message: "bitcoin fell below $25000".to_string(),
+            severity: Severity::Critical,
+            timestamp: Local::now().naive_local(),
+        }
+    }
+
+    #[test]
+    fn the_daily_limit_holds_across_restarts() {
+        let directory = std::env::temp_dir().join(format!("notify-twilio-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(TWILIO_SENT_FILE);
+        // Yesterday's count is dropped.
+        fs::write(&path, r#"{"sms": {"date": "2024-05-03", "count": 3}}"#).unwrap();
+
+        let stub = Arc::new(StubClient::default().respond(MESSAGES_URL, "{}"));
+        twilio(&directory, &stub).notify(&alert()).unwrap();
+        assert_eq!(stub.requests().len(), 2);
+
+        // A new notifier, as after a restart, has one message left.
+        let e = twilio(&directory, &stub).notify(&alert()).unwrap_err();
+        assert!(e.to_string().ends_with("twilio: daily limit of 3 messages reached"), "{}", e);
+        assert_eq!(stub.requests().len(), 3);
+        let sent = load_sent(&path).unwrap();
+        assert_eq!(sent["sms"], Sent { date: Local::now().date_naive().to_string(), count: 3 });
+
+        assert!(twilio(&directory, &stub).notify(&alert()).is_err());
+        assert_eq!(stub.requests().len(), 3);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
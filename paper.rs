@@ -0,0 +1,332 @@
+// Paper trading: orders checked against every new price and filled in
+// make-believe, with no exchange involved, to see how a plan would do.
+//
+//   [paper]
+//   cash = 10000.0
+//
+//   [[paper.orders]]
+//   name = "dip"
+//   asset = "bitcoin"
+//   side = "buy"
+//   amount = 0.1
+//   below = 60000.0
+//
+//   [[paper.orders]]
+//   name = "take-profit"
+//   asset = "bitcoin"
+//   side = "sell"
+//   amount = 0.1
+//   above = 70000.0
+//   after = "dip"
+//
+// Each order fills once, at the price that crossed its limit, and only with
+// the cash or holdings for it. Fills are kept in `paper_trades.csv`; the
+// `paper` command shows them with the positions and profit and loss.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::storage::csv::quote;
+use crate::storage::{PriceRecord, TIMESTAMP_FORMAT};
+use crate::{PriceError, Pricing};
+
+const TRADES_FILE: &str = "paper_trades.csv";
+const TRADES_HEADER: &str = "timestamp,order,asset,side,amount,price";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaperConfig {
+    // Starting balance in USD.
+    #[serde(default = "default_cash")]
+    pub cash: f64,
+    #[serde(default)]
+    pub orders: Vec<OrderConfig>,
+}
+
+fn default_cash() -> f64 {
+    10_000.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderConfig {
+    pub name: String,
+    pub asset: String,
+    pub side: Side,
+    pub amount: f64,
+    // Fills once the price is at or past one of these; exactly one is set.
+    pub below: Option<f64>,
+    pub above: Option<f64>,
+    // Another order that has to fill first.
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    fn parse(text: &str) -> Option<Side> {
+        match text {
+            "buy" => Some(Side::Buy),
+            "sell" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub timestamp: NaiveDateTime,
+    pub order: String,
+    pub asset: String,
+    pub side: Side,
+    pub amount: f64,
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    pub amount: f64,
+    // Average price paid for what is held.
+    pub cost: f64,
+}
+
+// Cash, holdings and realized profit after a sequence of fills.
+#[derive(Debug, Clone)]
+pub struct Book {
+    pub cash: f64,
+    pub positions: BTreeMap<String, Position>,
+    pub realized: f64,
+}
+
+impl Book {
+    fn new(cash: f64) -> Self {
+        Book { cash, positions: BTreeMap::new(), realized: 0.0 }
+    }
+
+    fn apply(&mut self, fill: &Fill) {
+        let position = self.positions.entry(fill.asset.clone()).or_default();
+        match fill.side {
+            Side::Buy => {
+                self.cash -= fill.amount * fill.price;
+                let paid = position.cost * position.amount + fill.price * fill.amount;
+                position.cost = paid / (position.amount + fill.amount);
+                position.amount += fill.amount;
+            }
+            Side::Sell => {
+                self.cash += fill.amount * fill.price;
+                self.realized += (fill.price - position.cost) * fill.amount;
+                position.amount -= fill.amount;
+            }
+        }
+    }
+
+    // Why `order` can't fill at `price`, if it can't.
+    fn shortfall(&self, order: &OrderConfig, price: f64) -> Option<String> {
+        let held = self.positions.get(&order.asset).map_or(0.0, |position| position.amount);
+        match order.side {
+            Side::Buy if order.amount * price > self.cash + 1e-9 => {
+                Some(format!("needs {:.2} in cash, {:.2} left", order.amount * price, self.cash))
+            }
+            Side::Sell if order.amount > held + 1e-12 => {
+                Some(format!("needs {} {}, {} held", order.amount, order.asset, held))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct PaperTrader {
+    config: PaperConfig,
+    path: PathBuf,
+    fills: Vec<Fill>,
+    book: Book,
+    dry_run: bool,
+    // Orders already reported as short of cash or holdings.
+    warned: HashSet<String>,
+}
+
+impl PaperTrader {
+    pub fn from_config(config: &Config, assets: &[Box<dyn Pricing>]) -> Result<Option<Self>, PriceError> {
+        let Some(paper) = &config.paper else {
+            return Ok(None);
+        };
+        let mut names = HashSet::new();
+        for order in &paper.orders {
+            let invalid = |reason: &str| PriceError::ParseError(format!("paper order '{}': {}", order.name, reason));
+            if !names.insert(order.name.as_str()) {
+                return Err(invalid("the name is used twice"));
+            }
+            if !assets.iter().any(|asset| asset.id() == order.asset) {
+                return Err(invalid(&format!("unknown asset '{}'", order.asset)));
+            }
+            if order.below.is_some() == order.above.is_some() {
+                return Err(invalid("set exactly one of below and above"));
+            }
+            if order.amount.is_nan() || order.amount <= 0.0 {
+                return Err(invalid("amount must be positive"));
+            }
+        }
+        for order in &paper.orders {
+            if let Some(after) = order.after.as_deref().filter(|after| !names.contains(after)) {
+                return Err(PriceError::ParseError(format!(
+                    "paper order '{}': comes after unknown order '{}'",
+                    order.name, after
+                )));
+            }
+        }
+
+        let path = config.storage.directory().join(TRADES_FILE);
+        let fills = read_fills(&path)?;
+        let mut book = Book::new(paper.cash);
+        fills.iter().for_each(|fill| book.apply(fill));
+        Ok(Some(PaperTrader {
+            config: paper.clone(),
+            path,
+            fills,
+            book,
+            dry_run: false,
+            warned: HashSet::new(),
+        }))
+    }
+
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    pub fn fills(&self) -> &[Fill] {
+        &self.fills
+    }
+
+    pub fn book(&self) -> &Book {
+        &self.book
+    }
+
+    pub fn starting_cash(&self) -> f64 {
+        self.config.cash
+    }
+
+    // Orders that haven't filled yet.
+    pub fn pending(&self) -> impl Iterator<Item = &OrderConfig> {
+        self.config.orders.iter().filter(|order| !self.filled(&order.name))
+    }
+
+    fn filled(&self, name: &str) -> bool {
+        self.fills.iter().any(|fill| fill.order == name)
+    }
+
+    // Fills the orders on `asset` that `record` crosses the limit of.
+    pub fn on_price(&mut self, asset: &dyn Pricing, record: &PriceRecord) {
+        let price = record.price;
+        let due: Vec<OrderConfig> = self
+            .pending()
+            .filter(|order| order.asset == asset.id())
+            .filter(|order| order.after.as_deref().is_none_or(|after| self.filled(after)))
+            .filter(|order| {
+                order.below.is_some_and(|below| price <= below)
+                    || order.above.is_some_and(|above| price >= above)
+            })
+            .cloned()
+            .collect();
+        for order in due {
+            if let Some(reason) = self.book.shortfall(&order, price) {
+                if self.warned.insert(order.name.clone()) {
+                    eprintln!("Paper order '{}' can't fill at {:.2}: {}", order.name, price, reason);
+                }
+                continue;
+            }
+            let fill = Fill {
+                timestamp: record.timestamp,
+                order: order.name.clone(),
+                asset: order.asset.clone(),
+                side: order.side,
+                amount: order.amount,
+                price,
+            };
+            let verb = if order.side == Side::Buy { "bought" } else { "sold" };
+            let prefix = if self.dry_run { "[dry run] " } else { "" };
+            if !self.dry_run {
+                if let Err(e) = append_fill(&self.path, &fill) {
+                    eprintln!("Error recording paper fill: {}", e);
+                    continue;
+                }
+            }
+            self.book.apply(&fill);
+            println!(
+                "{}Paper order '{}' {} {} {} at {:.*}",
+                prefix,
+                order.name,
+                verb,
+                order.amount,
+                asset.name(),
+                asset.precision(),
+                price
+            );
+            self.fills.push(fill);
+        }
+    }
+}
+
+fn read_fills(path: &Path) -> Result<Vec<Fill>, PriceError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let invalid =
+        |line: usize, reason: String| PriceError::ParseError(format!("{} line {}: {}", path.display(), line, reason));
+    let mut reader =
+        csv::Reader::from_path(path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    let mut fills = Vec::new();
+    for (index, row) in reader.records().enumerate() {
+        let line = index + 2;
+        let row = row.map_err(|e| invalid(line, e.to_string()))?;
+        let field = |n: usize| row.get(n).unwrap_or_default();
+        let number = |n: usize| field(n).parse::<f64>().map_err(|e| invalid(line, format!("'{}': {}", field(n), e)));
+        fills.push(Fill {
+            timestamp: NaiveDateTime::parse_from_str(field(0), TIMESTAMP_FORMAT)
+                .map_err(|e| invalid(line, format!("invalid timestamp '{}': {}", field(0), e)))?,
+            order: field(1).to_string(),
+            asset: field(2).to_string(),
+            side: Side::parse(field(3)).ok_or_else(|| invalid(line, format!("unknown side '{}'", field(3))))?,
+            amount: number(4)?,
+            price: number(5)?,
+        });
+    }
+    Ok(fills)
+}
+
+fn append_fill(path: &Path, fill: &Fill) -> Result<(), PriceError> {
+    let error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut file = OpenOptions::new().create(true).append(true).open(path).map_err(error)?;
+    let mut data = String::new();
+    if file.metadata().map_err(error)?.len() == 0 {
+        data.push_str(TRADES_HEADER);
+        data.push('\n');
+    }
+    data.push_str(&format!(
+        "{},{},{},{},{},{}\n",
+        fill.timestamp.format(TIMESTAMP_FORMAT),
+        quote(&fill.order),
+        quote(&fill.asset),
+        fill.side.name(),
+        fill.amount,
+        fill.price
+    ));
+    file.write_all(data.as_bytes()).map_err(error)
+}
@@ -0,0 +1,227 @@
+// Per-asset processing between fetching a record and storing it, configured
+// as `pipeline = [{ stage = "scale", factor = 0.0321507 }, ...]` under
+// `[assets.<id>]`. Stages run in order; one that fails drops the record.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::storage::{valid_currency, PriceRecord, Storage};
+use crate::PriceError;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "stage", rename_all = "lowercase", deny_unknown_fields)]
+pub enum StageConfig {
+    // Multiplies the price and quotes, e.g. to turn a per-ounce price into a
+    // per-gram one.
+    Scale { factor: f64 },
+    // Rejects prices outside [min, max] or moving more than `max_change`
+    // (a fraction, 0.5 for 50%) from the last accepted one.
+    Validate { min: Option<f64>, max: Option<f64>, max_change: Option<f64> },
+    // Replaces the price with the mean of the last `window` ones.
+    Smooth { window: usize },
+    // Adds the percent change since `period` ago as a change_<period> column.
+    Change {
+        #[serde(default = "default_change_period")]
+        period: String,
+    },
+    // Runs the record through a Rhai script's `transform` function.
+    Script { path: PathBuf },
+}
+
+fn default_change_period() -> String {
+    "24h".to_string()
+}
+
+pub trait Stage {
+    fn apply(&mut self, record: PriceRecord) -> Result<PriceRecord, PriceError>;
+
+    // Given each stored record, oldest first, when the tracker starts.
+    fn warm_up(&mut self, _record: &PriceRecord) {}
+}
+
+pub struct Pipeline {
+    stages: HashMap<String, Vec<Box<dyn Stage>>>,
+}
+
+impl Pipeline {
+    // A `transform` script set for an asset runs before its pipeline.
+    pub fn from_config(config: &Config) -> Result<Self, PriceError> {
+        let mut stages = HashMap::new();
+        for (id, overrides) in &config.assets {
+            let mut asset = Vec::new();
+            if let Some(path) = &overrides.transform {
+                let stage = StageConfig::Script { path: path.clone() };
+                asset.push(build(id, &format!("assets.{}.transform", id), &stage)?);
+            }
+            for (i, stage) in overrides.pipeline.iter().enumerate() {
+                asset.push(build(id, &format!("assets.{}.pipeline[{}]", id, i), stage)?);
+            }
+            if !asset.is_empty() {
+                stages.insert(id.clone(), asset);
+            }
+        }
+        Ok(Pipeline { stages })
+    }
+
+    pub fn warm_up(&mut self, storage: &dyn Storage) -> Result<(), PriceError> {
+        for (asset, stages) in &mut self.stages {
+            for record in storage.scan(asset, None)? {
+                let record = record?;
+                for stage in stages.iter_mut() {
+                    stage.warm_up(&record);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, asset: &str, mut record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        for stage in self.stages.get_mut(asset).into_iter().flatten() {
+            record = stage.apply(record)?;
+        }
+        Ok(record)
+    }
+}
+
+// `key` prefixes every error.
+fn build(id: &str, key: &str, config: &StageConfig) -> Result<Box<dyn Stage>, PriceError> {
+    let invalid = |reason: &str| Err(PriceError::ParseError(format!("{}: {}", key, reason)));
+    match config {
+        StageConfig::Scale { factor } if !(factor.is_finite() && *factor > 0.0) => invalid("factor must be positive"),
+        StageConfig::Scale { factor } => Ok(Box::new(Scale { factor: *factor })),
+        StageConfig::Validate { min, max, .. } if min.zip(*max).is_some_and(|(min, max)| min > max) => {
+            invalid("min is above max")
+        }
+        StageConfig::Validate { max_change: Some(change), .. } if *change <= 0.0 => {
+            invalid("max_change must be positive")
+        }
+        StageConfig::Validate { min, max, max_change } => {
+            Ok(Box::new(Validate { min: *min, max: *max, max_change: *max_change, last: None }))
+        }
+        StageConfig::Smooth { window: 0 } => invalid("window must be at least 1"),
+        StageConfig::Smooth { window } => Ok(Box::new(Smooth { window: *window, prices: VecDeque::new() })),
+        StageConfig::Change { period } => {
+            let label = period.trim().to_string();
+            if !valid_currency(&label) {
+                return invalid(&format!("invalid period '{}'", period));
+            }
+            let period =
+                parse_duration(&label).map_err(|e| PriceError::ParseError(format!("{}: {}", key, e.message())))?;
+            if period <= Duration::zero() {
+                return invalid("period must be positive");
+            }
+            Ok(Box::new(Change { label, period, history: VecDeque::new() }))
+        }
+        #[cfg(feature = "scripting")]
+        StageConfig::Script { path } => Ok(Box::new(crate::sources::script::Transform::new(id, key, path)?)),
+        #[cfg(not(feature = "scripting"))]
+        StageConfig::Script { path } => {
+            let _ = id;
+            invalid(&format!("{}: this build has no scripting support (the `scripting` feature)", path.display()))
+        }
+    }
+}
+
+struct Scale {
+    factor: f64,
+}
+
+impl Stage for Scale {
+    fn apply(&mut self, mut record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        record.price *= self.factor;
+        for price in record.quotes.values_mut() {
+            *price *= self.factor;
+        }
+        Ok(record)
+    }
+}
+
+struct Validate {
+    min: Option<f64>,
+    max: Option<f64>,
+    max_change: Option<f64>,
+    last: Option<f64>,
+}
+
+impl Stage for Validate {
+    fn apply(&mut self, record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        let price = record.price;
+        let rejected = |reason: String| Err(PriceError::ParseError(format!("rejected {}: {}", price, reason)));
+        if let Some(min) = self.min.filter(|min| price < *min) {
+            return rejected(format!("below the minimum of {}", min));
+        }
+        if let Some(max) = self.max.filter(|max| price > *max) {
+            return rejected(format!("above the maximum of {}", max));
+        }
+        if let (Some(max_change), Some(last)) = (self.max_change, self.last) {
+            let change = (price / last - 1.0).abs();
+            if change > max_change {
+                return rejected(format!("{:.1}% away from the last price {}", change * 100.0, last));
+            }
+        }
+        self.last = Some(price);
+        Ok(record)
+    }
+
+    fn warm_up(&mut self, record: &PriceRecord) {
+        self.last = Some(record.price);
+    }
+}
+
+struct Smooth {
+    window: usize,
+    prices: VecDeque<f64>,
+}
+
+impl Stage for Smooth {
+    fn apply(&mut self, mut record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        if self.prices.len() == self.window {
+            self.prices.pop_front();
+        }
+        self.prices.push_back(record.price);
+        let mean = self.prices.iter().sum::<f64>() / self.prices.len() as f64;
+        let ratio = mean / record.price;
+        record.price = mean;
+        for price in record.quotes.values_mut() {
+            *price *= ratio;
+        }
+        Ok(record)
+    }
+}
+
+struct Change {
+    label: String,
+    period: Duration,
+    // Enough history to know the price `period` before the newest record.
+    history: VecDeque<(chrono::NaiveDateTime, f64)>,
+}
+
+impl Change {
+    fn push(&mut self, record: &PriceRecord) {
+        self.history.push_back((record.timestamp, record.price));
+        let cutoff = record.timestamp.checked_sub_signed(self.period).unwrap_or(NaiveDateTime::MIN);
+        while self.history.get(1).is_some_and(|(timestamp, _)| *timestamp <= cutoff) {
+            self.history.pop_front();
+        }
+    }
+}
+
+impl Stage for Change {
+    fn apply(&mut self, mut record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        let cutoff = record.timestamp.checked_sub_signed(self.period).unwrap_or(NaiveDateTime::MIN);
+        self.push(&record);
+        let then = self.history.front().filter(|(timestamp, price)| *timestamp <= cutoff && *price > 0.0);
+        if let Some((_, then)) = then {
+            record.changes.insert(self.label.clone(), (record.price / then - 1.0) * 100.0);
+        }
+        Ok(record)
+    }
+
+    fn warm_up(&mut self, record: &PriceRecord) {
+        self.push(record);
+    }
+}
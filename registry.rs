@@ -0,0 +1,227 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::pipeline::StageConfig;
+use crate::watchlist::{self, WatchedAsset, Watchlist};
+use crate::PriceError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    CoinGecko,
+    Yahoo,
+    Mock,
+    Plugin,
+    Script,
+    Json,
+    Csv,
+    Scrape,
+    Futures,
+    Global,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Provider::CoinGecko => "coingecko",
+            Provider::Yahoo => "yahoo",
+            Provider::Mock => "mock",
+            Provider::Plugin => "plugin",
+            Provider::Script => "script",
+            Provider::Json => "json",
+            Provider::Csv => "csv",
+            Provider::Scrape => "scrape",
+            Provider::Futures => "futures",
+            Provider::Global => "global",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Crypto,
+    Stablecoin,
+    Index,
+    Stock,
+    Commodity,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Category::Crypto => "crypto",
+            Category::Stablecoin => "stablecoin",
+            Category::Index => "index",
+            Category::Stock => "stock",
+            Category::Commodity => "commodity",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AssetInfo {
+    // Used for storage and on the command line.
+    pub id: String,
+    pub name: String,
+    pub symbol: String,
+    pub provider: Provider,
+    // What the provider calls the asset, e.g. "^GSPC" on Yahoo.
+    pub provider_id: String,
+    pub quote_currency: String,
+    // Decimal places when showing prices.
+    pub precision: usize,
+    pub category: Category,
+}
+
+// `[assets.<id>]` in the config; every field is optional and replaces what
+// the registry would otherwise use.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AssetOverride {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub provider_id: Option<String>,
+    pub precision: Option<usize>,
+    pub category: Option<Category>,
+    // Rhai script run over every fetched record.
+    pub transform: Option<PathBuf>,
+    // Stages run over every fetched record, after `transform`.
+    #[serde(default)]
+    pub pipeline: Vec<StageConfig>,
+}
+
+pub fn built_in(id: &str) -> Option<AssetInfo> {
+    let (name, symbol, provider, provider_id, category) = match id {
+        "bitcoin" => ("Bitcoin", "btc", Provider::CoinGecko, "bitcoin", Category::Crypto),
+        "ethereum" => ("Ethereum", "eth", Provider::CoinGecko, "ethereum", Category::Crypto),
+        "sp500" => ("S&P 500", "sp500", Provider::Yahoo, "^GSPC", Category::Index),
+        _ => return None,
+    };
+    Some(AssetInfo {
+        id: id.to_string(),
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        provider,
+        provider_id: provider_id.to_string(),
+        quote_currency: "usd".to_string(),
+        precision: 2,
+        category,
+    })
+}
+
+// Anything not built in is a CoinGecko coin, described by what was cached
+// when it was added to the watchlist or ranked.
+pub fn coin(asset: &WatchedAsset) -> AssetInfo {
+    AssetInfo {
+        id: asset.id.clone(),
+        name: asset.name.clone().unwrap_or_else(|| asset.id.clone()),
+        symbol: asset.symbol.clone().unwrap_or_else(|| asset.id.clone()),
+        provider: Provider::CoinGecko,
+        provider_id: asset.id.clone(),
+        quote_currency: "usd".to_string(),
+        precision: 2,
+        category: Category::Crypto,
+    }
+}
+
+pub struct Registry {
+    // In tracking order: the watchlist, coins from the top ranking, then
+    // mock, plugin, script, JSON, CSV, scraped, Yahoo, futures and global
+    // market assets defined only in the config.
+    pub assets: Vec<AssetInfo>,
+    pub watched: usize,
+}
+
+impl Registry {
+    // TRACKER_ASSETS replaces the watchlist, still using the names cached in
+    // the file for the coins it lists.
+    pub fn load(config: &Config) -> Result<Self, PriceError> {
+        let mut entries = Watchlist::load(config.watchlist_path())?;
+        if let Some(ids) = &config.asset_list {
+            let assets = ids
+                .iter()
+                .map(|id| {
+                    let cached = entries.assets.iter().find(|asset| &asset.id == id);
+                    cached.cloned().unwrap_or_else(|| WatchedAsset::bare(id))
+                })
+                .collect();
+            entries = Watchlist { assets };
+        }
+        let watched = entries.assets.len();
+        if let Some(top) = &config.top {
+            for asset in watchlist::top_coins(top)? {
+                if !entries.contains(&asset.id) {
+                    entries.assets.push(asset);
+                }
+            }
+        }
+        let configured = configured(config);
+        for &(id, _, name) in &configured {
+            if !entries.contains(id) {
+                let name = name.clone().or_else(|| Some(id.clone()));
+                entries.assets.push(WatchedAsset { name, ..WatchedAsset::bare(id) });
+            }
+        }
+        let assets = entries
+            .assets
+            .iter()
+            .map(|asset| {
+                let mut info = built_in(&asset.id).unwrap_or_else(|| coin(asset));
+                if let Some((_, provider, name)) = configured.iter().find(|(id, ..)| **id == asset.id) {
+                    info.provider = *provider;
+                    info.provider_id = asset.id.clone();
+                    if let Some(name) = name {
+                        info.name = name.clone();
+                    }
+                }
+                if let Some(source) = config.yahoo.get(&asset.id).filter(|_| info.provider == Provider::Yahoo) {
+                    info.provider_id = source.symbol.clone();
+                    info.category = if source.symbol.starts_with('^') { Category::Index } else { Category::Stock };
+                }
+                apply(info, config.assets.get(&asset.id))
+            })
+            .collect();
+        Ok(Registry { assets, watched })
+    }
+}
+
+// Assets with a config section of their own, with the provider serving them
+// and the name configured there. A mock wins over the other kinds.
+fn configured(config: &Config) -> Vec<(&String, Provider, &Option<String>)> {
+    let mock = config.mock.iter().map(|(id, source)| (id, Provider::Mock, &source.name));
+    let plugins = config.plugins.iter().map(|(id, source)| (id, Provider::Plugin, &source.name));
+    let scripts = config.scripts.iter().map(|(id, source)| (id, Provider::Script, &source.name));
+    let json = config.json.iter().map(|(id, source)| (id, Provider::Json, &source.name));
+    let csv = config.csv.iter().map(|(id, source)| (id, Provider::Csv, &source.name));
+    let scrape = config.scrape.iter().map(|(id, source)| (id, Provider::Scrape, &source.name));
+    let yahoo = config.yahoo.iter().map(|(id, source)| (id, Provider::Yahoo, &source.name));
+    let futures = config.futures.iter().map(|(id, source)| (id, Provider::Futures, &source.name));
+    let global = config.global.iter().map(|(id, source)| (id, Provider::Global, &source.name));
+    let configured = mock.chain(plugins).chain(scripts).chain(json).chain(csv).chain(scrape);
+    configured.chain(yahoo).chain(futures).chain(global).collect()
+}
+
+fn apply(mut info: AssetInfo, overrides: Option<&AssetOverride>) -> AssetInfo {
+    let Some(overrides) = overrides else {
+        return info;
+    };
+    if let Some(name) = &overrides.name {
+        info.name = name.clone();
+    }
+    if let Some(symbol) = &overrides.symbol {
+        info.symbol = symbol.to_ascii_lowercase();
+    }
+    if let Some(provider_id) = &overrides.provider_id {
+        info.provider_id = provider_id.clone();
+    }
+    if let Some(precision) = overrides.precision {
+        info.precision = precision;
+    }
+    if let Some(category) = overrides.category {
+        info.category = category;
+    }
+    info
+}
@@ -0,0 +1,215 @@
+// Copies new records to S3 or an S3-compatible store such as MinIO every so
+// often, so the history outlives the host:
+//
+//   [s3]
+//   endpoint = "https://s3.eu-west-1.amazonaws.com"   # or "http://minio:9000"
+//   region = "eu-west-1"
+//   bucket = "prices"
+//   prefix = "tracker/"
+//   access_key = { secret = "s3_access_key" }
+//   secret_key = { secret = "s3_secret_key" }
+//   every = "1h"
+//   retries = 3
+//
+// Each upload is a CSV file of an asset's records since the last one, at
+// `<prefix><asset>/<first>_<last>.csv`, which `import` reads back; what was
+// uploaded is kept in `s3_uploads.json`. Requests use path-style URLs and
+// AWS Signature Version 4.
+
+use std::thread;
+
+use chrono::{DateTime, Duration, Local, NaiveDateTime, Utc};
+use ring::digest::{digest, SHA256};
+use ring::hmac;
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::http::{percent_encode, Client, HttpError};
+use crate::storage::csv::format_records;
+use crate::storage::{PriceRecord, Storage};
+use crate::uploads::Uploads;
+use crate::{PriceError, Pricing};
+
+const STATE_FILE: &str = "s3_uploads.json";
+// Records per object, so a first upload of a long history is split up.
+const BATCH: usize = 100_000;
+const OBJECT_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    pub endpoint: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default = "default_every")]
+    pub every: String,
+    // Further attempts at an upload failing on the network or the server's
+    // side, a second apart then doubling.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_every() -> String {
+    "1h".to_string()
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+pub struct S3Sink {
+    config: S3Config,
+    client: Client,
+    every: Duration,
+    next: NaiveDateTime,
+    uploads: Uploads,
+}
+
+impl S3Sink {
+    pub fn from_config(config: &Config, client: &Client) -> Result<Option<Self>, PriceError> {
+        let Some(s3) = &config.s3 else {
+            return Ok(None);
+        };
+        let every =
+            parse_duration(&s3.every).map_err(|e| PriceError::ParseError(format!("s3.every: {}", e.message())))?;
+        if !s3.endpoint.starts_with("http://") && !s3.endpoint.starts_with("https://") {
+            return Err(PriceError::ParseError(format!("s3.endpoint: '{}' is not an http(s) URL", s3.endpoint)));
+        }
+        if s3.bucket.is_empty() {
+            return Err(PriceError::ParseError("s3.bucket: must not be empty".to_string()));
+        }
+        Ok(Some(S3Sink {
+            config: s3.clone(),
+            client: client.clone(),
+            every,
+            next: Local::now().naive_local(),
+            uploads: Uploads::load(&config.storage.directory().join(STATE_FILE))?,
+        }))
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        now >= self.next
+    }
+
+    // Uploads what each asset recorded since the last upload. An asset
+    // whose upload fails is retried from the same record next time.
+    pub fn upload(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage, dry_run: bool) {
+        self.next = Local::now().naive_local().checked_add_signed(self.every).unwrap_or(NaiveDateTime::MAX);
+        for asset in assets {
+            if let Err(e) = self.upload_asset(asset.as_ref(), storage, dry_run) {
+                eprintln!("Error uploading the prices of {} to S3: {}", asset.name(), e);
+            }
+        }
+    }
+
+    fn upload_asset(&mut self, asset: &dyn Pricing, storage: &dyn Storage, dry_run: bool) -> Result<(), PriceError> {
+        let mut records = storage.scan(asset.id(), self.uploads.next_from(asset.id())?)?;
+        loop {
+            let batch: Vec<PriceRecord> = records.by_ref().take(BATCH).collect::<Result<_, _>>()?;
+            let (Some(first), Some(last)) = (batch.first(), batch.last()) else {
+                return Ok(());
+            };
+            let key = format!(
+                "{}{}/{}_{}.csv",
+                self.config.prefix,
+                asset.id(),
+                first.timestamp.format(OBJECT_TIME_FORMAT),
+                last.timestamp.format(OBJECT_TIME_FORMAT)
+            );
+            if dry_run {
+                println!("[dry run] would upload {} records to s3://{}/{}", batch.len(), self.config.bucket, key);
+                return Ok(());
+            }
+            self.put(&key, format_records(&batch).as_bytes())?;
+            println!("Uploaded {} records of {} to s3://{}/{}", batch.len(), asset.name(), self.config.bucket, key);
+            self.uploads.sent(asset.id(), last.timestamp)?;
+        }
+    }
+
+    fn put(&self, key: &str, body: &[u8]) -> Result<(), PriceError> {
+        let (mut attempt, mut delay) = (0, std::time::Duration::from_secs(1));
+        loop {
+            let error = match self.put_once(key, body).map_err(PriceError::from) {
+                Ok(()) => return Ok(()),
+                // Such as the request itself being wrong, when sending it
+                // again won't help.
+                Err(e) if !e.is_retryable() => return Err(e),
+                Err(e) => e,
+            };
+            if attempt == self.config.retries {
+                return Err(error);
+            }
+            eprintln!("Error uploading {}: {}; retrying in {}s", key, error.message(), delay.as_secs());
+            thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn put_once(&self, key: &str, body: &[u8]) -> Result<(), HttpError> {
+        let endpoint = self.config.endpoint.trim_end_matches('/');
+        let (scheme, rest) = endpoint.split_once("://").unwrap_or(("https", endpoint));
+        let (host, base) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let path = format!("{}/{}/{}", base, percent_encode(&self.config.bucket, ""), percent_encode(key, "/"));
+        let headers = sign(&self.config, "PUT", host, &path, body, Utc::now());
+        let headers: Vec<(&str, &str)> = headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        self.client.put(&format!("{}://{}{}", scheme, host, path), &headers, body).map(|_| ())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// The headers that authenticate a request with no query string, per AWS
+// Signature Version 4.
+fn sign(
+    config: &S3Config,
+    method: &str,
+    host: &str,
+    path: &str,
+    body: &[u8],
+    now: DateTime<Utc>,
+) -> Vec<(&'static str, String)> {
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload = hex(digest(&SHA256, body).as_ref());
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        method, path, host, payload, timestamp, signed_headers, payload
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        hex(digest(&SHA256, canonical.as_bytes()).as_ref())
+    );
+    let mut key = format!("AWS4{}", config.secret_key).into_bytes();
+    for part in [date.as_str(), config.region.as_str(), "s3", "aws4_request", to_sign.as_str()] {
+        key = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes()).as_ref().to_vec();
+    }
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key,
+        scope,
+        signed_headers,
+        hex(&key)
+    );
+    vec![
+        ("Authorization", authorization),
+        ("x-amz-content-sha256", payload),
+        ("x-amz-date", timestamp),
+        ("Content-Type", "text/csv".to_string()),
+    ]
+}
@@ -0,0 +1,258 @@
+// Pushes each asset's records to an SFTP server one day at a time, once the
+// day is over, for hosts where object storage is not an option:
+//
+//   [sftp]
+//   host = "backup.example.com"
+//   user = "tracker"
+//   directory = "prices"
+//   identity = "/home/tracker/.ssh/id_ed25519"
+//   every = "1h"
+//
+// Each day's records are written to `sftp_outbox/<asset>_<day>.csv` first
+// and that file is uploaded with the system's `sftp` client, so keys, known
+// hosts and proxies come from the usual OpenSSH configuration. A file whose
+// upload was cut short is kept and resumed from where the server's copy
+// ends. What was uploaded is kept in `sftp_uploads.json`. A client still
+// running after `timeout` is killed, so a server that stops answering holds
+// up the fetches for no longer than that.
+
+use std::fs;
+use std::io::{Read as _, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+
+use crate::config::{parse_duration, Config};
+use crate::storage::csv::write_records;
+use crate::storage::{PriceRecord, Storage};
+use crate::uploads::Uploads;
+use crate::{PriceError, Pricing};
+
+const STATE_FILE: &str = "sftp_uploads.json";
+const OUTBOX: &str = "sftp_outbox";
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SftpConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    // Remote directory the files go to, relative to the login directory
+    // unless absolute.
+    #[serde(default = "default_directory")]
+    pub directory: String,
+    // Private key to log in with, instead of the agent's or SSH's default.
+    pub identity: Option<PathBuf>,
+    #[serde(default = "default_every")]
+    pub every: String,
+    // The sftp client to run.
+    #[serde(default = "default_program")]
+    pub program: String,
+    // How long one upload may take before the client is killed.
+    #[serde(default = "default_timeout")]
+    pub timeout: String,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+fn default_directory() -> String {
+    ".".to_string()
+}
+
+fn default_every() -> String {
+    "1h".to_string()
+}
+
+fn default_program() -> String {
+    "sftp".to_string()
+}
+
+fn default_timeout() -> String {
+    "1m".to_string()
+}
+
+pub struct SftpSink {
+    config: SftpConfig,
+    every: Duration,
+    timeout: std::time::Duration,
+    next: NaiveDateTime,
+    outbox: PathBuf,
+    uploads: Uploads,
+}
+
+impl SftpSink {
+    pub fn from_config(config: &Config) -> Result<Option<Self>, PriceError> {
+        let Some(sftp) = &config.sftp else {
+            return Ok(None);
+        };
+        let every =
+            parse_duration(&sftp.every).map_err(|e| PriceError::ParseError(format!("sftp.every: {}", e.message())))?;
+        let timeout = parse_duration(&sftp.timeout)
+            .map_err(|e| PriceError::ParseError(format!("sftp.timeout: {}", e.message())))?
+            .to_std()
+            .map_err(|_| PriceError::ParseError("sftp.timeout: must not be negative".to_string()))?;
+        if sftp.host.is_empty() || sftp.user.is_empty() {
+            return Err(PriceError::ParseError("sftp: host and user must not be empty".to_string()));
+        }
+        let directory = config.storage.directory();
+        Ok(Some(SftpSink {
+            config: sftp.clone(),
+            every,
+            timeout,
+            next: Local::now().naive_local(),
+            outbox: directory.join(OUTBOX),
+            uploads: Uploads::load(&directory.join(STATE_FILE))?,
+        }))
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        now >= self.next
+    }
+
+    // Uploads every day that ended since the last upload, oldest first,
+    // stopping at an asset's first failure to try again next time.
+    pub fn upload(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage, dry_run: bool) {
+        self.next = Local::now().naive_local().checked_add_signed(self.every).unwrap_or(NaiveDateTime::MAX);
+        for asset in assets {
+            if let Err(e) = self.upload_asset(asset.as_ref(), storage, dry_run) {
+                eprintln!("Error uploading the prices of {} over SFTP: {}", asset.name(), e);
+            }
+        }
+    }
+
+    fn upload_asset(&mut self, asset: &dyn Pricing, storage: &dyn Storage, dry_run: bool) -> Result<(), PriceError> {
+        let today = Local::now().date_naive();
+        let mut records = storage.scan(asset.id(), self.uploads.next_from(asset.id())?)?;
+        let mut pending: Option<PriceRecord> = records.next().transpose()?;
+        while let Some(first) = pending.take() {
+            let day = first.timestamp.date();
+            if day >= today {
+                return Ok(());
+            }
+            let mut day_records = vec![first];
+            for record in records.by_ref() {
+                let record = record?;
+                if record.timestamp.date() != day {
+                    pending = Some(record);
+                    break;
+                }
+                day_records.push(record);
+            }
+
+            let path = self.outbox.join(format!("{}_{}.csv", asset.id(), day.format(DAY_FORMAT)));
+            if dry_run {
+                println!("[dry run] would upload {} records to {}", day_records.len(), self.target(&path));
+                continue;
+            }
+            // A file left from an earlier attempt is what the server has part
+            // of, so it is sent as it is.
+            let resume = path.exists();
+            if !resume {
+                fs::create_dir_all(&self.outbox)
+                    .map_err(|e| PriceError::FileError(format!("{}: {}", self.outbox.display(), e)))?;
+                write_records(&path, &day_records)?;
+            }
+            self.push(&path, resume)?;
+            let _ = fs::remove_file(&path);
+            self.uploads.sent(asset.id(), end_of(day))?;
+            println!("Uploaded {} records of {} to {}", day_records.len(), asset.name(), self.target(&path));
+        }
+        Ok(())
+    }
+
+    fn target(&self, path: &Path) -> String {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        format!("{}@{}:{}/{}", self.config.user, self.config.host, self.config.directory, name)
+    }
+
+    // `reput` resumes an upload but needs the server to have part of the
+    // file; if it has none, the file is sent from the start.
+    fn push(&self, path: &Path, resume: bool) -> Result<(), PriceError> {
+        if resume && self.run_batch(path, "reput").is_ok() {
+            return Ok(());
+        }
+        self.run_batch(path, "put")
+    }
+
+    fn run_batch(&self, path: &Path, command: &str) -> Result<(), PriceError> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let directory = &self.config.directory;
+        // A leading '-' lets the batch go on when the directory exists.
+        let batch = format!(
+            "-mkdir \"{}\"\n{} \"{}\" \"{}/{}\"\n",
+            directory,
+            command,
+            path.display(),
+            directory,
+            name
+        );
+        let mut sftp = Command::new(&self.config.program);
+        sftp.args(["-b", "-", "-P", &self.config.port.to_string(), "-o", "BatchMode=yes"]);
+        // Gives up on a connection that cannot be made or stops answering.
+        sftp.args(["-o", "ConnectTimeout=30", "-o", "ServerAliveInterval=15", "-o", "ServerAliveCountMax=3"]);
+        if let Some(identity) = &self.config.identity {
+            sftp.arg("-i").arg(identity);
+        }
+        let mut child = sftp
+            .arg(format!("{}@{}", self.config.user, self.config.host))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| PriceError::NetworkError(format!("could not run {}: {}", self.config.program, e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(batch.as_bytes())
+                .map_err(|e| PriceError::NetworkError(format!("{}: {}", self.config.program, e)))?;
+        }
+        // Read aside so a chatty client cannot fill the pipe while it is
+        // waited on.
+        let mut stderr = child.stderr.take();
+        let reader = thread::spawn(move || {
+            let mut text = String::new();
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_string(&mut text);
+            }
+            text
+        });
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(PriceError::NetworkError(format!(
+                        "{} {} timed out after {}",
+                        self.config.program, command, self.config.timeout
+                    )));
+                }
+                Ok(None) => thread::sleep(std::time::Duration::from_millis(50)),
+                Err(e) => return Err(PriceError::NetworkError(format!("{}: {}", self.config.program, e))),
+            }
+        };
+        if status.success() {
+            return Ok(());
+        }
+        let stderr = reader.join().unwrap_or_default();
+        Err(PriceError::NetworkError(format!(
+            "{} {} failed ({}): {}",
+            self.config.program,
+            command,
+            status,
+            stderr.trim()
+        )))
+    }
+}
+
+fn end_of(day: NaiveDate) -> NaiveDateTime {
+    day.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default())
+}
@@ -0,0 +1,221 @@
+// Appends new records to a Google Sheet through the Sheets API, one row per
+// record, for people who would rather open a spreadsheet:
+//
+//   [sheets]
+//   spreadsheet = "1BxiMVs0XRA5nFMdKvBdBZjgmUUqptlbs74OgvE2upms"   # from its URL
+//   sheet = "Prices"
+//   credentials = "service-account.json"
+//   every = "1m"
+//
+// The credentials are a service account's JSON key, and the sheet must be
+// shared with the account's email address. Rows are the timestamp, the
+// asset's name, the price, volume and market cap; records from before the
+// first sync are left out. What was sent is kept in `sheets_uploads.json`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{Duration, Local, NaiveDateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::{parse_duration, Config};
+use crate::http::{percent_encode, Body, Client, HttpError};
+use crate::storage::{Storage, TIMESTAMP_FORMAT};
+use crate::uploads::Uploads;
+use crate::{PriceError, Pricing};
+
+const STATE_FILE: &str = "sheets_uploads.json";
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+const HEADER: [&str; 5] = ["timestamp", "asset", "price", "volume", "market_cap"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SheetsConfig {
+    pub spreadsheet: String,
+    #[serde(default = "default_sheet")]
+    pub sheet: String,
+    pub credentials: PathBuf,
+    #[serde(default = "default_every")]
+    pub every: String,
+    // Where the Sheets API lives, for testing.
+    #[serde(default = "default_api")]
+    pub api: String,
+}
+
+fn default_sheet() -> String {
+    "Sheet1".to_string()
+}
+
+fn default_every() -> String {
+    "1m".to_string()
+}
+
+fn default_api() -> String {
+    "https://sheets.googleapis.com".to_string()
+}
+
+// The parts of a service account key that are used.
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+struct Token {
+    value: String,
+    expires: Instant,
+}
+
+pub struct SheetsSink {
+    config: SheetsConfig,
+    client: Client,
+    every: Duration,
+    next: NaiveDateTime,
+    started: NaiveDateTime,
+    account: ServiceAccount,
+    key: RsaKeyPair,
+    token: Option<Token>,
+    uploads: Uploads,
+}
+
+impl SheetsSink {
+    pub fn from_config(config: &Config, client: &Client) -> Result<Option<Self>, PriceError> {
+        let Some(sheets) = &config.sheets else {
+            return Ok(None);
+        };
+        let every = parse_duration(&sheets.every)
+            .map_err(|e| PriceError::ParseError(format!("sheets.every: {}", e.message())))?;
+        let path = &sheets.credentials;
+        let invalid =
+            |reason: String| PriceError::ParseError(format!("sheets.credentials: {}: {}", path.display(), reason));
+        let body = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let account: ServiceAccount = serde_json::from_str(&body).map_err(|e| invalid(e.to_string()))?;
+        let key = private_key(&account.private_key).map_err(invalid)?;
+        let now = Local::now().naive_local();
+        Ok(Some(SheetsSink {
+            config: sheets.clone(),
+            client: client.clone(),
+            every,
+            next: now,
+            started: now,
+            account,
+            key,
+            token: None,
+            uploads: Uploads::load(&config.storage.directory().join(STATE_FILE))?,
+        }))
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        now >= self.next
+    }
+
+    // Appends the records of every asset since the last sync in one
+    // request; if it fails they are sent with the next one.
+    pub fn upload(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage, dry_run: bool) {
+        self.next = Local::now().naive_local().checked_add_signed(self.every).unwrap_or(NaiveDateTime::MAX);
+        if let Err(e) = self.sync(assets, storage, dry_run) {
+            eprintln!("Error appending prices to the Google Sheet: {}", e);
+        }
+    }
+
+    fn sync(&mut self, assets: &[Box<dyn Pricing>], storage: &dyn Storage, dry_run: bool) -> Result<(), PriceError> {
+        let mut rows = Vec::new();
+        if self.uploads.is_empty() {
+            rows.push(json!(HEADER));
+        }
+        let mut last = Vec::new();
+        for asset in assets {
+            let from = self.uploads.next_from(asset.id())?.unwrap_or(self.started);
+            for record in storage.scan(asset.id(), Some(from))? {
+                let record = record?;
+                let timestamp = record.timestamp.format(TIMESTAMP_FORMAT).to_string();
+                rows.push(json!([timestamp, asset.name(), record.price, record.volume, record.market_cap]));
+                match last.last_mut() {
+                    Some((id, at)) if *id == asset.id() => *at = record.timestamp,
+                    _ => last.push((asset.id(), record.timestamp)),
+                }
+            }
+        }
+        if last.is_empty() {
+            return Ok(());
+        }
+        if dry_run {
+            println!("[dry run] would append {} rows to the Google Sheet", rows.len());
+            return Ok(());
+        }
+
+        let url = format!(
+            "{}/v4/spreadsheets/{}/values/{}:append?valueInputOption=USER_ENTERED&insertDataOption=INSERT_ROWS",
+            self.config.api.trim_end_matches('/'),
+            self.config.spreadsheet,
+            percent_encode(&format!("{}!A1", self.config.sheet), "")
+        );
+        let body = json!({ "values": rows }).to_string();
+        let authorization = format!("Bearer {}", self.token()?);
+        let headers = [("Authorization", authorization.as_str()), ("Content-Type", "application/json")];
+        let appended = self.client.post(&url, &headers, Body::Text(&body));
+        if let Err(HttpError::Status { code: 401, .. }) = appended {
+            // Revoked early, or the clock is off; fetch a new one next time.
+            self.token = None;
+        }
+        appended?;
+        println!("Appended {} rows to the Google Sheet", rows.len());
+        for (id, timestamp) in last {
+            self.uploads.sent(id, timestamp)?;
+        }
+        Ok(())
+    }
+
+    // An access token for the service account, reused until shortly before
+    // it expires.
+    fn token(&mut self) -> Result<String, PriceError> {
+        if let Some(token) = self.token.as_ref().filter(|token| token.expires > Instant::now()) {
+            return Ok(token.value.clone());
+        }
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "iss": self.account.client_email,
+            "scope": SCOPE,
+            "aud": self.account.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let unsigned = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(json!({ "alg": "RS256", "typ": "JWT" }).to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let mut signature = vec![0; self.key.public().modulus_len()];
+        self.key
+            .sign(&RSA_PKCS1_SHA256, &SystemRandom::new(), unsigned.as_bytes(), &mut signature)
+            .map_err(|_| PriceError::Internal("could not sign the token request".to_string()))?;
+        let assertion = format!("{}.{}", unsigned, URL_SAFE_NO_PAD.encode(signature));
+        let form = [("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())];
+        let response = self.client.post(&self.account.token_uri, &[], Body::Form(&form))?;
+        let response: Value = serde_json::from_str(&response)
+            .map_err(|e| PriceError::ParseError(format!("{}: {}", self.account.token_uri, e)))?;
+        let value = response["access_token"].as_str().ok_or_else(|| {
+            PriceError::ParseError(format!("{}: no access_token in the response", self.account.token_uri))
+        })?;
+        let lifetime = response["expires_in"].as_u64().unwrap_or(3600).saturating_sub(60);
+        self.token = Some(Token {
+            value: value.to_string(),
+            expires: Instant::now() + std::time::Duration::from_secs(lifetime),
+        });
+        Ok(value.to_string())
+    }
+}
+
+// The PKCS#8 key of a service account, in PEM.
+fn private_key(pem: &str) -> Result<RsaKeyPair, String> {
+    let encoded: String = pem.lines().filter(|line| !line.starts_with("-----")).collect();
+    let der = STANDARD.decode(encoded.trim()).map_err(|e| format!("invalid private_key: {}", e))?;
+    RsaKeyPair::from_pkcs8(&der).map_err(|e| format!("invalid private_key: {}", e))
+}
@@ -0,0 +1,261 @@
+// Price sources, built from the asset registry and sharing one HTTP client.
+
+pub mod coingecko;
+pub mod csv_http;
+pub mod futures;
+pub mod global;
+pub mod json;
+pub mod mock;
+pub mod orderbook;
+pub mod plugin;
+pub mod scrape;
+pub mod script;
+pub mod yahoo;
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::http::Client;
+use crate::registry::{AssetInfo, Provider, Registry};
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+pub type Fetched = Result<Option<PriceRecord>, PriceError>;
+
+// Calls fetch_if_changed on every asset from up to `workers` threads, so a
+// slow provider holds up only its own assets. The workers send each result
+// and how long it took over a channel to `handle`, which runs on the calling
+// thread in the order the results arrive. Sources that panicked are
+// returned, to be replaced.
+pub fn fetch_all<'a>(
+    assets: &[&'a dyn Pricing],
+    workers: usize,
+    mut handle: impl FnMut(&'a dyn Pricing, Fetched, Duration),
+) -> Vec<&'a dyn Pricing> {
+    let next = AtomicUsize::new(0);
+    let (sender, results) = mpsc::channel();
+    let mut panicked = Vec::new();
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, assets.len().max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || {
+                while let Some(asset) = assets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let started = Instant::now();
+                    let (result, panicked) = match caught(*asset, || asset.fetch_if_changed()) {
+                        Ok(result) => (result, false),
+                        Err(e) => (Err(e), true),
+                    };
+                    if sender.send((*asset, result, panicked, started.elapsed())).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (asset, result, caught_panic, elapsed) in results {
+            if caught_panic {
+                panicked.push(asset);
+            }
+            handle(asset, result, elapsed);
+        }
+    });
+    panicked
+}
+
+// Runs a call into `asset`'s source, turning a panic into an error so a
+// buggy source fails alone.
+pub fn guarded<T>(asset: &dyn Pricing, call: impl FnOnce() -> Result<T, PriceError>) -> Result<T, PriceError> {
+    caught(asset, call).and_then(|result| result)
+}
+
+// As guarded, but a panic is the outer error, kept apart from the errors
+// the source returns itself.
+fn caught<T>(
+    asset: &dyn Pricing,
+    call: impl FnOnce() -> Result<T, PriceError>,
+) -> Result<Result<T, PriceError>, PriceError> {
+    panic::catch_unwind(AssertUnwindSafe(call)).map_err(|payload| {
+        PriceError::Internal(format!("the source of {} panicked: {}", asset.id(), panic_message(payload)))
+    })
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| "unknown panic".to_string(), |m| m.to_string()),
+    }
+}
+
+pub fn build(info: &AssetInfo, config: &Config, client: &Client) -> Result<Box<dyn Pricing>, PriceError> {
+    let mut source = build_source(info, config, client)?;
+    if let Some(book) = config.orderbook.get(&info.id) {
+        source = Box::new(orderbook::WithOrderBook::new(source, book, client.clone())?);
+    }
+    Ok(Box::new(Attributed { inner: source, provider: info.provider }))
+}
+
+// Names the provider in the errors of the requests a source makes, which
+// the HTTP client cannot tell by itself.
+struct Attributed {
+    inner: Box<dyn Pricing>,
+    provider: Provider,
+}
+
+impl Attributed {
+    fn attribute<T>(&self, result: Result<T, PriceError>) -> Result<T, PriceError> {
+        result.map_err(|e| e.with_provider(&self.provider.to_string()))
+    }
+}
+
+impl Pricing for Attributed {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.attribute(self.inner.fetch_price())
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.attribute(self.inner.fetch_record())
+    }
+
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        self.attribute(self.inner.fetch_if_changed())
+    }
+
+    fn validate(&self) -> Result<(), PriceError> {
+        self.attribute(self.inner.validate())
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn symbol(&self) -> &str {
+        self.inner.symbol()
+    }
+
+    fn precision(&self) -> usize {
+        self.inner.precision()
+    }
+}
+
+fn build_source(info: &AssetInfo, config: &Config, client: &Client) -> Result<Box<dyn Pricing>, PriceError> {
+    match info.provider {
+        Provider::CoinGecko => {
+            Ok(Box::new(coingecko::Coin::new(info.clone(), config.currencies.clone(), client.clone())))
+        }
+        Provider::Mock => {
+            let mock = config.mock.get(&info.id).cloned().unwrap_or_default();
+            Ok(Box::new(mock::MockSource::new(info.clone(), mock)?))
+        }
+        #[cfg(feature = "plugins")]
+        Provider::Plugin => {
+            let plugin = &config.plugins[&info.id];
+            Ok(Box::new(plugin::PluginSource::new(info.clone(), plugin, client.clone())?))
+        }
+        #[cfg(not(feature = "plugins"))]
+        Provider::Plugin => Err(PriceError::ParseError(format!(
+            "plugins.{}: this build has no plugin support (the `plugins` feature)",
+            info.id
+        ))),
+        #[cfg(feature = "scripting")]
+        Provider::Script => {
+            let script = &config.scripts[&info.id];
+            Ok(Box::new(script::ScriptSource::new(info.clone(), script, client.clone())?))
+        }
+        #[cfg(not(feature = "scripting"))]
+        Provider::Script => Err(PriceError::ParseError(format!(
+            "scripts.{}: this build has no scripting support (the `scripting` feature)",
+            info.id
+        ))),
+        Provider::Json => {
+            let source = &config.json[&info.id];
+            Ok(Box::new(json::JsonSource::new(info.clone(), source, client.clone())?))
+        }
+        Provider::Csv => {
+            let source = &config.csv[&info.id];
+            Ok(Box::new(csv_http::CsvSource::new(info.clone(), source, client.clone())?))
+        }
+        #[cfg(feature = "scraping")]
+        Provider::Scrape => {
+            let source = &config.scrape[&info.id];
+            Ok(Box::new(scrape::ScrapeSource::new(info.clone(), source, client.clone())?))
+        }
+        #[cfg(not(feature = "scraping"))]
+        Provider::Scrape => Err(PriceError::ParseError(format!(
+            "scrape.{}: this build has no scraping support (the `scraping` feature)",
+            info.id
+        ))),
+        Provider::Yahoo => Ok(Box::new(yahoo::YahooSource::new(info.clone(), client.clone())?)),
+        Provider::Global => {
+            let source = &config.global[&info.id];
+            Ok(Box::new(global::GlobalSource::new(info.clone(), source, client.clone())))
+        }
+        Provider::Futures => {
+            let source = &config.futures[&info.id];
+            Ok(Box::new(futures::FuturesSource::new(info.clone(), source, client.clone())?))
+        }
+    }
+}
+
+// A fresh source for a tracked asset, e.g. after its old one panicked.
+pub fn rebuild(id: &str, config: &Config, client: &Client) -> Result<Box<dyn Pricing>, PriceError> {
+    let registry = Registry::load(config)?;
+    let info = registry.assets.iter().find(|info| info.id == id);
+    let info = info.ok_or_else(|| PriceError::ParseError(format!("{} is no longer tracked", id)))?;
+    build(info, config, client)
+}
+
+pub fn tracked(config: &Config, client: &Client) -> Result<Vec<Box<dyn Pricing>>, PriceError> {
+    Registry::load(config)?.assets.iter().map(|info| build(info, config, client)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Failing {
+        id: &'static str,
+        panics: bool,
+    }
+
+    impl Pricing for Failing {
+        fn fetch_price(&self) -> Result<f64, PriceError> {
+            if self.panics {
+                panic!("failing on purpose");
+            }
+            Err(PriceError::Internal("failed on purpose".to_string()))
+        }
+
+        fn name(&self) -> &str {
+            self.id
+        }
+
+        fn id(&self) -> &str {
+            self.id
+        }
+    }
+
+    #[test]
+    fn only_sources_that_panicked_are_returned() {
+        let failing = Failing { id: "failing", panics: false };
+        let panicking = Failing { id: "panicking", panics: true };
+        let assets: [&dyn Pricing; 2] = [&failing, &panicking];
+        let mut errors = Vec::new();
+        let panicked = fetch_all(&assets, 2, |asset, result, _| {
+            errors.push((asset.id().to_string(), result.unwrap_err().to_string()));
+        });
+        assert_eq!(panicked.iter().map(|asset| asset.id()).collect::<Vec<_>>(), ["panicking"]);
+        errors.sort();
+        assert!(errors[0].1.contains("failed on purpose"), "{:?}", errors);
+        assert!(errors[1].1.contains("the source of panicking panicked: failing on purpose"), "{:?}", errors);
+    }
+}
@@ -0,0 +1,218 @@
+use chrono::{DateTime, Local};
+
+use crate::http::fixtures::full_url;
+use crate::http::{Client, Revalidator};
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+const API: &str = "https://api.coingecko.com/api/v3";
+
+// Any coin CoinGecko lists, bitcoin and ethereum included.
+pub struct Coin {
+    info: AssetInfo,
+    currencies: Vec<String>,
+    // "usd" and the currencies, comma-separated.
+    vs_currencies: String,
+    client: Client,
+    revalidator: Revalidator,
+}
+
+impl Coin {
+    pub fn new(info: AssetInfo, currencies: Vec<String>, client: Client) -> Self {
+        let mut vs_currencies = String::from("usd");
+        for currency in &currencies {
+            vs_currencies.push(',');
+            vs_currencies.push_str(currency);
+        }
+        Coin { info, currencies, vs_currencies, client, revalidator: Revalidator::default() }
+    }
+
+    fn query(&self) -> [(&str, &str); 5] {
+        [
+            ("ids", &self.info.provider_id),
+            ("vs_currencies", &self.vs_currencies),
+            ("include_market_cap", "true"),
+            ("include_24hr_vol", "true"),
+            ("include_last_updated_at", "true"),
+        ]
+    }
+
+    fn parse(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        let url = full_url(&format!("{}/simple/price", API), &self.query());
+        parse_price(body, &self.info.provider_id, &self.info.name, &self.currencies)
+            .map_err(|e| e.with_response(&url, body))
+    }
+}
+
+pub fn parse_price(body: &str, id: &str, name: &str, currencies: &[String]) -> Result<PriceRecord, PriceError> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+
+    let quote = json.get(id)
+        .ok_or_else(|| PriceError::ParseError(format!("Failed to extract {} price", name)))?;
+    let price = quote.get("usd")
+        .and_then(|price| price.as_f64())
+        .ok_or_else(|| PriceError::ParseError(format!("Failed to extract {} price", name)))?;
+
+    let mut record = PriceRecord::new(Local::now().naive_local(), price);
+    record.volume = quote.get("usd_24h_vol").and_then(|v| v.as_f64());
+    record.market_cap = quote.get("usd_market_cap").and_then(|v| v.as_f64());
+    let updated = quote.get("last_updated_at").and_then(|v| v.as_i64());
+    record.source_time = updated.and_then(|time| DateTime::from_timestamp(time, 0));
+    for currency in currencies {
+        if let Some(price) = quote.get(currency).and_then(|v| v.as_f64()) {
+            record.quotes.insert(currency.clone(), price);
+        }
+    }
+    Ok(record)
+}
+
+// Looks up a coin's display name and symbol, which also confirms the id exists.
+pub fn lookup(client: &Client, id: &str) -> Result<(String, String), PriceError> {
+    let query = [
+        ("localization", "false"),
+        ("tickers", "false"),
+        ("market_data", "false"),
+        ("community_data", "false"),
+        ("developer_data", "false"),
+    ];
+    let response_str = client.get(&format!("{}/coins/{}", API, id), &query).map_err(|e| match e {
+        crate::http::HttpError::Status { code: 404, .. } => {
+            PriceError::ParseError(format!("CoinGecko has no coin with id '{}'", id))
+        }
+        e => e.into(),
+    })?;
+
+    let json: serde_json::Value = serde_json::from_str(&response_str)?;
+    let field = |key: &str| {
+        json.get(key)
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| PriceError::ParseError(format!("Failed to extract {} of {}", key, id)))
+    };
+    Ok((field("name")?, field("symbol")?.to_lowercase()))
+}
+
+impl Pricing for Coin {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.parse(&self.client.get(&format!("{}/simple/price", API), &self.query())?)
+    }
+
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        let url = format!("{}/simple/price", API);
+        let Some(body) = self.revalidator.get(&self.client, &url, &[], &self.query())? else {
+            return Ok(None);
+        };
+        // A body that does not parse is fetched in full next time.
+        self.parse(&body).map(Some).inspect_err(|_| self.revalidator.forget())
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::http::stub::StubClient;
+    use crate::registry::built_in;
+
+    const PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+    fn bitcoin(stub: &Arc<StubClient>) -> Coin {
+        Coin::new(built_in("bitcoin").unwrap(), vec!["eur".to_string()], stub.clone())
+    }
+
+    #[test]
+    fn a_quote_is_read_with_its_volume_market_cap_and_currencies() {
+        let body = r#"{"bitcoin": {"usd": 64000.5, "eur": 59000.25, "usd_24h_vol": 3.2e10,
+            "usd_market_cap": 1.26e12, "last_updated_at": 1714737600}}"#;
+        let stub = Arc::new(StubClient::default().respond(PRICE_URL, body));
+        let record = bitcoin(&stub).fetch_record().unwrap();
+        assert_eq!(record.price, 64000.5);
+        assert_eq!(record.quotes.get("eur"), Some(&59000.25));
+        assert_eq!((record.volume, record.market_cap), (Some(3.2e10), Some(1.26e12)));
+        assert_eq!(record.source_time.unwrap().timestamp(), 1714737600);
+        assert_eq!(
+            stub.requests(),
+            [format!(
+                "{}?ids=bitcoin&vs_currencies=usd,eur&include_market_cap=true&include_24hr_vol=true\
+                 &include_last_updated_at=true",
+                PRICE_URL
+            )]
+        );
+    }
+
+    #[test]
+    fn only_the_usd_price_is_required() {
+        let stub = Arc::new(StubClient::default().respond(PRICE_URL, r#"{"bitcoin": {"usd": 64000}}"#));
+        let record = bitcoin(&stub).fetch_record().unwrap();
+        assert_eq!(record.price, 64000.0);
+        assert!(record.volume.is_none() && record.market_cap.is_none() && record.source_time.is_none());
+        assert!(record.quotes.is_empty());
+    }
+
+    #[test]
+    fn missing_prices_are_parse_errors_with_the_response() {
+        for body in [r#"{}"#, r#"{"bitcoin": {"eur": 59000.25}}"#, r#"{"bitcoin": {"usd": "64000"}}"#] {
+            let stub = Arc::new(StubClient::default().respond(PRICE_URL, body));
+            let e = bitcoin(&stub).fetch_record().unwrap_err();
+            assert_eq!(e.to_string(), "Parse Error [E200]: Failed to extract Bitcoin price", "{}", body);
+            assert_eq!(e.parse().unwrap().body.as_deref(), Some(body));
+        }
+    }
+
+    #[test]
+    fn malformed_bodies_are_parse_errors() {
+        let stub = Arc::new(StubClient::default().respond(PRICE_URL, r#"{"bitcoin": {"usd": 64"#));
+        let e = bitcoin(&stub).fetch_record().unwrap_err();
+        assert!(e.is_parse() && !e.is_retryable(), "{}", e);
+        assert!(e.to_string().contains("EOF while parsing"), "{}", e);
+        assert!(e.parse().unwrap().url.as_deref().unwrap().starts_with(PRICE_URL));
+
+        let stub = Arc::new(StubClient::default().respond(PRICE_URL, "<html>Service Unavailable</html>"));
+        assert!(bitcoin(&stub).fetch_record().unwrap_err().is_parse());
+    }
+
+    #[test]
+    fn failed_requests_keep_their_status() {
+        let stub = Arc::new(StubClient::default().fail(PRICE_URL, 429));
+        let e = bitcoin(&stub).fetch_record().unwrap_err();
+        assert_eq!(e.http().unwrap().status, Some(429));
+        assert!(e.is_retryable());
+    }
+
+    #[test]
+    fn lookups_read_the_name_and_symbol() {
+        let url = "https://api.coingecko.com/api/v3/coins/solana";
+        let stub: Client = Arc::new(StubClient::default().respond(url, r#"{"name": "Solana", "symbol": "SOL"}"#));
+        assert_eq!(lookup(&stub, "solana").unwrap(), ("Solana".to_string(), "sol".to_string()));
+
+        let stub: Client = Arc::new(StubClient::default().respond(url, r#"{"name": "Solana"}"#));
+        let e = lookup(&stub, "solana").unwrap_err();
+        assert_eq!(e.to_string(), "Parse Error [E200]: Failed to extract symbol of solana");
+
+        let stub: Client = Arc::new(StubClient::default().fail(url, 404));
+        let e = lookup(&stub, "solana").unwrap_err();
+        assert_eq!(e.to_string(), "Parse Error [E200]: CoinGecko has no coin with id 'solana'");
+    }
+}
@@ -0,0 +1,230 @@
+// Quotes published as CSV over HTTP, configured per asset under `[csv.<id>]`:
+//
+//   [csv.sp500]
+//   url = "https://query1.finance.yahoo.com/v7/finance/download/{id}"
+//   column = "Close"
+//   row = "last"
+//
+// `column` is a header name or a 0-based index, `row` is "first" (the
+// default), "last" or an index, negative ones counting from the end. With
+// `select = { column = "Symbol", equals = "{id}" }` only the matching rows
+// are considered. `{id}` is replaced by the asset's provider id.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::http::{Client, Revalidator};
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvSourceConfig {
+    pub url: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub column: Column,
+    pub volume: Option<Column>,
+    #[serde(default)]
+    pub row: Row,
+    pub select: Option<Select>,
+    // Whether the first line names the columns.
+    #[serde(default = "default_has_headers")]
+    pub has_headers: bool,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+}
+
+fn default_has_headers() -> bool {
+    true
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Column::Index(index) => write!(f, "column {}", index),
+            Column::Name(name) => write!(f, "column '{}'", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    #[default]
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Row {
+    Index(i64),
+    Position(Position),
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Row::Position(Position::First)
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Row::Index(index) => write!(f, "row {}", index),
+            Row::Position(Position::First) => f.write_str("first row"),
+            Row::Position(Position::Last) => f.write_str("last row"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Select {
+    pub column: Column,
+    pub equals: String,
+}
+
+pub struct CsvSource {
+    info: AssetInfo,
+    url: String,
+    config: CsvSourceConfig,
+    client: Client,
+    revalidator: Revalidator,
+}
+
+impl CsvSource {
+    pub fn new(info: AssetInfo, config: &CsvSourceConfig, client: Client) -> Result<Self, PriceError> {
+        let invalid = |reason: &str| Err(PriceError::ParseError(format!("csv.{}.{}", info.id, reason)));
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return invalid("url: expected an http(s) URL");
+        }
+        if !config.delimiter.is_ascii() {
+            return invalid("delimiter: must be a single ASCII character");
+        }
+        let select = config.select.as_ref().map(|select| &select.column);
+        let columns = [Some(&config.column), config.volume.as_ref(), select];
+        if !config.has_headers && columns.iter().flatten().any(|column| matches!(column, Column::Name(_))) {
+            return invalid("column: names need has_headers = true, use an index");
+        }
+        let mut config = config.clone();
+        if let Some(select) = &mut config.select {
+            select.equals = select.equals.replace("{id}", &info.provider_id);
+        }
+        let url = config.url.replace("{id}", &info.provider_id);
+        Ok(CsvSource { url, info, config, client, revalidator: Revalidator::default() })
+    }
+
+    fn headers(&self) -> Vec<(&str, &str)> {
+        self.config.headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect()
+    }
+
+    fn parse(&self, body: &str) -> Result<PriceRecord, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(self.config.has_headers)
+            .delimiter(self.config.delimiter as u8)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(body.as_bytes());
+        let headers = if self.config.has_headers {
+            reader.headers().map_err(|e| e.to_string())?.clone()
+        } else {
+            csv::StringRecord::new()
+        };
+        let index = |column: &Column| match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => {
+                headers.iter().position(|header| header == name).ok_or_else(|| format!("no {} in the header", column))
+            }
+        };
+        let price_index = index(&self.config.column)?;
+        let volume_index = self.config.volume.as_ref().map(index).transpose()?;
+        let select = match &self.config.select {
+            Some(select) => Some((index(&select.column)?, select.equals.as_str())),
+            None => None,
+        };
+
+        let mut rows = Vec::new();
+        for (i, row) in reader.records().enumerate() {
+            let row = row.map_err(|e| format!("row {}: {}", i + 1, e))?;
+            if select.is_none_or(|(column, equals)| row.get(column) == Some(equals)) {
+                rows.push(row);
+            }
+        }
+        let chosen = match self.config.row {
+            Row::Position(Position::First) => rows.first(),
+            Row::Position(Position::Last) => rows.last(),
+            Row::Index(index) => match usize::try_from(index) {
+                Ok(index) => rows.get(index),
+                Err(_) => rows.len().checked_sub(index.unsigned_abs() as usize).and_then(|index| rows.get(index)),
+            },
+        };
+        let row = chosen.ok_or_else(|| format!("no {} among {} rows", self.config.row, rows.len()))?;
+        let number = |index: usize, column: &Column| -> Result<f64, String> {
+            let value = row.get(index).ok_or_else(|| format!("the row has no {}", column))?;
+            // Thousands separators are common in published sheets.
+            value.replace(',', "").parse().map_err(|_| format!("{} is not a number: '{}'", column, value))
+        };
+
+        let price = number(price_index, &self.config.column)?;
+        if !(price.is_finite() && price > 0.0) {
+            return Err(format!("{} is not a valid price: {}", self.config.column, price));
+        }
+        let mut record = PriceRecord::new(chrono::Local::now().naive_local(), price);
+        if let (Some(index), Some(column)) = (volume_index, &self.config.volume) {
+            record.volume = Some(number(index, column)?);
+        }
+        Ok(record)
+    }
+}
+
+impl Pricing for CsvSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        let body = self.client.get_with_headers(&self.url, &self.headers(), &[])?;
+        self.parse(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", self.url, e)))
+    }
+
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        let Some(body) = self.revalidator.get(&self.client, &self.url, &self.headers(), &[])? else {
+            return Ok(None);
+        };
+        let record = self.parse(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", self.url, e)));
+        record.map(Some).inspect_err(|_| self.revalidator.forget())
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
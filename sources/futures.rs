@@ -0,0 +1,177 @@
+// Perpetual futures from the public Binance and Bybit APIs, one series per
+// `[futures.<id>]` section so the mark price and the funding rate of a
+// contract sit next to its spot price:
+//
+//   [futures.btc-perp]
+//   exchange = "binance"
+//   symbol = "BTCUSDT"
+//
+//   [futures.btc-funding]
+//   exchange = "bybit"
+//   symbol = "BTCUSDT"
+//   series = "funding"
+//
+// Funding is stored in basis points per funding interval, so 1.00 is the
+// usual 0.01% every eight hours and two decimals keep its detail. Mark
+// prices come with the contract's 24 hour high, low, VWAP and quote volume.
+// Neither exchange needs an API key.
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::http::Client;
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+const BINANCE_API: &str = "https://fapi.binance.com/fapi/v1/premiumIndex";
+const BINANCE_TICKER_API: &str = "https://fapi.binance.com/fapi/v1/ticker/24hr";
+const BYBIT_API: &str = "https://api.bybit.com/v5/market/tickers";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    Binance,
+    Bybit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Series {
+    #[default]
+    Mark,
+    Funding,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FuturesConfig {
+    pub exchange: Exchange,
+    // The exchange's contract symbol, e.g. "BTCUSDT".
+    pub symbol: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub series: Series,
+}
+
+pub struct FuturesSource {
+    info: AssetInfo,
+    config: FuturesConfig,
+    client: Client,
+}
+
+impl FuturesSource {
+    pub fn new(info: AssetInfo, config: &FuturesConfig, client: Client) -> Result<Self, PriceError> {
+        if config.symbol.is_empty() || !config.symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(PriceError::ParseError(format!(
+                "futures.{}.symbol: '{}' is not a contract symbol like BTCUSDT",
+                info.id, config.symbol
+            )));
+        }
+        Ok(FuturesSource { info, config: config.clone(), client })
+    }
+
+    fn parse(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        let json: Value = serde_json::from_str(body)?;
+        let (ticker, mark, funding) = match self.config.exchange {
+            Exchange::Binance => (&json, "markPrice", "lastFundingRate"),
+            Exchange::Bybit => {
+                if let Some(code) = json["retCode"].as_i64().filter(|code| *code != 0) {
+                    let message = json["retMsg"].as_str().unwrap_or_default();
+                    return Err(PriceError::ParseError(format!("Bybit error {}: {}", code, message)));
+                }
+                let list = json["result"]["list"].as_array();
+                let ticker = list.and_then(|list| list.iter().find(|t| t["symbol"] == self.config.symbol.as_str()));
+                let ticker = ticker.ok_or_else(|| {
+                    PriceError::ParseError(format!("Bybit has no linear contract {}", self.config.symbol))
+                })?;
+                (ticker, "markPrice", "fundingRate")
+            }
+        };
+        let value = match self.config.series {
+            Series::Mark => self.number(ticker, mark)?,
+            Series::Funding => self.number(ticker, funding)? * 10_000.0,
+        };
+        let mut record = PriceRecord::new(Local::now().naive_local(), value);
+        // Binance stamps the index itself, Bybit the whole response, both in
+        // milliseconds.
+        let time = if self.config.exchange == Exchange::Binance { &ticker["time"] } else { &json["time"] };
+        record.source_time = time.as_i64().and_then(DateTime::from_timestamp_millis);
+        if self.config.exchange == Exchange::Bybit && self.config.series == Series::Mark {
+            self.add_day(&mut record, ticker, ["highPrice24h", "lowPrice24h", "turnover24h", "volume24h"]);
+        }
+        Ok(record)
+    }
+
+    fn number(&self, ticker: &Value, key: &str) -> Result<f64, PriceError> {
+        // Both exchanges send numbers as strings.
+        let value = &ticker[key];
+        value.as_str().and_then(|text| text.parse::<f64>().ok()).or_else(|| value.as_f64()).ok_or_else(|| {
+            PriceError::ParseError(format!("no {} for {} in the response", key, self.config.symbol))
+        })
+    }
+
+    // The 24 hour high, low, quote volume and base volume named by `keys`,
+    // the VWAP being the quote volume over the base volume. Missing ones are
+    // left out.
+    fn add_day(&self, record: &mut PriceRecord, ticker: &Value, keys: [&str; 4]) {
+        let [high, low, quote, base] = keys.map(|key| self.number(ticker, key).ok());
+        if let Some(high) = high {
+            record.day.insert("high".to_string(), high);
+        }
+        if let Some(low) = low {
+            record.day.insert("low".to_string(), low);
+        }
+        if let Some((quote, base)) = quote.zip(base.filter(|base| *base > 0.0)) {
+            record.day.insert("vwap".to_string(), quote / base);
+        }
+        record.volume = quote;
+    }
+
+    // Binance keeps the 24 hour statistics on an endpoint of their own.
+    fn add_binance_day(&self, record: &mut PriceRecord) -> Result<(), PriceError> {
+        let body = self.client.get(BINANCE_TICKER_API, &[("symbol", &self.config.symbol)])?;
+        let ticker: Value = serde_json::from_str(&body)?;
+        self.add_day(record, &ticker, ["highPrice", "lowPrice", "quoteVolume", "volume"]);
+        Ok(())
+    }
+}
+
+impl Pricing for FuturesSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        let symbol = self.config.symbol.as_str();
+        let body = match self.config.exchange {
+            Exchange::Binance => self.client.get(BINANCE_API, &[("symbol", symbol)])?,
+            Exchange::Bybit => self.client.get(BYBIT_API, &[("category", "linear"), ("symbol", symbol)])?,
+        };
+        let mut record = self.parse(&body)?;
+        if self.config.exchange == Exchange::Binance && self.config.series == Series::Mark {
+            // The price stands without them.
+            if let Err(e) = self.add_binance_day(&mut record) {
+                eprintln!("Error fetching the 24h statistics of {}: {}", self.info.name, e);
+            }
+        }
+        Ok(record)
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
@@ -0,0 +1,112 @@
+// Crypto market wide figures from CoinGecko's /global endpoint, each a
+// series of its own configured under `[global.<id>]`:
+//
+//   [global.crypto-cap]
+//   metric = "market_cap"     # total market cap in USD, with the 24h volume
+//
+//   [global.btc-dominance]
+//   metric = "dominance"      # a coin's share of the total, in percent
+//   coin = "btc"
+
+use chrono::Local;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::http::{Client, Revalidator};
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+const API: &str = "https://api.coingecko.com/api/v3/global";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    MarketCap,
+    Dominance,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GlobalConfig {
+    pub metric: Metric,
+    // The symbol whose dominance to record.
+    #[serde(default = "default_coin")]
+    pub coin: String,
+    pub name: Option<String>,
+}
+
+fn default_coin() -> String {
+    "btc".to_string()
+}
+
+pub struct GlobalSource {
+    info: AssetInfo,
+    config: GlobalConfig,
+    client: Client,
+    revalidator: Revalidator,
+}
+
+impl GlobalSource {
+    pub fn new(info: AssetInfo, config: &GlobalConfig, client: Client) -> Self {
+        let mut config = config.clone();
+        config.coin = config.coin.to_lowercase();
+        GlobalSource { info, config, client, revalidator: Revalidator::default() }
+    }
+
+    fn parse(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        self.read(body).map_err(|e| e.with_response(API, body))
+    }
+
+    fn read(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        let json: Value = serde_json::from_str(body)?;
+        let data = &json["data"];
+        let missing = |what: &str| PriceError::ParseError(format!("no {} in CoinGecko's global data", what));
+        match self.config.metric {
+            Metric::MarketCap => {
+                let cap = data["total_market_cap"]["usd"].as_f64().ok_or_else(|| missing("total market cap"))?;
+                let mut record = PriceRecord::new(Local::now().naive_local(), cap);
+                record.volume = data["total_volume"]["usd"].as_f64();
+                Ok(record)
+            }
+            Metric::Dominance => {
+                let share = data["market_cap_percentage"][&self.config.coin].as_f64();
+                let share = share.ok_or_else(|| missing(&format!("market cap share of {}", self.config.coin)))?;
+                Ok(PriceRecord::new(Local::now().naive_local(), share))
+            }
+        }
+    }
+}
+
+impl Pricing for GlobalSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.parse(&self.client.get(API, &[])?)
+    }
+
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        let Some(body) = self.revalidator.get(&self.client, API, &[], &[])? else {
+            return Ok(None);
+        };
+        self.parse(&body).map(Some).inspect_err(|_| self.revalidator.forget())
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
@@ -0,0 +1,197 @@
+// Any REST API answering with JSON, configured per asset under `[json.<id>]`
+// with its URL, extra headers and a path to the price in the response:
+//
+//   [json.gold]
+//   url = "https://api.example.com/v1/spot/{id}"
+//   headers = { "X-Api-Key" = { secret = "metals_key" } }
+//   price = "$.data[0].price"
+//
+// `{id}` is replaced by the asset's provider id. Paths are a subset of
+// JSONPath: `.key`, `['key']` and `[index]`, negative indexes counting from
+// the end. Numbers may also come as strings, as many APIs send them.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::http::{Client, Revalidator};
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JsonSourceConfig {
+    pub url: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub price: String,
+    pub volume: Option<String>,
+    pub market_cap: Option<String>,
+}
+
+enum Segment {
+    Key(String),
+    Index(i64),
+}
+
+struct JsonPath {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut rest = text.trim();
+        rest = rest.strip_prefix('$').unwrap_or(rest);
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err("empty key after '.'".to_string());
+                }
+                segments.push(Segment::Key(after[..end].to_string()));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or("unclosed '['")?;
+                let inside = after[..end].trim();
+                let quoted = ['\'', '"']
+                    .iter()
+                    .find_map(|quote| inside.strip_prefix(*quote).and_then(|key| key.strip_suffix(*quote)));
+                let segment = match quoted {
+                    Some(key) => Segment::Key(key.to_string()),
+                    None => Segment::Index(inside.parse().map_err(|_| format!("'{}' is not an index", inside))?),
+                };
+                segments.push(segment);
+                rest = &after[end + 1..];
+            } else if segments.is_empty() {
+                // A bare first key, as in `data.price`.
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                segments.push(Segment::Key(rest[..end].to_string()));
+                rest = &rest[end..];
+            } else {
+                return Err(format!("unexpected '{}'", rest));
+            }
+        }
+        Ok(JsonPath { text: text.trim().to_string(), segments })
+    }
+
+    fn find<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(value, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(index) => {
+                let items = value.as_array()?;
+                match usize::try_from(*index) {
+                    Ok(index) => items.get(index),
+                    Err(_) => items.get(items.len().checked_sub(index.unsigned_abs() as usize)?),
+                }
+            }
+        })
+    }
+
+    fn number(&self, value: &Value) -> Result<f64, String> {
+        match self.find(value) {
+            Some(Value::Number(number)) => number.as_f64().ok_or_else(|| format!("{} is out of range", self.text)),
+            Some(Value::String(text)) => {
+                text.trim().parse().map_err(|_| format!("{} is not a number: \"{}\"", self.text, text))
+            }
+            Some(other) => Err(format!("{} is not a number: {}", self.text, other)),
+            None => Err(format!("nothing at {}", self.text)),
+        }
+    }
+}
+
+pub struct JsonSource {
+    info: AssetInfo,
+    url: String,
+    headers: Vec<(String, String)>,
+    price: JsonPath,
+    volume: Option<JsonPath>,
+    market_cap: Option<JsonPath>,
+    client: Client,
+    revalidator: Revalidator,
+}
+
+impl JsonSource {
+    pub fn new(info: AssetInfo, config: &JsonSourceConfig, client: Client) -> Result<Self, PriceError> {
+        let path = |field: &str, text: &str| {
+            JsonPath::parse(text).map_err(|e| {
+                PriceError::ParseError(format!("json.{}.{}: invalid path '{}': {}", info.id, field, text, e))
+            })
+        };
+        let price = path("price", &config.price)?;
+        let volume = config.volume.as_deref().map(|text| path("volume", text)).transpose()?;
+        let market_cap = config.market_cap.as_deref().map(|text| path("market_cap", text)).transpose()?;
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return Err(PriceError::ParseError(format!("json.{}.url: expected an http(s) URL", info.id)));
+        }
+        Ok(JsonSource {
+            url: config.url.replace("{id}", &info.provider_id),
+            headers: config.headers.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            info,
+            price,
+            volume,
+            market_cap,
+            client,
+            revalidator: Revalidator::default(),
+        })
+    }
+
+    fn headers(&self) -> Vec<(&str, &str)> {
+        self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect()
+    }
+
+    fn parse(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        self.read(body).map_err(|e| e.with_response(&self.url, body))
+    }
+
+    fn read(&self, body: &str) -> Result<PriceRecord, PriceError> {
+        let value: Value = serde_json::from_str(body)?;
+        let invalid = |reason: String| PriceError::ParseError(format!("{}: {}", self.url, reason));
+
+        let price = self.price.number(&value).map_err(invalid)?;
+        if !(price.is_finite() && price > 0.0) {
+            return Err(invalid(format!("{} is not a valid price: {}", self.price.text, price)));
+        }
+        let mut record = PriceRecord::new(chrono::Local::now().naive_local(), price);
+        record.volume = self.volume.as_ref().map(|path| path.number(&value)).transpose().map_err(invalid)?;
+        record.market_cap = self.market_cap.as_ref().map(|path| path.number(&value)).transpose().map_err(invalid)?;
+        Ok(record)
+    }
+}
+
+impl Pricing for JsonSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.parse(&self.client.get_with_headers(&self.url, &self.headers(), &[])?)
+    }
+
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        let Some(body) = self.revalidator.get(&self.client, &self.url, &self.headers(), &[])? else {
+            return Ok(None);
+        };
+        self.parse(&body).map(Some).inspect_err(|_| self.revalidator.forget())
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
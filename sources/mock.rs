@@ -0,0 +1,179 @@
+// Offline price source for exercising storage, alerts and reports without
+// network access. Configured per asset under `[mock.<id>]`; an id that is
+// also tracked normally, such as bitcoin, is then served by the mock.
+
+use std::f64::consts::PI;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pattern {
+    // Random walk, repeatable when a seed is given.
+    #[default]
+    Walk,
+    // Swings around `start` by `amplitude` once every `period` fetches.
+    Sine,
+    // Returns `prices` in turn, starting over at the end.
+    Sequence,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockConfig {
+    pub pattern: Pattern,
+    pub name: Option<String>,
+    pub start: f64,
+    // Standard deviation of each walk step, as a fraction of the price.
+    pub volatility: f64,
+    // Fraction of `start`.
+    pub amplitude: f64,
+    pub period: u32,
+    pub prices: Vec<f64>,
+    pub seed: Option<u64>,
+    // Share of fetches that fail, to exercise outage alerts.
+    pub failure_rate: f64,
+    // Share of fetches that panic, to exercise the supervisor.
+    pub panic_rate: f64,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        MockConfig {
+            pattern: Pattern::Walk,
+            name: None,
+            start: 100.0,
+            volatility: 0.01,
+            amplitude: 0.1,
+            period: 60,
+            prices: Vec::new(),
+            seed: None,
+            failure_rate: 0.0,
+            panic_rate: 0.0,
+        }
+    }
+}
+
+impl MockConfig {
+    fn check(&self) -> Result<(), String> {
+        if self.start <= 0.0 {
+            return Err(format!("start must be positive, got {}", self.start));
+        }
+        if !(0.0..=1.0).contains(&self.failure_rate) {
+            return Err(format!("failure_rate must be between 0 and 1, got {}", self.failure_rate));
+        }
+        if !(0.0..=1.0).contains(&self.panic_rate) {
+            return Err(format!("panic_rate must be between 0 and 1, got {}", self.panic_rate));
+        }
+        match self.pattern {
+            Pattern::Walk if self.volatility < 0.0 => Err("volatility must not be negative".to_string()),
+            Pattern::Sine if self.period == 0 => Err("period must be at least 1".to_string()),
+            Pattern::Sine if !(0.0..1.0).contains(&self.amplitude) => {
+                Err(format!("amplitude must be between 0 and 1, got {}", self.amplitude))
+            }
+            Pattern::Sequence if self.prices.is_empty() => Err("the sequence pattern needs prices".to_string()),
+            Pattern::Sequence if self.prices.iter().any(|p| *p <= 0.0) => Err("prices must be positive".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct MockSource {
+    info: AssetInfo,
+    config: MockConfig,
+    state: Mutex<State>,
+}
+
+struct State {
+    price: f64,
+    step: u64,
+    rng: u64,
+}
+
+impl State {
+    // splitmix64, enough for test data and free of dependencies.
+    fn next_uniform(&mut self) -> f64 {
+        let mut z = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        self.rng = z;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_normal(&mut self) -> f64 {
+        let u1 = self.next_uniform().max(f64::MIN_POSITIVE);
+        let u2 = self.next_uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+}
+
+impl MockSource {
+    pub fn new(info: AssetInfo, config: MockConfig) -> Result<Self, PriceError> {
+        config
+            .check()
+            .map_err(|reason| PriceError::ParseError(format!("mock.{}: {}", info.id, reason)))?;
+        let seed = config.seed.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(1)
+        });
+        let state = State { price: config.start, step: 0, rng: seed };
+        Ok(MockSource { state: Mutex::new(state), info, config })
+    }
+}
+
+impl Pricing for MockSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_record().map(|record| record.price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        let mut state = self.state.lock().unwrap();
+        let step = state.step;
+        state.step += 1;
+        if self.config.failure_rate > 0.0 && state.next_uniform() < self.config.failure_rate {
+            return Err(PriceError::NetworkError(format!("mock failure for {}", self.info.id)));
+        }
+        if self.config.panic_rate > 0.0 && state.next_uniform() < self.config.panic_rate {
+            panic!("mock panic for {}", self.info.id);
+        }
+
+        let price = match self.config.pattern {
+            Pattern::Walk => {
+                let next = state.price * (1.0 + self.config.volatility * state.next_normal());
+                // Keeps a long walk from reaching zero.
+                let next = next.max(self.config.start * 1e-6);
+                state.price = next;
+                next
+            }
+            Pattern::Sine => {
+                let phase = 2.0 * PI * (step % self.config.period as u64) as f64 / self.config.period as f64;
+                self.config.start * (1.0 + self.config.amplitude * phase.sin())
+            }
+            Pattern::Sequence => self.config.prices[(step % self.config.prices.len() as u64) as usize],
+        };
+        Ok(PriceRecord::new(Local::now().naive_local(), price))
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
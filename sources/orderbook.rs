@@ -0,0 +1,186 @@
+// Order book snapshots taken with each price of an exchange-traded asset,
+// configured per asset under `[orderbook.<id>]`:
+//
+//   [orderbook.bitcoin]
+//   exchange = "binance"
+//   symbol = "BTCUSDT"
+//   market = "spot"           # or "linear" for the perpetual's book
+//
+// Each record then carries the best bid and ask, the spread in basis points
+// of the mid price and the quote value resting within 1% of the mid on
+// either side, as the book_* columns. A failed snapshot leaves them empty
+// rather than failing the price.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::http::Client;
+use crate::sources::futures::Exchange;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+// Enough levels to reach 1% from the mid on liquid pairs.
+const LEVELS: &str = "500";
+const DEPTH_RANGE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Market {
+    #[default]
+    Spot,
+    Linear,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderBookConfig {
+    pub exchange: Exchange,
+    pub symbol: String,
+    #[serde(default)]
+    pub market: Market,
+}
+
+// The metrics of one snapshot, in the quote currency.
+struct Snapshot {
+    bid: f64,
+    ask: f64,
+    spread_bps: f64,
+    bid_depth: f64,
+    ask_depth: f64,
+}
+
+impl Snapshot {
+    fn from_levels(bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Option<Self> {
+        let bid = bids.iter().map(|(price, _)| *price).reduce(f64::max)?;
+        let ask = asks.iter().map(|(price, _)| *price).reduce(f64::min)?;
+        let mid = (bid + ask) / 2.0;
+        let value = |levels: &[(f64, f64)], within: &dyn Fn(f64) -> bool| -> f64 {
+            levels.iter().filter(|(price, _)| within(*price)).map(|(price, size)| price * size).sum()
+        };
+        Some(Snapshot {
+            bid,
+            ask,
+            spread_bps: (ask - bid) / mid * 10_000.0,
+            bid_depth: value(bids, &|price| price >= mid * (1.0 - DEPTH_RANGE)),
+            ask_depth: value(asks, &|price| price <= mid * (1.0 + DEPTH_RANGE)),
+        })
+    }
+
+    fn record_into(&self, record: &mut PriceRecord) {
+        record.book.insert("bid".to_string(), self.bid);
+        record.book.insert("ask".to_string(), self.ask);
+        record.book.insert("spread".to_string(), self.spread_bps);
+        record.book.insert("biddepth".to_string(), self.bid_depth);
+        record.book.insert("askdepth".to_string(), self.ask_depth);
+    }
+}
+
+// Another source with the order book snapshot added to its records.
+pub struct WithOrderBook {
+    inner: Box<dyn Pricing>,
+    config: OrderBookConfig,
+    client: Client,
+}
+
+impl WithOrderBook {
+    pub fn new(inner: Box<dyn Pricing>, config: &OrderBookConfig, client: Client) -> Result<Self, PriceError> {
+        if config.symbol.is_empty() || !config.symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(PriceError::ParseError(format!(
+                "orderbook.{}.symbol: '{}' is not a trading pair like BTCUSDT",
+                inner.id(),
+                config.symbol
+            )));
+        }
+        Ok(WithOrderBook { inner, config: config.clone(), client })
+    }
+
+    fn snapshot(&self) -> Result<Snapshot, PriceError> {
+        let symbol = self.config.symbol.as_str();
+        let body = match (self.config.exchange, self.config.market) {
+            (Exchange::Binance, Market::Spot) => self
+                .client
+                .get("https://api.binance.com/api/v3/depth", &[("symbol", symbol), ("limit", LEVELS)])?,
+            (Exchange::Binance, Market::Linear) => self
+                .client
+                .get("https://fapi.binance.com/fapi/v1/depth", &[("symbol", symbol), ("limit", LEVELS)])?,
+            (Exchange::Bybit, market) => {
+                let category = if market == Market::Spot { "spot" } else { "linear" };
+                // Bybit serves at most 200 levels of a spot book.
+                let query = [("category", category), ("symbol", symbol), ("limit", "200")];
+                self.client.get("https://api.bybit.com/v5/market/orderbook", &query)?
+            }
+        };
+        parse(&body, self.config.exchange, symbol)
+    }
+
+    fn add_snapshot(&self, mut record: PriceRecord) -> PriceRecord {
+        match self.snapshot() {
+            Ok(snapshot) => snapshot.record_into(&mut record),
+            Err(e) => eprintln!("Error fetching the order book of {}: {}", self.inner.name(), e),
+        }
+        record
+    }
+}
+
+fn parse(body: &str, exchange: Exchange, symbol: &str) -> Result<Snapshot, PriceError> {
+    let json: Value = serde_json::from_str(body)?;
+    let (bids, asks) = match exchange {
+        Exchange::Binance => (&json["bids"], &json["asks"]),
+        Exchange::Bybit => {
+            if let Some(code) = json["retCode"].as_i64().filter(|code| *code != 0) {
+                let message = json["retMsg"].as_str().unwrap_or_default();
+                return Err(PriceError::ParseError(format!("Bybit error {}: {}", code, message)));
+            }
+            (&json["result"]["b"], &json["result"]["a"])
+        }
+    };
+    Snapshot::from_levels(&levels(bids)?, &levels(asks)?)
+        .ok_or_else(|| PriceError::ParseError(format!("the order book of {} is empty", symbol)))
+}
+
+// `[["price", "size"], ...]`, numbers as strings.
+fn levels(side: &Value) -> Result<Vec<(f64, f64)>, PriceError> {
+    let invalid = || PriceError::ParseError("expected order book levels of a price and a size".to_string());
+    let number = |value: &Value| value.as_str().and_then(|text| text.parse::<f64>().ok()).ok_or_else(invalid);
+    side.as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|level| Ok((number(&level[0])?, number(&level[1])?)))
+        .collect()
+}
+
+impl Pricing for WithOrderBook {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.inner.fetch_price()
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        self.inner.fetch_record().map(|record| self.add_snapshot(record))
+    }
+
+    // The book moves even when the price is unchanged, but the record is
+    // only written when there is a new price.
+    fn fetch_if_changed(&self) -> Result<Option<PriceRecord>, PriceError> {
+        Ok(self.inner.fetch_if_changed()?.map(|record| self.add_snapshot(record)))
+    }
+
+    fn validate(&self) -> Result<(), PriceError> {
+        self.inner.validate()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn symbol(&self) -> &str {
+        self.inner.symbol()
+    }
+
+    fn precision(&self) -> usize {
+        self.inner.precision()
+    }
+}
@@ -0,0 +1,39 @@
+// Price sources compiled to WebAssembly, configured per asset under
+// `[plugins.<id>]` with the path of a .wasm module (or its .wat text).
+//
+// A module exports `memory`, `alloc(len: i32) -> i32` and
+// `fetch_price(id_ptr: i32, id_len: i32) -> f64`, which is handed the asset's
+// provider id and returns its USD price. It may import from "tracker":
+//
+//   http_get(url_ptr: i32, url_len: i32) -> i32   fetches a URL, returning
+//                                                 the body's length or -1
+//   read_response(ptr: i32)                       copies that body to `ptr`
+//   error(ptr: i32, len: i32)                     fails the fetch with a message
+//   log(ptr: i32, len: i32)                       prints a line to stderr
+//
+// Strings are UTF-8 and the host writes into memory only where `alloc` or
+// the plugin's own pointers say. Each fetch may run `fuel` instructions.
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[cfg(feature = "plugins")]
+mod host;
+
+#[cfg(feature = "plugins")]
+pub use host::PluginSource;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub struct PluginConfig {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+}
+
+fn default_fuel() -> u64 {
+    10_000_000
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::sync::Mutex;
+
+use wasmi::{Caller, Engine, Extern, Linker, Memory, Module, Store, TypedFunc};
+
+use super::PluginConfig;
+use crate::http::Client;
+use crate::registry::AssetInfo;
+use crate::{PriceError, Pricing};
+
+struct HostState {
+    id: String,
+    client: Client,
+    // Body of the last http_get, until read_response copies it.
+    response: Vec<u8>,
+    // Reported by the plugin with `error`.
+    error: Option<String>,
+    // The last failed http_get, used when the plugin returns no price.
+    http_error: Option<String>,
+}
+
+pub struct PluginSource {
+    info: AssetInfo,
+    fuel: u64,
+    store: Mutex<Store<HostState>>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    fetch: TypedFunc<(i32, i32), f64>,
+}
+
+impl PluginSource {
+    pub fn new(info: AssetInfo, config: &PluginConfig, client: Client) -> Result<Self, PriceError> {
+        let invalid = |reason: String| {
+            PriceError::ParseError(format!("plugins.{}: {}: {}", info.id, config.path.display(), reason))
+        };
+        let bytes = fs::read(&config.path)
+            .map_err(|e| PriceError::FileError(format!("plugins.{}: {}: {}", info.id, config.path.display(), e)))?;
+        // Binary modules pass through unchanged.
+        let wasm = wat::parse_bytes(&bytes).map_err(|e| invalid(e.to_string()))?;
+
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, &wasm).map_err(|e| invalid(e.to_string()))?;
+        let state = HostState { id: info.id.clone(), client, response: Vec::new(), error: None, http_error: None };
+        let mut store = Store::new(&engine, state);
+        store.set_fuel(config.fuel).map_err(|e| invalid(e.to_string()))?;
+
+        let mut linker = Linker::new(&engine);
+        define_imports(&mut linker).map_err(|e| invalid(e.to_string()))?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| invalid(e.to_string()))?;
+        let memory =
+            instance.get_memory(&store, "memory").ok_or_else(|| invalid("does not export 'memory'".to_string()))?;
+        let alloc = instance.get_typed_func(&store, "alloc").map_err(|e| invalid(format!("alloc: {}", e)))?;
+        let fetch = instance.get_typed_func(&store, "fetch_price").map_err(|e| invalid(format!("fetch_price: {}", e)))?;
+
+        Ok(PluginSource { info, fuel: config.fuel, store: Mutex::new(store), memory, alloc, fetch })
+    }
+}
+
+impl Pricing for PluginSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        let failed = |reason: String| PriceError::NetworkError(format!("plugin for {}: {}", self.info.id, reason));
+        let mut store = self.store.lock().unwrap();
+        store.set_fuel(self.fuel).map_err(|e| failed(e.to_string()))?;
+        let state = store.data_mut();
+        state.error = None;
+        state.http_error = None;
+
+        let id = self.info.provider_id.as_bytes();
+        let id_ptr = self.alloc.call(&mut *store, id.len() as i32).map_err(|e| failed(e.to_string()))?;
+        self.memory
+            .write(&mut *store, id_ptr as usize, id)
+            .map_err(|e| failed(format!("alloc returned an invalid pointer: {}", e)))?;
+        let price = self.fetch.call(&mut *store, (id_ptr, id.len() as i32)).map_err(|e| failed(e.to_string()))?;
+
+        let state = store.data_mut();
+        if let Some(error) = state.error.take() {
+            return Err(failed(error));
+        }
+        if !price.is_finite() || price <= 0.0 {
+            return Err(failed(match state.http_error.take() {
+                Some(error) => error,
+                None => format!("returned {} instead of a price", price),
+            }));
+        }
+        Ok(price)
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
+
+fn define_imports(linker: &mut Linker<HostState>) -> Result<(), wasmi::Error> {
+    linker.func_wrap("tracker", "http_get", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let url = read_string(&caller, ptr, len)?;
+        let result = caller.data().client.get(&url, &[]);
+        let state = caller.data_mut();
+        Ok(match result {
+            Ok(body) => {
+                state.response = body.into_bytes();
+                state.response.len() as i32
+            }
+            Err(e) => {
+                state.http_error = Some(e.to_string());
+                -1
+            }
+        })
+    })?;
+    linker.func_wrap("tracker", "read_response", |mut caller: Caller<'_, HostState>, ptr: i32| {
+        let memory = memory(&caller)?;
+        let (data, state) = memory.data_and_store_mut(&mut caller);
+        let start = ptr as u32 as usize;
+        let target = data
+            .get_mut(start..start + state.response.len())
+            .ok_or_else(|| wasmi::Error::new("read_response: the body does not fit at this pointer"))?;
+        target.copy_from_slice(&state.response);
+        Ok(())
+    })?;
+    linker.func_wrap("tracker", "error", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let message = read_string(&caller, ptr, len)?;
+        caller.data_mut().error = Some(message);
+        Ok(())
+    })?;
+    linker.func_wrap("tracker", "log", |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let message = read_string(&caller, ptr, len)?;
+        eprintln!("[plugin {}] {}", caller.data().id, message);
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, wasmi::Error> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(wasmi::Error::new("the plugin does not export 'memory'")),
+    }
+}
+
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let mut bytes = vec![0; len.max(0) as usize];
+    memory(caller)?
+        .read(caller, ptr as u32 as usize, &mut bytes)
+        .map_err(|e| wasmi::Error::new(format!("string out of bounds: {}", e)))?;
+    String::from_utf8(bytes).map_err(|_| wasmi::Error::new("string is not UTF-8"))
+}
@@ -0,0 +1,47 @@
+// Prices shown only on web pages, configured per asset under `[scrape.<id>]`
+// with the page's URL and a CSS selector for the element holding the price:
+//
+//   [scrape.broker_eur]
+//   url = "https://broker.example/rates"
+//   selector = "table#rates tr.eur td.sell"
+//
+// The number is read from the element's text, or from `attribute` if set,
+// with `pattern`, a regex whose first group (or whole match) is the price;
+// thousands separators are dropped. Pages are fetched with `user_agent` and at
+// most once per `min_interval`, reusing the last price in between.
+
+use serde::Deserialize;
+
+#[cfg(feature = "scraping")]
+mod page;
+
+#[cfg(feature = "scraping")]
+pub use page::ScrapeSource;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "scraping"), allow(dead_code))]
+pub struct ScrapeConfig {
+    pub url: String,
+    pub name: Option<String>,
+    pub selector: String,
+    pub attribute: Option<String>,
+    #[serde(default = "default_pattern")]
+    pub pattern: String,
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    #[serde(default = "default_min_interval")]
+    pub min_interval: String,
+}
+
+fn default_pattern() -> String {
+    r"[-+]?[0-9][0-9,]*(?:\.[0-9]+)?".to_string()
+}
+
+fn default_user_agent() -> String {
+    concat!("crypto_price_tracker/", env!("CARGO_PKG_VERSION")).to_string()
+}
+
+fn default_min_interval() -> String {
+    "1m".to_string()
+}
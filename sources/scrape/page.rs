@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use scraper::{Html, Selector};
+
+use super::ScrapeConfig;
+use crate::config::parse_duration;
+use crate::http::Client;
+use crate::registry::AssetInfo;
+use crate::{PriceError, Pricing};
+
+pub struct ScrapeSource {
+    info: AssetInfo,
+    url: String,
+    selector: Selector,
+    // As configured, for messages.
+    selector_text: String,
+    attribute: Option<String>,
+    pattern: Regex,
+    user_agent: String,
+    min_interval: Duration,
+    // When the page was last fetched, and the price it showed.
+    last: Mutex<Option<(Instant, f64)>>,
+    client: Client,
+}
+
+impl ScrapeSource {
+    pub fn new(info: AssetInfo, config: &ScrapeConfig, client: Client) -> Result<Self, PriceError> {
+        let invalid =
+            |key: &str, reason: String| PriceError::ParseError(format!("scrape.{}.{}: {}", info.id, key, reason));
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return Err(invalid("url", "expected an http(s) URL".to_string()));
+        }
+        let selector = Selector::parse(&config.selector)
+            .map_err(|e| invalid("selector", format!("invalid selector '{}': {}", config.selector, e)))?;
+        let pattern = Regex::new(&config.pattern).map_err(|e| invalid("pattern", e.to_string()))?;
+        let min_interval = parse_duration(&config.min_interval)
+            .map_err(|e| invalid("min_interval", e.message().to_string()))?
+            .to_std()
+            .map_err(|_| invalid("min_interval", "must not be negative".to_string()))?;
+        if config.user_agent.trim().is_empty() {
+            return Err(invalid("user_agent", "must not be empty".to_string()));
+        }
+        Ok(ScrapeSource {
+            url: config.url.replace("{id}", &info.provider_id),
+            info,
+            selector,
+            selector_text: config.selector.clone(),
+            attribute: config.attribute.clone(),
+            pattern,
+            user_agent: config.user_agent.clone(),
+            min_interval,
+            last: Mutex::new(None),
+            client,
+        })
+    }
+
+    fn scrape(&self, body: &str) -> Result<f64, String> {
+        let page = Html::parse_document(body);
+        let element = page
+            .select(&self.selector)
+            .next()
+            .ok_or_else(|| format!("nothing matches the selector '{}'", self.selector_text))?;
+        let text = match &self.attribute {
+            Some(name) => {
+                let value = element.value().attr(name);
+                value.ok_or_else(|| format!("the element has no '{}' attribute", name))?.to_string()
+            }
+            None => element.text().collect::<String>(),
+        };
+        let captures = self.pattern.captures(&text).ok_or_else(|| format!("no number in '{}'", text.trim()))?;
+        let found = captures.get(1).or_else(|| captures.get(0)).map_or("", |found| found.as_str());
+        let price: f64 = found.replace(',', "").parse().map_err(|_| format!("'{}' is not a number", found))?;
+        if !(price.is_finite() && price > 0.0) {
+            return Err(format!("{} is not a valid price", price));
+        }
+        Ok(price)
+    }
+}
+
+impl Pricing for ScrapeSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        if let Some((fetched, price)) = *self.last.lock().unwrap() {
+            if fetched.elapsed() < self.min_interval {
+                return Ok(price);
+            }
+        }
+        let body = self.client.get_with_headers(&self.url, &[("User-Agent", &self.user_agent)], &[])?;
+        let price = self.scrape(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", self.url, e)))?;
+        *self.last.lock().unwrap() = Some((Instant::now(), price));
+        Ok(price)
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
@@ -0,0 +1,37 @@
+// Rhai scripts as price sources and as per-asset transforms.
+//
+// A source is configured under `[scripts.<id>]` and defines
+//
+//   fn url(id) { ... }          the URL to fetch, given the provider id
+//   fn price(body, id) { ... }  the USD price in the response body
+//
+// A transform is set with `transform = "<path>"` under `[assets.<id>]` and
+// defines `fn transform(record)`, which gets a map of asset, price, volume,
+// market_cap and quotes and returns it, changed as needed, or `()` to drop the
+// record. Scripts can `throw` to fail the fetch; `parse_json(text)` reads JSON
+// and `print` writes to stderr.
+
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+#[cfg(feature = "scripting")]
+mod host;
+
+#[cfg(feature = "scripting")]
+pub use host::{ScriptSource, Transform};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+pub struct ScriptConfig {
+    pub path: PathBuf,
+    pub name: Option<String>,
+    // Limit on the work of one call, against runaway loops.
+    #[serde(default = "default_max_operations")]
+    pub max_operations: u64,
+}
+
+pub fn default_max_operations() -> u64 {
+    1_000_000
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use rhai::{Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+
+use super::{default_max_operations, ScriptConfig};
+use crate::http::Client;
+use crate::pipeline::Stage;
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+fn engine(id: &str, max_operations: u64) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(max_operations);
+    let id = id.to_string();
+    engine.on_print(move |message| eprintln!("[script {}] {}", id, message));
+    // The built-in one only reads objects.
+    engine.register_fn("parse_json", |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        rhai::serde::to_dynamic(value)
+    });
+    engine
+}
+
+fn compile(engine: &Engine, path: &Path, key: &str, functions: &[&str]) -> Result<AST, PriceError> {
+    let ast = engine
+        .compile_file(path.to_path_buf())
+        .map_err(|e| PriceError::ParseError(format!("{}: {}: {}", key, path.display(), e)))?;
+    for name in functions {
+        if !ast.iter_functions().any(|f| f.name == *name) {
+            return Err(PriceError::ParseError(format!("{}: {}: no function '{}'", key, path.display(), name)));
+        }
+    }
+    Ok(ast)
+}
+
+fn number(value: &Dynamic) -> Option<f64> {
+    value.as_float().ok().or_else(|| value.as_int().ok().map(|n| n as f64))
+}
+
+pub struct ScriptSource {
+    info: AssetInfo,
+    path: String,
+    engine: Engine,
+    ast: AST,
+    client: Client,
+}
+
+impl ScriptSource {
+    pub fn new(info: AssetInfo, config: &ScriptConfig, client: Client) -> Result<Self, PriceError> {
+        let engine = engine(&info.id, config.max_operations);
+        let ast = compile(&engine, &config.path, &format!("scripts.{}", info.id), &["url", "price"])?;
+        Ok(ScriptSource { path: config.path.display().to_string(), info, engine, ast, client })
+    }
+
+    fn call(&self, name: &str, args: impl FuncArgs) -> Result<Dynamic, PriceError> {
+        self.engine
+            .call_fn(&mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| PriceError::ParseError(format!("{}: {}: {}", self.path, name, e)))
+    }
+}
+
+impl Pricing for ScriptSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        let id = self.info.provider_id.clone();
+        let url = self
+            .call("url", (id.clone(),))?
+            .into_string()
+            .map_err(|kind| PriceError::ParseError(format!("{}: url returned a {}, not a string", self.path, kind)))?;
+        let body = self.client.get(&url, &[])?;
+        let price = self.call("price", (body, id))?;
+        number(&price)
+            .filter(|price| price.is_finite() && *price > 0.0)
+            .ok_or_else(|| PriceError::ParseError(format!("{}: price returned {}, not a price", self.path, price)))
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
+
+// A pipeline stage running every record of an asset through a transform
+// script.
+pub struct Transform {
+    asset: String,
+    path: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl Transform {
+    pub fn new(asset: &str, key: &str, path: &Path) -> Result<Self, PriceError> {
+        let engine = engine(asset, default_max_operations());
+        let ast = compile(&engine, path, key, &["transform"])?;
+        Ok(Transform { asset: asset.to_string(), path: path.display().to_string(), engine, ast })
+    }
+}
+
+impl Stage for Transform {
+    fn apply(&mut self, mut record: PriceRecord) -> Result<PriceRecord, PriceError> {
+        let optional = |value: Option<f64>| value.map_or(Dynamic::UNIT, Dynamic::from_float);
+        let quotes: Map =
+            record.quotes.iter().map(|(currency, price)| (currency.into(), Dynamic::from_float(*price))).collect();
+        let mut map = Map::new();
+        map.insert("asset".into(), self.asset.as_str().into());
+        map.insert("price".into(), Dynamic::from_float(record.price));
+        map.insert("volume".into(), optional(record.volume));
+        map.insert("market_cap".into(), optional(record.market_cap));
+        map.insert("quotes".into(), quotes.into());
+
+        let invalid = |reason: String| PriceError::ParseError(format!("{}: transform: {}", self.path, reason));
+        let result: Dynamic =
+            self.engine.call_fn(&mut Scope::new(), &self.ast, "transform", (map,)).map_err(|e| invalid(e.to_string()))?;
+        if result.is_unit() {
+            return Err(invalid("dropped the record".to_string()));
+        }
+        let map = result.try_cast::<Map>().ok_or_else(|| invalid("expected the record map or ()".to_string()))?;
+        let field = |name: &str| map.get(name).filter(|value| !value.is_unit());
+
+        record.price = field("price")
+            .and_then(number)
+            .filter(|price| price.is_finite() && *price > 0.0)
+            .ok_or_else(|| invalid("the record has no valid price".to_string()))?;
+        record.volume = field("volume").and_then(number);
+        record.market_cap = field("market_cap").and_then(number);
+        if let Some(quotes) = field("quotes") {
+            let quotes = quotes.clone().try_cast::<Map>().ok_or_else(|| invalid("quotes is not a map".to_string()))?;
+            record.quotes =
+                quotes.iter().filter_map(|(currency, price)| Some((currency.to_string(), number(price)?))).collect();
+        }
+        Ok(record)
+    }
+}
@@ -0,0 +1,315 @@
+// Yahoo Finance quotes for any ticker it knows: indexes, ETFs and stocks
+// alike. The built-in `sp500` asset is ^GSPC; more are configured per asset:
+//
+//   [yahoo.qqq]
+//   symbol = "QQQ"
+//   name = "Invesco QQQ"
+//
+// Each is stored as its own series under the asset id. The symbol is
+// checked against Yahoo when tracking starts, so a typo fails at once rather
+// than on every fetch.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Deserialize;
+
+use crate::http::{percent_encode, Client, HttpError};
+use crate::registry::AssetInfo;
+use crate::storage::PriceRecord;
+use crate::{PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct YahooConfig {
+    pub symbol: String,
+    pub name: Option<String>,
+}
+
+pub struct YahooSource {
+    info: AssetInfo,
+    client: Client,
+}
+
+impl YahooSource {
+    // The symbol is the asset's provider id.
+    pub fn new(info: AssetInfo, client: Client) -> Result<Self, PriceError> {
+        if !valid_symbol(&info.provider_id) {
+            return Err(PriceError::ParseError(format!(
+                "{}: '{}' is not a Yahoo Finance symbol like SPY, BRK-B or ^IXIC",
+                info.id, info.provider_id
+            )));
+        }
+        Ok(YahooSource { info, client })
+    }
+
+    fn fetch_chart(&self) -> Result<String, PriceError> {
+        let symbol = &self.info.provider_id;
+        self.client.get(&chart_url(symbol), &[("range", "1d"), ("interval", "1d")]).map_err(|e| match e {
+            HttpError::Status { code: 404, .. } => {
+                PriceError::ParseError(format!("{}: Yahoo Finance has no symbol '{}'", self.info.id, symbol))
+            }
+            e => e.into(),
+        })
+    }
+
+    fn fetch_meta(&self) -> Result<Meta, PriceError> {
+        let (symbol, body) = (&self.info.provider_id, self.fetch_chart()?);
+        parse_meta(&body, symbol).map_err(|e| e.with_response(&chart_url(symbol), &body))
+    }
+}
+
+// Letters, digits and the punctuation Yahoo uses for indexes (^GSPC), share
+// classes (BRK-B), exchanges (VOD.L) and currencies or futures (EURUSD=X).
+fn valid_symbol(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && symbol.len() <= 20
+        && symbol.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '^' | '-' | '.' | '=' | '_'))
+}
+
+fn chart_url(symbol: &str) -> String {
+    format!("https://query1.finance.yahoo.com/v8/finance/chart/{}", percent_encode(symbol, ""))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    regular_market_price: f64,
+    regular_market_volume: Option<f64>,
+    regular_market_day_high: Option<f64>,
+    regular_market_day_low: Option<f64>,
+    // Seconds since 1970.
+    regular_market_time: Option<i64>,
+    currency: Option<String>,
+}
+
+fn parse_meta(body: &str, symbol: &str) -> Result<Meta, PriceError> {
+    let response: ChartResponse = serde_json::from_str(body)?;
+    if let Some(error) = response.chart.error {
+        return Err(PriceError::ParseError(format!("{}: {}", symbol, error.description)));
+    }
+    response
+        .chart
+        .result
+        .into_iter()
+        .find_map(|result| result.meta)
+        .ok_or_else(|| PriceError::ParseError(format!("{}: no quote in the response", symbol)))
+}
+
+impl Pricing for YahooSource {
+    fn fetch_price(&self) -> Result<f64, PriceError> {
+        self.fetch_meta().map(|meta| meta.regular_market_price)
+    }
+
+    fn fetch_record(&self) -> Result<PriceRecord, PriceError> {
+        let meta = self.fetch_meta()?;
+        let mut record = PriceRecord::new(Local::now().naive_local(), meta.regular_market_price);
+        record.volume = meta.regular_market_volume;
+        record.source_time = meta.regular_market_time.and_then(|time| DateTime::from_timestamp(time, 0));
+        // The session's range; Yahoo has no VWAP.
+        if let Some(high) = meta.regular_market_day_high {
+            record.day.insert("high".to_string(), high);
+        }
+        if let Some(low) = meta.regular_market_day_low {
+            record.day.insert("low".to_string(), low);
+        }
+        Ok(record)
+    }
+
+    // Prices are kept in USD, so tickers quoted in another currency are
+    // turned away along with unknown ones.
+    fn validate(&self) -> Result<(), PriceError> {
+        let body = match self.fetch_chart() {
+            Ok(body) => body,
+            Err(e) if e.is_parse() => return Err(e),
+            Err(_) => return Ok(()),
+        };
+        match parse_meta(&body, &self.info.provider_id).ok().and_then(|meta| meta.currency) {
+            Some(currency) if currency != "USD" => Err(PriceError::ParseError(format!(
+                "{}: {} is quoted in {}, only USD tickers can be tracked",
+                self.info.id, self.info.provider_id, currency
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn symbol(&self) -> &str {
+        &self.info.symbol
+    }
+
+    fn precision(&self) -> usize {
+        self.info.precision
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dividend {
+    // The ex-dividend day.
+    pub date: NaiveDate,
+    // Paid per share, in the quote currency.
+    pub amount: f64,
+}
+
+#[derive(Deserialize)]
+struct ChartResponse {
+    chart: Chart,
+}
+
+#[derive(Deserialize)]
+struct Chart {
+    #[serde(default)]
+    result: Vec<ChartResult>,
+    error: Option<ChartError>,
+}
+
+#[derive(Deserialize)]
+struct ChartError {
+    description: String,
+}
+
+#[derive(Deserialize)]
+struct ChartResult {
+    meta: Option<Meta>,
+    #[serde(default)]
+    events: Events,
+}
+
+#[derive(Default, Deserialize)]
+struct Events {
+    // Keyed by the Unix time of the ex-dividend day.
+    #[serde(default)]
+    dividends: BTreeMap<String, DividendEvent>,
+}
+
+#[derive(Deserialize)]
+struct DividendEvent {
+    amount: f64,
+    date: i64,
+}
+
+// Every dividend Yahoo Finance has for `symbol`, oldest first.
+pub fn fetch_dividends(client: &Client, symbol: &str) -> Result<Vec<Dividend>, PriceError> {
+    let body = client.get(&chart_url(symbol), &[("range", "max"), ("interval", "1d"), ("events", "div")])?;
+    parse_dividends(&body)
+}
+
+pub fn parse_dividends(body: &str) -> Result<Vec<Dividend>, PriceError> {
+    let response: ChartResponse = serde_json::from_str(body)?;
+    if let Some(error) = response.chart.error {
+        return Err(PriceError::ParseError(error.description));
+    }
+    let mut dividends = Vec::new();
+    for result in response.chart.result {
+        for event in result.events.dividends.into_values() {
+            let date = DateTime::from_timestamp(event.date, 0)
+                .ok_or_else(|| PriceError::ParseError(format!("invalid dividend date {}", event.date)))?;
+            dividends.push(Dividend { date: date.date_naive(), amount: event.amount });
+        }
+    }
+    dividends.sort_by_key(|dividend| dividend.date);
+    Ok(dividends)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::http::stub::StubClient;
+    use crate::registry::built_in;
+
+    const CHART_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart/%5EGSPC";
+
+    fn sp500(stub: &Arc<StubClient>) -> YahooSource {
+        YahooSource::new(built_in("sp500").unwrap(), stub.clone()).unwrap()
+    }
+
+    fn chart(meta: &str) -> String {
+        format!(r#"{{"chart": {{"result": [{{"meta": {}}}], "error": null}}}}"#, meta)
+    }
+
+    #[test]
+    fn a_quote_is_read_with_its_volume_and_range() {
+        let body = chart(
+            r#"{"currency": "USD", "symbol": "^GSPC", "regularMarketPrice": 5127.79, "regularMarketVolume": 3.1e9,
+                "regularMarketDayHigh": 5139.12, "regularMarketDayLow": 5101.22, "regularMarketTime": 1714766402}"#,
+        );
+        let stub = Arc::new(StubClient::default().respond(CHART_URL, &body));
+        let record = sp500(&stub).fetch_record().unwrap();
+        assert_eq!((record.price, record.volume), (5127.79, Some(3.1e9)));
+        assert_eq!((record.day.get("high"), record.day.get("low")), (Some(&5139.12), Some(&5101.22)));
+        assert_eq!(record.source_time.unwrap().timestamp(), 1714766402);
+        assert_eq!(stub.requests(), [format!("{}?range=1d&interval=1d", CHART_URL)]);
+    }
+
+    #[test]
+    fn only_the_price_is_required() {
+        let stub = Arc::new(StubClient::default().respond(CHART_URL, &chart(r#"{"regularMarketPrice": 5127}"#)));
+        let record = sp500(&stub).fetch_record().unwrap();
+        assert_eq!(record.price, 5127.0);
+        assert!(record.volume.is_none() && record.day.is_empty() && record.source_time.is_none());
+    }
+
+    #[test]
+    fn missing_quotes_are_parse_errors_with_the_response() {
+        for (body, message) in [
+            (chart(r#"{"currency": "USD"}"#), "missing field `regularMarketPrice`"),
+            (r#"{"chart": {"result": [], "error": null}}"#.to_string(), "^GSPC: no quote in the response"),
+            (
+                r#"{"chart": {"error": {"code": "Not Found", "description": "No data found"}}}"#.to_string(),
+                "^GSPC: No data found",
+            ),
+        ] {
+            let stub = Arc::new(StubClient::default().respond(CHART_URL, &body));
+            let e = sp500(&stub).fetch_record().unwrap_err();
+            assert!(e.is_parse() && e.to_string().contains(message), "{}: {}", body, e);
+            assert_eq!(e.parse().unwrap().url.as_deref(), Some(CHART_URL));
+        }
+    }
+
+    #[test]
+    fn malformed_bodies_are_parse_errors() {
+        let stub = Arc::new(StubClient::default().respond(CHART_URL, r#"{"chart": {"result": [{"meta": "#));
+        let e = sp500(&stub).fetch_record().unwrap_err();
+        assert!(e.is_parse() && e.to_string().contains("EOF while parsing"), "{}", e);
+        assert!(e.source().is_some());
+    }
+
+    #[test]
+    fn unknown_symbols_and_other_currencies_are_turned_away() {
+        let stub = Arc::new(StubClient::default().fail(CHART_URL, 404));
+        let e = sp500(&stub).fetch_record().unwrap_err();
+        assert_eq!(e.to_string(), "Parse Error [E200]: sp500: Yahoo Finance has no symbol '^GSPC'");
+        assert!(sp500(&stub).validate().is_err());
+
+        let body = chart(r#"{"currency": "EUR", "regularMarketPrice": 18000.0}"#);
+        let stub = Arc::new(StubClient::default().respond(CHART_URL, &body));
+        let e = sp500(&stub).validate().unwrap_err();
+        assert!(e.to_string().ends_with("sp500: ^GSPC is quoted in EUR, only USD tickers can be tracked"), "{}", e);
+
+        // Other failures are left to the fetches.
+        let stub = Arc::new(StubClient::default().fail(CHART_URL, 503));
+        assert!(sp500(&stub).validate().is_ok());
+    }
+
+    #[test]
+    fn dividends_are_read_oldest_first() {
+        let body = r#"{"chart": {"result": [{"events": {"dividends": {
+            "1710423000": {"amount": 1.60, "date": 1710423000},
+            "1702650600": {"amount": 1.90, "date": 1702650600}}}}], "error": null}}"#;
+        let dividends = parse_dividends(body).unwrap();
+        assert_eq!(dividends.iter().map(|d| d.amount).collect::<Vec<_>>(), [1.90, 1.60]);
+        assert_eq!(dividends[0].date, NaiveDate::from_ymd_opt(2023, 12, 15).unwrap());
+        let missing_date = r#"{"chart": {"result": [{"events": {"dividends": {"1": {"amount": 1}}}}]}}"#;
+        assert!(parse_dividends(missing_date).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+pub mod binary;
+pub mod chain;
+pub mod csv;
+pub mod dry_run;
+pub mod encrypted;
+mod index;
+pub mod sequence;
+pub mod sqlite;
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::config::{Config, StorageConfig};
+use crate::PriceError;
+
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// v1: timestamp, price
+// v2: adds optional volume and market_cap
+// v3: adds optional price_<currency> columns for extra quote currencies
+// v4: adds optional fx_<currency> columns with the rate of converted quotes
+// v5: adds optional in_<symbol> columns with the price in another asset
+// v6: adds optional change_<period> columns with the percent change over it
+// v7: adds optional book_<metric> columns from an order book snapshot
+// v8: adds optional day_high, day_low and day_vwap columns
+// v9: adds optional seq and source_time columns
+pub const SCHEMA_VERSION: u32 = 9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceRecord {
+    pub timestamp: NaiveDateTime,
+    pub price: f64,
+    pub volume: Option<f64>,
+    pub market_cap: Option<f64>,
+    // Prices in the configured extra currencies, keyed by lowercase code;
+    // `price` is always USD.
+    pub quotes: BTreeMap<String, f64>,
+    // USD rate used for quotes the FX layer converted rather than the
+    // source quoting them directly.
+    pub fx_rates: BTreeMap<String, f64>,
+    // Price in units of another tracked asset, keyed by its symbol, e.g.
+    // ethereum in "btc".
+    pub relative: BTreeMap<String, f64>,
+    // Percent change over a period, keyed by its label, e.g. "24h".
+    pub changes: BTreeMap<String, f64>,
+    // Order book metrics keyed by name, e.g. "spread".
+    pub book: BTreeMap<String, f64>,
+    // The source's "high", "low" and "vwap" over the last 24 hours, or the
+    // trading session for listed tickers, as far as it reports them.
+    pub day: BTreeMap<String, f64>,
+    // Counts the asset's records from 1, in the order they were fetched.
+    pub sequence: Option<u64>,
+    // When the source says the value is from, for sources that say.
+    pub source_time: Option<DateTime<Utc>>,
+}
+
+impl PriceRecord {
+    pub fn new(timestamp: NaiveDateTime, price: f64) -> Self {
+        PriceRecord {
+            timestamp,
+            price,
+            volume: None,
+            market_cap: None,
+            quotes: BTreeMap::new(),
+            fx_rates: BTreeMap::new(),
+            relative: BTreeMap::new(),
+            changes: BTreeMap::new(),
+            book: BTreeMap::new(),
+            day: BTreeMap::new(),
+            sequence: None,
+            source_time: None,
+        }
+    }
+
+    // The per-currency values as named columns, e.g. ("price_eur", 60123.45).
+    pub fn extra_columns(&self) -> Vec<(String, f64)> {
+        let quotes = self.quotes.iter().map(|(currency, price)| (format!("{}{}", QUOTE_PREFIX, currency), *price));
+        let rates = self.fx_rates.iter().map(|(currency, rate)| (format!("{}{}", FX_PREFIX, currency), *rate));
+        let relative = self.relative.iter().map(|(symbol, price)| (format!("{}{}", RELATIVE_PREFIX, symbol), *price));
+        let changes = self.changes.iter().map(|(period, change)| (format!("{}{}", CHANGE_PREFIX, period), *change));
+        let book = self.book.iter().map(|(metric, value)| (format!("{}{}", BOOK_PREFIX, metric), *value));
+        let day = self.day.iter().map(|(stat, value)| (format!("{}{}", DAY_PREFIX, stat), *value));
+        // The source time goes in seconds since 1970, like the numbers it
+        // is stored with.
+        let sequence = self.sequence.map(|sequence| (SEQUENCE_COLUMN.to_string(), sequence as f64));
+        let source_time = self.source_time.map(|time| (SOURCE_TIME_COLUMN.to_string(), time.timestamp() as f64));
+        let order = sequence.into_iter().chain(source_time);
+        quotes.chain(rates).chain(relative).chain(changes).chain(book).chain(day).chain(order).collect()
+    }
+
+    pub fn set_extra_column(&mut self, column: &str, value: f64) {
+        if let Some(currency) = column.strip_prefix(QUOTE_PREFIX) {
+            self.quotes.insert(currency.to_string(), value);
+        } else if let Some(currency) = column.strip_prefix(FX_PREFIX) {
+            self.fx_rates.insert(currency.to_string(), value);
+        } else if let Some(symbol) = column.strip_prefix(RELATIVE_PREFIX) {
+            self.relative.insert(symbol.to_string(), value);
+        } else if let Some(period) = column.strip_prefix(CHANGE_PREFIX) {
+            self.changes.insert(period.to_string(), value);
+        } else if let Some(metric) = column.strip_prefix(BOOK_PREFIX) {
+            self.book.insert(metric.to_string(), value);
+        } else if let Some(stat) = column.strip_prefix(DAY_PREFIX) {
+            self.day.insert(stat.to_string(), value);
+        } else if column == SEQUENCE_COLUMN {
+            self.sequence = Some(value as u64);
+        } else if column == SOURCE_TIME_COLUMN {
+            self.source_time = DateTime::from_timestamp(value as i64, 0);
+        }
+    }
+}
+
+// One fired alert as kept in the alert log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    pub timestamp: NaiveDateTime,
+    pub rule: String,
+    pub asset: String,
+    // The value that tripped the rule: the price, indicator or move size.
+    pub value: f64,
+    pub message: String,
+    // Delivery result per notifier, e.g. "console: ok".
+    pub outcome: String,
+}
+
+const QUOTE_PREFIX: &str = "price_";
+pub const FX_PREFIX: &str = "fx_";
+pub const RELATIVE_PREFIX: &str = "in_";
+const CHANGE_PREFIX: &str = "change_";
+const BOOK_PREFIX: &str = "book_";
+const DAY_PREFIX: &str = "day_";
+pub const SEQUENCE_COLUMN: &str = "seq";
+pub const SOURCE_TIME_COLUMN: &str = "source_time";
+
+// Whether `column` is one of the per-currency or per-period columns both
+// backends add on demand.
+pub fn is_extra_column(column: &str) -> bool {
+    if column == SEQUENCE_COLUMN || column == SOURCE_TIME_COLUMN {
+        return true;
+    }
+    [QUOTE_PREFIX, FX_PREFIX, RELATIVE_PREFIX, CHANGE_PREFIX, BOOK_PREFIX, DAY_PREFIX]
+        .iter()
+        .any(|prefix| column.strip_prefix(prefix).is_some_and(valid_currency))
+}
+
+// Currency codes end up in column names, so only plain codes are accepted.
+pub fn valid_currency(currency: &str) -> bool {
+    !currency.is_empty() && currency.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+// Records one at a time, oldest first.
+pub type Records<'a> = Box<dyn Iterator<Item = Result<PriceRecord, PriceError>> + 'a>;
+
+// Sent to the writer thread, hence Send.
+pub trait Storage: Send {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError>;
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError>;
+    // Like read, from `from` on if given, without holding the whole history
+    // in memory, for analytics over histories of any size.
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        let records = self.read(asset)?.into_iter();
+        Ok(Box::new(records.filter(move |record| from.is_none_or(|from| record.timestamp >= from)).map(Ok)))
+    }
+    // The newest record, without reading the whole history where the
+    // backend can avoid it.
+    fn last(&self, asset: &str) -> Result<Option<PriceRecord>, PriceError> {
+        self.scan(asset, None)?.last().transpose()
+    }
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError>;
+    // Deals with a record left half written by a crash. Backends that skip
+    // such a record as they read and write need not.
+    fn recover(&mut self, _asset: &str) -> Result<(), PriceError> {
+        Ok(())
+    }
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError>;
+    // Oldest first.
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError>;
+}
+
+pub fn open(config: &StorageConfig) -> Result<Box<dyn Storage>, PriceError> {
+    match config {
+        StorageConfig::Csv { directory } => Ok(Box::new(csv::CsvStorage::new(directory))),
+        StorageConfig::Binary { directory } => Ok(Box::new(binary::BinaryStorage::new(directory))),
+        StorageConfig::Sqlite { path } => Ok(Box::new(sqlite::SqliteStorage::open(path)?)),
+        StorageConfig::Encrypted { directory, key_file } => {
+            Ok(Box::new(encrypted::EncryptedStorage::open(directory, key_file)?))
+        }
+    }
+}
+
+// The backend for the tracker's own writes, chaining each record when
+// `hash_chain` is set.
+pub fn open_chained(config: &Config) -> Result<Box<dyn Storage>, PriceError> {
+    let storage = open(&config.storage)?;
+    if !config.hash_chain {
+        return Ok(storage);
+    }
+    Ok(Box::new(chain::ChainedStorage::new(storage, config.storage.directory())))
+}
+
+// Reads from the configured backend, if it exists yet, and only prints what
+// would be written.
+pub fn open_dry_run(config: &StorageConfig) -> Result<Box<dyn Storage>, PriceError> {
+    let inner: Option<Box<dyn Storage>> = match config {
+        StorageConfig::Csv { directory } => Some(Box::new(csv::CsvStorage::new(directory))),
+        StorageConfig::Binary { directory } => Some(Box::new(binary::BinaryStorage::new(directory))),
+        StorageConfig::Sqlite { path } if path.exists() => Some(Box::new(sqlite::SqliteStorage::open_read_only(path)?)),
+        StorageConfig::Sqlite { .. } => None,
+        StorageConfig::Encrypted { directory, key_file } => {
+            Some(Box::new(encrypted::EncryptedStorage::open(directory, key_file)?))
+        }
+    };
+    Ok(Box::new(dry_run::DryRunStorage::new(inner)))
+}
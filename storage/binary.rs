@@ -0,0 +1,323 @@
+// Histories as fixed-width little-endian records in `<asset>_prices.bin`,
+// half to a third the size of CSV and read without parsing text, for
+// high-frequency collection. Each file starts with a header naming its
+// columns:
+//
+//   "CPTB", format version (u8), column count (u8), then per column its
+//   name's length (u8) and name
+//
+// and each record is its timestamp in seconds since 1970 (u32) then the
+// price and every column as f64, NaN where a record has no value. Like CSV
+// files, a file is rewritten with a wider header when a new column appears.
+// The alert log stays in CSV.
+
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime};
+
+use super::csv::CsvStorage;
+use super::{is_extra_column, AlertEvent, PriceRecord, Records, Storage};
+use crate::PriceError;
+
+const MAGIC: &[u8; 4] = b"CPTB";
+const FORMAT_VERSION: u8 = 1;
+
+pub struct BinaryStorage {
+    directory: PathBuf,
+    // The columns of each file already opened this run.
+    current: HashMap<String, Vec<String>>,
+    alerts: CsvStorage,
+}
+
+impl BinaryStorage {
+    pub fn new(directory: &Path) -> Self {
+        let alerts = CsvStorage::new(directory);
+        BinaryStorage { directory: directory.to_path_buf(), current: HashMap::new(), alerts }
+    }
+
+    pub fn path_for(&self, asset: &str) -> PathBuf {
+        self.directory.join(format!("{}_prices.bin", asset))
+    }
+
+    fn columns(&mut self, asset: &str) -> Result<Option<Vec<String>>, PriceError> {
+        if let Some(columns) = self.current.get(asset) {
+            return Ok(Some(columns.clone()));
+        }
+        let path = self.path_for(asset);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let columns = BinaryReader::open(&path)?.columns;
+        self.current.insert(asset.to_string(), columns.clone());
+        Ok(Some(columns))
+    }
+}
+
+impl Storage for BinaryStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        let path = self.path_for(asset);
+        let Some(columns) = self.columns(asset)? else {
+            return self.replace(asset, std::slice::from_ref(record));
+        };
+        if columns_of(std::slice::from_ref(record)).iter().any(|column| !columns.contains(column)) {
+            let mut records = read_records(&path)?;
+            records.push(record.clone());
+            return self.replace(asset, &records);
+        }
+
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let mut file = OpenOptions::new().write(true).open(&path).map_err(file_error)?;
+        let start = header(&columns)?.len() as u64;
+        let records = file.metadata().map_err(file_error)?.len().saturating_sub(start) / width(&columns) as u64;
+        // Drops what is left of a record cut short by a crash.
+        let end = start + records * width(&columns) as u64;
+        file.set_len(end).map_err(file_error)?;
+        file.seek(SeekFrom::Start(end)).map_err(file_error)?;
+        file.write_all(&encode(record, &columns)?).map_err(file_error)
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        self.scan(asset, None)?.collect()
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        let path = self.path_for(asset);
+        if !path.exists() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let mut reader = BinaryReader::open(&path)?;
+        if let Some(from) = from {
+            reader.seek_to(from)?;
+        }
+        Ok(Box::new(reader))
+    }
+
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        let columns = columns_of(records);
+        write_stream(&self.path_for(asset), &columns, records.iter().map(Ok))?;
+        self.current.insert(asset.to_string(), columns);
+        Ok(())
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        self.alerts.log_alert(event)
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        self.alerts.alert_log()
+    }
+}
+
+// The columns records have values for: volume and market cap, then the
+// per-currency ones, sorted.
+#[derive(Default)]
+pub struct Columns {
+    volume: bool,
+    market_cap: bool,
+    extra: BTreeSet<String>,
+}
+
+impl Columns {
+    pub fn add(&mut self, record: &PriceRecord) {
+        self.volume |= record.volume.is_some();
+        self.market_cap |= record.market_cap.is_some();
+        self.extra.extend(record.extra_columns().into_iter().map(|(column, _)| column));
+    }
+
+    pub fn into_vec(self) -> Vec<String> {
+        let volume = self.volume.then(|| "volume".to_string());
+        let market_cap = self.market_cap.then(|| "market_cap".to_string());
+        volume.into_iter().chain(market_cap).chain(self.extra).collect()
+    }
+}
+
+fn columns_of(records: &[PriceRecord]) -> Vec<String> {
+    let mut columns = Columns::default();
+    for record in records {
+        columns.add(record);
+    }
+    columns.into_vec()
+}
+
+fn width(columns: &[String]) -> usize {
+    4 + 8 * (1 + columns.len())
+}
+
+fn header(columns: &[String]) -> Result<Vec<u8>, PriceError> {
+    let mut header = MAGIC.to_vec();
+    header.push(FORMAT_VERSION);
+    header.push(u8::try_from(columns.len()).map_err(|_| PriceError::FileError("too many columns".to_string()))?);
+    for column in columns {
+        header.push(column.len() as u8);
+        header.extend_from_slice(column.as_bytes());
+    }
+    Ok(header)
+}
+
+fn encode(record: &PriceRecord, columns: &[String]) -> Result<Vec<u8>, PriceError> {
+    let seconds = u32::try_from(record.timestamp.and_utc().timestamp()).map_err(|_| {
+        PriceError::ParseError(format!("timestamp {} does not fit the binary format", record.timestamp))
+    })?;
+    let mut bytes = Vec::with_capacity(width(columns));
+    bytes.extend_from_slice(&seconds.to_le_bytes());
+    bytes.extend_from_slice(&record.price.to_le_bytes());
+    let extra = record.extra_columns();
+    for column in columns {
+        let value = match column.as_str() {
+            "volume" => record.volume,
+            "market_cap" => record.market_cap,
+            _ => extra.iter().find(|(c, _)| c == column).map(|(_, value)| *value),
+        };
+        bytes.extend_from_slice(&value.unwrap_or(f64::NAN).to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+// Reads records in order, or from any point, as every record has the same
+// width.
+pub struct BinaryReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+    columns: Vec<String>,
+    // Where the records start, how many whole ones there are and the next.
+    start: u64,
+    count: u64,
+    next: u64,
+}
+
+impl BinaryReader {
+    pub fn open(path: &Path) -> Result<Self, PriceError> {
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let invalid = |reason: &str| PriceError::ParseError(format!("{}: {}", path.display(), reason));
+        let file = File::open(path).map_err(file_error)?;
+        let length = file.metadata().map_err(file_error)?.len();
+        let mut reader = BufReader::with_capacity(64 * 1024, file);
+
+        let mut intro = [0u8; 6];
+        reader.read_exact(&mut intro).map_err(|_| invalid("not a binary price history"))?;
+        if &intro[..4] != MAGIC {
+            return Err(invalid("not a binary price history"));
+        }
+        if intro[4] > FORMAT_VERSION {
+            return Err(invalid(&format!(
+                "format v{} is newer than this tracker supports (v{})",
+                intro[4], FORMAT_VERSION
+            )));
+        }
+        let mut columns = Vec::new();
+        let mut start = intro.len() as u64;
+        for _ in 0..intro[5] {
+            let mut length = [0u8];
+            reader.read_exact(&mut length).map_err(|_| invalid("truncated header"))?;
+            let mut name = vec![0u8; length[0] as usize];
+            reader.read_exact(&mut name).map_err(|_| invalid("truncated header"))?;
+            columns.push(String::from_utf8(name).map_err(|_| invalid("invalid column name"))?);
+            start += 1 + length[0] as u64;
+        }
+        let count = length.saturating_sub(start) / width(&columns) as u64;
+        Ok(BinaryReader { path: path.to_path_buf(), reader, columns, start, count, next: 0 })
+    }
+
+    // Records in the file.
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    // Per-currency columns, as in CSV headers.
+    pub fn extra_columns(&self) -> Vec<String> {
+        self.columns.iter().filter(|column| is_extra_column(column)).cloned().collect()
+    }
+
+    // Moves to the first record from `from` on, the records being in time
+    // order.
+    pub fn seek_to(&mut self, from: NaiveDateTime) -> Result<(), PriceError> {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let middle = (low + high) / 2;
+            self.go_to(middle)?;
+            match self.read_record()? {
+                record if record.timestamp < from => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+        self.go_to(low)
+    }
+
+    fn go_to(&mut self, index: u64) -> Result<(), PriceError> {
+        let offset = self.start + index * width(&self.columns) as u64;
+        self.reader
+            .seek(SeekFrom::Start(offset))
+            .map_err(|e| PriceError::FileError(format!("{}: {}", self.path.display(), e)))?;
+        self.next = index;
+        Ok(())
+    }
+
+    fn read_record(&mut self) -> Result<PriceRecord, PriceError> {
+        let mut bytes = vec![0u8; width(&self.columns)];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", self.path.display(), e)))?;
+        self.next += 1;
+        let number = |at: usize| f64::from_le_bytes(bytes[at..at + 8].try_into().unwrap_or_default());
+        let seconds = u32::from_le_bytes(bytes[..4].try_into().unwrap_or_default());
+        let timestamp = DateTime::from_timestamp(seconds.into(), 0).unwrap_or_default().naive_utc();
+        let mut record = PriceRecord::new(timestamp, number(4));
+        for (index, column) in self.columns.iter().enumerate() {
+            let value = Some(number(12 + 8 * index)).filter(|value| !value.is_nan());
+            match column.as_str() {
+                "volume" => record.volume = value,
+                "market_cap" => record.market_cap = value,
+                _ => {
+                    if let Some(value) = value {
+                        record.set_extra_column(column, value);
+                    }
+                }
+            }
+        }
+        Ok(record)
+    }
+}
+
+impl Iterator for BinaryReader {
+    type Item = Result<PriceRecord, PriceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.count {
+            return None;
+        }
+        let record = self.read_record();
+        if record.is_err() {
+            self.next = self.count;
+        }
+        Some(record)
+    }
+}
+
+fn read_records(path: &Path) -> Result<Vec<PriceRecord>, PriceError> {
+    BinaryReader::open(path)?.collect()
+}
+
+// Writes a whole file through a temporary sibling, as for CSV files;
+// `columns` must cover every value of `records`.
+pub fn write_stream<R: Borrow<PriceRecord>>(
+    path: &Path,
+    columns: &[String],
+    records: impl IntoIterator<Item = Result<R, PriceError>>,
+) -> Result<(), PriceError> {
+    let tmp_path = path.with_extension("bin.tmp");
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", tmp_path.display(), e));
+    let mut file = BufWriter::new(File::create(&tmp_path).map_err(file_error)?);
+    file.write_all(&header(columns)?).map_err(file_error)?;
+    for record in records {
+        file.write_all(&encode(record?.borrow(), columns)?).map_err(file_error)?;
+    }
+    file.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .map_err(file_error)?;
+    fs::rename(&tmp_path, path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+}
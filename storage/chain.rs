@@ -0,0 +1,288 @@
+// With `hash_chain = true`, every record written also gets a line in
+// `<asset>_chain.csv` holding its timestamp and the SHA-256 of the previous
+// line's hash and the record, so editing, deleting or inserting a record
+// breaks the chain from there on; `verify` recomputes it. Records are hashed
+// as CSV writes them, to the cent, which keeps a chain valid through
+// `convert` and `migrate`. The last hash, the head, can be kept elsewhere to
+// also catch the whole chain file being rewritten.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use ring::digest::{Context, SHA256};
+
+use super::csv::format_line_to_cent;
+use super::{AlertEvent, PriceRecord, Records, Storage, TIMESTAMP_FORMAT};
+use crate::PriceError;
+
+const HEADER: &str = "timestamp,hash";
+// What the first record is chained to.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+// A timestamp, a comma, a hash in hex and a newline.
+const LINE_LENGTH: u64 = 19 + 1 + 64 + 1;
+
+pub struct ChainedStorage {
+    inner: Box<dyn Storage>,
+    directory: PathBuf,
+}
+
+impl ChainedStorage {
+    pub fn new(inner: Box<dyn Storage>, directory: &Path) -> Self {
+        ChainedStorage { inner, directory: directory.to_path_buf() }
+    }
+
+    // Chains the history written before the chain was turned on, returning
+    // its head.
+    fn start(&self, asset: &str) -> Result<String, PriceError> {
+        let path = path_for(&self.directory, asset);
+        let (count, head) = write_chain(&path, asset, self.inner.scan(asset, None)?)?;
+        if count > 0 {
+            println!("Started the hash chain of {} over {} existing records", asset, count);
+        }
+        Ok(head)
+    }
+}
+
+impl Storage for ChainedStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        let path = path_for(&self.directory, asset);
+        let previous = match head(&path)? {
+            Some(head) => head,
+            None => self.start(asset)?,
+        };
+        self.inner.append(asset, record)?;
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let mut file = OpenOptions::new().append(true).open(&path).map_err(file_error)?;
+        writeln!(file, "{}", entry(record, &hash(&previous, asset, record))).map_err(file_error)
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        self.inner.read(asset)
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        self.inner.scan(asset, from)
+    }
+
+    // Rewrites made by the tracker itself, such as imports, are chained
+    // afresh.
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        self.inner.replace(asset, records)?;
+        write_chain(&path_for(&self.directory, asset), asset, records.iter().map(Ok)).map(|_| ())
+    }
+
+    fn last(&self, asset: &str) -> Result<Option<PriceRecord>, PriceError> {
+        self.inner.last(asset)
+    }
+
+    // A record is written before its chain entry, so a crash between the
+    // two leaves records at the end that the chain does not cover, and
+    // perhaps half an entry. Both are put right here rather than left for
+    // `verify` to report as tampering.
+    fn recover(&mut self, asset: &str) -> Result<(), PriceError> {
+        self.inner.recover(asset)?;
+        let path = path_for(&self.directory, asset);
+        if !path.exists() {
+            return Ok(());
+        }
+        let (entries, tail) = trim_chain(&path)?;
+        let Some(last) = self.inner.last(asset)? else {
+            return Ok(());
+        };
+        let last = last.timestamp.format(TIMESTAMP_FORMAT).to_string();
+        if tail.as_ref().is_some_and(|(timestamp, _)| *timestamp >= last) {
+            return Ok(());
+        }
+
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let mut file = OpenOptions::new().append(true).open(&path).map_err(file_error)?;
+        let mut previous = tail.map_or_else(|| GENESIS.to_string(), |(_, hash)| hash);
+        let mut linked = 0;
+        for record in self.inner.scan(asset, None)?.skip(entries) {
+            let record = record?;
+            previous = hash(&previous, asset, &record);
+            writeln!(file, "{}", entry(&record, &previous)).map_err(file_error)?;
+            linked += 1;
+        }
+        if linked > 0 {
+            eprintln!("Chained {} record(s) of {} written without a chain entry", linked, asset);
+        }
+        Ok(())
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        self.inner.log_alert(event)
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        self.inner.alert_log()
+    }
+}
+
+pub fn path_for(directory: &Path, asset: &str) -> PathBuf {
+    directory.join(format!("{}_chain.csv", asset))
+}
+
+// The asset is hashed too, so one asset's history and chain cannot pass
+// for another's, and so are the column names, so values cannot swap
+// columns.
+fn hash(previous: &str, asset: &str, record: &PriceRecord) -> String {
+    let columns: Vec<String> = record.extra_columns().into_iter().map(|(column, _)| column).collect();
+    let mut context = Context::new(&SHA256);
+    for part in [previous, asset, &columns.join(","), &format_line_to_cent(record, &columns)] {
+        context.update(part.as_bytes());
+        context.update(b"\n");
+    }
+    context.finish().as_ref().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn entry(record: &PriceRecord, hash: &str) -> String {
+    format!("{},{}", record.timestamp.format(TIMESTAMP_FORMAT), hash)
+}
+
+// The hash of the last line, or None when there is no chain yet.
+fn head(path: &Path) -> Result<Option<String>, PriceError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut file = File::open(path).map_err(file_error)?;
+    let length = file.metadata().map_err(file_error)?.len();
+    if length < HEADER.len() as u64 + 1 + LINE_LENGTH {
+        return Ok(Some(GENESIS.to_string()));
+    }
+    let mut line = String::new();
+    file.seek(SeekFrom::Start(length - LINE_LENGTH)).map_err(file_error)?;
+    file.read_to_string(&mut line).map_err(file_error)?;
+    match parse(&line) {
+        Some((_, hash)) => Ok(Some(hash.to_string())),
+        None => Err(PriceError::ParseError(format!("{}: the last line is not a chain entry", path.display()))),
+    }
+}
+
+// Drops an entry cut short, returning how many whole ones are left and the
+// timestamp and hash of the last.
+fn trim_chain(path: &Path) -> Result<(usize, Option<(String, String)>), PriceError> {
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut file = OpenOptions::new().read(true).write(true).open(path).map_err(file_error)?;
+    let length = file.metadata().map_err(file_error)?.len();
+    let start = HEADER.len() as u64 + 1;
+    let entries = length.saturating_sub(start) / LINE_LENGTH;
+    let whole = start + entries * LINE_LENGTH;
+    if length > whole {
+        file.set_len(whole).map_err(file_error)?;
+        eprintln!("Dropped the incomplete last entry of {}", path.display());
+    }
+    if entries == 0 {
+        return Ok((0, None));
+    }
+    let mut line = String::new();
+    file.seek(SeekFrom::Start(whole - LINE_LENGTH)).map_err(file_error)?;
+    file.read_to_string(&mut line).map_err(file_error)?;
+    match parse(&line) {
+        Some((timestamp, hash)) => Ok((entries as usize, Some((timestamp.to_string(), hash.to_string())))),
+        None => Err(PriceError::ParseError(format!("{}: the last line is not a chain entry", path.display()))),
+    }
+}
+
+fn parse(line: &str) -> Option<(&str, &str)> {
+    let (timestamp, hash) = line.trim_end().split_once(',')?;
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then_some((timestamp, hash))
+}
+
+// Writes the chain of `records` through a temporary sibling, returning how
+// many there were and the head.
+fn write_chain<R: Borrow<PriceRecord>>(
+    path: &Path,
+    asset: &str,
+    records: impl IntoIterator<Item = Result<R, PriceError>>,
+) -> Result<(usize, String), PriceError> {
+    let tmp_path = path.with_extension("csv.tmp");
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", tmp_path.display(), e));
+    let mut file = BufWriter::new(File::create(&tmp_path).map_err(file_error)?);
+    writeln!(file, "{}", HEADER).map_err(file_error)?;
+    let (mut count, mut previous) = (0, GENESIS.to_string());
+    for record in records {
+        let record = record?;
+        previous = hash(&previous, asset, record.borrow());
+        writeln!(file, "{}", entry(record.borrow(), &previous)).map_err(file_error)?;
+        count += 1;
+    }
+    file.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .map_err(file_error)?;
+    fs::rename(&tmp_path, path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    Ok((count, previous))
+}
+
+pub enum Verdict {
+    Intact { records: usize, head: String },
+    // What the first break in the chain looks like.
+    Broken(String),
+    Unchained,
+}
+
+// Replays the chain of `asset` over its history in `storage`, stopping at
+// the first record that does not match.
+pub fn verify(storage: &dyn Storage, directory: &Path, asset: &str) -> Result<Verdict, PriceError> {
+    let path = path_for(directory, asset);
+    if !path.exists() {
+        return Ok(Verdict::Unchained);
+    }
+    let file = File::open(&path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    let mut lines = BufReader::new(file).lines().enumerate().filter(|(_, line)| {
+        line.as_ref().map_or(true, |line| !line.trim().is_empty())
+    });
+    let mut entries = std::iter::from_fn(move || {
+        let (index, line) = lines.next()?;
+        let invalid =
+            |reason: &str| PriceError::ParseError(format!("{} line {}: {}", path.display(), index + 1, reason));
+        Some(match line {
+            Ok(line) if index == 0 => (line == HEADER).then(Default::default).ok_or_else(|| invalid("not a chain")),
+            Ok(line) => parse(&line)
+                .map(|(timestamp, hash)| (timestamp.to_string(), hash.to_string()))
+                .ok_or_else(|| invalid("not a chain entry")),
+            Err(e) => Err(invalid(&e.to_string())),
+        })
+    });
+    entries.next().transpose()?;
+
+    let mut records = storage.scan(asset, None)?;
+    let (mut count, mut previous) = (0, GENESIS.to_string());
+    loop {
+        let (record, expected) = match (records.next().transpose()?, entries.next().transpose()?) {
+            (None, None) => return Ok(Verdict::Intact { records: count, head: previous }),
+            (Some(record), None) => {
+                return Ok(Verdict::Broken(format!(
+                    "{} record(s) from {} on are not in the chain",
+                    1 + records.count(),
+                    record.timestamp.format(TIMESTAMP_FORMAT)
+                )))
+            }
+            (None, Some((timestamp, _))) => {
+                return Ok(Verdict::Broken(format!(
+                    "{} record(s) from {} on were deleted",
+                    1 + entries.count(),
+                    timestamp
+                )))
+            }
+            (Some(record), Some(expected)) => (record, expected),
+        };
+        let actual = hash(&previous, asset, &record);
+        if actual != expected.1 {
+            let timestamp = record.timestamp.format(TIMESTAMP_FORMAT).to_string();
+            return Ok(Verdict::Broken(match timestamp.cmp(&expected.0) {
+                Ordering::Equal => format!("the record at {} was edited", timestamp),
+                Ordering::Greater => format!("the record at {} was deleted", expected.0),
+                Ordering::Less => format!("a record at {} was inserted", timestamp),
+            }));
+        }
+        previous = actual;
+        count += 1;
+    }
+}
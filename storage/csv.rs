@@ -0,0 +1,633 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+
+use super::{
+    index, is_extra_column, AlertEvent, PriceRecord, Records, Storage, FX_PREFIX, RELATIVE_PREFIX, SCHEMA_VERSION,
+    SEQUENCE_COLUMN, SOURCE_TIME_COLUMN, TIMESTAMP_FORMAT,
+};
+use crate::PriceError;
+
+pub const HEADER: &str = "timestamp,price,volume,market_cap";
+const VERSION_MARKER: &str = "# schema_version=";
+const ALERT_LOG_FILE: &str = "alert_log.csv";
+const ALERT_LOG_HEADER: &str = "timestamp,rule,asset,value,outcome,message";
+
+pub struct CsvStorage {
+    directory: PathBuf,
+    // Per-currency columns in the header of each file already checked this
+    // run.
+    current: HashMap<String, Vec<String>>,
+}
+
+impl CsvStorage {
+    pub fn new(directory: &Path) -> Self {
+        CsvStorage { directory: directory.to_path_buf(), current: HashMap::new() }
+    }
+
+    pub fn path_for(&self, asset: &str) -> PathBuf {
+        self.directory.join(format!("{}_prices.csv", asset))
+    }
+
+    pub fn alert_log_path(&self) -> PathBuf {
+        self.directory.join(ALERT_LOG_FILE)
+    }
+
+    // Rewrites files written by older releases in the current format before
+    // anything new is appended to them, and returns the file's per-currency
+    // columns.
+    fn ensure_current(&mut self, asset: &str) -> Result<Vec<String>, PriceError> {
+        if let Some(columns) = self.current.get(asset) {
+            return Ok(columns.clone());
+        }
+
+        let path = self.path_for(asset);
+        let mut columns = Vec::new();
+        if path.exists() {
+            repair_tail(&path)?;
+            let version = schema_version(&path)?;
+            if version < SCHEMA_VERSION {
+                let records = read_records(&path)?;
+                write_records(&path, &records)?;
+                println!(
+                    "Upgraded {} from schema v{} to v{}",
+                    path.display(),
+                    version,
+                    SCHEMA_VERSION
+                );
+            }
+            columns = CsvReader::open(&path)?.extra_columns();
+        }
+
+        self.current.insert(asset.to_string(), columns.clone());
+        Ok(columns)
+    }
+}
+
+impl Storage for CsvStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        let mut columns = self.ensure_current(asset)?;
+        let path = self.path_for(asset);
+
+        // A newly configured currency needs its own column, so the file is
+        // rewritten with a wider header once.
+        if path.exists() && record.extra_columns().iter().any(|(c, _)| !columns.contains(c)) {
+            let mut records = read_records(&path)?;
+            records.push(record.clone());
+            write_records(&path, &records)?;
+            self.current.insert(asset.to_string(), extra_columns(&records));
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+
+        let mut data = String::new();
+        let is_empty = file
+            .metadata()
+            .map(|m| m.len() == 0)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        if is_empty {
+            columns = extra_columns(std::slice::from_ref(record));
+            data.push_str(&preamble(&columns));
+            self.current.insert(asset.to_string(), columns.clone());
+        }
+        data.push_str(&format_line(record, &columns));
+        data.push('\n');
+
+        file.write_all(data.as_bytes())
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        let path = self.path_for(asset);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        read_records(&path)
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        let path = self.path_for(asset);
+        if !path.exists() {
+            return Ok(Box::new(std::iter::empty()));
+        }
+        let mut reader = CsvReader::open(&path)?;
+        let Some(from) = from else {
+            return Ok(Box::new(reader));
+        };
+        if let Some((position, line_number)) = index::start_of(&path, from)? {
+            reader.seek(position, line_number)?;
+        }
+        Ok(Box::new(reader.skip_while(move |record| record.as_ref().is_ok_and(|record| record.timestamp < from))))
+    }
+
+    fn last(&self, asset: &str) -> Result<Option<PriceRecord>, PriceError> {
+        let path = self.path_for(asset);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut reader = CsvReader::open(&path)?;
+        if let Some((position, line_number)) = index::last_day(&path)? {
+            reader.seek(position, line_number)?;
+        }
+        reader.last().transpose()
+    }
+
+    fn recover(&mut self, asset: &str) -> Result<(), PriceError> {
+        let path = self.path_for(asset);
+        if path.exists() {
+            repair_tail(&path)?;
+        }
+        Ok(())
+    }
+
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        write_records(&self.path_for(asset), records)?;
+        self.current.insert(asset.to_string(), extra_columns(records));
+        Ok(())
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        let path = self.alert_log_path();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+
+        let mut data = String::new();
+        let is_empty = file
+            .metadata()
+            .map(|m| m.len() == 0)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        if is_empty {
+            data.push_str(ALERT_LOG_HEADER);
+            data.push('\n');
+        }
+        data.push_str(&format_alert_line(event));
+        data.push('\n');
+
+        file.write_all(data.as_bytes())
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        let path = self.alert_log_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = File::open(&path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+
+        let mut events = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+            if line.trim().is_empty() || (index == 0 && line.trim() == ALERT_LOG_HEADER) {
+                continue;
+            }
+            let event = parse_alert_line(&line)
+                .map_err(|e| PriceError::ParseError(format!("{} line {}: {}", path.display(), index + 1, e)))?;
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+// A crash mid-append can leave the last line without its newline. Even one
+// that parses may have lost digits from its last column, and appending to
+// it would spoil the next record too, so it is cut off.
+fn repair_tail(path: &Path) -> Result<(), PriceError> {
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut file = OpenOptions::new().read(true).write(true).open(path).map_err(file_error)?;
+    let length = file.metadata().map_err(file_error)?.len();
+    let start = length.saturating_sub(READ_BUFFER as u64);
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(start)).map_err(file_error)?;
+    file.read_to_end(&mut tail).map_err(file_error)?;
+    if tail.is_empty() || tail.ends_with(b"\n") {
+        return Ok(());
+    }
+    let cut = tail.iter().rposition(|byte| *byte == b'\n').map_or(0, |newline| newline + 1);
+    file.set_len(start + cut as u64).map_err(file_error)?;
+    eprintln!(
+        "Dropped the incomplete last line of {}: '{}'",
+        path.display(),
+        String::from_utf8_lossy(&tail[cut..])
+    );
+    // Its positions may no longer match.
+    index::remove(path);
+    Ok(())
+}
+
+pub fn format_alert_line(event: &AlertEvent) -> String {
+    format!(
+        "{},{},{},{},{},{}",
+        event.timestamp.format(TIMESTAMP_FORMAT),
+        quote(&event.rule),
+        quote(&event.asset),
+        event.value,
+        quote(&event.outcome),
+        quote(&event.message)
+    )
+}
+
+pub fn parse_alert_line(line: &str) -> Result<AlertEvent, String> {
+    let fields = split_quoted(line)?;
+    let [timestamp, rule, asset, value, outcome, message] = <[String; 6]>::try_from(fields)
+        .map_err(|fields| format!("expected 6 columns, found {} in '{}'", fields.len(), line))?;
+    Ok(AlertEvent {
+        timestamp: NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT)
+            .map_err(|e| format!("invalid timestamp '{}': {}", timestamp, e))?,
+        rule,
+        asset,
+        value: value.parse().map_err(|e| format!("invalid value '{}': {}", value, e))?,
+        message,
+        outcome,
+    })
+}
+
+// Minimal CSV quoting for the free-text columns of the alert log.
+pub fn quote(field: &str) -> String {
+    let field = field.replace(['\r', '\n'], " ");
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn split_quoted(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("unterminated quote in '{}'", line));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn preamble(columns: &[String]) -> String {
+    format!("{}{}\n{}\n", VERSION_MARKER, SCHEMA_VERSION, header(columns))
+}
+
+fn header(columns: &[String]) -> String {
+    let mut header = HEADER.to_string();
+    for column in columns {
+        header.push(',');
+        header.push_str(column);
+    }
+    header
+}
+
+// Every per-currency column used by `records`, sorted.
+fn extra_columns(records: &[PriceRecord]) -> Vec<String> {
+    let columns: BTreeSet<String> =
+        records.iter().flat_map(|r| r.extra_columns()).map(|(column, _)| column).collect();
+    columns.into_iter().collect()
+}
+
+// Files without a marker line predate versioning and are v1.
+pub fn schema_version(path: &Path) -> Result<u32, PriceError> {
+    let file = File::open(path)
+        .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    let mut first = String::new();
+    BufReader::new(file)
+        .read_line(&mut first)
+        .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    parse_marker(&first)
+        .unwrap_or(Ok(1))
+        .map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+fn parse_marker(line: &str) -> Option<Result<u32, String>> {
+    let version = line.trim().strip_prefix(VERSION_MARKER)?;
+    Some(
+        version
+            .parse::<u32>()
+            .map_err(|_| format!("invalid schema marker '{}'", line.trim())),
+    )
+}
+
+// Column positions resolved from a file's header, so v1 and later files are
+// read through the same code path.
+struct Layout {
+    width: usize,
+    timestamp: usize,
+    price: usize,
+    volume: Option<usize>,
+    market_cap: Option<usize>,
+    extra: Vec<(String, usize)>,
+}
+
+impl Layout {
+    fn from_header(header: &str) -> Result<Self, String> {
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        let position = |name: &str| columns.iter().position(|c| *c == name);
+
+        let mut extra = Vec::new();
+        for (index, column) in columns.iter().enumerate() {
+            if is_extra_column(column) {
+                extra.push((column.to_string(), index));
+            } else if !["timestamp", "price", "volume", "market_cap"].contains(column) {
+                return Err(format!("unknown column '{}' in header '{}'", column, header));
+            }
+        }
+
+        Ok(Layout {
+            width: columns.len(),
+            timestamp: position("timestamp")
+                .ok_or_else(|| format!("header '{}' has no timestamp column", header))?,
+            price: position("price")
+                .ok_or_else(|| format!("header '{}' has no price column", header))?,
+            volume: position("volume"),
+            market_cap: position("market_cap"),
+            extra,
+        })
+    }
+
+    fn parse(&self, line: &str) -> Result<PriceRecord, String> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != self.width {
+            return Err(format!("expected {} columns, found {} in '{}'", self.width, fields.len(), line));
+        }
+
+        let timestamp = NaiveDateTime::parse_from_str(fields[self.timestamp], TIMESTAMP_FORMAT)
+            .map_err(|e| format!("invalid timestamp '{}': {}", fields[self.timestamp], e))?;
+
+        let mut record = PriceRecord::new(timestamp, parse_number(fields[self.price], "price")?);
+        record.volume = optional_number(&fields, self.volume, "volume")?;
+        record.market_cap = optional_number(&fields, self.market_cap, "market_cap")?;
+        for (column, index) in &self.extra {
+            if let Some(value) = optional_number(&fields, Some(*index), column)? {
+                record.set_extra_column(column, value);
+            }
+        }
+        Ok(record)
+    }
+}
+
+// Funding rates and changes go below zero, so only non-finite numbers are
+// turned away.
+fn parse_number(value: &str, column: &str) -> Result<f64, String> {
+    let number = value
+        .parse::<f64>()
+        .map_err(|e| format!("invalid {} '{}': {}", column, value, e))?;
+    if !number.is_finite() {
+        return Err(format!("invalid {} '{}'", column, value));
+    }
+    Ok(number)
+}
+
+fn optional_number(fields: &[&str], index: Option<usize>, column: &str) -> Result<Option<f64>, String> {
+    match index.map(|i| fields[i]) {
+        None | Some("") => Ok(None),
+        Some(value) => parse_number(value, column).map(Some),
+    }
+}
+
+// Cents where they are exact, so most lines read as they always have, and
+// the shortest form that reads back the same otherwise: display precision
+// is no use for coins worth a fraction of a cent.
+fn format_number(value: f64) -> String {
+    let cents = format!("{:.2}", value);
+    if cents.parse::<f64>() == Ok(value) {
+        cents
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_cents(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+pub fn format_line(record: &PriceRecord, columns: &[String]) -> String {
+    write_line(record, columns, format_number)
+}
+
+// The line as it was written before prices were kept in full, which hash
+// chains go on being computed over.
+pub fn format_line_to_cent(record: &PriceRecord, columns: &[String]) -> String {
+    write_line(record, columns, format_cents)
+}
+
+fn write_line(record: &PriceRecord, columns: &[String], number: fn(f64) -> String) -> String {
+    let format_optional = |value: Option<f64>| value.map(number).unwrap_or_default();
+    let mut line = format!(
+        "{},{},{},{}",
+        record.timestamp.format(TIMESTAMP_FORMAT),
+        number(record.price),
+        format_optional(record.volume),
+        format_optional(record.market_cap)
+    );
+    let values = record.extra_columns();
+    for column in columns {
+        line.push(',');
+        let value = values.iter().find(|(c, _)| c == column).map(|(_, v)| *v);
+        // Exchange rates and prices in BTC or ETH terms need more than cents.
+        match value {
+            Some(rate) if column.starts_with(FX_PREFIX) => line.push_str(&format!("{:.6}", rate)),
+            Some(price) if column.starts_with(RELATIVE_PREFIX) => line.push_str(&format!("{:.8}", price)),
+            Some(whole) if column == SEQUENCE_COLUMN || column == SOURCE_TIME_COLUMN => {
+                line.push_str(&format!("{:.0}", whole))
+            }
+            _ => line.push_str(&format_optional(value)),
+        }
+    }
+    line
+}
+
+// What write_records would write, for sending a history elsewhere.
+pub fn format_records(records: &[PriceRecord]) -> String {
+    let columns = extra_columns(records);
+    let mut text = preamble(&columns);
+    for record in records {
+        text.push_str(&format_line(record, &columns));
+        text.push('\n');
+    }
+    text
+}
+
+// One record on its own, its header then its line, for backends that store
+// records one at a time.
+pub fn format_record(record: &PriceRecord) -> String {
+    let columns = extra_columns(std::slice::from_ref(record));
+    format!("{}\n{}", header(&columns), format_line(record, &columns))
+}
+
+pub fn parse_record(text: &str) -> Result<PriceRecord, String> {
+    let (header, line) = text.split_once('\n').ok_or_else(|| format!("no header in '{}'", text))?;
+    Layout::from_header(header)?.parse(line)
+}
+
+// Read size for history files; a line is well under 200 bytes.
+const READ_BUFFER: usize = 64 * 1024;
+
+// Yields records one line at a time so callers can stream files of any size.
+pub struct CsvReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+    layout: Option<Layout>,
+    line_number: usize,
+    // Bytes read so far, where the next line starts.
+    position: u64,
+}
+
+impl CsvReader {
+    pub fn open(path: &Path) -> Result<Self, PriceError> {
+        let file = File::open(path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        let mut reader = CsvReader {
+            path: path.to_path_buf(),
+            reader: BufReader::with_capacity(READ_BUFFER, file),
+            layout: None,
+            line_number: 0,
+            position: 0,
+        };
+
+        let mut first = reader.next_line()?;
+        if let Some(marker) = first.as_deref().and_then(parse_marker) {
+            let version = marker.map_err(|e| reader.context(e))?;
+            if version > SCHEMA_VERSION {
+                return Err(reader.context(format!(
+                    "schema v{} is newer than this tracker supports (v{})",
+                    version, SCHEMA_VERSION
+                )));
+            }
+            first = reader.next_line()?;
+        }
+
+        if let Some(header) = first {
+            reader.layout = Some(Layout::from_header(&header).map_err(|e| reader.context(e))?);
+        }
+        Ok(reader)
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, PriceError> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", self.path.display(), e)))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.position += read as u64;
+        self.line_number += 1;
+        let end = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(end);
+        Ok(Some(line))
+    }
+
+    // Continues from `position`, the start of the line after `line_number`,
+    // as returned by `next_located`.
+    pub fn seek(&mut self, position: u64, line_number: usize) -> Result<(), PriceError> {
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .map_err(|e| PriceError::FileError(format!("{}: {}", self.path.display(), e)))?;
+        self.position = position;
+        self.line_number = line_number;
+        Ok(())
+    }
+
+    // The next record with where its line starts, as a position and the
+    // number of the line before it.
+    pub fn next_located(&mut self) -> Option<Result<(u64, usize, PriceRecord), PriceError>> {
+        self.layout.as_ref()?;
+        loop {
+            let (position, line_number) = (self.position, self.line_number);
+            let line = match self.next_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = self.layout.as_ref()?.parse(&line).map_err(|e| self.context(e));
+            return Some(record.map(|record| (position, line_number, record)));
+        }
+    }
+
+    // Where the next line starts, as for `seek`.
+    pub fn position(&self) -> (u64, usize) {
+        (self.position, self.line_number)
+    }
+
+    // Per-currency columns named in the file's header.
+    pub fn extra_columns(&self) -> Vec<String> {
+        self.layout
+            .as_ref()
+            .map(|layout| layout.extra.iter().map(|(column, _)| column.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    fn context(&self, message: String) -> PriceError {
+        PriceError::ParseError(format!("{} line {}: {}", self.path.display(), self.line_number, message))
+    }
+}
+
+impl Iterator for CsvReader {
+    type Item = Result<PriceRecord, PriceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_located().map(|located| located.map(|(_, _, record)| record))
+    }
+}
+
+pub fn read_records(path: &Path) -> Result<Vec<PriceRecord>, PriceError> {
+    CsvReader::open(path)?.collect()
+}
+
+// Rewrites the whole file through a temporary sibling so a crash never
+// leaves a half-written history behind.
+pub fn write_records(path: &Path, records: &[PriceRecord]) -> Result<(), PriceError> {
+    write_stream(path, &extra_columns(records), records.iter().map(Ok))
+}
+
+// Like write_records, for records that need not all be in memory; `columns`
+// must cover every per-currency value of `records`.
+pub fn write_stream<R: Borrow<PriceRecord>>(
+    path: &Path,
+    columns: &[String],
+    records: impl IntoIterator<Item = Result<R, PriceError>>,
+) -> Result<(), PriceError> {
+    let tmp_path = path.with_extension("csv.tmp");
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", tmp_path.display(), e));
+    let mut file = BufWriter::new(File::create(&tmp_path).map_err(file_error)?);
+    file.write_all(preamble(columns).as_bytes()).map_err(file_error)?;
+    for record in records {
+        writeln!(file, "{}", format_line(record?.borrow(), columns)).map_err(file_error)?;
+    }
+    file.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|file| file.sync_all())
+        .map_err(file_error)?;
+
+    fs::rename(&tmp_path, path)
+        .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    // Its positions no longer match.
+    index::remove(path);
+    Ok(())
+}
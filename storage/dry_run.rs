@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+
+use super::{AlertEvent, PriceRecord, Records, Storage, TIMESTAMP_FORMAT};
+use crate::PriceError;
+
+// Serves reads from the real backend so alerts see their history, and prints
+// every write instead of performing it.
+pub struct DryRunStorage {
+    inner: Option<Box<dyn Storage>>,
+}
+
+impl DryRunStorage {
+    pub fn new(inner: Option<Box<dyn Storage>>) -> Self {
+        DryRunStorage { inner }
+    }
+}
+
+impl Storage for DryRunStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        let columns: Vec<String> = record
+            .extra_columns()
+            .into_iter()
+            .map(|(column, value)| format!("{}={}", column, value))
+            .collect();
+        println!(
+            "[dry run] would append to {}: {} price={}{}{}{}",
+            asset,
+            record.timestamp.format(TIMESTAMP_FORMAT),
+            record.price,
+            record.volume.map(|v| format!(" volume={}", v)).unwrap_or_default(),
+            record.market_cap.map(|v| format!(" market_cap={}", v)).unwrap_or_default(),
+            if columns.is_empty() { String::new() } else { format!(" {}", columns.join(" ")) }
+        );
+        Ok(())
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        match &self.inner {
+            Some(inner) => inner.read(asset),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        match &self.inner {
+            Some(inner) => inner.scan(asset, from),
+            None => Ok(Box::new(std::iter::empty())),
+        }
+    }
+
+    fn last(&self, asset: &str) -> Result<Option<PriceRecord>, PriceError> {
+        match &self.inner {
+            Some(inner) => inner.last(asset),
+            None => Ok(None),
+        }
+    }
+
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        println!("[dry run] would replace the history of {} with {} records", asset, records.len());
+        Ok(())
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        println!("[dry run] would log alert '{}' for {} [{}]", event.rule, event.asset, event.outcome);
+        Ok(())
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        match &self.inner {
+            Some(inner) => inner.alert_log(),
+            None => Ok(Vec::new()),
+        }
+    }
+}
@@ -0,0 +1,322 @@
+// Histories and the alert log sealed with ChaCha20-Poly1305, for data kept on
+// shared machines, in `<asset>_prices.enc` and `alert_log.enc` under a key
+// kept in a file of its own, written by `keygen`. Each file starts with
+// "CPTE" and a format version (u8), then holds one frame per record or
+// alert:
+//
+//   length of the rest (u32), a random 12-byte nonce, the sealed CSV text
+//
+// so appending never rewrites what is there. Frames are bound to the name
+// of their file and do not open in another, but not to their place in it:
+// a frame taken out, or frames put in another order, go unnoticed. The
+// encryption keeps the history from being read, not rearranged.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use chrono::NaiveDateTime;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use super::csv::{format_alert_line, format_record, parse_alert_line, parse_record};
+use super::{AlertEvent, PriceRecord, Records, Storage};
+use crate::config::secrets::check_permissions;
+use crate::PriceError;
+
+const MAGIC: &[u8; 4] = b"CPTE";
+const FORMAT_VERSION: u8 = 1;
+const KEY_LENGTH: usize = 32;
+// Far more than a record or an alert needs, and checked before a frame is
+// read, so a damaged length cannot ask for gigabytes.
+const MAX_FRAME_LENGTH: usize = 1 << 20;
+const ALERT_LOG_FILE: &str = "alert_log.enc";
+
+pub struct EncryptedStorage {
+    directory: PathBuf,
+    key: LessSafeKey,
+    random: SystemRandom,
+    // Files already checked for a frame cut short this run.
+    checked: HashSet<PathBuf>,
+}
+
+impl EncryptedStorage {
+    pub fn open(directory: &Path, key_file: &Path) -> Result<Self, PriceError> {
+        let key = read_key(key_file)?;
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+            .map_err(|_| PriceError::ParseError(format!("{}: not a ChaCha20-Poly1305 key", key_file.display())))?;
+        Ok(EncryptedStorage {
+            directory: directory.to_path_buf(),
+            key: LessSafeKey::new(key),
+            random: SystemRandom::new(),
+            checked: HashSet::new(),
+        })
+    }
+
+    pub fn path_for(&self, asset: &str) -> PathBuf {
+        self.directory.join(format!("{}_prices.enc", asset))
+    }
+
+    fn seal(&self, path: &Path, text: &str) -> Result<Vec<u8>, PriceError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.random
+            .fill(&mut nonce)
+            .map_err(|_| PriceError::Internal("no randomness for a nonce".to_string()))?;
+        let mut sealed = text.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), aad(path), &mut sealed)
+            .map_err(|_| PriceError::Internal(format!("{}: could not seal a frame", path.display())))?;
+        let length = u32::try_from(NONCE_LEN + sealed.len())
+            .ok()
+            .filter(|length| *length as usize <= MAX_FRAME_LENGTH)
+            .ok_or_else(|| PriceError::Internal(format!("{}: frame too long", path.display())))?;
+        let mut frame = length.to_le_bytes().to_vec();
+        frame.extend_from_slice(&nonce);
+        frame.extend_from_slice(&sealed);
+        Ok(frame)
+    }
+
+    // Appends a frame, first dropping what is left of one cut short by a
+    // crash.
+    fn append_frame(&mut self, path: &Path, text: &str) -> Result<(), PriceError> {
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+        let frame = self.seal(path, text)?;
+        if !path.exists() {
+            let mut data = intro();
+            data.extend_from_slice(&frame);
+            return fs::write(path, data).map_err(file_error);
+        }
+        if !self.checked.contains(path) {
+            let end = Frames::open(path, &self.key)?.whole_length()?;
+            let file = OpenOptions::new().write(true).open(path).map_err(file_error)?;
+            file.set_len(end).map_err(file_error)?;
+            self.checked.insert(path.to_path_buf());
+        }
+        let mut file = OpenOptions::new().append(true).open(path).map_err(file_error)?;
+        file.write_all(&frame).map_err(file_error)
+    }
+
+    fn frames(&self, path: &Path) -> Result<Option<Frames<'_>>, PriceError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Frames::open(path, &self.key).map(Some)
+    }
+}
+
+impl Storage for EncryptedStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        self.append_frame(&self.path_for(asset), &format_record(record))
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        self.scan(asset, None)?.collect()
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        let path = self.path_for(asset);
+        let Some(frames) = self.frames(&path)? else {
+            return Ok(Box::new(std::iter::empty()));
+        };
+        let records = frames.map(move |text| {
+            text.and_then(|(number, text)| {
+                parse_record(&text)
+                    .map_err(|e| PriceError::ParseError(format!("{} frame {}: {}", path.display(), number, e)))
+            })
+        });
+        Ok(Box::new(records.filter(move |record| {
+            record.as_ref().map_or(true, |record| from.is_none_or(|from| record.timestamp >= from))
+        })))
+    }
+
+    // Through a temporary sibling, as for the other backends.
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        let path = self.path_for(asset);
+        let tmp_path = path.with_extension("enc.tmp");
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", tmp_path.display(), e));
+        let mut file = BufWriter::new(File::create(&tmp_path).map_err(file_error)?);
+        file.write_all(&intro()).map_err(file_error)?;
+        for record in records {
+            file.write_all(&self.seal(&path, &format_record(record))?).map_err(file_error)?;
+        }
+        file.into_inner()
+            .map_err(|e| e.into_error())
+            .and_then(|file| file.sync_all())
+            .map_err(file_error)?;
+        fs::rename(&tmp_path, &path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        self.checked.insert(path);
+        Ok(())
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        self.append_frame(&self.directory.join(ALERT_LOG_FILE), &format_alert_line(event))
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        let path = self.directory.join(ALERT_LOG_FILE);
+        let Some(frames) = self.frames(&path)? else {
+            return Ok(Vec::new());
+        };
+        frames
+            .map(|text| {
+                text.and_then(|(number, text)| {
+                    parse_alert_line(&text)
+                        .map_err(|e| PriceError::ParseError(format!("{} frame {}: {}", path.display(), number, e)))
+                })
+            })
+            .collect()
+    }
+}
+
+fn intro() -> Vec<u8> {
+    let mut intro = MAGIC.to_vec();
+    intro.push(FORMAT_VERSION);
+    intro
+}
+
+fn aad(path: &Path) -> Aad<Vec<u8>> {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    Aad::from(name.into_bytes())
+}
+
+// Opens each frame of a file in turn, with its number, and stops at the
+// end of the last whole one.
+struct Frames<'a> {
+    path: PathBuf,
+    reader: BufReader<File>,
+    key: &'a LessSafeKey,
+    number: usize,
+    done: bool,
+}
+
+impl<'a> Frames<'a> {
+    fn open(path: &Path, key: &'a LessSafeKey) -> Result<Self, PriceError> {
+        let invalid = |reason: &str| PriceError::ParseError(format!("{}: {}", path.display(), reason));
+        let file = File::open(path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        let mut reader = BufReader::with_capacity(64 * 1024, file);
+        let mut intro = [0u8; 5];
+        reader.read_exact(&mut intro).map_err(|_| invalid("not an encrypted history"))?;
+        if &intro[..4] != MAGIC {
+            return Err(invalid("not an encrypted history"));
+        }
+        if intro[4] > FORMAT_VERSION {
+            return Err(invalid(&format!(
+                "format v{} is newer than this tracker supports (v{})",
+                intro[4], FORMAT_VERSION
+            )));
+        }
+        Ok(Frames { path: path.to_path_buf(), reader, key, number: 0, done: false })
+    }
+
+    fn file_error(&self, e: std::io::Error) -> PriceError {
+        PriceError::FileError(format!("{}: {}", self.path.display(), e))
+    }
+
+    // The next frame still sealed, or None at the end or a frame cut short.
+    fn next_sealed(&mut self) -> Result<Option<Vec<u8>>, PriceError> {
+        let mut length = [0u8; 4];
+        match self.reader.read_exact(&mut length) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(self.file_error(e)),
+        }
+        let length = u32::from_le_bytes(length) as usize;
+        if length > MAX_FRAME_LENGTH {
+            return Err(PriceError::ParseError(format!(
+                "{}: frame {} claims {} bytes, more than a frame holds; the file was altered",
+                self.path.display(),
+                self.number + 1,
+                length
+            )));
+        }
+        let mut frame = vec![0u8; length];
+        match self.reader.read_exact(&mut frame) {
+            Ok(()) if frame.len() > NONCE_LEN => Ok(Some(frame)),
+            Ok(()) => Err(PriceError::ParseError(format!("{}: frame {} is empty", self.path.display(), self.number))),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(self.file_error(e)),
+        }
+    }
+
+    // Where the last whole frame ends.
+    fn whole_length(mut self) -> Result<u64, PriceError> {
+        let mut end = MAGIC.len() as u64 + 1;
+        while let Some(frame) = self.next_sealed()? {
+            end += 4 + frame.len() as u64;
+        }
+        Ok(end)
+    }
+
+    fn open_next(&mut self) -> Result<Option<(usize, String)>, PriceError> {
+        let Some(mut frame) = self.next_sealed()? else {
+            return Ok(None);
+        };
+        self.number += 1;
+        let nonce = Nonce::try_assume_unique_for_key(&frame[..NONCE_LEN])
+            .map_err(|_| PriceError::Internal("invalid nonce length".to_string()))?;
+        let text = self.key.open_in_place(nonce, aad(&self.path), &mut frame[NONCE_LEN..]).map_err(|_| {
+            PriceError::ParseError(format!(
+                "{} frame {}: cannot be decrypted, the key is wrong or the file was altered",
+                self.path.display(),
+                self.number
+            ))
+        })?;
+        let text = String::from_utf8(text.to_vec())
+            .map_err(|_| PriceError::ParseError(format!("{} frame {}: not text", self.path.display(), self.number)))?;
+        Ok(Some((self.number, text)))
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<(usize, String), PriceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let frame = self.open_next().transpose();
+        self.done = !matches!(frame, Some(Ok(_)));
+        frame
+    }
+}
+
+fn read_key(path: &Path) -> Result<Vec<u8>, PriceError> {
+    if !path.exists() {
+        return Err(PriceError::FileError(format!(
+            "key file {} does not exist; create one with `keygen`",
+            path.display()
+        )));
+    }
+    check_permissions(path)?;
+    let text = fs::read_to_string(path).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+    let key = base64::engine::general_purpose::STANDARD
+        .decode(text.trim())
+        .map_err(|e| PriceError::ParseError(format!("{}: not a base64 key: {}", path.display(), e)))?;
+    if key.len() != KEY_LENGTH {
+        return Err(PriceError::ParseError(format!(
+            "{}: expected a {}-byte key, found {} bytes",
+            path.display(),
+            KEY_LENGTH,
+            key.len()
+        )));
+    }
+    Ok(key)
+}
+
+// Writes a new random key readable by its owner only; an existing key is
+// never replaced, as what it sealed would be lost.
+pub fn write_key(path: &Path) -> Result<(), PriceError> {
+    let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", path.display(), e));
+    let mut key = [0u8; KEY_LENGTH];
+    SystemRandom::new()
+        .fill(&mut key)
+        .map_err(|_| PriceError::Internal("no randomness for a key".to_string()))?;
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(file_error)?;
+    writeln!(file, "{}", base64::engine::general_purpose::STANDARD.encode(key)).map_err(file_error)
+}
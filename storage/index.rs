@@ -0,0 +1,121 @@
+// A sidecar to each CSV history, `<asset>_prices.csv.idx`, holding the time
+// span of the file and where each day's records start, so reads of a recent
+// range seek straight to it instead of parsing months of history first.
+// Records appended since the index was saved are added to it on the next
+// ranged read; rewriting the file deletes it.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::csv::CsvReader;
+use super::TIMESTAMP_FORMAT;
+use crate::PriceError;
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    // How much of the file is indexed, in bytes and lines.
+    length: u64,
+    lines: usize,
+    first: Option<String>,
+    last: Option<String>,
+    // Position and preceding line number of each day's first record, by
+    // day; the format sorts like the dates.
+    days: BTreeMap<String, (u64, usize)>,
+}
+
+pub fn path_for(history: &Path) -> PathBuf {
+    history.with_extension("csv.idx")
+}
+
+pub fn remove(history: &Path) {
+    let _ = fs::remove_file(path_for(history));
+}
+
+// Where to start reading `history` for records from `from` on: the start of
+// the latest day up to `from`, or None to read from the top.
+pub fn start_of(history: &Path, from: NaiveDateTime) -> Result<Option<(u64, usize)>, PriceError> {
+    let index = update(history)?;
+    if index.last.as_deref().is_some_and(|last| last < from.format(TIMESTAMP_FORMAT).to_string().as_str()) {
+        return Ok(Some((index.length, index.lines)));
+    }
+    let day = from.format(DAY_FORMAT).to_string();
+    Ok(index.days.range(..=day).next_back().map(|(_, start)| *start))
+}
+
+// Where the last day's records start, or None for an empty history.
+pub fn last_day(history: &Path) -> Result<Option<(u64, usize)>, PriceError> {
+    Ok(update(history)?.days.values().next_back().copied())
+}
+
+// The saved index with anything appended since added, or a new one if it
+// does not match the file any more.
+fn update(history: &Path) -> Result<Index, PriceError> {
+    let length = fs::metadata(history)
+        .map_err(|e| PriceError::FileError(format!("{}: {}", history.display(), e)))?
+        .len();
+    let mut index = load(history).filter(|index| matches(history, index, length)).unwrap_or_default();
+    if index.length == length {
+        return Ok(index);
+    }
+
+    let mut reader = CsvReader::open(history)?;
+    if index.length > 0 {
+        reader.seek(index.length, index.lines)?;
+    }
+    while let Some(located) = reader.next_located() {
+        let (position, line_number, record) = located?;
+        let timestamp = record.timestamp.format(TIMESTAMP_FORMAT).to_string();
+        let day = record.timestamp.format(DAY_FORMAT).to_string();
+        index.days.entry(day).or_insert((position, line_number));
+        index.first.get_or_insert_with(|| timestamp.clone());
+        index.last = Some(timestamp);
+    }
+    let (length, lines) = reader.position();
+    index.length = length;
+    index.lines = lines;
+    save(history, &index);
+    Ok(index)
+}
+
+fn load(history: &Path) -> Option<Index> {
+    let body = fs::read_to_string(path_for(history)).ok()?;
+    serde_json::from_str(&body).ok()
+}
+
+// Appends only add whole lines after the indexed part, which must still end
+// where a line does.
+fn matches(history: &Path, index: &Index, length: u64) -> bool {
+    if index.length > length {
+        return false;
+    }
+    if index.length == 0 {
+        return true;
+    }
+    let mut last = [0u8];
+    File::open(history)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::Start(index.length - 1))?;
+            file.read_exact(&mut last)
+        })
+        .is_ok_and(|_| last[0] == b'\n')
+}
+
+// The index only saves time, so failing to write it is not an error.
+fn save(history: &Path, index: &Index) {
+    let path = path_for(history);
+    let tmp_path = path.with_extension("idx.tmp");
+    let written = serde_json::to_string(index)
+        .map_err(|e| e.to_string())
+        .and_then(|body| fs::write(&tmp_path, body).map_err(|e| e.to_string()))
+        .and_then(|_| fs::rename(&tmp_path, &path).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Warning: could not save {}: {}", path.display(), e);
+    }
+}
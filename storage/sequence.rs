@@ -0,0 +1,42 @@
+// Numbers each asset's records in the order the tracker fetched them,
+// carrying on from the last stored number after a restart, so a consumer
+// reading the history elsewhere can tell a record that arrived out of order
+// or twice from one that is merely late. Picking up from the stored history
+// also catches a first fetch after a restart that only repeats the last
+// record written before it.
+
+use std::collections::HashMap;
+
+use super::{PriceRecord, Storage};
+
+#[derive(Default)]
+pub struct Sequencer {
+    last: HashMap<String, u64>,
+}
+
+impl Sequencer {
+    // Numbers `record`, or returns false if it is the stored record again.
+    pub fn assign(&mut self, asset: &str, record: &mut PriceRecord, storage: &dyn Storage) -> bool {
+        if !self.last.contains_key(asset) {
+            let stored = storage.last(asset).unwrap_or_else(|e| {
+                eprintln!("Error reading the last record of {}: {}", asset, e);
+                None
+            });
+            let number = stored.as_ref().and_then(|stored| stored.sequence).unwrap_or(0);
+            self.last.insert(asset.to_string(), number);
+            if stored.is_some_and(|stored| same_instant(&stored, record)) {
+                return false;
+            }
+        }
+        let last = self.last.entry(asset.to_string()).or_default();
+        *last += 1;
+        record.sequence = Some(*last);
+        true
+    }
+}
+
+// Stamped in the same second, or dated alike by a source that dates its
+// values.
+fn same_instant(stored: &PriceRecord, record: &PriceRecord) -> bool {
+    stored.timestamp == record.timestamp || (stored.source_time.is_some() && stored.source_time == record.source_time)
+}
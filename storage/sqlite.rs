@@ -0,0 +1,340 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Timelike};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags};
+
+use super::{is_extra_column, AlertEvent, PriceRecord, Records, Storage, SCHEMA_VERSION, TIMESTAMP_FORMAT};
+use crate::PriceError;
+
+// Records per query when scanning a history.
+const PAGE_SIZE: usize = 10_000;
+
+// How long a write waits for another connection's to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct SqliteStorage {
+    conn: Connection,
+    // Per-currency columns (price_, fx_ and in_) in the prices table.
+    columns: BTreeSet<String>,
+}
+
+fn db_error(e: rusqlite::Error) -> PriceError {
+    PriceError::FileError(format!("sqlite: {}", e))
+}
+
+fn extra_columns(conn: &Connection) -> Result<BTreeSet<String>, PriceError> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info('prices')").map_err(db_error)?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(db_error)?;
+    let mut columns = BTreeSet::new();
+    for name in names {
+        let name = name.map_err(db_error)?;
+        if is_extra_column(&name) {
+            columns.insert(name);
+        }
+    }
+    Ok(columns)
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, PriceError> {
+        let conn = Connection::open(path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        // The tracker writes prices and alerts over separate connections.
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+        upgrade(&conn)?;
+        // The alert log does not depend on the price schema version.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS alert_log (
+                 id INTEGER PRIMARY KEY,
+                 timestamp TEXT NOT NULL,
+                 rule TEXT NOT NULL,
+                 asset TEXT NOT NULL,
+                 value REAL NOT NULL,
+                 message TEXT NOT NULL,
+                 outcome TEXT NOT NULL
+             );",
+        )
+        .map_err(db_error)?;
+
+        let columns = extra_columns(&conn)?;
+        Ok(SqliteStorage { conn, columns })
+    }
+
+    // Skips creating and upgrading tables, for dry runs that must not touch
+    // the database.
+    pub fn open_read_only(path: &Path) -> Result<Self, PriceError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        let columns = extra_columns(&conn)?;
+        Ok(SqliteStorage { conn, columns })
+    }
+
+    // Adds every per-currency column used by `records` that the table lacks.
+    fn add_columns(&mut self, records: &[PriceRecord]) -> Result<(), PriceError> {
+        for (column, _) in records.iter().flat_map(|r| r.extra_columns()) {
+            if self.columns.contains(&column) {
+                continue;
+            }
+            if !is_extra_column(&column) {
+                return Err(PriceError::ParseError(format!("invalid column name '{}'", column)));
+            }
+            self.conn
+                .execute_batch(&format!("ALTER TABLE prices ADD COLUMN {} REAL;", column))
+                .map_err(db_error)?;
+            self.columns.insert(column);
+        }
+        Ok(())
+    }
+
+    // Inserts records in a single transaction, skipping timestamps that are
+    // already stored. Returns how many rows were actually added.
+    pub fn insert_batch(&mut self, asset: &str, records: &[PriceRecord]) -> Result<usize, PriceError> {
+        self.add_columns(records)?;
+        let tx = self.conn.transaction().map_err(db_error)?;
+        let inserted = insert_rows(&tx, asset, records)?;
+        tx.commit().map_err(db_error)?;
+        Ok(inserted)
+    }
+
+    pub fn count(&self, asset: &str) -> Result<usize, PriceError> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM prices WHERE asset = ?1", params![asset], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|count| count as usize)
+            .map_err(db_error)
+    }
+
+    // Up to `limit` records (all of them if negative) newer than `after`, a
+    // timestamp in TIMESTAMP_FORMAT, which sorts the way the times do.
+    fn page(&self, asset: &str, after: &str, limit: i64) -> Result<Vec<PriceRecord>, PriceError> {
+        let extra_columns: String = self.columns.iter().map(|c| format!(", {}", c)).collect();
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT timestamp, price, volume, market_cap{} FROM prices
+                 WHERE asset = ?1 AND timestamp > ?2 ORDER BY timestamp LIMIT ?3",
+                extra_columns
+            ))
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map(params![asset, after, limit], |row| {
+                let mut extra = Vec::new();
+                for (index, column) in self.columns.iter().enumerate() {
+                    if let Some(value) = row.get::<_, Option<f64>>(4 + index)? {
+                        extra.push((column.as_str(), value));
+                    }
+                }
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<f64>>(3)?,
+                    extra,
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (timestamp, price, volume, market_cap, extra) = row.map_err(db_error)?;
+            let timestamp = NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT)
+                .map_err(|e| PriceError::ParseError(format!("invalid timestamp '{}': {}", timestamp, e)))?;
+            let mut record = PriceRecord::new(timestamp, price);
+            record.volume = volume;
+            record.market_cap = market_cap;
+            for (column, value) in extra {
+                record.set_extra_column(column, value);
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn append(&mut self, asset: &str, record: &PriceRecord) -> Result<(), PriceError> {
+        self.add_columns(std::slice::from_ref(record))?;
+        let (sql, values) = insert_statement("INSERT OR REPLACE", asset, record);
+        self.conn.execute(&sql, params_from_iter(values)).map(|_| ()).map_err(db_error)
+    }
+
+    fn read(&self, asset: &str) -> Result<Vec<PriceRecord>, PriceError> {
+        self.page(asset, "", -1)
+    }
+
+    fn scan(&self, asset: &str, from: Option<NaiveDateTime>) -> Result<Records<'_>, PriceError> {
+        // Pages start after a timestamp, in whole seconds.
+        let after = from.map(|from| {
+            let before = if from.nanosecond() == 0 { from - chrono::Duration::seconds(1) } else { from };
+            before.format(TIMESTAMP_FORMAT).to_string()
+        });
+        let after = Some(after.unwrap_or_default());
+        Ok(Box::new(Pages { storage: self, asset: asset.to_string(), after, page: Vec::new() }))
+    }
+
+    fn replace(&mut self, asset: &str, records: &[PriceRecord]) -> Result<(), PriceError> {
+        self.add_columns(records)?;
+        let tx = self.conn.transaction().map_err(db_error)?;
+        tx.execute("DELETE FROM prices WHERE asset = ?1", params![asset])
+            .map_err(db_error)?;
+        insert_rows(&tx, asset, records)?;
+        tx.commit().map_err(db_error)
+    }
+
+    fn log_alert(&mut self, event: &AlertEvent) -> Result<(), PriceError> {
+        let timestamp = event.timestamp.format(TIMESTAMP_FORMAT).to_string();
+        self.conn
+            .execute(
+                "INSERT INTO alert_log (timestamp, rule, asset, value, message, outcome)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![timestamp, event.rule, event.asset, event.value, event.message, event.outcome],
+            )
+            .map(|_| ())
+            .map_err(db_error)
+    }
+
+    fn alert_log(&self) -> Result<Vec<AlertEvent>, PriceError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT timestamp, rule, asset, value, message, outcome FROM alert_log ORDER BY id")
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    AlertEvent {
+                        timestamp: NaiveDateTime::default(),
+                        rule: row.get(1)?,
+                        asset: row.get(2)?,
+                        value: row.get(3)?,
+                        message: row.get(4)?,
+                        outcome: row.get(5)?,
+                    },
+                ))
+            })
+            .map_err(db_error)?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let (timestamp, mut event) = row.map_err(db_error)?;
+            event.timestamp = NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT)
+                .map_err(|e| PriceError::ParseError(format!("invalid timestamp '{}': {}", timestamp, e)))?;
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+// Reads a history PAGE_SIZE records at a time, each page picking up after
+// the last timestamp of the one before.
+struct Pages<'a> {
+    storage: &'a SqliteStorage,
+    asset: String,
+    // None once the last page is read.
+    after: Option<String>,
+    // Reversed, to pop from the end.
+    page: Vec<PriceRecord>,
+}
+
+impl Iterator for Pages<'_> {
+    type Item = Result<PriceRecord, PriceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.page.is_empty() {
+            let after = self.after.take()?;
+            match self.storage.page(&self.asset, &after, PAGE_SIZE as i64) {
+                Ok(mut page) => {
+                    if page.len() == PAGE_SIZE {
+                        self.after = page.last().map(|record| record.timestamp.format(TIMESTAMP_FORMAT).to_string());
+                    }
+                    page.reverse();
+                    self.page = page;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        self.page.pop().map(Ok)
+    }
+}
+
+fn insert_rows(conn: &Connection, asset: &str, records: &[PriceRecord]) -> Result<usize, PriceError> {
+    let mut inserted = 0;
+    for record in records {
+        let (sql, values) = insert_statement("INSERT OR IGNORE", asset, record);
+        let mut stmt = conn.prepare_cached(&sql).map_err(db_error)?;
+        inserted += stmt.execute(params_from_iter(values)).map_err(db_error)?;
+    }
+    Ok(inserted)
+}
+
+// Builds an insert naming only the quote columns the record has; the
+// columns must already exist.
+fn insert_statement(verb: &str, asset: &str, record: &PriceRecord) -> (String, Vec<Value>) {
+    let mut columns = String::from("asset, timestamp, price, volume, market_cap");
+    let mut values = vec![
+        Value::from(asset.to_string()),
+        Value::from(record.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+        Value::from(record.price),
+        record.volume.map(Value::from).unwrap_or(Value::Null),
+        record.market_cap.map(Value::from).unwrap_or(Value::Null),
+    ];
+    for (column, value) in record.extra_columns() {
+        columns.push_str(", ");
+        columns.push_str(&column);
+        values.push(Value::from(value));
+    }
+    let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+    (format!("{} INTO prices ({}) VALUES ({})", verb, columns, placeholders.join(", ")), values)
+}
+
+// The schema version lives in SQLite's user_version pragma. Databases created
+// before versioning report 0 but already hold the v1 table.
+fn upgrade(conn: &Connection) -> Result<(), PriceError> {
+    let version: u32 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(db_error)?;
+    if version > SCHEMA_VERSION {
+        return Err(PriceError::FileError(format!(
+            "database schema v{} is newer than this tracker supports (v{})",
+            version, SCHEMA_VERSION
+        )));
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let has_table: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'prices'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+
+    // v3 to v9 only allow per-currency columns, which are added on demand.
+    let migration = if has_table && version >= 2 {
+        ""
+    } else if has_table {
+        "ALTER TABLE prices ADD COLUMN volume REAL;
+         ALTER TABLE prices ADD COLUMN market_cap REAL;"
+    } else {
+        "CREATE TABLE prices (
+             asset TEXT NOT NULL,
+             timestamp TEXT NOT NULL,
+             price REAL NOT NULL,
+             volume REAL,
+             market_cap REAL,
+             PRIMARY KEY (asset, timestamp)
+         );"
+    };
+
+    conn.execute_batch(&format!(
+        "BEGIN; {} PRAGMA user_version = {}; COMMIT;",
+        migration, SCHEMA_VERSION
+    ))
+    .map_err(db_error)
+}
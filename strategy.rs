@@ -0,0 +1,160 @@
+// Trading logic that turns prices into buy and sell signals, shared by the
+// backtester and by live `[[signals]]`. Strategies are configured by name:
+//
+//   [strategies.dips]
+//   type = "threshold"
+//   buy_below = 60000.0
+//   sell_above = 70000.0
+//
+//   [strategies.trend]
+//   type = "crossover"        # SMA(fast) crossing SMA(slow)
+//   fast = 50
+//   slow = 200
+//
+//   [strategies.oversold]
+//   type = "indicator"
+//   indicator = "rsi(14)"
+//   buy_below = 30.0
+//   sell_above = 70.0
+//
+//   [strategies.streak]
+//   type = "custom"           # one written in Rust, see strategy/custom.rs
+//   strategy = "momentum"
+//   params = { rises = 3, falls = 2 }
+
+mod custom;
+
+use std::collections::VecDeque;
+
+use serde::Deserialize;
+
+use crate::analytics::{Cross, Crossover, Indicator};
+use crate::storage::PriceRecord;
+use crate::PriceError;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StrategyConfig {
+    Threshold { buy_below: f64, sell_above: f64 },
+    Crossover { fast: usize, slow: usize },
+    Indicator { indicator: String, buy_below: f64, sell_above: f64 },
+    // A strategy from strategy/custom.rs, given its `params` table.
+    Custom {
+        strategy: String,
+        #[serde(default)]
+        params: toml::Table,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Buy,
+    Sell,
+}
+
+impl Signal {
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Buy => "buy",
+            Signal::Sell => "sell",
+        }
+    }
+}
+
+pub trait Strategy {
+    // What to do after `record`, if anything. Callers drop signals for the
+    // side already taken, so a strategy need not track its position.
+    fn on_price(&mut self, record: &PriceRecord) -> Option<Signal>;
+
+    // Recent records to replay before live ones, when restarting the tracker.
+    fn records_needed(&self) -> usize {
+        0
+    }
+}
+
+// Builds a strategy, `name` being its key under `[strategies]`.
+pub fn build(name: &str, config: &StrategyConfig) -> Result<Box<dyn Strategy>, PriceError> {
+    let invalid = |reason: String| PriceError::ParseError(format!("strategies.{}: {}", name, reason));
+    Ok(match config {
+        StrategyConfig::Threshold { buy_below, sell_above } => {
+            if buy_below >= sell_above {
+                return Err(invalid("buy_below must be under sell_above".to_string()));
+            }
+            Box::new(Threshold { buy_below: *buy_below, sell_above: *sell_above })
+        }
+        StrategyConfig::Crossover { fast, slow } => {
+            if *fast == 0 || fast >= slow {
+                return Err(invalid("fast must be a shorter period than slow".to_string()));
+            }
+            Box::new(Crossover::new(*fast, *slow))
+        }
+        StrategyConfig::Indicator { indicator, buy_below, sell_above } => Box::new(IndicatorStrategy {
+            indicator: indicator.parse().map_err(invalid)?,
+            prices: VecDeque::new(),
+            buy_below: *buy_below,
+            sell_above: *sell_above,
+        }),
+        StrategyConfig::Custom { strategy, params } => {
+            let Some((_, factory)) = custom::STRATEGIES.iter().find(|(known, _)| known == strategy) else {
+                let known: Vec<&str> = custom::STRATEGIES.iter().map(|(known, _)| *known).collect();
+                return Err(invalid(format!("unknown strategy '{}', expected one of: {}", strategy, known.join(", "))));
+            };
+            factory(params).map_err(invalid)?
+        }
+    })
+}
+
+struct Threshold {
+    buy_below: f64,
+    sell_above: f64,
+}
+
+impl Strategy for Threshold {
+    fn on_price(&mut self, record: &PriceRecord) -> Option<Signal> {
+        if record.price <= self.buy_below {
+            Some(Signal::Buy)
+        } else if record.price >= self.sell_above {
+            Some(Signal::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+impl Strategy for Crossover {
+    fn on_price(&mut self, record: &PriceRecord) -> Option<Signal> {
+        match self.push(record.price)? {
+            (Cross::Above, _, _) => Some(Signal::Buy),
+            (Cross::Below, _, _) => Some(Signal::Sell),
+        }
+    }
+
+    fn records_needed(&self) -> usize {
+        Crossover::records_needed(self)
+    }
+}
+
+struct IndicatorStrategy {
+    indicator: Indicator,
+    prices: VecDeque<f64>,
+    buy_below: f64,
+    sell_above: f64,
+}
+
+impl Strategy for IndicatorStrategy {
+    fn on_price(&mut self, record: &PriceRecord) -> Option<Signal> {
+        self.prices.push_back(record.price);
+        if self.prices.len() > self.indicator.records_needed() {
+            self.prices.pop_front();
+        }
+        match self.indicator.compute(self.prices.make_contiguous()) {
+            Some(value) if value <= self.buy_below => Some(Signal::Buy),
+            Some(value) if value >= self.sell_above => Some(Signal::Sell),
+            _ => None,
+        }
+    }
+
+    fn records_needed(&self) -> usize {
+        self.indicator.records_needed()
+    }
+}
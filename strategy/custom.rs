@@ -0,0 +1,57 @@
+// Strategies written in Rust. To add one, implement `Strategy` and list a
+// constructor for it below; `[strategies.<name>]` then selects it with
+// `type = "custom"` and `strategy = "<its name here>"`, passing `params`.
+
+use std::cmp::Ordering;
+
+use super::{Signal, Strategy};
+use crate::storage::PriceRecord;
+
+pub type Factory = fn(&toml::Table) -> Result<Box<dyn Strategy>, String>;
+
+pub const STRATEGIES: &[(&str, Factory)] = &[("momentum", Momentum::build)];
+
+// Buys after `rises` higher prices in a row, sells after `falls` lower ones.
+struct Momentum {
+    rises: usize,
+    falls: usize,
+    last: Option<f64>,
+    // Positive while the price keeps rising, negative while it keeps falling.
+    streak: i64,
+}
+
+impl Momentum {
+    fn build(params: &toml::Table) -> Result<Box<dyn Strategy>, String> {
+        let count = |key: &str| match params.get(key) {
+            None => Ok(3),
+            Some(toml::Value::Integer(n)) if *n > 0 => Ok(*n as usize),
+            Some(value) => Err(format!("params.{} must be a positive integer, got {}", key, value)),
+        };
+        if let Some(key) = params.keys().find(|key| !["rises", "falls"].contains(&key.as_str())) {
+            return Err(format!("unknown param '{}', expected rises or falls", key));
+        }
+        Ok(Box::new(Momentum { rises: count("rises")?, falls: count("falls")?, last: None, streak: 0 }))
+    }
+}
+
+impl Strategy for Momentum {
+    fn on_price(&mut self, record: &PriceRecord) -> Option<Signal> {
+        let last = self.last.replace(record.price)?;
+        self.streak = match record.price.partial_cmp(&last)? {
+            Ordering::Greater => self.streak.max(0) + 1,
+            Ordering::Less => self.streak.min(0) - 1,
+            Ordering::Equal => self.streak,
+        };
+        if self.streak >= self.rises as i64 {
+            Some(Signal::Buy)
+        } else if -self.streak >= self.falls as i64 {
+            Some(Signal::Sell)
+        } else {
+            None
+        }
+    }
+
+    fn records_needed(&self) -> usize {
+        self.rises.max(self.falls) + 1
+    }
+}
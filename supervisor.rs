@@ -0,0 +1,84 @@
+// Keeps an asset whose source panicked out of the following rounds, for a
+// backoff that doubles with each panic in a row, then builds its source
+// afresh: the panic may have left its state half updated or its locks
+// poisoned. Other assets are fetched as usual meanwhile.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::http::Client;
+use crate::{sources, Pricing};
+
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+struct Restart {
+    // Panics in a row.
+    panics: u32,
+    at: Instant,
+    // Whether the asset has a fresh source yet.
+    rebuilt: bool,
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    restarts: HashMap<String, Restart>,
+}
+
+impl Supervisor {
+    // Builds new sources for the assets whose backoff is over.
+    pub fn restart_due(&mut self, assets: &mut [Box<dyn Pricing>], config: &Config, client: &Client) {
+        let now = Instant::now();
+        for asset in assets.iter_mut() {
+            let Some(restart) = self.restarts.get_mut(asset.id()) else {
+                continue;
+            };
+            if restart.rebuilt || restart.at > now {
+                continue;
+            }
+            match sources::rebuild(asset.id(), config, client) {
+                Ok(source) => {
+                    eprintln!("Restarting the source of {} after {} panic(s)", asset.name(), restart.panics);
+                    *asset = source;
+                    restart.rebuilt = true;
+                }
+                Err(e) => {
+                    eprintln!("Error restarting the source of {}: {}", asset.name(), e);
+                    restart.at = now + backoff(restart.panics);
+                }
+            }
+        }
+    }
+
+    // Whether `asset` sits this round out.
+    pub fn is_waiting(&self, asset: &dyn Pricing) -> bool {
+        self.restarts.get(asset.id()).is_some_and(|restart| !restart.rebuilt)
+    }
+
+    // Records a round: `fetched` were sent to the workers and `panicked`
+    // failed with a panic.
+    pub fn finish_round(&mut self, fetched: &[&dyn Pricing], panicked: &[&dyn Pricing]) {
+        for asset in fetched {
+            if !panicked.iter().any(|other| other.id() == asset.id()) {
+                self.restarts.remove(asset.id());
+            }
+        }
+        for asset in panicked {
+            let restart = self.restarts.entry(asset.id().to_string()).or_insert(Restart {
+                panics: 0,
+                at: Instant::now(),
+                rebuilt: false,
+            });
+            restart.panics += 1;
+            restart.rebuilt = false;
+            let wait = backoff(restart.panics);
+            restart.at = Instant::now() + wait;
+            eprintln!("The source of {} will be restarted in {}s", asset.name(), wait.as_secs());
+        }
+    }
+}
+
+fn backoff(panics: u32) -> Duration {
+    FIRST_BACKOFF.saturating_mul(1 << panics.saturating_sub(1).min(16)).min(MAX_BACKOFF)
+}
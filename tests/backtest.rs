@@ -0,0 +1,100 @@
+// Backtests strategies over a seeded history.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+
+[strategies.dips]
+type = "threshold"
+buy_below = 90.0
+sell_above = 110.0
+
+[strategies.trend]
+type = "crossover"
+fast = 2
+slow = 3
+
+[strategies.oversold]
+type = "indicator"
+indicator = "rsi(2)"
+buy_below = 10.0
+sell_above = 90.0
+"#;
+
+// Down to 80, up to 120, down to 60 and back to 100.
+const PRICES: [f64; 9] = [100.0, 90.0, 80.0, 100.0, 120.0, 100.0, 60.0, 80.0, 100.0];
+
+fn backtest(scratch: &Scratch, strategy: &str) -> String {
+    let output = scratch.run(&["backtest", "gold", "--strategy", strategy, "--fee", "0"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+#[test]
+fn strategies_report_trades_returns_and_drawdown() {
+    let scratch = Scratch::new("backtest");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    let mut history = "# schema_version=6\ntimestamp,price,volume,market_cap\n".to_string();
+    for (day, price) in PRICES.iter().enumerate() {
+        history.push_str(&format!("2024-01-{:02} 00:00:00,{:.2},,\n", day + 1, price));
+    }
+    scratch.write("gold_prices.csv", &history);
+
+    // Buys at 90, sells at 120, buys at 60 and holds to 100.
+    let dips = backtest(&scratch, "dips");
+    assert!(dips.contains("[2024-01-02 00:00:00] buy  111.111111 at 90.00, equity 10000.00"), "{}", dips);
+    assert!(dips.contains("[2024-01-05 00:00:00] sell 111.111111 at 120.00, equity 13333.33"), "{}", dips);
+    assert!(dips.contains("dips on gold: 3 trades, 1 of 1 sells at a profit"), "{}", dips);
+    // 13333.33 / 60 * 100, against 100 to 100 for holding; the worst fall
+    // was from 10000 to 80 / 90 of it.
+    let summary = "Return +122.22% (10000.00 to 22222.22), buy and hold +0.00%, max drawdown 11.11%";
+    assert!(dips.contains(summary), "{}", dips);
+
+    // SMA(2) crosses above SMA(3) on the way to 120 and below it on the
+    // way down to 60.
+    let trend = backtest(&scratch, "trend");
+    assert!(trend.contains("[2024-01-05 00:00:00] buy ") && trend.contains("[2024-01-07 00:00:00] sell"), "{}", trend);
+    assert!(trend.contains("trend on gold: 3 trades, 0 of 1 sells at a profit"), "{}", trend);
+    let oversold = backtest(&scratch, "oversold");
+    assert!(oversold.contains("oversold on gold: 4 trades, 2 of 2 sells at a profit"), "{}", oversold);
+
+    let unknown = scratch.run(&["backtest", "gold", "--strategy", "moon"]);
+    assert!(text(&unknown.stderr).contains("unknown strategy 'moon', expected one of: dips, oversold, trend"));
+
+    scratch.write("tracker.toml", &CONFIG.replace("fast = 2", "fast = 5"));
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("strategies.trend: fast must be a shorter period than slow"), "{}", report);
+}
+
+#[test]
+fn custom_strategies_are_picked_by_name() {
+    let scratch = Scratch::new("backtest_custom");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = "[mock.gold]\npattern = \"sequence\"\nprices = [100.0]\n\n[strategies.streak]\ntype = \"custom\"\n\
+                  strategy = \"momentum\"\nparams = { rises = 2, falls = 2 }\n";
+    scratch.write("tracker.toml", config);
+    let mut history = "# schema_version=6\ntimestamp,price,volume,market_cap\n".to_string();
+    for (day, price) in PRICES.iter().enumerate() {
+        history.push_str(&format!("2024-01-{:02} 00:00:00,{:.2},,\n", day + 1, price));
+    }
+    scratch.write("gold_prices.csv", &history);
+
+    // The first two falls come before anything is held, so the first trade
+    // is the buy after 100 and 120.
+    let streak = backtest(&scratch, "streak");
+    assert!(streak.contains("[2024-01-05 00:00:00] buy  83.333333 at 120.00"), "{}", streak);
+    assert!(streak.contains("[2024-01-07 00:00:00] sell 83.333333 at 60.00"), "{}", streak);
+    assert!(streak.contains("streak on gold: 3 trades, 0 of 1 sells at a profit"), "{}", streak);
+
+    scratch.write("tracker.toml", &config.replace("momentum", "rocket"));
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("strategies.streak: unknown strategy 'rocket', expected one of: momentum"), "{}", report);
+}
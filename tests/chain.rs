@@ -0,0 +1,76 @@
+// Tracks into a hash-chained history, then edits and deletes records behind
+// the tracker's back for `verify` to find.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+interval = "1s"
+hash_chain = true
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0, 300.0]
+"#;
+
+fn verify(scratch: &Scratch) -> (bool, String) {
+    let output = scratch.run(&["verify", "gold"]);
+    (output.status.success(), text(&output.stdout) + &text(&output.stderr))
+}
+
+#[test]
+fn verify_finds_edits_and_deletions() {
+    let scratch = Scratch::new("chain");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    // Records from before the chain was turned on are chained first.
+    scratch.write(
+        "gold_prices.csv",
+        "# schema_version=6\ntimestamp,price,volume,market_cap\n2024-01-01 00:00:00,50.00,,\n",
+    );
+    let output = scratch.track(&[], "gold: $300.00", 1);
+    assert!(output.contains("over 1 existing records") && !output.contains("Error"), "{}", output);
+
+    let (verified, output) = verify(&scratch);
+    assert!(verified && output.contains("gold: 4 records verified, head "), "{}", output);
+    let original = scratch.read("gold_prices.csv");
+
+    scratch.write("gold_prices.csv", &original.replacen(",200.00,", ",250.00,", 1));
+    let (verified, output) = verify(&scratch);
+    assert!(!verified && output.contains("was edited"), "{}", output);
+
+    let lines: Vec<&str> = original.lines().collect();
+    let deleted = [&lines[..3], &lines[4..]].concat().join("\n") + "\n";
+    scratch.write("gold_prices.csv", &deleted);
+    let (verified, output) = verify(&scratch);
+    assert!(!verified && output.contains("was deleted"), "{}", output);
+
+    scratch.write("gold_prices.csv", &(lines[..5].join("\n") + "\n"));
+    let (verified, output) = verify(&scratch);
+    assert!(!verified && output.contains("1 record(s) from"), "{}", output);
+}
+
+#[test]
+fn records_written_without_their_chain_entry_are_linked_on_start() {
+    let scratch = Scratch::new("chain-crash");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    scratch.track(&[], "gold: $300.00", 1);
+    assert!(verify(&scratch).0);
+
+    // As if the tracker died after writing the last record, while writing
+    // its chain entry.
+    let chain = scratch.read("gold_chain.csv");
+    let mut lines: Vec<&str> = chain.lines().collect();
+    lines.pop();
+    scratch.write("gold_chain.csv", &format!("{}\n2024-05-03 12:00:00,4f2a", lines.join("\n")));
+    let (verified, output) = verify(&scratch);
+    assert!(!verified, "{}", output);
+
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(output.contains("Dropped the incomplete last entry of ./gold_chain.csv"), "{}", output);
+    assert!(output.contains("Chained 1 record(s) of gold written without a chain entry"), "{}", output);
+    let (verified, output) = verify(&scratch);
+    assert!(verified && output.contains("records verified"), "{}", output);
+}
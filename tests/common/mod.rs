@@ -0,0 +1,208 @@
+// Runs the binary in a scratch directory of its own per test.
+
+// Each test crate uses a different part of this.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::net::TcpListener;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const BIN: &str = env!("CARGO_BIN_EXE_crypto_price_tracker");
+
+pub const CONFIG: &str = r#"
+interval = "1s"
+currencies = ["eur"]
+
+[http]
+timeout = "1s"
+"#;
+
+pub const WATCHLIST: &str = r#"
+[[assets]]
+id = "bitcoin"
+
+[[assets]]
+id = "sp500"
+"#;
+
+pub struct Scratch {
+    pub dir: PathBuf,
+}
+
+impl Scratch {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("tracker-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("tracker.toml"), CONFIG).unwrap();
+        fs::write(dir.join("watchlist.toml"), WATCHLIST).unwrap();
+        Scratch { dir }
+    }
+
+    // An empty watchlist and a config fetching every second, with a short
+    // HTTP timeout, followed by `extra`: for assets the config defines.
+    pub fn with_config(name: &str, extra: &str) -> Self {
+        let scratch = Scratch::new(name);
+        scratch.write("watchlist.toml", "assets = []\n");
+        scratch.write("tracker.toml", &format!("interval = \"1s\"\n[http]\ntimeout = \"1s\"\n{}", extra));
+        scratch
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(BIN);
+        command.args(args).current_dir(&self.dir).env_remove("TRACKER_PROFILE").env_remove("TRACKER_ASSETS");
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    // Starts the tracker with its stdout and stderr lines, interleaved, sent
+    // to the returned channel.
+    pub fn start(&self, args: &[&str]) -> (Child, mpsc::Receiver<String>) {
+        let mut child = self
+            .command(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (sender, lines) = mpsc::channel();
+        forward(child.stdout.take().unwrap(), sender.clone());
+        forward(child.stderr.take().unwrap(), sender);
+        (child, lines)
+    }
+
+    // Starts the tracker and collects its output until `count` lines contain
+    // `needle` or ten seconds pass.
+    pub fn track(&self, args: &[&str], needle: &str, count: usize) -> String {
+        let (mut child, lines) = self.start(args);
+        let (output, complete) = collect(&lines, needle, count);
+        stop(&mut child);
+        assert!(complete, "expected {} lines with '{}', got:\n{}", count, needle, output);
+        output
+    }
+
+    pub fn write(&self, file: &str, contents: &str) {
+        fs::write(self.dir.join(file), contents).unwrap();
+    }
+
+    pub fn read(&self, file: &str) -> String {
+        fs::read_to_string(self.dir.join(file)).unwrap_or_default()
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// Reads `lines` until `count` of them contain `needle`, giving up after ten
+// seconds; returns what was read and whether that many were.
+pub fn collect(lines: &mpsc::Receiver<String>, needle: &str, count: usize) -> (String, bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut output = String::new();
+    let mut seen = 0;
+    while seen < count {
+        let Some(left) = deadline.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        match lines.recv_timeout(left) {
+            Ok(line) => {
+                seen += line.contains(needle) as usize;
+                output.push_str(&line);
+                output.push('\n');
+            }
+            Err(_) => break,
+        }
+    }
+    (output, seen >= count)
+}
+
+fn forward(stream: impl Read + Send + 'static, sender: mpsc::Sender<String>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+pub fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+pub fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+// One request to a server started by `serve`.
+pub struct Request {
+    // e.g. "PUT /prices/gold.csv HTTP/1.1"
+    pub line: String,
+    pub headers: Vec<String>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find_map(|header| {
+            let (key, value) = header.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+}
+
+// Serves HTTP on a free local port, one request per connection, answering
+// each of them with the status line and body `respond` returns for it and
+// its number, counted from 0, and passing them on; returns the base URL.
+pub fn serve(
+    respond: impl Fn(&Request, usize) -> (&'static str, String) + Send + 'static,
+) -> (String, mpsc::Receiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                headers.push(header.trim().to_string());
+            }
+            let mut request = Request { line: line.trim().to_string(), headers, body: String::new() };
+            let length = request.header("Content-Length").map_or(0, |length| length.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.body = String::from_utf8(body).unwrap();
+
+            let (status, body) = respond(&request, index);
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            if sender.send(request).is_err() {
+                return;
+            }
+        }
+    });
+    (address, requests)
+}
@@ -0,0 +1,62 @@
+// Two stored histories compared over the period both cover.
+
+mod common;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use common::{text, Scratch};
+
+fn history(now: NaiveDateTime, start_hours_ago: i64, prices: &[f64]) -> String {
+    let start = now - Duration::hours(start_hours_ago);
+    let mut history = String::from("timestamp,price,volume,market_cap\n");
+    for (hour, price) in prices.iter().enumerate() {
+        let timestamp = start + Duration::hours(hour as i64);
+        history.push_str(&format!("{},{:.2},,\n", timestamp.format("%Y-%m-%d %H:%M:%S"), price));
+    }
+    history
+}
+
+fn scratch(name: &str) -> Scratch {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n\n[[assets]]\nid = \"ethereum\"\n");
+    scratch
+}
+
+#[test]
+fn series_are_normalized_and_correlated() {
+    let scratch = scratch("compare");
+    let now = Local::now().naive_local();
+    let bitcoin: Vec<f64> = (0..=10).map(|hour| 100.0 + 2.0 * hour as f64).collect();
+    scratch.write("bitcoin_prices.csv", &history(now, 10, &bitcoin));
+    // Starting an hour later, so both are compared from there.
+    let ethereum: Vec<f64> = (0..10).map(|hour| if hour < 9 { 50.0 } else { 55.0 }).collect();
+    scratch.write("ethereum_prices.csv", &history(now, 9, &ethereum));
+
+    let output = scratch.run(&["compare", "bitcoin", "ethereum", "--since", "2d", "--rows", "3"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let lines: Vec<String> = text(&output.stdout).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 8, "{:?}", lines);
+    assert!(lines[0].starts_with("Bitcoin and Ethereum from "), "{:?}", lines);
+    assert!(lines[1].starts_with("Time") && lines[1].ends_with("Bitcoin  Ethereum"), "{:?}", lines);
+    assert!(lines[2].ends_with("  100.00    100.00"), "{:?}", lines);
+    // Bitcoin from 102, its price when ethereum's history starts.
+    assert!(lines[3].ends_with("  107.84    100.00"), "{:?}", lines);
+    assert!(lines[4].ends_with("  117.65    110.00"), "{:?}", lines);
+    assert_eq!(lines[5], "Change: Bitcoin +17.65%, Ethereum +10.00%");
+    let relative = "Relative performance of Bitcoin to Ethereum: 106.95 (Bitcoin ahead by 7.65 percentage points)";
+    assert_eq!(lines[6], relative);
+    // Ethereum moves once, in the last interval, when bitcoin also rises.
+    assert_eq!(lines[7], "Correlation of returns: 0.30 over 200 intervals");
+}
+
+#[test]
+fn histories_must_overlap() {
+    let scratch = scratch("compare-apart");
+    let now = Local::now().naive_local();
+    scratch.write("bitcoin_prices.csv", &history(now, 50, &[100.0, 101.0]));
+    scratch.write("ethereum_prices.csv", &history(now, 10, &[50.0, 51.0]));
+
+    let output = scratch.run(&["compare", "bitcoin", "ethereum"]);
+    assert!(text(&output.stderr).contains("Bitcoin and Ethereum have no prices over the same part of the period"));
+    let output = scratch.run(&["compare", "bitcoin", "ethereum", "--since", "1d"]);
+    assert!(text(&output.stderr).contains("no prices stored for Bitcoin in the period"), "{}", text(&output.stderr));
+}
@@ -0,0 +1,82 @@
+// Generates the completions and man page and checks them against `--help`,
+// running the bash ones through bash itself.
+
+mod common;
+
+use std::process::Command;
+
+use common::{text, Scratch};
+
+fn generate(scratch: &Scratch, target: &str) -> String {
+    let output = scratch.run(&["completions", target]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+// What the bash completion offers after `line`, the last word being the one
+// completed.
+fn complete(script: &str, line: &str) -> Vec<String> {
+    let words: Vec<String> = line.split(' ').map(|word| format!("'{}'", word)).collect();
+    let program = format!(
+        "{}\nCOMP_WORDS=({})\nCOMP_CWORD={}\n_crypto_price_tracker\nprintf '%s\\n' \"${{COMPREPLY[@]}}\"\n",
+        script,
+        words.join(" "),
+        words.len() - 1
+    );
+    let output = Command::new("bash").arg("-c").arg(program).output().unwrap();
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout).lines().filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+#[test]
+fn every_command_is_covered() {
+    let scratch = Scratch::new("completions");
+    let help = text(&scratch.run(&["--help"]).stdout);
+    let commands: Vec<&str> = help
+        .split("Commands:")
+        .nth(1)
+        .unwrap()
+        .split("Options:")
+        .next()
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|command| *command != "help")
+        .collect();
+    assert!(commands.contains(&"repl") && commands.contains(&"completions"), "{}", help);
+
+    for target in ["bash", "zsh", "fish", "man"] {
+        let generated = generate(&scratch, target);
+        for command in &commands {
+            assert!(generated.contains(command), "{} is missing from the {} output:\n{}", command, target, generated);
+        }
+    }
+
+    let man = generate(&scratch, "man");
+    assert!(man.starts_with(".TH CRYPTO_PRICE_TRACKER 1"), "{}", man);
+    assert!(man.contains("\\fB\\-\\-dry\\-run\\fR"), "{}", man);
+    assert!(man.contains(".SS \"crypto_price_tracker asset add <ID>\""), "{}", man);
+    assert!(man.contains("One of: beancount, hledger, gnucash, xlsx"), "{}", man);
+
+    let zsh = generate(&scratch, "zsh");
+    assert!(zsh.starts_with("#compdef crypto_price_tracker\n"), "{}", zsh);
+    assert!(zsh.contains("_crypto_price_tracker__asset__add() {"), "{}", zsh);
+
+    let fish = generate(&scratch, "fish");
+    let format = "-n '__fish_seen_subcommand_from export' -l format -x -a 'beancount hledger gnucash xlsx'";
+    assert!(fish.contains(format), "{}", fish);
+}
+
+#[test]
+fn bash_completes_commands_flags_and_values() {
+    let scratch = Scratch::new("completions-bash");
+    let script = generate(&scratch, "bash");
+    assert_eq!(complete(&script, "crypto_price_tracker ch"), ["chart"]);
+    let asset = ["--config", "--profile", "--record", "--replay", "--mock-server", "--verbose", "-h", "--help"];
+    let asset = [asset.as_slice(), &["add", "remove", "list"]].concat();
+    assert_eq!(complete(&script, "crypto_price_tracker asset "), asset);
+    assert_eq!(complete(&script, "crypto_price_tracker --dry-run asset re"), ["remove"]);
+    assert_eq!(complete(&script, "crypto_price_tracker export --format h"), ["hledger"]);
+    assert_eq!(complete(&script, "crypto_price_tracker chart --sma "), Vec::<String>::new());
+    assert_eq!(complete(&script, "crypto_price_tracker status --si"), ["--since"]);
+}
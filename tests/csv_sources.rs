@@ -0,0 +1,73 @@
+// Runs CSV-over-HTTP sources against the --mock-server's Yahoo downloads.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn named_columns_of_the_last_row() {
+    let config = r#"
+[csv.index]
+url = "https://query1.finance.yahoo.com/v7/finance/download/{id}"
+name = "Index"
+column = "Close"
+volume = "Volume"
+row = "last"
+
+[assets.index]
+provider_id = "%5EGSPC"
+"#;
+    let scratch = Scratch::with_config("csv", config);
+    let output = scratch.track(&["--mock-server"], "Index: $", 1);
+    assert!(output.contains("Index: $5100.25"), "{}", output);
+
+    let history = scratch.read("index_prices.csv");
+    assert!(history.contains(",5100.25,3635860000"), "{}", history);
+}
+
+#[test]
+fn rows_are_selected_by_index_or_value() {
+    let config = r#"
+[csv.first]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = 4
+row = -2
+
+[csv.second]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = "Open"
+select = { column = "Date", equals = "2024-05-01" }
+"#;
+    let scratch = Scratch::with_config("csv-rows", config);
+    let output = scratch.track(&["--mock-server"], ": $", 2);
+    assert!(output.contains("first: $5064.20"), "{}", output);
+    assert!(output.contains("second: $5029.03"), "{}", output);
+}
+
+#[test]
+fn unknown_columns_fail_the_fetch() {
+    let config = r#"
+[csv.index]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = "Last"
+"#;
+    let scratch = Scratch::with_config("csv-unknown", config);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for index", 1);
+    assert!(output.contains("Parse Error [E200]: "), "{}", output);
+    assert!(output.contains("no column 'Last' in the header"), "{}", output);
+}
+
+#[test]
+fn column_names_need_a_header() {
+    let config = r#"
+[csv.index]
+url = "https://example.com/quotes.csv"
+column = "Close"
+has_headers = false
+"#;
+    let scratch = Scratch::with_config("csv-invalid", config);
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(report.contains("csv.index.column: names need has_headers = true"), "{}", report);
+}
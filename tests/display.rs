@@ -0,0 +1,52 @@
+// Prices printed by locale from [display], while the history keeps plain
+// numbers.
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [1234567.891]
+"#;
+
+fn scratch(name: &str, display: &str) -> Scratch {
+    Scratch::with_config(name, &format!("{}\n[display]\n{}", CONFIG, display))
+}
+
+#[test]
+fn prices_are_shown_by_locale() {
+    let plain = scratch("display-plain", "");
+    let output = plain.track(&[], "gold: $1234567.89", 1);
+    assert!(!output.contains("Error"), "{}", output);
+
+    let german = scratch("display-de", "locale = \"de_DE.UTF-8\"\n");
+    german.track(&[], "gold: $1.234.567,89", 1);
+    assert!(german.read("gold_prices.csv").contains(",1234567.89"), "{}", german.read("gold_prices.csv"));
+
+    let symbols = scratch("display-symbols", "locale = \"de\"\ncurrency_symbols = true\n");
+    symbols.track(&[], "gold: 1.234.567,89 $", 1);
+
+    let swiss = scratch("display-ch", "locale = \"de-CH\"\ncurrency_symbols = true\n");
+    swiss.track(&[], "gold: $1’234’567.89", 1);
+
+    let french = scratch("display-fr", "locale = \"fr\"\n");
+    french.track(&[], "gold: $1\u{a0}234\u{a0}567,89", 1);
+    let mut repl = french.command(&["repl"]).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    repl.stdin.take().unwrap().write_all(b"price gold\n").unwrap();
+    let output = text(&repl.wait_with_output().unwrap().stdout);
+    assert!(output.contains("gold: $1\u{a0}234\u{a0}567,89"), "{}", output);
+}
+
+#[test]
+fn unknown_locales_are_reported() {
+    let scratch = scratch("display-check", "locale = \"xx\"\n");
+    let check = scratch.run(&["config", "check"]);
+    assert!(!check.status.success());
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("display.locale: unknown locale 'xx', expected one of: en,"), "{}", report);
+}
@@ -0,0 +1,53 @@
+// Fetches seeded dividends through a replayed Yahoo Finance response.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CHART: &str = r#"{"chart": {"result": [{"events": {"dividends": {
+    "1706797800": {"amount": 2.0, "date": 1706797800},
+    "1701441000": {"amount": 1.0, "date": 1701441000}
+}}}], "error": null}}"#;
+
+// Where --replay looks for a recorded response, see http/fixtures.rs.
+fn fixture_name(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    let slug: String = url["https://".len()..]
+        .split('?')
+        .next()
+        .unwrap()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}-{:016x}.body", slug, hash)
+}
+
+#[test]
+fn dividends_add_up_to_a_total_return() {
+    let scratch = Scratch::new("dividends");
+    let url = "https://query1.finance.yahoo.com/v8/finance/chart/%5EGSPC?range=max&interval=1d&events=div";
+    scratch.write(&fixture_name(url), CHART);
+    let history = "# schema_version=6\ntimestamp,price,volume,market_cap\n\
+                   2024-01-10 00:00:00,100.00,,\n2024-02-10 00:00:00,100.00,,\n2024-03-10 00:00:00,110.00,,\n";
+    scratch.write("sp500_prices.csv", history);
+
+    let output = scratch.run(&["--replay", ".", "dividends", "sp500", "-o", "total.csv"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let report = text(&output.stdout);
+    // December's dividend predates the history; February's 2.00 is
+    // reinvested at 100.
+    assert!(report.contains("2024-02-01 dividend 2.0000\n"), "{}", report);
+    assert!(!report.contains("2023-12-01"), "{}", report);
+    let summary = "S&P 500 since 2024-01-10: 1 dividends, price return +10.00%, total return +12.20%";
+    assert!(report.contains(summary), "{}", report);
+    let series = scratch.read("total.csv");
+    assert!(series.starts_with("timestamp,price,total_return\n2024-01-10 00:00:00,100,100\n"), "{}", series);
+    assert_eq!(scratch.read("dividends_sp500.csv"), "date,amount\n2023-12-01,1\n2024-02-01,2\n");
+
+    // The list is cached for a day, so this needs no response.
+    let cached = scratch.run(&["dividends", "sp500"]);
+    assert!(text(&cached.stdout).contains(summary), "{}", text(&cached.stderr));
+
+    let bitcoin = scratch.run(&["dividends", "bitcoin"]);
+    assert!(text(&bitcoin.stderr).contains("bitcoin: dividends are only available for Yahoo Finance tickers"));
+}
@@ -0,0 +1,70 @@
+// Tracks into the encrypted backend and reads the sealed history and alert
+// log back through the usual commands.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+interval = "1s"
+
+[storage]
+backend = "encrypted"
+key_file = "tracker.key"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+
+[[alerts]]
+name = "gold-high"
+asset = "gold"
+type = "threshold"
+above = 150.0
+"#;
+
+#[test]
+fn encrypted_histories_read_back_with_the_key() {
+    let scratch = Scratch::new("encrypted");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    let missing = scratch.run(&["alerts"]);
+    assert!(!missing.status.success() && text(&missing.stderr).contains("keygen"), "{}", text(&missing.stderr));
+
+    let keygen = scratch.run(&["keygen"]);
+    assert!(keygen.status.success(), "{}", text(&keygen.stderr));
+    assert!(!scratch.run(&["keygen"]).status.success());
+
+    let output = scratch.track(&[], "gold: $200.00", 1);
+    assert!(output.contains("gold: $100.00") && !output.contains("Error"), "{}", output);
+    let sealed = std::fs::read(scratch.dir.join("gold_prices.enc")).unwrap();
+    assert!(sealed.starts_with(b"CPTE"));
+    assert!(!String::from_utf8_lossy(&sealed).contains("100.00"));
+    assert!(!scratch.dir.join("alert_log.csv").exists());
+
+    let alerts = scratch.run(&["alerts"]);
+    assert!(alerts.status.success(), "{}", text(&alerts.stderr));
+    assert!(text(&alerts.stdout).contains("gold-high (gold, value 200.00)"), "{}", text(&alerts.stdout));
+    let digest = text(&scratch.run(&["digest", "--print"]).stdout);
+    assert!(digest.lines().any(|line| line.starts_with("gold") && line.contains("200.00")), "{}", digest);
+
+    // A damaged length is turned away before it is read.
+    let log = scratch.dir.join("alert_log.enc");
+    let sealed = std::fs::read(&log).unwrap();
+    let mut damaged = sealed.clone();
+    damaged[8] = 0xff;
+    std::fs::write(&log, &damaged).unwrap();
+    let alerts = scratch.run(&["alerts"]);
+    assert!(!alerts.status.success(), "{}", text(&alerts.stdout));
+    let stderr = text(&alerts.stderr);
+    assert!(stderr.contains("alert_log.enc: frame 1 claims "), "{}", stderr);
+    assert!(stderr.contains(" bytes, more than a frame holds; the file was altered"), "{}", stderr);
+    std::fs::write(&log, &sealed).unwrap();
+
+    // Another key opens nothing.
+    std::fs::remove_file(scratch.dir.join("tracker.key")).unwrap();
+    assert!(scratch.run(&["keygen"]).status.success());
+    let alerts = scratch.run(&["alerts"]);
+    assert!(!alerts.status.success(), "{}", text(&alerts.stdout));
+    assert!(text(&alerts.stderr).contains("the key is wrong"), "{}", text(&alerts.stderr));
+}
@@ -0,0 +1,97 @@
+// Errors as the library's callers see them, coded and classified with the
+// failed request kept apart from the message, and the binary's exit codes.
+
+mod common;
+
+use std::error::Error;
+
+use common::{text, Scratch};
+use crypto_price_tracker::{HttpFailure, PriceError};
+
+fn http(status: Option<u16>) -> PriceError {
+    let url = "https://api.coingecko.com/api/v3/simple/price".to_string();
+    let message = format!("{}: status code {}", url, status.unwrap_or_default());
+    PriceError::Http(Box::new(HttpFailure { provider: None, url, status, message, source: None }))
+}
+
+#[test]
+fn errors_are_classified_for_retrying() {
+    for (error, code, retryable) in [
+        (http(Some(429)), "E102", true),
+        (http(Some(503)), "E102", true),
+        (http(Some(404)), "E102", false),
+        (http(None), "E101", true),
+        (PriceError::NetworkError("smtp: connection refused".to_string()), "E100", true),
+        (PriceError::ParseError("EOF while parsing".to_string()), "E200", false),
+        (PriceError::FileError("prices.db: permission denied".to_string()), "E300", false),
+    ] {
+        assert_eq!(error.code(), code, "{}", error);
+        assert_eq!(error.is_retryable(), retryable, "{}", error);
+    }
+}
+
+#[test]
+fn the_provider_is_shown_with_the_request() {
+    let error = http(Some(429)).with_provider("coingecko");
+    assert_eq!(
+        error.to_string(),
+        "Network Error [E102]: coingecko: https://api.coingecko.com/api/v3/simple/price: status code 429"
+    );
+    let failure = error.http().unwrap();
+    assert_eq!((failure.provider.as_deref(), failure.status), (Some("coingecko"), Some(429)));
+    // The first provider named is kept, and errors without a request are as they were.
+    assert_eq!(error.with_provider("yahoo").http().unwrap().provider.as_deref(), Some("coingecko"));
+    assert_eq!(PriceError::ParseError("bad".to_string()).with_provider("yahoo").to_string(), "Parse Error [E200]: bad");
+}
+
+#[test]
+fn parse_errors_keep_their_cause_and_the_response() {
+    let body = format!("{{\"bitcoin\": \"{}", "a".repeat(600));
+    let cause = serde_json::from_str::<serde_json::Value>(&body).unwrap_err();
+    let error = PriceError::from(cause).with_response("https://api.coingecko.com/api/v3/simple/price", &body);
+    assert!(error.is_parse() && !error.is_retryable());
+    assert!(error.to_string().starts_with("Parse Error [E200]: EOF while parsing"), "{}", error);
+    assert!(error.source().unwrap().downcast_ref::<serde_json::Error>().is_some());
+
+    let failure = error.parse().unwrap();
+    assert_eq!(failure.body.as_ref().unwrap().len(), 500);
+    assert_eq!(failure.body_length, body.len());
+    let diagnostic = error.diagnostic();
+    assert_eq!(diagnostic[0], "  request: https://api.coingecko.com/api/v3/simple/price");
+    assert!(diagnostic[1].starts_with("  response (613 bytes, the first 500 characters): \"{\\\"bitcoin\\\": \\\"aaa"));
+    assert!(diagnostic[2].starts_with("  caused by: EOF while parsing"), "{:?}", diagnostic);
+    // Only the first response is kept.
+    assert_eq!(error.with_response("https://elsewhere", "{}").parse().unwrap().body_length, body.len());
+}
+
+#[test]
+fn failures_exit_with_their_own_codes() {
+    let scratch = Scratch::new("exit-codes");
+    let code = |args: &[&str]| scratch.run(args).status.code();
+    assert_eq!(code(&["quote", "bitcoin", "--mock-server=rate-limit"]), Some(4));
+    assert_eq!(code(&["quote", "bitcoin", "--mock-server=malformed"]), Some(5));
+    assert_eq!(code(&["quote", "bitcoin", "--mock-server"]), Some(0));
+    assert_eq!(code(&["chart", "--no-such-flag"]), Some(2));
+
+    scratch.write("tracker.toml", "interval = [\n");
+    let output = scratch.run(&["quote", "bitcoin", "--mock-server"]);
+    assert_eq!(output.status.code(), Some(3), "{}", text(&output.stderr));
+}
+
+#[test]
+fn durations_out_of_range_are_parse_errors() {
+    let scratch = Scratch::new("errors-durations");
+    // Past what a duration holds, then past the earliest date.
+    for (args, message) in [
+        (["alerts", "--since", "9999999999999999d"], "duration '9999999999999999d' is out of range"),
+        (["alerts", "--since", "100000000d"], "100000000 days back is out of range"),
+        (["report", "--since", "100000000d"], "100000000 days back is out of range"),
+    ] {
+        let output = scratch.run(&args);
+        let stderr = text(&output.stderr);
+        assert!(!output.status.success() && stderr.contains(message), "{:?}: {}", args, stderr);
+        assert!(!stderr.contains("panicked"), "{:?}: {}", args, stderr);
+    }
+    let output = scratch.run(&["chart", "bitcoin", "--since", "1d", "--until", "100000000d", "-o", "chart.svg"]);
+    assert!(text(&output.stderr).contains("100000000 days back is out of range"), "{}", text(&output.stderr));
+}
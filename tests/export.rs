@@ -0,0 +1,102 @@
+// Exports a seeded history as beancount and hledger price directives.
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use common::{text, Scratch};
+use flate2::read::DeflateDecoder;
+
+const HISTORY: &str = "# schema_version=6\ntimestamp,price,volume,market_cap,price_eur
+2024-06-01 09:00:00,66000.00,,,60720.00
+2024-06-01 21:00:00,67000.00,,,61640.00
+2024-06-02 09:00:00,68000.125,,,
+";
+
+fn export(scratch: &Scratch, args: &[&str]) -> String {
+    let output = scratch.run(&[&["export"], args].concat());
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+#[test]
+fn daily_prices_are_exported_as_directives() {
+    let scratch = Scratch::new("export");
+    scratch.write("bitcoin_prices.csv", HISTORY);
+
+    let beancount = export(&scratch, &["--format", "beancount", "--asset", "bitcoin"]);
+    assert_eq!(beancount, "2024-06-01 price BTC 67000.00 USD\n2024-06-02 price BTC 68000.12 USD\n");
+    let hledger = export(&scratch, &["--format", "hledger", "--currency", "EUR"]);
+    assert_eq!(hledger, "P 2024-06-01 BTC 61640.00 EUR\n");
+
+    export(&scratch, &["--format", "beancount", "--output", "prices.beancount"]);
+    assert_eq!(scratch.read("prices.beancount"), beancount);
+    let unknown = scratch.run(&["export", "--format", "hledger", "--currency", "gbp"]);
+    assert!(!unknown.status.success());
+    let error = text(&unknown.stderr);
+    assert!(error.contains("'gbp' is not one of the configured currencies"), "{}", error);
+}
+
+#[test]
+fn gnucash_prices_use_the_mapped_commodities() {
+    let scratch = Scratch::new("export-gnucash");
+    scratch.write("bitcoin_prices.csv", HISTORY);
+    scratch.write(
+        "sp500_prices.csv",
+        "# schema_version=6\ntimestamp,price,volume,market_cap\n2024-06-01 16:00:00,5277.51,,\n",
+    );
+    scratch.write(
+        "tracker.toml",
+        "[gnucash]\nnamespace = \"FX\"\ncommodities.sp500 = { namespace = \"INDEX\", symbol = \"^GSPC\" }\n",
+    );
+    let csv = export(&scratch, &["--format", "gnucash", "--since", "100000d"]);
+    assert_eq!(
+        csv,
+        "date,namespace,symbol,price,currency\n2024-06-01,FX,BTC,67000.00,USD\n2024-06-02,FX,BTC,68000.12,USD\n\
+         2024-06-01,INDEX,^GSPC,5277.51,USD\n"
+    );
+}
+
+// The files in a zip archive, by walking its local headers.
+fn unzip(archive: &[u8]) -> BTreeMap<String, String> {
+    let mut files = BTreeMap::new();
+    let mut at = 0;
+    while archive[at..].starts_with(&[0x50, 0x4b, 0x03, 0x04]) {
+        let number = |offset: usize, size: usize| {
+            archive[at + offset..at + offset + size].iter().rev().fold(0, |n, byte| n << 8 | *byte as usize)
+        };
+        let (compressed, name_length, extra) = (number(18, 4), number(26, 2), number(28, 2));
+        let name = String::from_utf8(archive[at + 30..at + 30 + name_length].to_vec()).unwrap();
+        let start = at + 30 + name_length + extra;
+        let mut contents = String::new();
+        DeflateDecoder::new(&archive[start..start + compressed]).read_to_string(&mut contents).unwrap();
+        files.insert(name, contents);
+        at = start + compressed;
+    }
+    files
+}
+
+#[test]
+fn xlsx_has_a_sheet_per_asset() {
+    let scratch = Scratch::new("export-xlsx");
+    scratch.write("bitcoin_prices.csv", HISTORY);
+    let missing = scratch.run(&["export", "--format", "xlsx"]);
+    assert!(text(&missing.stderr).contains("needs --output"), "{}", text(&missing.stderr));
+
+    export(&scratch, &["--format", "xlsx", "--output", "prices.xlsx"]);
+    let files = unzip(&std::fs::read(scratch.dir.join("prices.xlsx")).unwrap());
+    let workbook = &files["xl/workbook.xml"];
+    assert!(workbook.contains(r#"<sheet name="Overview" sheetId="1""#), "{}", workbook);
+    assert!(workbook.contains(r#"<sheet name="Bitcoin" sheetId="2""#), "{}", workbook);
+    // Only bitcoin has a history.
+    assert!(!files.contains_key("xl/worksheets/sheet3.xml"));
+
+    let overview = &files["xl/worksheets/sheet1.xml"];
+    assert!(overview.contains("<t>Bitcoin</t>") && overview.contains("<v>68000.125</v>"), "{}", overview);
+    let bitcoin = &files["xl/worksheets/sheet2.xml"];
+    assert!(bitcoin.contains("<t>Price (EUR)</t>"), "{}", bitcoin);
+    // 2024-06-01 09:00 as an Excel date, styled as one.
+    assert!(bitcoin.contains(r#"<c r="A2" s="1"><v>45444.375</v></c><c r="B2"><v>66000</v></c>"#), "{}", bitcoin);
+    assert_eq!(bitcoin.matches("<row ").count(), 4);
+}
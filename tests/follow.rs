@@ -0,0 +1,46 @@
+// Follows the records a running tracker stores.
+
+mod common;
+
+use common::{collect, stop, Scratch};
+
+const CONFIG: &str = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [10.0, 20.0, 30.0]
+"#;
+
+#[test]
+fn new_records_are_printed_as_they_are_stored() {
+    let scratch = Scratch::new("follow");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    scratch.write("gold_prices.csv", "timestamp,price,volume,market_cap\n2024-01-01 00:00:00,5.00,,\n");
+
+    let (mut json, json_lines) = scratch.start(&["follow", "--asset", "gold", "--json"]);
+    let (mut plain, plain_lines) = scratch.start(&["follow"]);
+    // Both have read where the history ends before the tracker adds to it.
+    assert!(collect(&json_lines, "Following gold", 1).1);
+    let (followed, _) = collect(&plain_lines, "Following gold", 1);
+    assert!(followed.contains("Following gold, press Ctrl+C to stop"), "{}", followed);
+    // Held so the tracker's output has somewhere to go.
+    let (mut tracker, _output) = scratch.start(&[]);
+
+    let (output, complete) = collect(&json_lines, "\"id\":\"gold\"", 2);
+    let (printed, printed_all) = collect(&plain_lines, "] gold: $", 2);
+    stop(&mut tracker);
+    stop(&mut json);
+    stop(&mut plain);
+    assert!(complete, "{}", output);
+    assert!(printed_all, "{}", printed);
+
+    // The record stored before following started is not repeated.
+    assert!(!output.contains("2024-01-01") && !printed.contains("2024-01-01"), "{}\n{}", output, printed);
+    let records: Vec<serde_json::Value> =
+        output.lines().filter(|line| line.starts_with('{')).map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records[0]["price"], 10.0);
+    assert_eq!(records[1]["price"], 20.0);
+    assert!(printed.contains("] gold: $10.00\n") && printed.contains("] gold: $20.00"), "{}", printed);
+}
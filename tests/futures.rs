@@ -0,0 +1,79 @@
+// Tracks perpetual futures mark prices and funding rates from the Binance and
+// Bybit endpoints of the --mock-server.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn mark_price_and_funding_are_separate_series() {
+    let config = r#"
+[futures.btc-perp]
+exchange = "binance"
+symbol = "BTCUSDT"
+name = "BTC perp"
+
+[futures.btc-funding]
+exchange = "binance"
+symbol = "BTCUSDT"
+series = "funding"
+
+[futures.eth-funding]
+exchange = "bybit"
+symbol = "ETHUSDT"
+series = "funding"
+"#;
+    let scratch = Scratch::with_config("futures", config);
+    let output = scratch.track(&["--mock-server"], "eth-funding: $", 1);
+    assert!(output.contains("BTC perp: $65032.50"), "{}", output);
+    assert!(output.contains("btc-funding: $1.00"), "{}", output);
+    assert!(output.contains("eth-funding: $-0.50"), "{}", output);
+
+    assert!(scratch.read("btc-perp_prices.csv").contains(",65032.50,"));
+    assert!(scratch.read("btc-funding_prices.csv").contains(",1.00,"));
+
+    // Negative funding reads back like any other value.
+    let output = scratch.run(&["export", "--format", "hledger", "--asset", "eth-funding"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert!(text(&output.stdout).contains("-0.50"), "{}", text(&output.stdout));
+}
+
+#[test]
+fn mark_prices_carry_the_24h_range() {
+    let config = r#"
+[futures.btc-perp]
+exchange = "binance"
+symbol = "BTCUSDT"
+
+[futures.eth-perp]
+exchange = "bybit"
+symbol = "ETHUSDT"
+"#;
+    let scratch = Scratch::with_config("futures-day", config);
+    scratch.track(&["--mock-server"], "eth-perp: $", 2);
+
+    let btc = scratch.read("btc-perp_prices.csv");
+    assert!(btc.contains("day_high,day_low,day_vwap,seq,source_time\n"), "{}", btc);
+    assert!(btc.contains(",65032.50,64707337.50,,66333.15,"), "{}", btc);
+    assert!(btc.contains(",63081.525,64707.3375,1,1714521600\n"), "{}", btc);
+    let eth = scratch.read("eth-perp_prices.csv");
+    assert!(eth.contains(",3201.60,3185592.00,,3265.632,"), "{}", eth);
+    assert!(eth.contains(",3185.592,1,1714521600\n"), "{}", eth);
+}
+
+#[test]
+fn unknown_contracts_fail_the_fetch() {
+    let config = "[futures.perp]\nexchange = \"bybit\"\nsymbol = \"DOGEUSDC\"\n";
+    let scratch = Scratch::with_config("futures-unknown", config);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for perp", 1);
+    assert!(output.contains("Bybit has no linear contract DOGEUSDC"), "{}", output);
+}
+
+#[test]
+fn exchanges_are_checked() {
+    let scratch = Scratch::with_config("futures-check", "[futures.perp]\nexchange = \"kraken\"\nsymbol = \"BTCUSD\"\n");
+    let output = scratch.run(&["config", "check"]);
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(!output.status.success(), "{}", report);
+    assert!(report.contains("unknown variant `kraken`"), "{}", report);
+}
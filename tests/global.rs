@@ -0,0 +1,45 @@
+// Records CoinGecko's market wide figures from the --mock-server.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn market_cap_and_dominance_are_series() {
+    let config = r#"
+[global.crypto-cap]
+metric = "market_cap"
+name = "Crypto market cap"
+
+[global.btc-dominance]
+metric = "dominance"
+
+[global.eth-dominance]
+metric = "dominance"
+coin = "ETH"
+"#;
+    let scratch = Scratch::with_config("global", config);
+    let output = scratch.track(&["--mock-server"], "eth-dominance: ", 1);
+    assert!(output.contains("Crypto market cap: $1298671375000.00"), "{}", output);
+    assert!(output.contains("btc-dominance: $95.10"), "{}", output);
+    assert!(output.contains("eth-dominance: $4.68"), "{}", output);
+
+    let cap = scratch.read("crypto-cap_prices.csv");
+    assert!(cap.contains(",1298671375000.00,25973427500.00,"), "{}", cap);
+}
+
+#[test]
+fn unknown_coins_fail_the_fetch() {
+    let scratch = Scratch::with_config("global-unknown", "[global.xrp]\nmetric = \"dominance\"\ncoin = \"xrp\"\n");
+    let output = scratch.track(&["--mock-server"], "Error fetching price for xrp", 1);
+    assert!(output.contains("no market cap share of xrp in CoinGecko's global data"), "{}", output);
+}
+
+#[test]
+fn metrics_are_checked() {
+    let scratch = Scratch::with_config("global-check", "[global.fees]\nmetric = \"fees\"\n");
+    let output = scratch.run(&["config", "check"]);
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(!output.status.success(), "{}", report);
+    assert!(report.contains("unknown variant `fees`"), "{}", report);
+}
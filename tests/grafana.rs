@@ -0,0 +1,90 @@
+// Serves a seeded history and alert log to requests shaped like those of
+// Grafana's JSON datasource plugin.
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use common::{collect, stop, Scratch};
+use serde_json::{json, Value};
+
+const CONFIG: &str = r#"
+currencies = ["eur"]
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+"#;
+
+const HISTORY: &str = "# schema_version=6\ntimestamp,price,volume,market_cap,price_eur
+2024-01-01 00:00:00,50.00,5.00,,46.00
+2024-01-01 12:00:00,60.00,,,55.20
+2024-01-02 00:00:00,70.00,7.00,,64.40
+2024-01-05 00:00:00,80.00,8.00,,73.60
+";
+
+const RANGE: &str = r#"{"from": "2023-12-31T00:00:00.000Z", "to": "2024-01-03T00:00:00.000Z"}"#;
+
+// Sends one request and returns the status code and the body as JSON.
+fn request(address: &str, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    let head = format!("{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n", method, path, address, body.len());
+    stream.write_all((head + body).as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn grafana_queries_chart_the_history() {
+    let scratch = Scratch::new("grafana");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    scratch.write("gold_prices.csv", HISTORY);
+    scratch.write(
+        "alert_log.csv",
+        "timestamp,rule,asset,value,outcome,message\n2024-01-01 12:00:00,gold-high,gold,60,console: ok,gold above 55\n",
+    );
+    let (mut child, lines) = scratch.start(&["serve", "--listen", "127.0.0.1:0"]);
+    let (output, started) = collect(&lines, "Serving", 1);
+    assert!(started, "{}", output);
+    let address = output.trim().rsplit("http://").next().unwrap().to_string();
+
+    assert_eq!(request(&address, "GET", "/", "").0, 200);
+    let (_, targets) = request(&address, "POST", "/search", "{}");
+    assert_eq!(targets, json!(["gold", "gold.volume", "gold.market_cap", "gold.eur"]));
+
+    let query = format!(
+        r#"{{"range": {}, "maxDataPoints": 100, "targets": [{{"target": "gold", "refId": "A"}},
+            {{"target": "gold.volume", "refId": "B"}}, {{"target": "gold.eur", "refId": "C"}}]}}"#,
+        RANGE
+    );
+    let (status, series) = request(&address, "POST", "/query", &query);
+    assert_eq!(status, 200, "{}", series);
+    let values = |index: usize| -> Vec<f64> {
+        series[index]["datapoints"].as_array().unwrap().iter().map(|point| point[0].as_f64().unwrap()).collect()
+    };
+    assert_eq!(series[0]["target"], "gold");
+    assert_eq!(values(0), [50.0, 60.0, 70.0]);
+    assert_eq!(values(1), [5.0, 7.0]);
+    assert_eq!(values(2), [46.0, 55.2, 64.4]);
+    let times: Vec<i64> = series[0]["datapoints"].as_array().unwrap().iter().map(|p| p[1].as_i64().unwrap()).collect();
+    assert_eq!(times[1] - times[0], 12 * 3600 * 1000);
+
+    let thinned = format!(r#"{{"range": {}, "maxDataPoints": 2, "targets": [{{"target": "gold"}}]}}"#, RANGE);
+    let (_, series) = request(&address, "POST", "/query", &thinned);
+    assert_eq!(series[0]["datapoints"].as_array().unwrap().len(), 2);
+
+    let unknown = format!(r#"{{"range": {}, "targets": [{{"target": "silver"}}]}}"#, RANGE);
+    let (status, error) = request(&address, "POST", "/query", &unknown);
+    assert_eq!(status, 400);
+    assert!(error["error"].as_str().unwrap().contains("unknown target 'silver'"), "{}", error);
+
+    let (_, annotations) = request(&address, "POST", "/annotations", &format!(r#"{{"range": {}}}"#, RANGE));
+    assert_eq!(annotations[0]["title"], "gold-high");
+    assert_eq!(annotations[0]["text"], "gold above 55");
+    stop(&mut child);
+}
@@ -0,0 +1,116 @@
+// Runs chart and report over histories longer than a chart has points or a
+// database query returns at once, and ranged reads through the CSV index.
+
+mod common;
+
+use std::fmt::Write as _;
+
+use common::{text, Scratch};
+
+fn scratch(name: &str, records: usize) -> Scratch {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    // Half a minute off the boundaries of ranges given in minutes.
+    let start = chrono::Local::now().naive_local() - chrono::Duration::minutes(records as i64)
+        + chrono::Duration::seconds(30);
+    let mut history = String::from("# schema_version=9\ntimestamp,price,volume,market_cap\n");
+    for minute in 0..records {
+        let timestamp = start + chrono::Duration::minutes(minute as i64);
+        let price = 30000.0 + (minute % 500) as f64;
+        writeln!(history, "{},{:.2},,", timestamp.format("%Y-%m-%d %H:%M:%S"), price).unwrap();
+    }
+    scratch.write("bitcoin_prices.csv", &history);
+    scratch
+}
+
+fn chart(scratch: &Scratch, since: &str) -> String {
+    let output = scratch.run(&["chart", "bitcoin", "-o", "chart.svg", "--since", since]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+#[test]
+fn charts_cover_histories_longer_than_their_width() {
+    let scratch = scratch("history-chart", 30_000);
+    let output = scratch.run(&["chart", "bitcoin", "-o", "chart.svg", "--width", "400", "--sma", "20"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert!(text(&output.stdout).contains("chart of 30000 records"), "{}", text(&output.stdout));
+    let svg = scratch.read("chart.svg");
+    assert!(svg.contains("SMA(20)"), "{}", svg);
+}
+
+#[test]
+fn reports_read_databases_page_by_page() {
+    let scratch = scratch("history-sqlite", 25_000);
+    scratch.write("tracker.toml", "[storage]\nbackend = \"sqlite\"\npath = \"prices.db\"\n");
+    let migrated = scratch.run(&["migrate"]);
+    assert!(migrated.status.success(), "{}", text(&migrated.stderr));
+
+    let output = scratch.run(&["report", "--since", "30d"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let report = scratch.read("report.html");
+    assert!(report.contains("<td>30499.00</td><td>30000.00</td>"), "{}", report);
+    assert!(report.contains("<td>25000</td></tr>"), "{}", report);
+}
+
+#[test]
+fn ranged_reads_follow_the_index() {
+    let scratch = scratch("history-index", 14_400);
+    assert!(chart(&scratch, "1d").contains("chart of 1440 records"));
+    assert!(scratch.dir.join("bitcoin_prices.csv.idx").exists());
+    assert!(chart(&scratch, "90m").contains("chart of 90 records"));
+
+    // Appended records are picked up from where the index ends.
+    let now = chrono::Local::now().naive_local();
+    let mut history = scratch.read("bitcoin_prices.csv");
+    for second in [10, 20] {
+        let timestamp = now + chrono::Duration::seconds(second);
+        writeln!(history, "{},31000.00,,", timestamp.format("%Y-%m-%d %H:%M:%S")).unwrap();
+    }
+    scratch.write("bitcoin_prices.csv", &history);
+    assert!(chart(&scratch, "90m").contains("chart of 92 records"));
+
+    // A shorter file no longer matches the index.
+    let short: Vec<&str> = history.lines().take(1002).collect();
+    scratch.write("bitcoin_prices.csv", &(short.join("\n") + "\n"));
+    let output = scratch.run(&["chart", "bitcoin", "-o", "chart.svg", "--since", "1d"]);
+    assert!(!output.status.success());
+    assert!(text(&output.stderr).contains("not enough Bitcoin records"), "{}", text(&output.stderr));
+    assert!(chart(&scratch, "30d").contains("chart of 1000 records"));
+}
+
+#[test]
+fn binary_histories_convert_both_ways() {
+    let scratch = scratch("history-binary", 5_000);
+    let original = scratch.read("bitcoin_prices.csv");
+    let converted = scratch.run(&["convert", "--to", "binary"]);
+    assert!(converted.status.success(), "{}", text(&converted.stderr));
+    assert!(text(&converted.stdout).contains("Bitcoin: converted 5000 records"), "{}", text(&converted.stdout));
+    let size = std::fs::metadata(scratch.dir.join("bitcoin_prices.bin")).unwrap().len();
+    assert!(size * 2 < original.len() as u64, "{} bytes", size);
+
+    let again = scratch.run(&["convert", "--to", "binary"]);
+    assert!(!again.status.success() && text(&again.stderr).contains("already exists"), "{}", text(&again.stderr));
+
+    std::fs::remove_file(scratch.dir.join("bitcoin_prices.csv")).unwrap();
+    let config = r#"
+interval = "1s"
+
+[storage]
+backend = "binary"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+"#;
+    scratch.write("tracker.toml", config);
+    assert!(chart(&scratch, "90m").contains("chart of 90 records"));
+    let output = scratch.track(&[], "gold: $200.00", 1);
+    assert!(!output.contains("Error saving"), "{}", output);
+
+    let converted = scratch.run(&["convert", "--to", "csv"]);
+    assert!(converted.status.success(), "{}", text(&converted.stderr));
+    assert_eq!(scratch.read("bitcoin_prices.csv"), original);
+    let gold = scratch.read("gold_prices.csv");
+    assert!(gold.contains(",100.00,") && gold.contains(",200.00,"), "{}", gold);
+}
@@ -0,0 +1,83 @@
+// Merging external CSV history into what the tracker has stored.
+
+mod common;
+
+use common::{text, Scratch};
+
+const HEADER: &str = "# schema_version=9\ntimestamp,price,volume,market_cap\n";
+
+fn scratch(name: &str, stored: &str) -> Scratch {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    scratch.write("bitcoin_prices.csv", &format!("{}{}", HEADER, stored));
+    scratch
+}
+
+fn rows(scratch: &Scratch) -> Vec<String> {
+    let history = scratch.read("bitcoin_prices.csv");
+    history.lines().skip(2).map(|line| line.split(',').take(2).collect::<Vec<_>>().join(",")).collect()
+}
+
+#[test]
+fn imports_are_merged_in_timestamp_order() {
+    let scratch = scratch("import-merge", "2024-01-02 00:00:00,42000.00,,\n2024-01-04 00:00:00,44000.00,,\n");
+    scratch.write("old.csv", "timestamp,price\n2024-01-03 00:00:00,43000\n2024-01-01 00:00:00,41000\n");
+
+    let output = scratch.run(&["import", "bitcoin", "old.csv"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert!(
+        text(&output.stdout).contains("Imported 2 records into Bitcoin (0 duplicates skipped, 4 total)"),
+        "{}",
+        text(&output.stdout)
+    );
+    assert_eq!(
+        rows(&scratch),
+        [
+            "2024-01-01 00:00:00,41000.00",
+            "2024-01-02 00:00:00,42000.00",
+            "2024-01-03 00:00:00,43000.00",
+            "2024-01-04 00:00:00,44000.00"
+        ]
+    );
+}
+
+#[test]
+fn stored_prices_win_over_imported_duplicates() {
+    // Two stored records share a second; both are kept.
+    let stored = "2024-01-02 00:00:00,42000.00,,\n2024-01-02 00:00:00,42010.00,,\n2024-01-03 00:00:00,43000.00,,\n";
+    let scratch = scratch("import-duplicates", stored);
+    scratch.write(
+        "old.csv",
+        "timestamp,price\n2024-01-01 00:00:00,41000\n2024-01-02 00:00:00,1\n2024-01-01 00:00:00,2\n",
+    );
+
+    let output = scratch.run(&["import", "bitcoin", "old.csv"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    assert!(
+        text(&output.stdout).contains("Imported 1 records into Bitcoin (2 duplicates skipped, 4 total)"),
+        "{}",
+        text(&output.stdout)
+    );
+    assert_eq!(
+        rows(&scratch),
+        [
+            "2024-01-01 00:00:00,41000.00",
+            "2024-01-02 00:00:00,42000.00",
+            "2024-01-02 00:00:00,42010.00",
+            "2024-01-03 00:00:00,43000.00"
+        ]
+    );
+}
+
+#[test]
+fn future_timestamps_are_refused_before_anything_is_written() {
+    let scratch = scratch("import-future", "2024-01-02 00:00:00,42000.00,,\n");
+    let later = (chrono::Local::now() + chrono::Duration::days(1)).format("%Y-%m-%d %H:%M:%S").to_string();
+    scratch.write("old.csv", &format!("timestamp,price\n2024-01-01 00:00:00,41000\n{},50000\n", later));
+
+    let output = scratch.run(&["import", "bitcoin", "old.csv"]);
+    assert!(!output.status.success());
+    let stderr = text(&output.stderr);
+    assert!(stderr.contains(&format!("old.csv: timestamp {} is in the future", later)), "{}", stderr);
+    assert_eq!(rows(&scratch), ["2024-01-02 00:00:00,42000.00"]);
+}
@@ -0,0 +1,36 @@
+// Deflates a seeded history by a cached CPI series.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CPI: &str = "observation_date,CPIAUCSL\n2024-01-01,300.0\n2024-02-01,.\n2024-03-01,330.0\n";
+
+#[test]
+fn reports_show_prices_in_base_month_dollars() {
+    let scratch = Scratch::new("inflation");
+    scratch.write("tracker.toml", "[inflation]\nbase = \"2024-01\"\n");
+    // Written just now, so the cache counts as fresh and nothing is fetched.
+    scratch.write("cpi_CPIAUCSL.csv", CPI);
+    let history = "# schema_version=6\ntimestamp,price,volume,market_cap\n\
+                   2023-12-15 00:00:00,90.00,,\n2024-01-15 00:00:00,100.00,,\n2024-03-10 00:00:00,110.00,,\n";
+    scratch.write("bitcoin_prices.csv", history);
+
+    let output = scratch.run(&["report", "--since", "5000d", "--real", "-o", "real.html"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let html = scratch.read("real.html");
+    assert!(html.contains("<h1>Price report — last 5000 days, in 2024-01 dollars</h1>"), "{}", html);
+    // 110 at March's index of 330 is 100 at January's 300; December predates
+    // the series and is left out.
+    assert!(html.contains("<tr><td>Bitcoin</td><td>100.00</td><td class=\"up\">+0.00</td>"), "{}", html);
+    assert!(html.contains("<td>2</td></tr>"), "{}", html);
+
+    scratch.write("tracker.toml", "[inflation]\nbase = \"2024-02\"\n");
+    let missing = scratch.run(&["report", "--real"]);
+    assert!(text(&missing.stderr).contains("inflation.base: the series has no value for 2024-02"));
+
+    scratch.write("tracker.toml", "[inflation]\nbase = \"January\"\n");
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("inflation.base: 'January' is not a month like 2020-01"), "{}", report);
+}
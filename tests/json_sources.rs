@@ -0,0 +1,102 @@
+// Runs config-defined JSON API sources against the --mock-server.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn prices_are_extracted_by_path() {
+    let config = r#"
+[json.coin]
+url = "https://api.coingecko.com/api/v3/simple/price?ids={id}&vs_currencies=usd&include_market_cap=true"
+headers = { "X-Api-Key" = "demo" }
+name = "Custom"
+price = "$.bitcoin.usd"
+market_cap = "bitcoin.usd_market_cap"
+
+[assets.coin]
+provider_id = "bitcoin"
+"#;
+    let scratch = Scratch::with_config("json", config);
+    let output = scratch.track(&["--mock-server"], "Custom: $", 1);
+    assert!(output.contains("Custom: $65000.00"), "{}", output);
+
+    let history = scratch.read("coin_prices.csv");
+    assert!(history.contains(",65000.00,") && history.contains("1235000000000"), "{}", history);
+}
+
+#[test]
+fn quoted_keys_and_string_numbers() {
+    let config = r#"
+[json.index]
+url = "https://query1.finance.yahoo.com/v8/finance/chart/%5EGSPC"
+price = "$['Global Quote'][\"05. price\"]"
+"#;
+    let scratch = Scratch::with_config("json-quoted", config);
+    let output = scratch.track(&["--mock-server"], "index: $", 1);
+    assert!(output.contains("index: $5100.25"), "{}", output);
+}
+
+#[test]
+fn missing_values_fail_the_fetch() {
+    let config = r#"
+[json.coin]
+url = "https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&vs_currencies=usd"
+price = "$.bitcoin.eur"
+"#;
+    let scratch = Scratch::with_config("json-missing", config);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for coin", 1);
+    assert!(output.contains("nothing at $.bitcoin.eur"), "{}", output);
+}
+
+#[test]
+fn invalid_paths_are_config_problems() {
+    let config = r#"
+[json.coin]
+url = "https://example.com/price"
+price = "$.data["
+"#;
+    let scratch = Scratch::with_config("json-invalid", config);
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(report.contains("json.coin.price: invalid path '$.data[': unclosed '['"), "{}", report);
+}
+
+#[test]
+fn configured_headers_are_sent_per_host() {
+    let (base, requests) = common::serve(|_, _| ("200 OK", r#"{"price": 42.5}"#.to_string()));
+    let config = format!(
+        r#"
+user_agent = "tracker-test (ops@example.com)"
+
+[http.headers."127.0.0.1"]
+x-cg-demo-api-key = "demo-key"
+x-source = "configured"
+
+[http.headers."example.com"]
+x-other = "never sent"
+
+[json.coin]
+url = "{}/price"
+headers = {{ "X-Source" = "own" }}
+price = "$.price"
+"#,
+        base
+    );
+    let scratch = Scratch::with_config("json-headers", &config);
+    let output = scratch.track(&[], "coin: $42.50", 1);
+    assert!(!output.contains("Error"), "{}", output);
+
+    let request = requests.recv().unwrap();
+    assert_eq!(request.header("x-cg-demo-api-key"), Some("demo-key"));
+    assert_eq!(request.header("x-source"), Some("own"));
+    assert_eq!(request.header("user-agent"), Some("tracker-test (ops@example.com)"));
+    assert!(request.header("x-other").is_none());
+
+    scratch.write("tracker.toml", "[http.headers.\"https://example.com\"]\n\"bad name\" = \"x\"\n");
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("http.headers.\"https://example.com\".bad name: invalid header name"), "{}", report);
+    assert!(report.contains("http.headers: 'https://example.com' is not a host name"), "{}", report);
+}
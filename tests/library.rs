@@ -0,0 +1,92 @@
+// The stored history read through the library rather than the binary.
+
+mod common;
+
+use chrono::NaiveDate;
+use common::Scratch;
+use crypto_price_tracker::history::{HistoryReader, PriceRecord};
+
+#[test]
+fn records_are_read_from_the_configured_storage() {
+    let scratch = Scratch::new("library");
+    // Absolute, as relative paths would be taken from the test's directory.
+    let config = format!(
+        "watchlist = {:?}\n\n[storage]\nbackend = \"csv\"\ndirectory = {:?}\n",
+        scratch.dir.join("watchlist.toml").display().to_string(),
+        scratch.dir.display().to_string()
+    );
+    scratch.write("tracker.toml", &config);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    scratch.write(
+        "bitcoin_prices.csv",
+        "timestamp,price,volume,market_cap\n2024-03-01 09:00:00,100.00,5,\n2024-03-02 09:00:00,110.00,,\n",
+    );
+
+    let history = HistoryReader::open(Some(&scratch.dir.join("tracker.toml"))).unwrap();
+    assert_eq!(history.assets(), ["bitcoin"]);
+
+    let records: Vec<PriceRecord> = history.records("bitcoin").unwrap().collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].asset, "bitcoin");
+    assert_eq!(records[0].price, 100.0);
+    assert_eq!(records[0].volume, Some(5.0));
+
+    let since = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let later: Vec<PriceRecord> = history.records_since("bitcoin", since).unwrap().map(Result::unwrap).collect();
+    assert_eq!(later, records[1..]);
+    assert_eq!(history.last("bitcoin").unwrap(), Some(records[1].clone()));
+
+    let json = serde_json::to_string(&records[1]).unwrap();
+    assert_eq!(
+        json,
+        r#"{"asset":"bitcoin","timestamp":"2024-03-02 09:00:00","price":110.0,"volume":null,"market_cap":null}"#
+    );
+    assert_eq!(serde_json::from_str::<PriceRecord>(&json).unwrap(), records[1]);
+}
+
+#[test]
+fn converted_quotes_and_source_times_are_kept() {
+    let scratch = Scratch::new("library-extra");
+    let config = format!(
+        "watchlist = {:?}\n\n[storage]\nbackend = \"csv\"\ndirectory = {:?}\n",
+        scratch.dir.join("watchlist.toml").display().to_string(),
+        scratch.dir.display().to_string()
+    );
+    scratch.write("tracker.toml", &config);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    scratch.write(
+        "bitcoin_prices.csv",
+        "timestamp,price,volume,market_cap,price_eur,fx_eur,source_time\n\
+         2024-03-01 09:00:00,100.00,,,92.00,0.92,1709283570\n",
+    );
+
+    let history = HistoryReader::open(Some(&scratch.dir.join("tracker.toml"))).unwrap();
+    let record = history.last("bitcoin").unwrap().unwrap();
+    assert_eq!(record.fx_rates.get("eur"), Some(&0.92));
+    assert_eq!(record.source_time.unwrap().timestamp(), 1709283570);
+
+    let json = serde_json::to_string(&record).unwrap();
+    assert!(json.contains(r#""fx_rates":{"eur":0.92}"#), "{}", json);
+    assert!(json.ends_with(r#""source_time":"2024-03-01T08:59:30Z"}"#), "{}", json);
+    assert_eq!(serde_json::from_str::<PriceRecord>(&json).unwrap(), record);
+}
+
+#[test]
+fn sub_cent_prices_are_stored_in_full() {
+    let mock = "[mock.pepe]\npattern = \"sequence\"\nprices = [0.00001234, 0.5]\n";
+    let scratch = Scratch::with_config("library-sub-cent", mock);
+    scratch.track(&[], "pepe: $", 3);
+    let history = scratch.read("pepe_prices.csv");
+    assert!(history.contains(",0.00001234,"), "{}", history);
+    assert!(history.contains(",0.50,"), "{}", history);
+
+    let config = format!(
+        "watchlist = {:?}\n\n[storage]\nbackend = \"csv\"\ndirectory = {:?}\n",
+        scratch.dir.join("watchlist.toml").display().to_string(),
+        scratch.dir.display().to_string()
+    );
+    scratch.write("library.toml", &config);
+    let history = HistoryReader::open(Some(&scratch.dir.join("library.toml"))).unwrap();
+    let prices: Vec<f64> = history.records("pepe").unwrap().map(|record| record.unwrap().price).collect();
+    assert_eq!(prices[..2], [0.00001234, 0.5]);
+}
@@ -0,0 +1,64 @@
+// Copies CSV history into a SQLite database, with progress and row counts.
+
+mod common;
+
+use std::fmt::Write as _;
+
+use common::{text, Scratch};
+use rusqlite::Connection;
+
+fn scratch(name: &str, records: usize) -> Scratch {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n[[assets]]\nid = \"ethereum\"\n");
+    scratch.write("tracker.toml", "[storage]\nbackend = \"sqlite\"\npath = \"prices.db\"\n");
+    let start = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+    let mut history = String::from("# schema_version=9\ntimestamp,price,volume,market_cap\n");
+    for minute in 0..records {
+        let timestamp = start + chrono::Duration::minutes(minute as i64);
+        writeln!(history, "{},{:.2},,", timestamp.format("%Y-%m-%d %H:%M:%S"), 40000.0 + minute as f64).unwrap();
+    }
+    scratch.write("bitcoin_prices.csv", &history);
+    scratch
+}
+
+fn rows(scratch: &Scratch) -> u64 {
+    let conn = Connection::open(scratch.dir.join("prices.db")).unwrap();
+    conn.query_row("SELECT COUNT(*) FROM prices WHERE asset = 'bitcoin'", [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn histories_are_copied_in_batches_and_counted() {
+    let scratch = scratch("migrate", 2500);
+
+    let output = scratch.run(&["migrate"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let stdout = text(&output.stdout);
+    // The progress line is redrawn after every batch.
+    let progress = "\rBitcoin: 1000 rows read\rBitcoin: 2000 rows read\r\
+                    Bitcoin: 2500 rows read, 2500 inserted, 0 already present\n";
+    assert!(stdout.contains(progress), "{:?}", stdout);
+    assert!(stdout.contains("Ethereum: no CSV history at ./ethereum_prices.csv, skipping"), "{}", stdout);
+    assert!(stdout.contains("Migration into prices.db complete"), "{}", stdout);
+    assert_eq!(rows(&scratch), 2500);
+
+    // Run again, the rows are all there already.
+    let output = scratch.run(&["migrate"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let stdout = text(&output.stdout);
+    assert!(stdout.contains("\rBitcoin: 2500 rows read, 0 inserted, 2500 already present\n"), "{:?}", stdout);
+    assert_eq!(rows(&scratch), 2500);
+
+    let query = scratch.run(&["query", "SELECT MIN(price), MAX(price) FROM prices"]);
+    assert!(text(&query.stdout).contains("40000"), "{}", text(&query.stdout));
+    assert!(text(&query.stdout).contains("42499"), "{}", text(&query.stdout));
+}
+
+#[test]
+fn only_a_database_backend_can_be_migrated_into() {
+    let scratch = scratch("migrate-csv", 10);
+    scratch.write("tracker.toml", "[storage]\nbackend = \"csv\"\n");
+    let output = scratch.run(&["migrate"]);
+    assert!(!output.status.success());
+    let stderr = text(&output.stderr);
+    assert!(stderr.contains("migrate needs a database backend; set [storage] backend = \"sqlite\""), "{}", stderr);
+}
@@ -0,0 +1,351 @@
+// Runs the binary against its --mock-server.
+
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use common::{collect, stop, text, Scratch};
+
+#[test]
+fn tracks_prices_and_writes_history() {
+    let scratch = Scratch::new("ok");
+    let output = scratch.track(&["--mock-server=ok"], "S&P 500: $", 2);
+    assert!(output.contains("Bitcoin: $65000.00 (EUR 59800.00)"), "{}", output);
+    assert!(output.contains("S&P 500: $5100.25"), "{}", output);
+    assert!(!output.contains("Error"), "{}", output);
+    assert!(!output.contains("local clock"), "{}", output);
+
+    let history = scratch.read("bitcoin_prices.csv");
+    let rows: Vec<&str> = history.lines().filter(|line| line.starts_with(|c: char| c.is_ascii_digit())).collect();
+    assert!(!rows.is_empty() && rows.iter().all(|row| row.contains(",65000.00,")), "{}", history);
+    let history = scratch.read("sp500_prices.csv");
+    assert!(history.lines().filter(|line| line.contains(",5100.25")).count() >= 2, "{}", history);
+}
+
+#[test]
+fn records_are_numbered_across_restarts() {
+    let scratch = Scratch::new("sequence");
+    scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    let first = scratch.read("sp500_prices.csv");
+    let output = scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    // CoinGecko dates its quotes, so the first fetch after the restart is
+    // seen to repeat the stored one.
+    assert!(output.contains("Bitcoin: same as the last stored price, not written again"), "{}", output);
+    let history = scratch.read("bitcoin_prices.csv");
+    let mut lines = history.lines().skip(1);
+    assert!(lines.next().unwrap().ends_with(",seq,source_time"), "{}", history);
+    let rows: Vec<&str> = lines.collect();
+    assert!(rows.len() == 1 && rows[0].ends_with(",1,1714521600"), "{}", history);
+
+    let numbers: Vec<u64> = scratch
+        .read("sp500_prices.csv")
+        .lines()
+        .skip(2)
+        .map(|line| line.rsplit(',').next().unwrap().parse().unwrap())
+        .collect();
+    let expected: Vec<u64> = (1..=numbers.len() as u64).collect();
+    assert!(numbers.len() > first.lines().count() - 2 && numbers == expected, "{:?}", numbers);
+}
+
+#[test]
+fn incomplete_last_lines_are_dropped() {
+    let scratch = Scratch::new("incomplete");
+    let history = "# schema_version=9\ntimestamp,price,volume,market_cap,seq\n\
+                   2024-05-01 00:00:00,64000.00,,,7\n2024-05-01 00:01:00,640";
+    scratch.write("bitcoin_prices.csv", history);
+    let output = scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    assert!(output.contains("Dropped the incomplete last line of "), "{}", output);
+    assert!(output.contains("bitcoin_prices.csv: '2024-05-01 00:01:00,640'"), "{}", output);
+
+    let history = scratch.read("bitcoin_prices.csv");
+    let rows: Vec<&str> = history.lines().skip(2).collect();
+    assert!(rows.len() == 2 && rows[0].ends_with(",7,"), "{}", history);
+    assert!(rows[1].contains(",65000.00,") && rows[1].ends_with(",8,1714521600"), "{}", history);
+}
+
+#[test]
+fn clock_skew_is_warned_about_once() {
+    let scratch = Scratch::new("skewed");
+    let output = scratch.track(&["--mock-server=skewed"], "S&P 500: $", 2);
+    // Ten minutes, give or take the second the Date header is rounded to.
+    let warning = output.lines().find(|line| line.starts_with("Warning: the local clock is ")).unwrap_or_default();
+    assert!(warning.contains(" behind the servers' Date headers"), "{}", output);
+    assert!(warning.contains(" is 10m 0s ") || warning.contains(" is 9m 59s "), "{}", output);
+    assert_eq!(output.matches("local clock").count(), 1, "{}", output);
+
+    scratch.write("tracker.toml", &common::CONFIG.replace("[http]\n", "[http]\nmax_clock_skew = \"15m\"\n"));
+    let output = scratch.track(&["--mock-server=skewed"], "S&P 500: $", 2);
+    assert!(!output.contains("local clock"), "{}", output);
+}
+
+#[test]
+fn unchanged_responses_are_not_stored_again() {
+    let scratch = Scratch::new("not-modified");
+    let output = scratch.track(&["--mock-server"], "Bitcoin: unchanged", 2);
+    assert!(output.contains("Bitcoin: $65000.00"), "{}", output);
+
+    let history = scratch.read("bitcoin_prices.csv");
+    let rows = history.lines().filter(|line| line.starts_with(|c: char| c.is_ascii_digit())).count();
+    assert_eq!(rows, 1, "{}", history);
+}
+
+#[test]
+fn dry_run_writes_nothing() {
+    let scratch = Scratch::new("dry-run");
+    let mut child = scratch
+        .command(&["--dry-run", "--mock-server"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while stdout.read_line(&mut line).unwrap() > 0 && !line.contains("S&P 500: $") {
+        line.clear();
+    }
+    stop(&mut child);
+    assert!(line.contains("S&P 500: $5100.25"), "{}", line);
+    assert!(!scratch.dir.join("bitcoin_prices.csv").exists());
+}
+
+#[test]
+fn rate_limited_requests_fail_without_stopping_the_tracker() {
+    let scratch = Scratch::new("rate-limit");
+    let output = scratch.track(&["--mock-server=rate-limit"], "Error fetching price for Bitcoin", 2);
+    // Coded, and with the provider the request failed at.
+    let error = "Error fetching price for Bitcoin: Network Error [E102]: coingecko: http://";
+    assert!(output.contains(error), "{}", output);
+    assert!(output.contains("status code 429"), "{}", output);
+    assert!(scratch.read("bitcoin_prices.csv").is_empty());
+}
+
+#[test]
+fn malformed_json_is_a_parse_error() {
+    let scratch = Scratch::new("malformed");
+    let output = scratch.track(&["--mock-server=malformed"], "Error fetching price for", 2);
+    let error = "Error fetching price for Bitcoin: Parse Error [E200]: coingecko: EOF while parsing";
+    assert!(output.contains(error), "{}", output);
+    assert!(output.contains("Error fetching price for S&P 500: Parse Error [E200]"), "{}", output);
+    assert!(scratch.read("bitcoin_prices.csv").is_empty());
+}
+
+#[test]
+fn verbose_errors_show_the_response() {
+    let scratch = Scratch::new("malformed-verbose");
+    let output = scratch.track(&["--mock-server=malformed", "--verbose"], "  caused by:", 2);
+    assert!(output.contains("Error fetching price for Bitcoin: Parse Error [E200]: coingecko: EOF"), "{}", output);
+    let request = "  request: https://api.coingecko.com/api/v3/simple/price?ids=bitcoin&";
+    assert!(output.contains(request), "{}", output);
+    assert!(output.contains(" bytes): \"{\\\"bitcoin\\\":{"), "{}", output);
+}
+
+#[test]
+fn slow_responses_time_out() {
+    let scratch = Scratch::new("timeout");
+    let started = Instant::now();
+    // Both assets are fetched at once, in no particular order.
+    let output = scratch.track(&["--mock-server=timeout"], "timed out", 2);
+    assert!(output.contains("Error fetching price for Bitcoin"), "{}", output);
+    assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+}
+
+#[test]
+fn asset_add_looks_the_coin_up() {
+    let scratch = Scratch::new("asset-add");
+    let output = scratch.run(&["--mock-server", "asset", "add", "solana"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let watchlist = scratch.read("watchlist.toml");
+    assert!(watchlist.contains("name = \"Solana\"") && watchlist.contains("symbol = \"sol\""), "{}", watchlist);
+
+    let output = scratch.run(&["--mock-server", "asset", "add", "no-such-coin"]);
+    assert!(!output.status.success());
+    assert!(text(&output.stderr).contains("CoinGecko has no coin with id 'no-such-coin'"), "{}", text(&output.stderr));
+}
+
+#[test]
+fn asset_add_reports_a_rate_limit() {
+    let scratch = Scratch::new("asset-add-429");
+    let output = scratch.run(&["--mock-server=rate-limit", "asset", "add", "solana"]);
+    assert!(!output.status.success());
+    assert!(text(&output.stderr).contains("status code 429"), "{}", text(&output.stderr));
+    assert!(!scratch.read("watchlist.toml").contains("solana"));
+}
+
+#[test]
+fn search_ranks_exact_symbols_first() {
+    let scratch = Scratch::new("search");
+    let output = scratch.run(&["--mock-server", "search", "sol"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let stdout = text(&output.stdout);
+    let first = stdout.lines().nth(1).unwrap_or_default();
+    assert!(first.contains("solana") && first.contains("Solana (sol)"), "{}", stdout);
+}
+
+#[test]
+fn connections_are_kept_open_between_rounds() {
+    let scratch = Scratch::new("keep-alive");
+    let (mut child, lines) = scratch.start(&["--mock-server"]);
+    let (output, complete) = collect(&lines, "S&P 500: $", 3);
+    let stats = output.lines().find_map(|line| line.split("listening on http://").nth(1)).map(get_stats);
+    stop(&mut child);
+    assert!(complete, "{}", output);
+
+    // Three rounds of bitcoin and the S&P 500, plus our own request.
+    let stats = stats.unwrap_or_else(|| panic!("no mock server address in:\n{}", output));
+    assert!(stats["requests"].as_u64().unwrap() >= 7, "{}", stats);
+    assert!(stats["connections"].as_u64().unwrap() <= 3, "{}", stats);
+}
+
+#[test]
+fn each_url_is_fetched_once_per_round() {
+    let scratch = Scratch::new("cycle-cache");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[csv.open]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = "Open"
+
+[csv.close]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = "Close"
+row = "last"
+"#;
+    scratch.write("tracker.toml", config);
+    let (mut child, lines) = scratch.start(&["--mock-server"]);
+    let (output, complete) = collect(&lines, ": unchanged", 2);
+    let stats = output.lines().find_map(|line| line.split("listening on http://").nth(1)).map(get_stats);
+    stop(&mut child);
+    assert!(complete, "{}", output);
+    assert!(output.contains("open: $5029.03") && output.contains("close: $5100.25"), "{}", output);
+
+    // One download per round for both assets, plus our own request.
+    let stats = stats.unwrap_or_else(|| panic!("no mock server address in:\n{}", output));
+    assert_eq!(stats["requests"].as_u64().unwrap(), 3, "{}", stats);
+}
+
+#[test]
+fn requests_are_counted_against_quotas() {
+    let scratch = Scratch::new("quotas");
+    let quotas = "quotas = { \"api.coingecko.com\" = 3 }\nquota_warning = 0.5\n";
+    let storage = "[storage]\nbackend = \"csv\"\ndirectory = \"data\"\n";
+    scratch.write("tracker.toml", &format!("{}{}\n{}", common::CONFIG, quotas, storage));
+    fs::create_dir(scratch.dir.join("data")).unwrap();
+
+    // Counted, but not saved.
+    let output = scratch.track(&["--dry-run", "--mock-server"], "is used up", 1);
+    assert!(output.contains("Warning: the daily quota of 3 requests to api.coingecko.com is used up"), "{}", output);
+    assert!(!scratch.dir.join("data/http_usage.json").exists());
+
+    let output = scratch.track(&["--mock-server"], "is used up", 1);
+    assert!(output.contains("Warning: 2 of the 3 daily requests to api.coingecko.com are used"), "{}", output);
+    assert!(output.contains("Warning: the daily quota of 3 requests to api.coingecko.com is used up"), "{}", output);
+    assert!(!output.contains("query1.finance.yahoo.com"), "{}", output);
+    // With the other state, in the storage directory.
+    assert!(scratch.read("data/http_usage.json").contains("\"api.coingecko.com\""));
+    assert!(!scratch.dir.join("http_usage.json").exists());
+
+    let usage = scratch.run(&["usage"]);
+    assert!(usage.status.success(), "{}", text(&usage.stderr));
+    let stdout = text(&usage.stdout);
+    let coingecko = stdout.lines().find(|line| line.contains("api.coingecko.com")).unwrap_or_default();
+    assert!(coingecko.contains(" of 3 ("), "{}", stdout);
+    assert!(stdout.contains("query1.finance.yahoo.com"), "{}", stdout);
+}
+
+#[test]
+fn rate_limits_space_out_requests_to_a_host() {
+    let scratch = Scratch::new("rate-limit");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[http]
+rate_limits = { "query1.finance.yahoo.com" = "1/2s" }
+
+[csv.first]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EGSPC"
+column = "Close"
+
+[csv.second]
+url = "https://query1.finance.yahoo.com/v7/finance/download/%5EDJI"
+column = "Close"
+"#;
+    scratch.write("tracker.toml", config);
+    let (mut child, lines) = scratch.start(&["--mock-server"]);
+    let (started, started_complete) = collect(&lines, "Press Ctrl+C", 1);
+    let round = Instant::now();
+    let (output, complete) = collect(&lines, "second: $", 1);
+    let elapsed = round.elapsed();
+    stop(&mut child);
+    assert!(started_complete && complete, "{}{}", started, output);
+    // The second download waits for the host's budget to refill.
+    assert!(elapsed >= Duration::from_millis(1500), "the first round took {:?}", elapsed);
+}
+
+#[test]
+fn invalid_rate_limits_are_config_problems() {
+    let scratch = Scratch::new("rate-limit-invalid");
+    scratch.write("tracker.toml", "[http]\nrate_limits = { \"api.coingecko.com\" = \"30 per minute\" }\n");
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    let expected = "http.rate_limits.\"api.coingecko.com\": expected <count>/<period> such as 30/min";
+    assert!(report.contains(expected), "{}", report);
+}
+
+// Two downloads that take 1.5s each, and how long the first round took.
+fn slow_round(name: &str, concurrency: usize) -> Duration {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = format!(
+        r#"
+interval = "1s"
+concurrency = {}
+
+[csv.first]
+url = "https://query1.finance.yahoo.com/mock/slow/first"
+column = "Close"
+
+[csv.second]
+url = "https://query1.finance.yahoo.com/mock/slow/second"
+column = "Close"
+"#,
+        concurrency
+    );
+    scratch.write("tracker.toml", &config);
+    let (mut child, lines) = scratch.start(&["--mock-server"]);
+    let (started, started_complete) = collect(&lines, "Press Ctrl+C", 1);
+    let round = Instant::now();
+    let (output, complete) = collect(&lines, ": $", 2);
+    let elapsed = round.elapsed();
+    stop(&mut child);
+    assert!(started_complete && complete, "{}{}", started, output);
+    elapsed
+}
+
+#[test]
+fn assets_are_fetched_in_parallel() {
+    let elapsed = slow_round("parallel", 4);
+    assert!(elapsed < Duration::from_millis(2500), "the first round took {:?}", elapsed);
+}
+
+#[test]
+fn concurrency_caps_parallel_fetches() {
+    let elapsed = slow_round("sequential", 1);
+    assert!(elapsed >= Duration::from_millis(2500), "the first round took {:?}", elapsed);
+}
+
+fn get_stats(address: &str) -> serde_json::Value {
+    let mut stream = TcpStream::connect(address.trim()).unwrap();
+    write!(stream, "GET /mock/stats HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", address.trim()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or_default();
+    serde_json::from_str(body).unwrap()
+}
@@ -0,0 +1,76 @@
+// Samples order books with the prices of tracked assets from the Binance and
+// Bybit endpoints of the --mock-server.
+
+mod common;
+
+use common::{text, Scratch};
+
+// The value of `column` in the first record of `history`.
+fn column(history: &str, column: &str) -> String {
+    let mut lines = history.lines().filter(|line| !line.starts_with('#'));
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+    let index = header.iter().position(|name| *name == column);
+    let index = index.unwrap_or_else(|| panic!("no {} in {}", column, history));
+    lines.next().unwrap_or_default().split(',').nth(index).unwrap_or_default().to_string()
+}
+
+// Stored in full, compared to the cent.
+fn cents(value: String) -> String {
+    format!("{:.2}", value.parse::<f64>().unwrap_or_else(|_| panic!("not a number: '{}'", value)))
+}
+
+#[test]
+fn snapshots_are_recorded_with_the_price() {
+    let config = r#"
+[futures.perp]
+exchange = "binance"
+symbol = "BTCUSDT"
+
+[orderbook.perp]
+exchange = "binance"
+symbol = "BTCUSDT"
+market = "linear"
+
+[futures.eth]
+exchange = "bybit"
+symbol = "ETHUSDT"
+
+[orderbook.eth]
+exchange = "bybit"
+symbol = "ETHUSDT"
+"#;
+    let scratch = Scratch::with_config("orderbook", config);
+    let output = scratch.track(&["--mock-server"], "eth: $", 2);
+    assert!(!output.contains("order book"), "{}", output);
+
+    let history = scratch.read("perp_prices.csv");
+    assert!(history.starts_with("# schema_version=9\n"), "{}", history);
+    assert_eq!(cents(column(&history, "book_bid")), "65026.00");
+    assert_eq!(cents(column(&history, "book_ask")), "65039.00");
+    assert_eq!(cents(column(&history, "book_spread")), "2.00");
+    // Only the two levels within 1% of the mid on each side.
+    assert_eq!(cents(column(&history, "book_biddepth")), "97220.34");
+    assert_eq!(cents(column(&history, "book_askdepth")), "104444.80");
+    assert_eq!(cents(column(&scratch.read("eth_prices.csv"), "book_spread")), "2.00");
+}
+
+#[test]
+fn failed_snapshots_keep_the_price() {
+    let config = "[futures.perp]\nexchange = \"binance\"\nsymbol = \"BTCUSDT\"\n\
+                  [orderbook.perp]\nexchange = \"bybit\"\nsymbol = \"DOGEUSDC\"\n";
+    let scratch = Scratch::with_config("orderbook-empty", config);
+    let output = scratch.track(&["--mock-server"], "perp: $", 2);
+    assert!(output.contains("Error fetching the order book of perp: Parse Error [E200]"), "{}", output);
+    assert!(output.contains("the order book of DOGEUSDC is empty"), "{}", output);
+    assert!(!scratch.read("perp_prices.csv").contains("book_"));
+}
+
+#[test]
+fn books_need_a_tracked_asset() {
+    let config = "[orderbook.doge]\nexchange = \"binance\"\nsymbol = \"DOGEUSDT\"\n";
+    let scratch = Scratch::with_config("orderbook-check", config);
+    let output = scratch.run(&["config", "check"]);
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(!output.status.success(), "{}", report);
+    assert!(report.contains("orderbook.doge: 'doge' is not a tracked asset"), "{}", report);
+}
@@ -0,0 +1,68 @@
+// Paper trades a mock asset through a dip and a recovery.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 50.0, 80.0, 120.0]
+
+[paper]
+cash = 1000.0
+
+[[paper.orders]]
+name = "dip"
+asset = "gold"
+side = "buy"
+amount = 10
+below = 60.0
+
+[[paper.orders]]
+name = "too-big"
+asset = "gold"
+side = "buy"
+amount = 1000
+below = 60.0
+
+[[paper.orders]]
+name = "take-profit"
+asset = "gold"
+side = "sell"
+amount = 10
+above = 110.0
+after = "dip"
+"#;
+
+#[test]
+fn orders_fill_once_their_limits_are_crossed() {
+    let scratch = Scratch::new("paper");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    let output = scratch.track(&[], "Paper order 'take-profit' sold 10 gold at 120.00", 1);
+    assert!(output.contains("Paper order 'dip' bought 10 gold at 50.00"), "{}", output);
+    assert!(output.contains("Paper order 'too-big' can't fill at 50.00: needs 50000.00 in cash"), "{}", output);
+    // The sell only opens after the buy, so the first 100.00 didn't count.
+    assert_eq!(output.matches("take-profit").count(), 1, "{}", output);
+
+    let trades = scratch.read("paper_trades.csv");
+    assert!(trades.starts_with("timestamp,order,asset,side,amount,price\n"), "{}", trades);
+    assert_eq!(trades.lines().count(), 3, "{}", trades);
+
+    let paper = scratch.run(&["paper"]);
+    let summary = text(&paper.stdout);
+    assert!(paper.status.success(), "{}", text(&paper.stderr));
+    assert!(summary.contains("dip: bought 10 gold at 50.00"), "{}", summary);
+    assert!(summary.contains("Open: too-big: buy 1000 gold at or below 60.00"), "{}", summary);
+    let totals = "Cash 1700.00, realized +700.00, unrealized +0.00, total +700.00 on 1000.00";
+    assert!(summary.contains(totals), "{}", summary);
+
+    scratch.write("tracker.toml", &CONFIG.replace("after = \"dip\"", "after = \"rip\""));
+    let check = scratch.run(&["config", "check"]);
+    assert!(!check.status.success());
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("paper order 'take-profit': comes after unknown order 'rip'"), "{}", report);
+}
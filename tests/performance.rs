@@ -0,0 +1,48 @@
+// Compares seeded returns with the S&P 500's over several windows.
+
+mod common;
+
+use chrono::{Duration, Local};
+use common::{text, Scratch};
+
+fn history(prices: &[(Duration, f64)]) -> String {
+    let now = Local::now().naive_local();
+    let mut history = "# schema_version=6\ntimestamp,price,volume,market_cap\n".to_string();
+    for (ago, price) in prices {
+        history.push_str(&format!("{},{:.2},,\n", (now - *ago).format("%Y-%m-%d %H:%M:%S"), price));
+    }
+    history
+}
+
+#[test]
+fn returns_are_shown_against_the_benchmark() {
+    let scratch = Scratch::new("performance");
+    let (days, hour) = (Duration::days, Duration::hours(1));
+    let bitcoin = [(days(40), 100.0), (days(8), 110.0), (days(2), 120.0), (hour, 125.0)];
+    let sp500 = [(days(40), 1000.0), (days(8), 1000.0), (days(2), 1050.0), (hour, 1050.0)];
+    scratch.write("bitcoin_prices.csv", &history(&bitcoin));
+    scratch.write("sp500_prices.csv", &history(&sp500));
+
+    let output = scratch.run(&["performance", "--benchmark", "sp500"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let table = text(&output.stdout);
+    let row = |name: &str| -> Vec<&str> {
+        table.lines().find(|line| line.starts_with(name)).unwrap_or_default().split_whitespace().collect()
+    };
+    assert_eq!(row("Asset"), ["Asset", "1d", "7d", "30d", "365d"], "{}", table);
+    let expected = ["Bitcoin", "+4.17%", "(+4.17)", "+13.64%", "(+8.64)", "+25.00%", "(+20.00)", "n/a"];
+    assert_eq!(row("Bitcoin"), expected, "{}", table);
+    // The benchmark itself has nothing to be compared with.
+    assert_eq!(row("S&P 500"), ["S&P", "500", "+0.00%", "+5.00%", "+5.00%", "n/a"], "{}", table);
+
+    scratch.write("tracker.toml", "[benchmark]\nasset = \"sp500\"\nwindows = [\"7d\"]\n");
+    let configured = text(&scratch.run(&["performance"]).stdout);
+    let bitcoin = configured.lines().find(|line| line.starts_with("Bitcoin")).unwrap_or_default();
+    assert!(bitcoin.ends_with("+13.64% (+8.64)"), "{}", configured);
+
+    scratch.write("tracker.toml", "[benchmark]\nasset = \"gold\"\nwindows = [\"7y\"]\n");
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("benchmark.asset: 'gold' is not a tracked asset"), "{}", report);
+    assert!(report.contains("benchmark.windows: invalid duration '7y'"), "{}", report);
+}
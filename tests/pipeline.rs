@@ -0,0 +1,88 @@
+// Runs per-asset pipelines over mock prices.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn stages_scale_and_reject_prices() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 1000.0]
+
+[assets.gold]
+pipeline = [{ stage = "scale", factor = 0.5 }, { stage = "validate", max = 400.0 }]
+"#;
+    let scratch = Scratch::with_config("pipeline-validate", config);
+    let output = scratch.track(&[], "Dropped the price of gold", 1);
+    assert!(output.contains("gold: $50.00"), "{}", output);
+    assert!(output.contains("rejected 500: above the maximum of 400"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    assert!(history.contains(",50.00") && !history.contains(",500.00"), "{}", history);
+}
+
+#[test]
+fn sudden_moves_are_rejected() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 105.0, 300.0]
+
+[assets.gold]
+pipeline = [{ stage = "validate", max_change = 0.1 }]
+"#;
+    let scratch = Scratch::with_config("pipeline-max-change", config);
+    let output = scratch.track(&[], "Dropped the price of gold", 1);
+    assert!(output.contains("gold: $105.00"), "{}", output);
+    assert!(output.contains("rejected 300: 185.7% away from the last price 105"), "{}", output);
+}
+
+#[test]
+fn smoothing_averages_recent_prices() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+
+[assets.gold]
+pipeline = [{ stage = "smooth", window = 2 }]
+"#;
+    let scratch = Scratch::with_config("pipeline-smooth", config);
+    let output = scratch.track(&[], "gold: $150.00", 1);
+    assert!(output.contains("gold: $100.00"), "{}", output);
+}
+
+#[test]
+fn changes_are_shown_and_stored() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 110.0]
+
+[assets.gold]
+pipeline = [{ stage = "change", period = "1s" }]
+"#;
+    let scratch = Scratch::with_config("pipeline-change", config);
+    let output = scratch.track(&[], "gold: $110.00 (1s +10.00%)", 1);
+    assert!(output.contains("gold: $100.00"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    assert!(history.contains("change_1s"), "{}", history);
+}
+
+#[test]
+fn invalid_stages_are_config_problems() {
+    let config = r#"
+[mock.gold]
+
+[assets.gold]
+pipeline = [{ stage = "scale", factor = -1.0 }]
+"#;
+    let scratch = Scratch::with_config("pipeline-invalid", config);
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(report.contains("assets.gold.pipeline[0]: factor must be positive"), "{}", report);
+}
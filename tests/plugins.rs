@@ -0,0 +1,147 @@
+// Runs WASM price plugins, written as .wat text, inside the tracker.
+
+#![cfg(feature = "plugins")]
+
+mod common;
+
+use common::Scratch;
+
+const CONSTANT: &str = r#"
+(module
+  (import "tracker" "log" (func $log (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "fetching")
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "fetch_price") (param $id i32) (param $len i32) (result f64)
+    (call $log (i32.const 0) (i32.const 8))
+    (f64.const 1923.5)))
+"#;
+
+const FAILING: &str = r#"
+(module
+  (import "tracker" "error" (func $error (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "market closed")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "fetch_price") (param i32 i32) (result f64)
+    (call $error (i32.const 0) (i32.const 13))
+    (f64.const 0)))
+"#;
+
+const ENDLESS: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  (func (export "fetch_price") (param i32 i32) (result f64)
+    (loop $forever (br $forever))
+    (f64.const 1)))
+"#;
+
+// Fetches the URL it is given as the provider id and returns the last number
+// in the response.
+const LAST_NUMBER: &str = r#"
+(module
+  (import "tracker" "http_get" (func $http_get (param i32 i32) (result i32)))
+  (import "tracker" "read_response" (func $read_response (param i32)))
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "fetch_price") (param $url i32) (param $url_len i32) (result f64)
+    (local $len i32) (local $body i32) (local $i i32) (local $c i32) (local $in i32)
+    (local $value f64) (local $scale f64) (local $last f64)
+    (local.set $len (call $http_get (local.get $url) (local.get $url_len)))
+    (if (i32.lt_s (local.get $len) (i32.const 0)) (then (return (f64.const -1))))
+    (local.set $body (global.get $next))
+    (call $read_response (local.get $body))
+    (local.set $last (f64.const -1))
+    (block $done
+      (loop $scan
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $c (i32.load8_u (i32.add (local.get $body) (local.get $i))))
+        (if (i32.and (i32.ge_u (local.get $c) (i32.const 48)) (i32.le_u (local.get $c) (i32.const 57)))
+          (then
+            (if (i32.eqz (local.get $in))
+              (then
+                (local.set $in (i32.const 1))
+                (local.set $value (f64.const 0))
+                (local.set $scale (f64.const 0))))
+            (local.set $c (i32.sub (local.get $c) (i32.const 48)))
+            (if (f64.eq (local.get $scale) (f64.const 0))
+              (then
+                (local.set $value
+                  (f64.add (f64.mul (local.get $value) (f64.const 10)) (f64.convert_i32_u (local.get $c)))))
+              (else
+                (local.set $scale (f64.div (local.get $scale) (f64.const 10)))
+                (local.set $value
+                  (f64.add (local.get $value) (f64.mul (local.get $scale) (f64.convert_i32_u (local.get $c)))))))
+            (local.set $last (local.get $value)))
+          (else
+            (if (i32.and (local.get $in) (i32.eq (local.get $c) (i32.const 46)))
+              (then (local.set $scale (f64.const 1)))
+              (else (local.set $in (i32.const 0))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $scan)))
+    (local.get $last)))
+"#;
+
+fn scratch(name: &str, plugin: &str, extra: &str) -> Scratch {
+    let config = format!("[plugins.gold]\npath = \"plugin.wat\"\nname = \"Gold\"\n{}", extra);
+    let scratch = Scratch::with_config(name, &config);
+    scratch.write("plugin.wat", plugin);
+    scratch
+}
+
+#[test]
+fn plugin_prices_are_tracked() {
+    let scratch = scratch("plugin", CONSTANT, "");
+    let output = scratch.track(&[], "Gold: $", 2);
+    assert!(output.contains("[plugin gold] fetching"), "{}", output);
+    assert!(output.contains("Gold: $1923.50"), "{}", output);
+    assert!(scratch.read("gold_prices.csv").contains(",1923.50"), "{}", scratch.read("gold_prices.csv"));
+
+    let output = scratch.run(&["asset", "list"]);
+    assert!(common::text(&output.stdout).contains("plugin:gold"), "{}", common::text(&output.stdout));
+}
+
+#[test]
+fn plugin_errors_fail_the_fetch() {
+    let scratch = scratch("plugin-error", FAILING, "");
+    let output = scratch.track(&[], "Error fetching price for Gold", 1);
+    assert!(output.contains("plugin for gold: market closed"), "{}", output);
+}
+
+#[test]
+fn endless_plugins_run_out_of_fuel() {
+    let scratch = scratch("plugin-fuel", ENDLESS, "fuel = 100000\n");
+    let output = scratch.track(&[], "Error fetching price for Gold", 2);
+    assert!(output.contains("fuel"), "{}", output);
+}
+
+#[test]
+fn plugins_fetch_through_the_tracker() {
+    let url = "[assets.gold]\nprovider_id = \"https://query1.finance.yahoo.com/v8/finance/chart/%5EGSPC\"\n";
+    let scratch = scratch("plugin-http", LAST_NUMBER, url);
+    let output = scratch.track(&["--mock-server"], "Gold: $", 1);
+    assert!(output.contains("Gold: $5100.25"), "{}", output);
+
+    let output = scratch.track(&["--mock-server=rate-limit"], "Error fetching price for Gold", 1);
+    assert!(output.contains("plugin for gold: ") && output.contains("status code 429"), "{}", output);
+}
+
+#[test]
+fn invalid_plugins_are_config_problems() {
+    let scratch = scratch("plugin-invalid", "(module)", "");
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let stderr = common::text(&output.stderr);
+    assert!(stderr.contains("plugins.gold: plugin.wat: does not export 'memory'"), "{}", stderr);
+}
@@ -0,0 +1,49 @@
+// SQL over the stored history, the same from CSV files as from SQLite.
+
+mod common;
+
+use common::{text, Scratch};
+
+const HISTORY: &str = "timestamp,price,volume,market_cap
+2024-03-01 09:00:00,100.00,,
+2024-03-01 15:00:00,110.00,,
+2024-03-02 09:00:00,120.00,,
+";
+
+fn query(scratch: &Scratch, args: &[&str]) -> String {
+    let output = scratch.run(&[&["query"], args].concat());
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+#[test]
+fn queries_run_over_any_backend() {
+    let scratch = Scratch::new("query");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n\n[[assets]]\nid = \"ethereum\"\n");
+    scratch.write("bitcoin_prices.csv", HISTORY);
+    scratch.write("ethereum_prices.csv", "timestamp,price,volume,market_cap\n2024-03-01 10:00:00,5.00,,\n");
+
+    let daily = "SELECT date_trunc('day', ts) AS day, avg(price) AS average FROM bitcoin GROUP BY 1";
+    let expected = "day                  average\n\
+                    2024-03-01 00:00:00      105\n\
+                    2024-03-02 00:00:00      120\n\
+                    (2 rows)\n";
+    assert_eq!(query(&scratch, &[daily]), expected);
+
+    let all = "SELECT asset, count(*) AS records FROM prices GROUP BY asset ORDER BY asset";
+    assert_eq!(
+        query(&scratch, &[all, "--json"]),
+        "{\"asset\":\"bitcoin\",\"records\":3}\n{\"asset\":\"ethereum\",\"records\":1}\n"
+    );
+
+    scratch.write("tracker.toml", "[storage]\nbackend = \"sqlite\"\npath = \"prices.db\"\n");
+    let migrated = scratch.run(&["migrate"]);
+    assert!(migrated.status.success(), "{}", text(&migrated.stderr));
+    // Emptied, so the rows can only come from the database.
+    scratch.write("bitcoin_prices.csv", "");
+    assert_eq!(query(&scratch, &[daily]), expected);
+
+    let bad = scratch.run(&["query", "SELECT date_trunc('fortnight', ts) FROM bitcoin"]);
+    assert!(!bad.status.success());
+    assert!(text(&bad.stderr).contains("date_trunc: unknown unit 'fortnight'"), "{}", text(&bad.stderr));
+}
@@ -0,0 +1,44 @@
+// One-off quotes of tracked and untracked assets from the --mock-server,
+// none of which are stored.
+
+mod common;
+
+use common::{text, Scratch};
+
+fn quote(scratch: &Scratch, args: &[&str]) -> String {
+    let output = scratch.run(&[&["--mock-server", "quote"], args].concat());
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout)
+}
+
+#[test]
+fn coins_and_tickers_are_quoted_without_storing_them() {
+    let scratch = Scratch::new("quote");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", "[mock.gold]\npattern = \"sequence\"\nprices = [1900.5]\n");
+
+    assert_eq!(quote(&scratch, &["bitcoin"]), "Bitcoin (BTC): $65000.00\n");
+    assert_eq!(quote(&scratch, &["eth"]), "Ethereum (ETH): $3200.00\n");
+    assert_eq!(quote(&scratch, &["solana"]), "Solana (SOL): $150.00\n");
+    assert_eq!(quote(&scratch, &["AAPL"]), "AAPL: $189.50\n");
+    // Not a coin, so a ticker.
+    assert_eq!(quote(&scratch, &["qqq"]), "QQQ: $440.10\n");
+    assert_eq!(quote(&scratch, &["gold"]), "gold (GOLD): $1900.50\n");
+
+    let json: serde_json::Value = serde_json::from_str(&quote(&scratch, &["^GSPC", "--json"])).unwrap();
+    assert_eq!(json["id"], "^gspc");
+    assert_eq!(json["price"], 5100.25);
+    assert_eq!(json["currency"], "usd");
+
+    let missing = scratch.run(&["--mock-server", "quote", "nosuchthing"]);
+    assert!(!missing.status.success());
+    assert!(
+        text(&missing.stderr).contains("'nosuchthing' is neither a CoinGecko coin id nor a Yahoo Finance symbol"),
+        "{}",
+        text(&missing.stderr)
+    );
+
+    for history in ["bitcoin_prices.csv", "aapl_prices.csv", "gold_prices.csv"] {
+        assert!(!scratch.dir.join(history).exists(), "{} was written", history);
+    }
+}
@@ -0,0 +1,85 @@
+// Runs commands through `repl` against a stored history, and rules it adds
+// through a running tracker.
+
+mod common;
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::process::Stdio;
+
+use common::{collect, stop, text, Scratch};
+
+// What `repl` prints for `input`, stdout and stderr together.
+fn repl(scratch: &Scratch, input: &str) -> String {
+    let mut child = scratch
+        .command(&["repl"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    text(&output.stdout) + &text(&output.stderr)
+}
+
+#[test]
+fn commands_read_the_stored_history() {
+    let scratch = Scratch::new("repl");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    // Half a minute off the boundaries of periods given in minutes.
+    let start = chrono::Local::now().naive_local() - chrono::Duration::minutes(90) + chrono::Duration::seconds(30);
+    let mut history = String::from("# schema_version=9\ntimestamp,price,volume,market_cap\n");
+    for minute in 0..90 {
+        let timestamp = start + chrono::Duration::minutes(minute);
+        writeln!(history, "{},{:.2},,", timestamp.format("%Y-%m-%d %H:%M:%S"), 30000.0 + minute as f64).unwrap();
+    }
+    scratch.write("bitcoin_prices.csv", &history);
+
+    let output = repl(
+        &scratch,
+        "price btc\nstats bitcoin 30m\nstats btc 10s\nalert add btc > 70000\nalert add BTC >70000\n\
+         alert add btc < 20000.5\nalert list\nalert add btc 5\nprice doge\nbogus\nquit\nprice btc\n",
+    );
+    assert!(output.contains("] Bitcoin: $30089.00"), "{}", output);
+    assert_eq!(output.matches("Bitcoin: $30089.00").count(), 1, "{}", output);
+    let stats = "Bitcoin over 30m: 30 records, last $30089.00 (+0.10%), high $30089.00, low $30060.00";
+    assert!(output.contains(stats), "{}", output);
+    assert!(output.contains("No prices stored for Bitcoin in the last 10s"), "{}", output);
+    assert!(output.contains("Added bitcoin_above_70000"), "{}", output);
+    assert!(output.contains("bitcoin_above_70000 is already set"), "{}", output);
+    assert!(output.contains("bitcoin_below_20000.5"), "{}", output);
+    assert!(output.contains("expected > or < and a price"), "{}", output);
+    assert!(output.contains("unknown asset 'doge'"), "{}", output);
+    assert!(output.contains("unknown command 'bogus'"), "{}", output);
+
+    let rules = scratch.read("alerts.toml");
+    assert!(rules.contains("asset = \"bitcoin\"\nabove = 70000.0\n"), "{}", rules);
+    assert!(rules.contains("asset = \"bitcoin\"\nbelow = 20000.5\n"), "{}", rules);
+
+    let output = repl(&scratch, "alert remove bitcoin_above_70000\nalert remove nothing\nalert list\n");
+    assert!(output.contains("Removed bitcoin_above_70000"), "{}", output);
+    assert!(output.contains("no added alert rule 'nothing'"), "{}", output);
+    assert!(!scratch.read("alerts.toml").contains("70000"));
+}
+
+#[test]
+fn a_running_tracker_picks_up_added_rules() {
+    let scratch = Scratch::new("repl-tracker");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write(
+        "tracker.toml",
+        "interval = \"1s\"\n[mock.gold]\npattern = \"sequence\"\nprices = [10.0, 20.0, 30.0]\n",
+    );
+    let (mut child, lines) = scratch.start(&[]);
+    let (output, started) = collect(&lines, "gold: $", 1);
+    assert!(started, "{}", output);
+
+    let added = repl(&scratch, "alert add gold > 25\n");
+    assert!(added.contains("Added gold_above_25"), "{}", added);
+    let (output, fired) = collect(&lines, "ALERT gold_above_25: gold is above 25.00 at 30.00", 1);
+    stop(&mut child);
+    assert!(fired, "{}", output);
+    assert!(output.contains("Loaded 1 alert rules from alerts.toml"), "{}", output);
+}
@@ -0,0 +1,44 @@
+// Runs the sources over the responses recorded in tests/fixtures/replay. To
+// refresh them, track the same watchlist with `--record tests/fixtures/replay`.
+
+mod common;
+
+use std::path::Path;
+
+use common::Scratch;
+
+#[test]
+fn recorded_responses_are_replayed_through_the_sources() {
+    let scratch = Scratch::with_config("replay", "");
+    let watchlist = "[[assets]]\nid = \"bitcoin\"\n[[assets]]\nid = \"sp500\"\n[[assets]]\nid = \"ethereum\"\n";
+    scratch.write("watchlist.toml", watchlist);
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+
+    let output = scratch.track(&["--replay", fixtures.to_str().unwrap()], "S&P 500: $5127.79", 3);
+    assert!(output.contains("] Bitcoin: $62871.00"), "{}", output);
+    // Recorded while CoinGecko answered with a challenge page.
+    assert!(
+        output.contains("Error fetching price for Ethereum: Parse Error [E200]: coingecko: expected value at line 1"),
+        "{}",
+        output
+    );
+
+    let bitcoin = scratch.read("bitcoin_prices.csv");
+    assert!(bitcoin.contains(",62871.00,28104695740.31,1238660341935.70,"), "{}", bitcoin);
+    assert!(bitcoin.lines().nth(2).unwrap_or_default().ends_with(",1714766402"), "{}", bitcoin);
+    let sp500 = scratch.read("sp500_prices.csv");
+    assert!(sp500.contains(",5127.79,3094767000.00,,5139.12,5101.22,"), "{}", sp500);
+}
+
+#[test]
+fn requests_without_a_recording_name_the_missing_fixture() {
+    let scratch = Scratch::with_config("replay-missing", "");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"solana\"\n");
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay");
+
+    let output = scratch.track(&["--replay", fixtures.to_str().unwrap()], "Error fetching price for solana", 1);
+    let missing = "no recorded response for https://api.coingecko.com/api/v3/simple/price?ids=solana&";
+    assert!(output.contains(missing), "{}", output);
+    let expected = fixtures.join("api.coingecko.com_api_v3_simple_price-");
+    assert!(output.contains(&format!("(expected {}", expected.display())), "{}", output);
+}
@@ -0,0 +1,54 @@
+// Tracks with an [s3] section pointed at a stand-in for S3 that fails its
+// first request, and checks what reaches it.
+
+mod common;
+
+use common::{serve, Request, Scratch};
+
+#[test]
+fn new_records_are_uploaded_once() {
+    let (endpoint, requests) = serve(|_, index| {
+        let status = if index == 0 { "500 Internal Server Error" } else { "200 OK" };
+        (status, String::new())
+    });
+    let scratch = Scratch::new("s3");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = format!(
+        r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+
+[s3]
+endpoint = "{}"
+bucket = "prices"
+prefix = "tracker/"
+access_key = "AKIDEXAMPLE"
+secret_key = "secret"
+every = "1s"
+retries = 1
+"#,
+        endpoint
+    );
+    scratch.write("tracker.toml", &config);
+    let output = scratch.track(&[], "Uploaded", 3);
+    assert!(output.contains("retrying in 1s"), "{}", output);
+
+    let uploads: Vec<Request> = requests.try_iter().collect();
+    let mut lines = Vec::new();
+    for upload in &uploads[1..] {
+        assert!(upload.line.starts_with("PUT /prices/tracker/gold/"), "{}", upload.line);
+        let authorization = upload.header("Authorization").unwrap();
+        assert!(authorization.contains("Credential=AKIDEXAMPLE/"), "{}", authorization);
+        assert!(authorization.contains("/us-east-1/s3/aws4_request, SignedHeaders=host;"), "{}", authorization);
+        assert!(upload.body.starts_with("# schema_version="), "{}", upload.body);
+        lines.extend(upload.body.lines().skip(2).map(str::to_string));
+    }
+    let count = lines.len();
+    lines.sort();
+    lines.dedup();
+    assert_eq!(lines.len(), count, "{:?}", lines);
+    assert!(scratch.read("s3_uploads.json").contains("\"gold\""));
+}
@@ -0,0 +1,113 @@
+// Histories written by older releases, upgraded to the current schema and
+// read back.
+
+mod common;
+
+use chrono::NaiveDate;
+use common::Scratch;
+use crypto_price_tracker::history::HistoryReader;
+use rusqlite::Connection;
+
+const SCHEMA_VERSION: u32 = 9;
+
+// Absolute, as the library takes relative paths from the test's directory.
+fn reader(scratch: &Scratch, storage: &str) -> HistoryReader {
+    let config = format!(
+        "watchlist = {:?}\n\n[storage]\n{}",
+        scratch.dir.join("watchlist.toml").display().to_string(),
+        storage
+    );
+    scratch.write("library.toml", &config);
+    HistoryReader::open(Some(&scratch.dir.join("library.toml"))).unwrap()
+}
+
+fn prices(history: &HistoryReader, asset: &str) -> Vec<(String, f64)> {
+    let records = history.records(asset).unwrap().map(Result::unwrap);
+    records.map(|record| (record.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(), record.price)).collect()
+}
+
+#[test]
+fn two_column_csv_files_are_upgraded_before_appending() {
+    let scratch = Scratch::with_config("schema-csv", "[mock.gold]\npattern = \"sequence\"\nprices = [100.0]\n");
+    scratch.write("gold_prices.csv", "timestamp,price\n2024-01-01 00:00:00,42000.50\n2024-01-02 00:00:00,43000\n");
+
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(output.contains("gold_prices.csv from schema v1 to v9"), "{}", output);
+    let file = scratch.read("gold_prices.csv");
+    let mut lines = file.lines();
+    assert_eq!(lines.next(), Some(format!("# schema_version={}", SCHEMA_VERSION).as_str()), "{}", file);
+    assert!(lines.next().unwrap_or_default().starts_with("timestamp,price,volume,market_cap"), "{}", file);
+    assert!(lines.next().unwrap_or_default().starts_with("2024-01-01 00:00:00,42000.50,,"), "{}", file);
+
+    let directory = format!("backend = \"csv\"\ndirectory = {:?}\n", scratch.dir.display().to_string());
+    let prices = prices(&reader(&scratch, &directory), "gold");
+    assert_eq!(prices.len(), 3, "{:?}", prices);
+    assert_eq!(prices[0], ("2024-01-01 00:00:00".to_string(), 42000.5));
+    assert_eq!(prices[1], ("2024-01-02 00:00:00".to_string(), 43000.0));
+    assert_eq!(prices[2].1, 100.0);
+
+    // Already current, so it is left as it is.
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(!output.contains("Upgraded"), "{}", output);
+}
+
+#[test]
+fn csv_files_from_a_newer_release_are_refused() {
+    let scratch = Scratch::new("schema-csv-newer");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    scratch.write("bitcoin_prices.csv", "# schema_version=99\ntimestamp,price\n2024-01-01 00:00:00,42000.50\n");
+    let directory = format!("backend = \"csv\"\ndirectory = {:?}\n", scratch.dir.display().to_string());
+    let history = reader(&scratch, &directory);
+    let e = history.records("bitcoin").err().unwrap();
+    assert!(e.to_string().contains("schema v99 is newer than this tracker supports (v9)"), "{}", e);
+}
+
+#[test]
+fn databases_from_before_versioning_are_upgraded_on_open() {
+    let scratch = Scratch::new("schema-sqlite");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    let path = scratch.dir.join("prices.db");
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE prices (
+             asset TEXT NOT NULL,
+             timestamp TEXT NOT NULL,
+             price REAL NOT NULL,
+             PRIMARY KEY (asset, timestamp)
+         );
+         INSERT INTO prices VALUES ('bitcoin', '2024-01-01 00:00:00', 42000.5);
+         INSERT INTO prices VALUES ('bitcoin', '2024-01-02 00:00:00', 43000.0);",
+    )
+    .unwrap();
+    drop(conn);
+
+    let history = reader(&scratch, &format!("backend = \"sqlite\"\npath = {:?}\n", path.display().to_string()));
+    assert_eq!(
+        prices(&history, "bitcoin"),
+        [("2024-01-01 00:00:00".to_string(), 42000.5), ("2024-01-02 00:00:00".to_string(), 43000.0)]
+    );
+    let last = history.last("bitcoin").unwrap().unwrap();
+    assert_eq!(last.timestamp, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(0, 0, 0).unwrap());
+    assert_eq!((last.volume, last.market_cap), (None, None));
+    drop(history);
+
+    let conn = Connection::open(&path).unwrap();
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+    assert_eq!(version, SCHEMA_VERSION);
+    let mut columns = conn.prepare("SELECT name FROM pragma_table_info('prices')").unwrap();
+    let columns: Vec<String> = columns.query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+    assert_eq!(columns, ["asset", "timestamp", "price", "volume", "market_cap"]);
+}
+
+#[test]
+fn databases_from_a_newer_release_are_refused() {
+    let scratch = Scratch::new("schema-sqlite-newer");
+    let path = scratch.dir.join("prices.db");
+    Connection::open(&path).unwrap().execute_batch("PRAGMA user_version = 99;").unwrap();
+    let config = format!("[storage]\nbackend = \"sqlite\"\npath = {:?}\n", path.display().to_string());
+    scratch.write("tracker.toml", &config);
+    let output = scratch.run(&["query", "SELECT 1"]);
+    let stderr = common::text(&output.stderr);
+    assert!(!output.status.success(), "{}", stderr);
+    assert!(stderr.contains("database schema v99 is newer than this tracker supports (v9)"), "{}", stderr);
+}
@@ -0,0 +1,70 @@
+// Scrapes the --mock-server's quote page.
+
+#![cfg(feature = "scraping")]
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn prices_are_read_from_the_selected_element() {
+    let config = r#"
+[scrape.text]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = "fin-streamer[data-field=regularMarketPrice]"
+
+[scrape.attribute]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = ".quote fin-streamer"
+attribute = "data-value"
+
+[scrape.pattern]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = "span.change"
+pattern = '\(\+([0-9.]+)%\)'
+"#;
+    let scratch = Scratch::with_config("scrape", config);
+    let output = scratch.track(&["--mock-server"], ": $", 3);
+    assert!(output.contains("text: $5100.25"), "{}", output);
+    assert!(output.contains("attribute: $5100.25"), "{}", output);
+    assert!(output.contains("pattern: $0.48"), "{}", output);
+}
+
+#[test]
+fn library_user_agents_are_turned_away() {
+    let config = r#"
+[scrape.index]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = "fin-streamer"
+user_agent = "ureq/2.9"
+"#;
+    let scratch = Scratch::with_config("scrape-agent", config);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for index", 1);
+    assert!(output.contains("status code 403"), "{}", output);
+}
+
+#[test]
+fn missing_elements_fail_the_fetch() {
+    let config = r#"
+[scrape.index]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = "div#price"
+"#;
+    let scratch = Scratch::with_config("scrape-missing", config);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for index", 1);
+    assert!(output.contains("nothing matches the selector 'div#price'"), "{}", output);
+}
+
+#[test]
+fn invalid_selectors_are_config_problems() {
+    let config = r#"
+[scrape.index]
+url = "https://finance.yahoo.com/quote/%5EGSPC"
+selector = "div >"
+"#;
+    let scratch = Scratch::with_config("scrape-invalid", config);
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(report.contains("scrape.index.selector: invalid selector 'div >'"), "{}", report);
+}
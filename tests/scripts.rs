@@ -0,0 +1,107 @@
+// Runs Rhai source and transform scripts inside the tracker.
+
+#![cfg(feature = "scripting")]
+
+mod common;
+
+use common::{text, Scratch};
+
+const SOURCE: &str = r#"
+fn url(id) {
+    `https://api.coingecko.com/api/v3/simple/price?ids=${id}&vs_currencies=usd`
+}
+
+fn price(body, id) {
+    let quotes = parse_json(body);
+    if !quotes.contains(id) {
+        throw `no quote for ${id}`;
+    }
+    quotes[id].usd
+}
+"#;
+
+// Prices per gram instead of per troy ounce, ignoring implausible ones.
+const TRANSFORM: &str = r#"
+fn transform(record) {
+    if record.price > 5000.0 {
+        print(`ignoring ${record.price}`);
+        return ();
+    }
+    record.price /= 31.1035;
+    record
+}
+"#;
+
+const ENDLESS: &str = r#"
+fn transform(record) {
+    loop {}
+}
+"#;
+
+#[test]
+fn script_sources_fetch_and_extract() {
+    let config = r#"
+[scripts.coin]
+path = "coin.rhai"
+name = "Scripted"
+
+[assets.coin]
+provider_id = "bitcoin"
+"#;
+    let scratch = Scratch::with_config("script", config);
+    scratch.write("coin.rhai", SOURCE);
+    let output = scratch.track(&["--mock-server"], "Scripted: $", 1);
+    assert!(output.contains("Scripted: $65000.00"), "{}", output);
+
+    let output = scratch.run(&["asset", "list"]);
+    assert!(text(&output.stdout).contains("script:bitcoin"), "{}", text(&output.stdout));
+}
+
+#[test]
+fn script_errors_fail_the_fetch() {
+    let config = "[scripts.coin]\npath = \"coin.rhai\"\n[assets.coin]\nprovider_id = \"no-such-coin\"\n";
+    let scratch = Scratch::with_config("script-error", config);
+    scratch.write("coin.rhai", SOURCE);
+    let output = scratch.track(&["--mock-server"], "Error fetching price for coin", 1);
+    assert!(output.contains("coin.rhai: price: Runtime error: no quote for no-such-coin"), "{}", output);
+}
+
+#[test]
+fn transforms_convert_and_filter_records() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [2332.76, 9999.0]
+
+[assets.gold]
+transform = "per_gram.rhai"
+"#;
+    let scratch = Scratch::with_config("transform", config);
+    scratch.write("per_gram.rhai", TRANSFORM);
+    let output = scratch.track(&[], "Dropped the price of gold", 1);
+    assert!(output.contains("gold: $75.00"), "{}", output);
+    assert!(output.contains("[script gold] ignoring 9999"), "{}", output);
+    assert!(output.contains("per_gram.rhai: transform: dropped the record"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    let price: f64 = history.lines().nth(2).and_then(|line| line.split(',').nth(1)).unwrap().parse().unwrap();
+    assert!((price - 75.0).abs() < 0.005 && !history.contains(",9999"), "{}", history);
+}
+
+#[test]
+fn runaway_transforms_are_stopped() {
+    let config = "[mock.gold]\n[assets.gold]\ntransform = \"endless.rhai\"\n";
+    let scratch = Scratch::with_config("transform-endless", config);
+    scratch.write("endless.rhai", ENDLESS);
+    let output = scratch.track(&[], "Dropped the price of gold", 1);
+    assert!(output.contains("Too many operations"), "{}", output);
+}
+
+#[test]
+fn scripts_missing_functions_are_config_problems() {
+    let scratch = Scratch::with_config("script-invalid", "[scripts.coin]\npath = \"coin.rhai\"\n");
+    scratch.write("coin.rhai", "fn url(id) { id }\n");
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    assert!(text(&output.stderr).contains("scripts.coin: coin.rhai: no function 'price'"), "{}", text(&output.stderr));
+}
@@ -0,0 +1,118 @@
+// Tracks with an [sftp] section whose client is a stand-in that copies files
+// locally and drops the first connection, and checks what it was sent.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::Scratch;
+
+// Reads the batch from stdin like `sftp -b -` does, with the remote paths
+// being local ones.
+const CLIENT: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+while read -r command rest; do
+    echo "$command" >> "$dir/commands.log"
+    case "$command" in
+        -mkdir) mkdir -p "$(echo "$rest" | tr -d '"')" ;;
+        put|reput)
+            if [ ! -e "$dir/dropped" ]; then
+                touch "$dir/dropped"
+                echo "Connection closed" >&2
+                exit 1
+            fi
+            set -- $(echo "$rest" | tr -d '"')
+            cp "$1" "$2" ;;
+    esac
+done
+"#;
+
+#[test]
+fn finished_days_are_uploaded_and_resumed() {
+    let scratch = Scratch::new("sftp");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("sftp-client", CLIENT);
+    let client = scratch.dir.join("sftp-client");
+    fs::set_permissions(&client, fs::Permissions::from_mode(0o755)).unwrap();
+    let remote = scratch.dir.join("remote");
+    let config = format!(
+        r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+
+[sftp]
+host = "backup.example.com"
+user = "tracker"
+directory = "{}"
+every = "1s"
+program = "{}"
+"#,
+        remote.display(),
+        client.display()
+    );
+    scratch.write("tracker.toml", &config);
+    scratch.write(
+        "gold_prices.csv",
+        "# schema_version=6\ntimestamp,price,volume,market_cap\n\
+         2024-01-01 09:00:00,50.00,,\n2024-01-01 18:00:00,60.00,,\n2024-01-02 09:00:00,70.00,,\n",
+    );
+    let output = scratch.track(&[], "Uploaded", 2);
+    assert!(output.contains("Connection closed"), "{}", output);
+
+    let commands = scratch.read("commands.log");
+    assert!(commands.contains("reput"), "{}", commands);
+    let first = fs::read_to_string(remote.join("gold_2024-01-01.csv")).unwrap();
+    assert!(first.contains("50.00") && first.contains("60.00") && !first.contains("70.00"), "{}", first);
+    let second = fs::read_to_string(remote.join("gold_2024-01-02.csv")).unwrap();
+    assert!(second.contains("70.00"), "{}", second);
+    // Today isn't over yet.
+    assert_eq!(fs::read_dir(&remote).unwrap().count(), 2);
+    assert_eq!(fs::read_dir(scratch.dir.join("sftp_outbox")).unwrap().count(), 0);
+    assert!(scratch.read("sftp_uploads.json").contains("2024-01-02"));
+}
+
+#[test]
+fn a_stalled_upload_is_killed_and_tracking_goes_on() {
+    let scratch = Scratch::new("sftp-stalled");
+    scratch.write("watchlist.toml", "assets = []\n");
+    // Takes the batch, then never answers.
+    scratch.write("sftp-client", "#!/bin/sh\ncat > /dev/null\nexec sleep 30\n");
+    let client = scratch.dir.join("sftp-client");
+    fs::set_permissions(&client, fs::Permissions::from_mode(0o755)).unwrap();
+    let config = format!(
+        r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+
+[sftp]
+host = "backup.example.com"
+user = "tracker"
+every = "1s"
+timeout = "1s"
+program = "{}"
+"#,
+        client.display()
+    );
+    scratch.write("tracker.toml", &config);
+    scratch.write(
+        "gold_prices.csv",
+        "# schema_version=9\ntimestamp,price,volume,market_cap\n2024-01-01 09:00:00,50.00,,\n",
+    );
+
+    let output = scratch.track(&[], "timed out after 1s", 2);
+    let stalled = format!("over SFTP: Network Error [E100]: {} put timed out after 1s", client.display());
+    assert!(output.contains(&stalled), "{}", output);
+    let (_, after) = output.split_once("timed out after 1s").unwrap();
+    assert!(after.contains("gold: $100.00"), "{}", output);
+    // Kept to be resumed.
+    assert_eq!(fs::read_dir(scratch.dir.join("sftp_outbox")).unwrap().count(), 1);
+}
@@ -0,0 +1,103 @@
+// Moving average crossings on a mock asset, recorded and sent to notifiers.
+
+mod common;
+
+use common::{text, Scratch};
+
+const CONFIG: &str = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [12.0, 10.0, 8.0, 30.0, 31.0, 33.0, 5.0, 6.0, 4.0]
+
+[[signals]]
+name = "trend"
+asset = "gold"
+fast = 2
+slow = 3
+
+[[signals]]
+name = "loud"
+asset = "gold"
+fast = 2
+slow = 3
+notify = []
+"#;
+
+#[test]
+fn crossings_are_recorded_and_optionally_notified() {
+    let scratch = Scratch::new("signals");
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", CONFIG);
+    let output = scratch.track(&[], "ALERT loud: gold: death cross", 1);
+    assert!(output.contains("gold: golden cross, SMA(2) 19.00 crossed above SMA(3) 16.00"), "{}", output);
+    assert!(output.contains("ALERT loud: gold: golden cross"), "{}", output);
+    assert!(!output.contains("ALERT trend"), "{}", output);
+
+    let signals = scratch.read("signals.csv");
+    let mut lines = signals.lines();
+    assert_eq!(lines.next(), Some("timestamp,signal,asset,event,price,fast,slow"));
+    let events: Vec<&str> = lines.map(|line| line.split(',').nth(3).unwrap()).collect();
+    assert!(events.starts_with(&["golden_cross", "golden_cross", "death_cross", "death_cross"]), "{}", signals);
+    assert!(signals.contains(",trend,gold,death_cross,5,19,23\n"), "{}", signals);
+
+    scratch.write("tracker.toml", &CONFIG.replace("slow = 3\n", "slow = 2\n"));
+    let check = scratch.run(&["config", "check"]);
+    assert!(!check.status.success());
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("signal 'trend': fast must be a shorter period than slow"), "{}", report);
+}
+
+#[test]
+fn strategies_run_live_as_signals() {
+    let scratch = Scratch::new("signals_strategy");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = CONFIG.split("[[signals]]").next().unwrap().to_string()
+        + "[strategies.dips]\ntype = \"threshold\"\nbuy_below = 8.0\nsell_above = 30.0\n\n\
+           [[signals]]\nname = \"dips\"\nasset = \"gold\"\nstrategy = \"dips\"\n";
+    scratch.write("tracker.toml", &config);
+    let output = scratch.track(&[], "gold: strategy 'dips' signals buy at 5.00", 1);
+    assert!(output.contains("gold: strategy 'dips' signals buy at 8.00"), "{}", output);
+    assert!(output.contains("gold: strategy 'dips' signals sell at 30.00"), "{}", output);
+    // 31.00 and 33.00 only repeat the sell.
+    assert_eq!(output.matches("signals sell").count(), 1, "{}", output);
+
+    let signals = scratch.read("signals.csv");
+    assert!(signals.contains(",dips,gold,sell,30,,\n"), "{}", signals);
+
+    scratch.write("tracker.toml", &config.replace("strategy = \"dips\"", "strategy = \"dips\"\nfast = 5"));
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("signal 'dips': set either a strategy or fast and slow"), "{}", report);
+}
+
+#[test]
+fn long_histories_warm_up_from_their_tail() {
+    let scratch = Scratch::new("signals_warm_up");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let mut history = String::from("timestamp,price\n");
+    for minute in 0..20_000 {
+        let price = if minute < 19_997 { 100.0 } else { 200.0 };
+        let timestamp = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap()
+            + chrono::Duration::minutes(minute);
+        history.push_str(&format!("{},{:.2}\n", timestamp.format("%Y-%m-%d %H:%M:%S"), price));
+    }
+    scratch.write("gold_prices.csv", &history);
+    let config = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [200.0]
+
+[[alerts]]
+name = "high-average"
+asset = "gold"
+type = "expression"
+when = "sma(4) > 190 && stddev(3) < 1"
+"#;
+    scratch.write("tracker.toml", config);
+    let output = scratch.track(&[], "ALERT high-average", 1);
+    assert!(output.contains("gold matched 'sma(4) > 190 && stddev(3) < 1' at 200.00"), "{}", output);
+}
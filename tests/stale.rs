@@ -0,0 +1,43 @@
+// Staleness alerts for feeds that stop producing new records.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn unchanged_answers_turn_stale() {
+    let config = "[global.crypto-cap]\nmetric = \"market_cap\"\n\n[staleness]\nafter = 2\n";
+    let scratch = Scratch::with_config("stale-quiet", config);
+    let output = scratch.track(&["--mock-server"], "ALERT source_stale", 1);
+    assert!(output.contains("crypto-cap: unchanged"), "{}", output);
+    assert!(output.contains("crypto-cap has had no new price for "), "{}", output);
+    assert!(output.contains(" (2 intervals)"), "{}", output);
+}
+
+#[test]
+fn repeated_values_turn_stale_until_the_price_moves() {
+    let config = r#"
+[mock.gold]
+pattern = "sequence"
+prices = [5.0, 5.0, 5.0, 6.0]
+
+[staleness]
+after = 100
+repeats = 3
+"#;
+    let scratch = Scratch::with_config("stale-repeats", config);
+    let output = scratch.track(&[], "ALERT source_fresh", 1);
+    assert!(
+        output.contains("ALERT source_stale: gold has returned the same price, 5.00, 3 times in a row"),
+        "{}",
+        output
+    );
+    assert!(output.contains("ALERT source_fresh: gold has new prices again after "), "{}", output);
+    assert_eq!(output.matches("ALERT source_stale").count(), 1, "{}", output);
+
+    scratch.write("tracker.toml", &config.replace("repeats = 3", "repeats = 1"));
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(!check.status.success(), "{}", report);
+    assert!(report.contains("staleness: 'repeats' must be at least 2"), "{}", report);
+}
@@ -0,0 +1,73 @@
+// Counts fetches per provider while tracking and reports them with `status`.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn providers_are_reported_with_their_errors() {
+    let scratch = Scratch::new("status");
+    let output = scratch.run(&["status"]);
+    assert!(text(&output.stdout).contains("No fetches recorded yet"), "{}", text(&output.stdout));
+
+    scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    let output = scratch.run(&["status"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let stdout = text(&output.stdout);
+    let row = |provider: &str| stdout.lines().find(|line| line.starts_with(provider)).unwrap_or_default().to_string();
+    assert!(row("coingecko").contains(" 0.0% "), "{}", stdout);
+    assert!(row("yahoo").contains(" ms ") && row("yahoo").ends_with(" -"), "{}", stdout);
+    assert!(!stdout.contains("Every provider is failing"), "{}", stdout);
+
+    scratch.track(&["--mock-server=rate-limit"], "Error fetching price for S&P 500", 2);
+    let stdout = text(&scratch.run(&["status"]).stdout);
+    let coingecko = stdout.lines().find(|line| line.starts_with("coingecko")).unwrap_or_default();
+    assert!(coingecko.contains("status code 429 at "), "{}", stdout);
+    assert!(!coingecko.contains(" 0.0% ") && !coingecko.contains("never"), "{}", stdout);
+    assert!(stdout.contains("Every provider is failing"), "{}", stdout);
+}
+
+#[test]
+fn status_is_printed_as_json() {
+    let scratch = Scratch::new("status-json");
+    scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    let output = scratch.run(&["status", "--json"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let status: serde_json::Value = serde_json::from_str(&text(&output.stdout)).unwrap();
+
+    let assets = status["assets"].as_array().unwrap();
+    assert_eq!(assets[0]["id"], "bitcoin");
+    assert!(assets[0]["price"].as_f64().unwrap() > 0.0, "{}", status);
+    assert!(assets[1]["age_seconds"].as_i64().unwrap() < 60, "{}", status);
+    let yahoo = status["providers"].as_array().unwrap().iter().find(|p| p["provider"] == "yahoo").unwrap();
+    assert!(yahoo["fetches"].as_u64().unwrap() >= 2 && yahoo["errors"] == 0, "{}", status);
+    assert!(yahoo["last_success_age_seconds"].as_i64().unwrap() < 60, "{}", status);
+    assert_eq!(yahoo["failing"], false);
+    assert_eq!(status["disk"]["directory"], ".");
+    assert!(status["disk"]["used_bytes"].as_u64().unwrap() > 0, "{}", status);
+}
+
+#[test]
+fn disk_usage_is_reported_against_the_budget() {
+    let scratch = Scratch::new("status-disk");
+    scratch.write("tracker.toml", &format!("{}\n[disk]\nbudget = \"100B\"\n", common::CONFIG));
+    let output = scratch.track(&["--mock-server"], "S&P 500: $", 2);
+    assert_eq!(output.matches("over its 100 B budget").count(), 1, "{}", output);
+    assert!(scratch.read("disk_usage.json").contains("\"hours\""));
+
+    let stdout = text(&scratch.run(&["status"]).stdout);
+    assert!(stdout.contains("Data in .: ") && stdout.contains("Over the 100 B budget"), "{}", stdout);
+
+    // A kilobyte an hour, against a budget a megabyte away.
+    let samples = r#"{"hours": {"2026-01-01 00:00:00": 1000, "2026-01-01 01:00:00": 2000}}"#;
+    scratch.write("disk_usage.json", samples);
+    scratch.write("tracker.toml", &format!("{}\n[disk]\nbudget = \"1MiB\"\n", common::CONFIG));
+    let stdout = text(&scratch.run(&["status"]).stdout);
+    assert!(stdout.contains("Growing by 24.0 KB a day"), "{}", stdout);
+    assert!(stdout.contains("of the 1.0 MB budget, full in 4"), "{}", stdout);
+
+    scratch.write("tracker.toml", "[disk]\nbudget = \"lots\"\n");
+    let output = scratch.run(&["config", "check"]);
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(!output.status.success() && report.contains("disk.budget: invalid size 'lots'"), "{}", report);
+}
@@ -0,0 +1,30 @@
+// Runs the tracker with a source that panics on every fetch.
+
+mod common;
+
+use common::Scratch;
+
+#[test]
+fn panicking_sources_are_restarted_with_backoff() {
+    let scratch = Scratch::new("supervisor");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[mock.gold]
+panic_rate = 1.0
+
+[mock.silver]
+pattern = "sequence"
+prices = [25.0]
+"#;
+    scratch.write("tracker.toml", config);
+    let output = scratch.track(&[], "silver: $25.00", 3);
+    let panicked =
+        "Error fetching price for gold: Internal Error [E500]: the source of gold panicked: mock panic for gold";
+    assert!(output.contains(panicked), "{}", output);
+    assert!(output.contains("The source of gold will be restarted in 1s"), "{}", output);
+    assert!(output.contains("Restarting the source of gold after 1 panic(s)"), "{}", output);
+    assert!(output.contains("The source of gold will be restarted in 2s"), "{}", output);
+    assert!(!output.contains("gold: $"), "{}", output);
+}
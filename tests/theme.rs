@@ -0,0 +1,83 @@
+// Colors from [theme] on the console, in charts and in reports.
+
+mod common;
+
+use common::{text, Scratch};
+
+const TRACKER: &str = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [200.0]
+
+[[alerts]]
+name = "gold-high"
+asset = "gold"
+type = "threshold"
+above = 150.0
+"#;
+
+const HISTORY: &str = "timestamp,price,volume,market_cap\n";
+
+fn scratch(name: &str, theme: &str) -> Scratch {
+    let scratch = Scratch::new(name);
+    scratch.write("watchlist.toml", "assets = []\n");
+    scratch.write("tracker.toml", &format!("{}\n[theme]\n{}", TRACKER, theme));
+    scratch
+}
+
+#[test]
+fn the_console_is_colored_when_asked() {
+    let plain = scratch("theme-plain", "");
+    let output = plain.track(&[], "gold: $200.00", 1);
+    assert!(output.contains("] ALERT gold-high:"), "{:?}", output);
+    assert!(!output.contains('\x1b'), "{:?}", output);
+
+    let dark = scratch("theme-console", "base = \"dark\"\nconsole = \"always\"\n");
+    let output = dark.track(&[], "gold: $200.00", 1);
+    // The muted timestamp and the alert in the dark down color.
+    assert!(output.contains("[\x1b[38;2;139;148;158m"), "{:?}", output);
+    assert!(output.contains("\x1b[38;2;248;81;73mALERT\x1b[0m"), "{:?}", output);
+}
+
+#[test]
+fn reports_and_charts_follow_the_theme() {
+    let scratch = Scratch::new("theme-report");
+    scratch.write("watchlist.toml", "[[assets]]\nid = \"bitcoin\"\n");
+    scratch.write("tracker.toml", "[theme]\nbase = \"dark\"\nup = \"#00FF00\"\n");
+    let now = chrono::Local::now().naive_local();
+    let mut history = HISTORY.to_string();
+    for (minutes, price) in [(30, 100.0), (20, 105.0), (10, 110.0)] {
+        let timestamp = now - chrono::Duration::minutes(minutes);
+        history.push_str(&format!("{},{:.2},,\n", timestamp.format("%Y-%m-%d %H:%M:%S"), price));
+    }
+    scratch.write("bitcoin_prices.csv", &history);
+
+    let output = scratch.run(&["report"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let html = scratch.read("report.html");
+    assert!(html.contains("color: #e6edf3; background: #0d1117;"), "{}", html);
+    assert!(html.contains(".up { color: #00ff00; }"), "{}", html);
+    assert!(html.contains("<td class=\"up\">+10.00%</td>"), "{}", html);
+
+    let output = scratch.run(&["chart", "bitcoin", "-o", "chart.svg"]);
+    assert!(output.status.success(), "{}", text(&output.stderr));
+    let svg = scratch.read("chart.svg").to_lowercase();
+    assert!(svg.contains("#0d1117"), "{}", svg);
+    assert!(svg.contains("#58a6ff"), "{}", svg);
+}
+
+#[test]
+fn bad_colors_are_reported() {
+    let scratch = scratch("theme-check", "up = \"green\"\n");
+    let check = scratch.run(&["config", "check"]);
+    assert!(!check.status.success());
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("theme.up: 'green' is not a hex color like #1a7f37"), "{}", report);
+
+    scratch.write("tracker.toml", "[theme]\nbase = \"solarized\"\n");
+    let check = scratch.run(&["config", "check"]);
+    let report = text(&check.stdout) + &text(&check.stderr);
+    assert!(report.contains("theme.base: unknown theme 'solarized', expected light or dark"), "{}", report);
+}
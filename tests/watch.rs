@@ -0,0 +1,43 @@
+// The tracker's --watch table, redrawn in place of the scrolling lines.
+
+mod common;
+
+use common::Scratch;
+
+const CONFIG: &str = r#"
+[theme]
+console = "never"
+
+[mock.gold]
+pattern = "sequence"
+prices = [10.0, 20.0, 30.0]
+
+[mock.lead]
+failure_rate = 1.0
+"#;
+
+#[test]
+fn the_table_is_redrawn_every_round() {
+    let scratch = Scratch::with_config("watch", CONFIG);
+
+    let output = scratch.track(&["--watch"], "lead ", 2);
+    let screens: Vec<&str> = output.split("\x1b[H\x1b[2J").skip(1).collect();
+    assert!(screens.len() >= 2, "{}", output);
+    let second = screens[1];
+    let header = second.lines().next().unwrap_or_default();
+    assert!(header.starts_with("Tracking 2 assets every 1s, as of "), "{}", second);
+    let row = |name: &str| -> Vec<&str> {
+        second.lines().find(|line| line.starts_with(name)).unwrap_or_default().split_whitespace().collect()
+    };
+    assert_eq!(row("Asset"), ["Asset", "Price", "Change", "Updated", "Status"], "{}", second);
+    // Changed since the first round's price.
+    let gold = row("gold");
+    assert_eq!((gold[1], gold[2], gold[4]), ("$20.00", "+100.00%", "ok"), "{}", second);
+    let lead = row("lead");
+    assert_eq!(lead[1..4], ["-", "-", "-"], "{}", second);
+    assert!(lead.join(" ").ends_with("mock failure for lead"), "{}", second);
+
+    // Nothing scrolls by besides the table, though the prices are stored.
+    assert!(!output.contains("] gold: $") && !output.contains("Error fetching price"), "{}", output);
+    assert!(scratch.read("gold_prices.csv").contains(",10.00,"), "{}", scratch.read("gold_prices.csv"));
+}
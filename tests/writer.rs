@@ -0,0 +1,196 @@
+// Runs the tracker with prices appended from a writer thread while alerts
+// are logged over a second database connection.
+
+mod common;
+
+use common::{collect, stop, text, Scratch};
+
+#[test]
+fn prices_and_alerts_share_the_database() {
+    let scratch = Scratch::new("writer-sqlite");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[storage]
+backend = "sqlite"
+path = "prices.db"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0]
+
+[[alerts]]
+name = "gold-high"
+asset = "gold"
+type = "threshold"
+above = 150.0
+"#;
+    scratch.write("tracker.toml", config);
+    let output = scratch.track(&[], "gold: $200.00", 1);
+    assert!(output.contains("gold: $100.00") && !output.contains("Error"), "{}", output);
+
+    let alerts = scratch.run(&["alerts"]);
+    assert!(alerts.status.success(), "{}", text(&alerts.stderr));
+    assert!(text(&alerts.stdout).contains("gold-high (gold, value 200.00)"), "{}", text(&alerts.stdout));
+
+    let digest = scratch.run(&["digest", "--print"]);
+    let digest = text(&digest.stdout);
+    let gold = digest.lines().find(|line| line.starts_with("gold")).unwrap_or_default();
+    assert!(gold.contains("200.00"), "{}", digest);
+}
+
+#[test]
+fn spilled_records_are_written_after_a_restart() {
+    let scratch = Scratch::new("writer-spill");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[queues.csv]
+overflow = "spill"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+"#;
+    scratch.write("tracker.toml", config);
+    let spilled = r#"{"asset":"gold","name":"gold","precision":2,"timestamp":"2024-05-03 12:00:00","price":123.0}"#;
+    scratch.write("csv_queue.jsonl", &format!("{}\n", spilled));
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(output.contains("[2024-05-03 12:00:00] gold: $123.00"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    assert!(history.contains("2024-05-03 12:00:00,123.00"), "{}", history);
+    assert!(!scratch.dir.join("csv_queue.jsonl").exists());
+}
+
+#[test]
+fn spilled_records_survive_a_crash_while_drained() {
+    let scratch = Scratch::new("writer-spill-crash");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[queues.csv]
+overflow = "spill"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+"#;
+    scratch.write("tracker.toml", config);
+    let spilled = r#"{"asset":"gold","name":"gold","precision":2,"timestamp":"2024-05-03 12:00:00","price":123.0}"#;
+    scratch.write("csv_queue.jsonl", &format!("{}\n", spilled));
+    // Taken from the spill file but held, as the sink fails, when the tracker is killed.
+    std::fs::create_dir(scratch.dir.join("gold_prices.csv")).unwrap();
+    let output = scratch.track(&[], "Error saving prices to csv", 1);
+    assert!(!scratch.dir.join("csv_queue.jsonl").exists(), "{}", output);
+    assert!(scratch.read("csv_journal.jsonl").contains("123.0"), "{}", scratch.read("csv_journal.jsonl"));
+
+    std::fs::remove_dir(scratch.dir.join("gold_prices.csv")).unwrap();
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(output.contains("Wrote the journaled price of gold from 2024-05-03 12:00:00"), "{}", output);
+    assert!(scratch.read("gold_prices.csv").contains("2024-05-03 12:00:00,123.00"), "{}", output);
+}
+
+#[test]
+fn queue_files_are_kept_in_the_storage_directory() {
+    let scratch = Scratch::new("writer-directory");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[storage]
+backend = "csv"
+directory = "data"
+
+[queues.csv]
+overflow = "spill"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+"#;
+    scratch.write("tracker.toml", config);
+    std::fs::create_dir(scratch.dir.join("data")).unwrap();
+    let spilled = r#"{"asset":"gold","name":"gold","precision":2,"timestamp":"2024-05-03 12:00:00","price":123.0}"#;
+    scratch.write("data/csv_queue.jsonl", &format!("{}\n", spilled));
+    // Held, as the sink fails, so the journal still has it when the tracker is killed.
+    std::fs::create_dir(scratch.dir.join("data/gold_prices.csv")).unwrap();
+    let output = scratch.track(&[], "Error saving prices to csv", 1);
+    assert!(!scratch.dir.join("data/csv_queue.jsonl").exists(), "{}", output);
+    assert!(scratch.read("data/csv_journal.jsonl").contains("123.0"), "{}", output);
+    assert!(!scratch.dir.join("csv_journal.jsonl").exists(), "{}", output);
+}
+
+#[test]
+fn queues_must_name_the_sink() {
+    let scratch = Scratch::new("writer-invalid");
+    scratch.write("tracker.toml", "[queues.sqlite]\ncapacity = 0\n");
+    let output = scratch.run(&["config", "check"]);
+    assert!(!output.status.success());
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(report.contains("queues.sqlite: no such sink, prices are written to csv"), "{}", report);
+    assert!(report.contains("queues.sqlite.capacity: must be at least 1"), "{}", report);
+}
+
+#[test]
+fn journaled_records_are_written_after_a_crash() {
+    let scratch = Scratch::new("writer-journal");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0]
+"#;
+    scratch.write("tracker.toml", config);
+    let journal = [
+        r#"{"id":1,"asset":"gold","name":"gold","precision":2,"timestamp":"2024-05-03 12:00:00","price":121.0}"#,
+        r#"{"id":2,"asset":"gold","name":"gold","precision":2,"timestamp":"2024-05-03 12:01:00","price":122.0}"#,
+        r#"{"ack":1}"#,
+        r#"{"id":3,"asset":"gold","name":"go"#,
+    ];
+    scratch.write("csv_journal.jsonl", &(journal.join("\n") + "\n"));
+    let output = scratch.track(&[], "gold: $100.00", 1);
+    assert!(output.contains("Wrote the journaled price of gold from 2024-05-03 12:01:00"), "{}", output);
+    assert!(output.contains("Error reading ./csv_journal.jsonl"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    assert!(history.contains("2024-05-03 12:01:00,122.00") && !history.contains("121.00"), "{}", history);
+    // What is left, if anything, is the tracker's own record, acknowledged or not.
+    let journal = scratch.read("csv_journal.jsonl");
+    assert!(!journal.contains("122.0") && !journal.contains("\"id\":3"), "{}", journal);
+}
+
+#[test]
+fn records_are_held_while_the_sink_fails() {
+    let scratch = Scratch::new("writer-failing");
+    scratch.write("watchlist.toml", "assets = []\n");
+    let config = r#"
+interval = "1s"
+
+[mock.gold]
+pattern = "sequence"
+prices = [100.0, 200.0, 300.0]
+"#;
+    scratch.write("tracker.toml", config);
+    // Appending to a directory fails like a full disk would.
+    std::fs::create_dir(scratch.dir.join("gold_prices.csv")).unwrap();
+    let (mut child, lines) = scratch.start(&[]);
+    let (mut output, failing) = collect(&lines, "ALERT sink_failing", 1);
+    assert!(failing, "{}", output);
+    assert_eq!(output.matches("Error saving prices to csv").count(), 1, "{}", output);
+
+    std::fs::remove_dir(scratch.dir.join("gold_prices.csv")).unwrap();
+    let (rest, recovered) = collect(&lines, "ALERT sink_recovered", 1);
+    stop(&mut child);
+    output += &rest;
+    assert!(recovered, "{}", output);
+    assert!(output.contains("Prices are written to csv again after"), "{}", output);
+
+    let history = scratch.read("gold_prices.csv");
+    assert!(history.contains(",100.00") && history.contains(",200.00"), "{}", history);
+}
@@ -0,0 +1,54 @@
+// Tracks Yahoo Finance tickers configured under `[yahoo.<id>]` against the
+// --mock-server.
+
+mod common;
+
+use common::{text, Scratch};
+
+#[test]
+fn each_ticker_is_its_own_series() {
+    let config = r#"
+[yahoo.spy]
+symbol = "SPY"
+name = "SPDR S&P 500"
+
+[yahoo.nasdaq]
+symbol = "^IXIC"
+"#;
+    let scratch = Scratch::with_config("yahoo", config);
+    let output = scratch.track(&["--mock-server"], "nasdaq: $", 2);
+    assert!(output.contains("SPDR S&P 500: $510.75"), "{}", output);
+    assert!(output.contains("nasdaq: $16000.50"), "{}", output);
+
+    let spy = scratch.read("spy_prices.csv");
+    assert!(spy.contains("day_high,day_low,seq\n"), "{}", spy);
+    assert!(spy.contains(",510.75,,,515.8575,505.6425,1\n"), "{}", spy);
+    assert!(scratch.read("nasdaq_prices.csv").contains(",16000.50,"));
+}
+
+#[test]
+fn unknown_symbols_stop_the_tracker() {
+    let scratch = Scratch::with_config("yahoo-unknown", "[yahoo.typo]\nsymbol = \"SPYY\"\n");
+    let output = scratch.run(&["--mock-server"]);
+    assert!(!output.status.success());
+    let stderr = text(&output.stderr);
+    assert!(stderr.contains("typo: Yahoo Finance has no symbol 'SPYY'"), "{}", stderr);
+}
+
+#[test]
+fn tickers_outside_usd_are_refused() {
+    let scratch = Scratch::with_config("yahoo-currency", "[yahoo.vodafone]\nsymbol = \"VOD.L\"\n");
+    let output = scratch.run(&["--mock-server"]);
+    assert!(!output.status.success());
+    let stderr = text(&output.stderr);
+    assert!(stderr.contains("VOD.L is quoted in GBp"), "{}", stderr);
+}
+
+#[test]
+fn malformed_symbols_are_config_problems() {
+    let scratch = Scratch::with_config("yahoo-check", "[yahoo.bad]\nsymbol = \"S P Y\"\n");
+    let output = scratch.run(&["config", "check"]);
+    let report = text(&output.stdout) + &text(&output.stderr);
+    assert!(!output.status.success(), "{}", report);
+    assert!(report.contains("'S P Y' is not a Yahoo Finance symbol"), "{}", report);
+}
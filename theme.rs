@@ -0,0 +1,159 @@
+// Colors for the console, charts and HTML reports, set in [theme]:
+//
+//   [theme]
+//   base = "dark"          # or "light", the default
+//   up = "#3fb950"         # any of the colors below, as hex, over the base
+//   console = "auto"       # color the console on a terminal unless NO_COLOR
+//                          # is set; or "always" or "never"
+//
+// The light base keeps the colors reports and charts have always had.
+
+use std::io::IsTerminal;
+
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::PriceError;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub muted: Option<String>,
+    pub grid: Option<String>,
+    // The price line and other highlights.
+    pub accent: Option<String>,
+    pub up: Option<String>,
+    pub down: Option<String>,
+    pub console: ColorMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+
+    // "#1a7f37" or "1a7f37".
+    fn parse(value: &str) -> Option<Rgb> {
+        let hex = value.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Rgb,
+    pub text: Rgb,
+    pub muted: Rgb,
+    pub grid: Rgb,
+    pub accent: Rgb,
+    pub up: Rgb,
+    pub down: Rgb,
+    // Whether console output is colored.
+    console: bool,
+}
+
+const LIGHT: Theme = Theme {
+    background: Rgb(0xff, 0xff, 0xff),
+    text: Rgb(0x22, 0x22, 0x22),
+    muted: Rgb(0x88, 0x88, 0x88),
+    grid: Rgb(0xdd, 0xdd, 0xdd),
+    accent: Rgb(0x00, 0x00, 0xff),
+    up: Rgb(0x1a, 0x7f, 0x37),
+    down: Rgb(0xcf, 0x22, 0x2e),
+    console: false,
+};
+
+const DARK: Theme = Theme {
+    background: Rgb(0x0d, 0x11, 0x17),
+    text: Rgb(0xe6, 0xed, 0xf3),
+    muted: Rgb(0x8b, 0x94, 0x9e),
+    grid: Rgb(0x30, 0x36, 0x3d),
+    accent: Rgb(0x58, 0xa6, 0xff),
+    up: Rgb(0x3f, 0xb9, 0x50),
+    down: Rgb(0xf8, 0x51, 0x49),
+    console: false,
+};
+
+impl Default for Theme {
+    fn default() -> Self {
+        LIGHT
+    }
+}
+
+impl Theme {
+    pub fn from_config(config: &Config) -> Result<Self, PriceError> {
+        let Some(theme) = &config.theme else {
+            return Ok(Theme { console: ColorMode::Auto.enabled(), ..LIGHT });
+        };
+        let mut built = match theme.base.as_deref() {
+            None | Some("light") => LIGHT,
+            Some("dark") => DARK,
+            Some(other) => {
+                return Err(PriceError::ParseError(format!(
+                    "theme.base: unknown theme '{}', expected light or dark",
+                    other
+                )))
+            }
+        };
+        let colors = [
+            ("background", &theme.background, &mut built.background),
+            ("text", &theme.text, &mut built.text),
+            ("muted", &theme.muted, &mut built.muted),
+            ("grid", &theme.grid, &mut built.grid),
+            ("accent", &theme.accent, &mut built.accent),
+            ("up", &theme.up, &mut built.up),
+            ("down", &theme.down, &mut built.down),
+        ];
+        for (key, value, color) in colors {
+            if let Some(value) = value {
+                *color = Rgb::parse(value).ok_or_else(|| {
+                    PriceError::ParseError(format!("theme.{}: '{}' is not a hex color like #1a7f37", key, value))
+                })?;
+            }
+        }
+        built.console = theme.console.enabled();
+        Ok(built)
+    }
+
+    // `text` in `color` on a colored console, otherwise as it is.
+    pub fn paint(&self, color: Rgb, text: &str) -> String {
+        if !self.console {
+            return text.to_string();
+        }
+        format!("\x1b[38;2;{};{};{}m{}\x1b[0m", color.0, color.1, color.2, text)
+    }
+
+    // In the up or down color by the sign of `change`.
+    pub fn change(&self, change: f64, text: &str) -> String {
+        self.paint(if change < 0.0 { self.down } else { self.up }, text)
+    }
+}
+
+impl ColorMode {
+    fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// What a sink copying histories elsewhere has sent so far: the timestamp of
+// the last record sent per asset, kept in a JSON file next to the history so
+// restarts neither skip nor repeat records.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDateTime};
+
+use crate::storage::TIMESTAMP_FORMAT;
+use crate::PriceError;
+
+pub struct Uploads {
+    path: PathBuf,
+    last: BTreeMap<String, String>,
+}
+
+impl Uploads {
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        let last = match fs::read_to_string(path) {
+            Ok(body) => {
+                serde_json::from_str(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))?
+            }
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(Uploads { path: path.to_path_buf(), last })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last.is_empty()
+    }
+
+    // Where the records not sent yet start, for `Storage::scan`.
+    pub fn next_from(&self, asset: &str) -> Result<Option<NaiveDateTime>, PriceError> {
+        let Some(last) = self.last.get(asset) else {
+            return Ok(None);
+        };
+        let last = NaiveDateTime::parse_from_str(last, TIMESTAMP_FORMAT)
+            .map_err(|e| PriceError::ParseError(format!("{}: {}", self.path.display(), e)))?;
+        Ok(Some(last + Duration::seconds(1)))
+    }
+
+    // Notes that the records of `asset` up to `timestamp` were sent.
+    pub fn sent(&mut self, asset: &str, timestamp: NaiveDateTime) -> Result<(), PriceError> {
+        self.last.insert(asset.to_string(), timestamp.format(TIMESTAMP_FORMAT).to_string());
+        let file_error = |e: std::io::Error| PriceError::FileError(format!("{}: {}", self.path.display(), e));
+        let body = serde_json::to_string_pretty(&self.last).map_err(|e| PriceError::Internal(e.to_string()))?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, body).map_err(file_error)?;
+        fs::rename(&tmp_path, &self.path).map_err(file_error)
+    }
+}
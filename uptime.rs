@@ -0,0 +1,140 @@
+// How each provider has been doing: fetches, failures and latency per hour,
+// with the last success and failure, kept in STATS_FILE for `status`. Hours
+// older than RETENTION are dropped as the file is saved.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Duration as Period, Local, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::registry::Registry;
+use crate::storage::TIMESTAMP_FORMAT;
+use crate::PriceError;
+
+pub const STATS_FILE: &str = "provider_stats.json";
+
+const RETENTION: Period = Period::days(30);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub providers: BTreeMap<String, ProviderStats>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ProviderStats {
+    // Keyed by the hour's start, in TIMESTAMP_FORMAT so they sort in time.
+    pub hours: BTreeMap<String, Hour>,
+    pub last_success: Option<String>,
+    pub last_failure: Option<String>,
+    pub last_error: Option<String>,
+    // Whether the last fetch failed.
+    #[serde(default)]
+    pub failing: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Hour {
+    pub fetches: u64,
+    pub failures: u64,
+    // Summed over the fetches.
+    pub latency_ms: u64,
+}
+
+impl Stats {
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        match fs::read_to_string(path) {
+            Ok(body) => {
+                serde_json::from_str(&body).map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+            }
+            Err(_) => Ok(Stats::default()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let body = serde_json::to_string_pretty(self).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        fs::write(path, body).map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+
+    // The hours starting at or after `from`, summed per provider.
+    pub fn since(&self, from: NaiveDateTime) -> BTreeMap<&str, Hour> {
+        let from = hour_of(from);
+        self.providers
+            .iter()
+            .map(|(provider, stats)| {
+                let total = stats.hours.range(from.clone()..).fold(Hour::default(), |total, (_, hour)| Hour {
+                    fetches: total.fetches + hour.fetches,
+                    failures: total.failures + hour.failures,
+                    latency_ms: total.latency_ms + hour.latency_ms,
+                });
+                (provider.as_str(), total)
+            })
+            .collect()
+    }
+}
+
+// Counts the tracker's fetches, saving them after every round.
+pub struct Uptime {
+    path: PathBuf,
+    stats: Stats,
+    // Provider of each tracked asset.
+    providers: BTreeMap<String, String>,
+}
+
+impl Uptime {
+    pub fn new(config: &Config) -> Self {
+        let path = PathBuf::from(STATS_FILE);
+        let stats = Stats::load(&path).unwrap_or_else(|e| {
+            eprintln!("Error reading provider statistics: {}", e);
+            Stats::default()
+        });
+        let mut uptime = Uptime { path, stats, providers: BTreeMap::new() };
+        uptime.reload(config);
+        uptime
+    }
+
+    // Picks up the providers of assets added since.
+    pub fn reload(&mut self, config: &Config) {
+        if let Ok(registry) = Registry::load(config) {
+            self.providers =
+                registry.assets.iter().map(|info| (info.id.clone(), info.provider.to_string())).collect();
+        }
+    }
+
+    pub fn record(&mut self, asset: &str, latency: Duration, error: Option<&PriceError>) {
+        let provider = self.providers.get(asset).cloned().unwrap_or_else(|| "unknown".to_string());
+        let now = Local::now().naive_local();
+        let stats = self.stats.providers.entry(provider).or_default();
+        let hour = stats.hours.entry(hour_of(now)).or_default();
+        hour.fetches += 1;
+        hour.latency_ms += latency.as_millis() as u64;
+        let now = now.format(TIMESTAMP_FORMAT).to_string();
+        match error {
+            Some(e) => {
+                hour.failures += 1;
+                stats.last_failure = Some(now);
+                stats.last_error = Some(e.to_string());
+            }
+            None => stats.last_success = Some(now),
+        }
+        stats.failing = error.is_some();
+    }
+
+    pub fn save(&mut self) {
+        let oldest = hour_of(Local::now().naive_local() - RETENTION);
+        for stats in self.stats.providers.values_mut() {
+            stats.hours = stats.hours.split_off(&oldest);
+        }
+        if let Err(e) = self.stats.save(&self.path) {
+            eprintln!("Error saving provider statistics: {}", e);
+        }
+    }
+}
+
+pub fn hour_of(at: NaiveDateTime) -> String {
+    let start = at.with_minute(0).and_then(|at| at.with_second(0)).unwrap_or(at);
+    start.format(TIMESTAMP_FORMAT).to_string()
+}
@@ -0,0 +1,139 @@
+// The --watch console: instead of a line per price, the tracker clears the
+// screen after each round's fetches and redraws one row per asset, so a
+// terminal or tmux pane left open shows where everything stands. Whatever
+// else the round prints, such as alerts, stays below the table until the
+// next redraw.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::config::Config;
+use crate::display::NumberFormat;
+use crate::storage::PriceRecord;
+use crate::theme::Theme;
+use crate::{PriceError, Pricing};
+
+// Moves the cursor home and clears the screen.
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+// Longer errors are cut, to keep rows on one line.
+const MAX_STATUS: usize = 60;
+
+#[derive(Default)]
+struct Row {
+    // As first fetched, for the change since the tracker started.
+    first: Option<f64>,
+    price: Option<f64>,
+    updated: Option<NaiveDateTime>,
+    // The last fetch's error, until one succeeds.
+    error: Option<String>,
+    unchanged: bool,
+}
+
+pub struct WatchScreen {
+    format: NumberFormat,
+    theme: Theme,
+    interval: String,
+    started: NaiveDateTime,
+    rows: HashMap<String, Row>,
+}
+
+impl WatchScreen {
+    pub fn from_config(config: &Config) -> Result<Self, PriceError> {
+        Ok(WatchScreen {
+            format: NumberFormat::from_config(config)?,
+            theme: Theme::from_config(config)?,
+            interval: config.interval.clone().unwrap_or_else(|| "10s".to_string()),
+            started: Local::now().naive_local(),
+            rows: HashMap::new(),
+        })
+    }
+
+    pub fn fetched(&mut self, asset: &dyn Pricing, record: &PriceRecord) {
+        let row = self.rows.entry(asset.id().to_string()).or_default();
+        row.first.get_or_insert(record.price);
+        row.price = Some(record.price);
+        row.updated = Some(record.timestamp);
+        row.error = None;
+        row.unchanged = false;
+    }
+
+    pub fn unchanged(&mut self, asset: &dyn Pricing, now: NaiveDateTime) {
+        let row = self.rows.entry(asset.id().to_string()).or_default();
+        row.updated = Some(now);
+        row.error = None;
+        row.unchanged = true;
+    }
+
+    pub fn failed(&mut self, asset: &dyn Pricing, error: &PriceError) {
+        let message = error.to_string();
+        let row = self.rows.entry(asset.id().to_string()).or_default();
+        row.error = Some(match message.chars().count() > MAX_STATUS {
+            true => format!("{}...", message.chars().take(MAX_STATUS - 3).collect::<String>()),
+            false => message,
+        });
+    }
+
+    // Assets not fetched yet are listed with dashes.
+    pub fn draw(&self, assets: &[Box<dyn Pricing>]) {
+        let now = Local::now().naive_local();
+        let mut lines = vec![["Asset", "Price", "Change", "Updated", "Status"].map(String::from)];
+        // The change and whether the fetch failed, to color the row by.
+        let mut marks = vec![(None, false)];
+        for asset in assets {
+            let row = self.rows.get(asset.id());
+            let price = row.and_then(|row| row.price);
+            let change = row.and_then(|row| Some((price? / row.first? - 1.0) * 100.0));
+            let status = match row {
+                Some(Row { error: Some(error), .. }) => error.clone(),
+                Some(Row { unchanged: true, .. }) => "unchanged".to_string(),
+                Some(Row { updated: Some(_), .. }) => "ok".to_string(),
+                _ => "waiting".to_string(),
+            };
+            lines.push([
+                asset.name().to_string(),
+                price.map_or("-".to_string(), |price| self.format.money(price, asset.precision(), "usd")),
+                change.map_or("-".to_string(), |change| format!("{}%", self.format.signed(change, 2))),
+                row.and_then(|row| row.updated).map_or("-".to_string(), |at| at.format("%H:%M:%S").to_string()),
+                status,
+            ]);
+            marks.push((change, row.is_some_and(|row| row.error.is_some())));
+        }
+        let widths: Vec<usize> =
+            (0..5).map(|i| lines.iter().map(|line| line[i].chars().count()).max().unwrap_or(0)).collect();
+
+        let mut screen = String::from(CLEAR);
+        screen.push_str(&format!(
+            "Tracking {} asset{} every {}, as of {} (changes since {})\n\n",
+            assets.len(),
+            if assets.len() == 1 { "" } else { "s" },
+            self.interval,
+            now.format("%H:%M:%S"),
+            self.started.format("%H:%M:%S")
+        ));
+        for (line, (change, failed)) in lines.iter().zip(marks) {
+            // Padded before painting, as the color codes take no room.
+            let mut cells: Vec<String> = line
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| match i {
+                    1 | 2 => format!("{:>width$}", cell, width = widths[i]),
+                    4 => cell.clone(),
+                    _ => format!("{:<width$}", cell, width = widths[i]),
+                })
+                .collect();
+            if let Some(change) = change {
+                cells[2] = self.theme.change(change, &cells[2]);
+            }
+            if failed {
+                cells[4] = self.theme.paint(self.theme.down, &cells[4]);
+            }
+            screen.push_str(&cells.join("  "));
+            screen.push('\n');
+        }
+        print!("{}", screen);
+        let _ = io::stdout().flush();
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{parse_duration, Config};
+use crate::http::Client;
+use crate::PriceError;
+
+pub const DEFAULT_WATCHLIST_FILE: &str = "watchlist.toml";
+
+// Assets with their own source; anything else is looked up on CoinGecko.
+pub const BUILT_IN: [&str; 3] = ["bitcoin", "ethereum", "sp500"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedAsset {
+    pub id: String,
+    // Only stored for CoinGecko coins; built-in assets know their own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+}
+
+impl WatchedAsset {
+    pub fn bare(id: &str) -> Self {
+        WatchedAsset { id: id.to_string(), name: None, symbol: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watchlist {
+    #[serde(default)]
+    pub assets: Vec<WatchedAsset>,
+}
+
+impl Default for Watchlist {
+    fn default() -> Self {
+        Watchlist { assets: BUILT_IN.iter().map(|id| WatchedAsset::bare(id)).collect() }
+    }
+}
+
+impl Watchlist {
+    // Without a watchlist file the built-in assets are tracked.
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        Self::read(path).map(Option::unwrap_or_default)
+    }
+
+    fn read(path: &Path) -> Result<Option<Self>, PriceError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map(Some)
+            .map_err(|e| PriceError::ParseError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PriceError> {
+        let body = toml::to_string(self).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        self.write(path, &format!("# Managed by `crypto_price_tracker asset add/remove`.\n\n{}", body))
+    }
+
+    // Written to a temporary file first so a running tracker never reads a
+    // half-written list.
+    fn write(&self, path: &Path, contents: &str) -> Result<(), PriceError> {
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, contents)
+            .and_then(|_| fs::rename(&temp, path))
+            .map_err(|e| PriceError::FileError(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.assets.iter().any(|asset| asset.id == id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.assets.len();
+        self.assets.retain(|asset| asset.id != id);
+        self.assets.len() != before
+    }
+}
+
+// Used by the tracker to notice edits made while it is running.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TopConfig {
+    // Track this many of the largest coins by market cap besides the watchlist.
+    pub count: usize,
+    #[serde(default = "default_refresh")]
+    pub refresh: String,
+    // Last fetched ranking, so commands other than the tracker see the same coins.
+    #[serde(default = "default_cache")]
+    pub cache: PathBuf,
+}
+
+fn default_refresh() -> String {
+    "6h".to_string()
+}
+
+fn default_cache() -> PathBuf {
+    PathBuf::from("top_coins.toml")
+}
+
+// CoinGecko serves at most this many coins per page.
+const MAX_TOP: usize = 250;
+
+#[derive(Deserialize)]
+struct MarketEntry {
+    id: String,
+    symbol: String,
+    name: String,
+}
+
+// The coins from the last ranking; empty until the tracker has fetched one.
+pub fn top_coins(config: &TopConfig) -> Result<Vec<WatchedAsset>, PriceError> {
+    Watchlist::read(&config.cache).map(|ranking| ranking.map(|ranking| ranking.assets).unwrap_or_default())
+}
+
+pub struct TopCoins {
+    config: TopConfig,
+    refresh: Duration,
+    next: Option<NaiveDateTime>,
+    client: Client,
+}
+
+impl TopCoins {
+    pub fn from_config(config: &Config, client: Client) -> Result<Option<Self>, PriceError> {
+        let Some(top) = config.top.clone() else {
+            return Ok(None);
+        };
+        if top.count == 0 || top.count > MAX_TOP {
+            return Err(PriceError::ParseError(format!("top.count: must be between 1 and {}", MAX_TOP)));
+        }
+        Ok(Some(TopCoins { refresh: parse_duration(&top.refresh)?, config: top, next: None, client }))
+    }
+
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        self.next.is_none_or(|next| now >= next)
+    }
+
+    // Fetches the current ranking and, unless `save` is false, stores it in
+    // the cache, returning the ids that entered and left it. A failed fetch
+    // is retried after a minute.
+    pub fn refresh(&mut self, now: NaiveDateTime, save: bool) -> Result<(Vec<String>, Vec<String>), PriceError> {
+        self.next = Some(now + Duration::minutes(1));
+        let count = self.config.count.to_string();
+        let body = self
+            .client
+            .get(
+                "https://api.coingecko.com/api/v3/coins/markets",
+                &[("vs_currency", "usd"), ("order", "market_cap_desc"), ("per_page", &count), ("page", "1")],
+            )
+            .map_err(|e| PriceError::NetworkError(format!("top coins: {}", e)))?;
+        let entries: Vec<MarketEntry> = serde_json::from_str(&body)
+            .map_err(|e| PriceError::ParseError(format!("top coins: {}", e)))?;
+        self.next = Some(now.checked_add_signed(self.refresh).unwrap_or(NaiveDateTime::MAX));
+
+        let previous = top_coins(&self.config)?;
+        let ranking = Watchlist {
+            assets: entries
+                .into_iter()
+                .map(|entry| WatchedAsset {
+                    id: entry.id,
+                    name: Some(entry.name),
+                    symbol: Some(entry.symbol.to_lowercase()),
+                })
+                .collect(),
+        };
+        let entered = ranking.assets.iter().filter(|a| !previous.iter().any(|p| p.id == a.id)).map(|a| a.id.clone());
+        let left = previous.iter().filter(|p| !ranking.contains(&p.id)).map(|p| p.id.clone());
+        let changes = (entered.collect(), left.collect());
+        if !save {
+            return Ok(changes);
+        }
+
+        let body = toml::to_string(&ranking).map_err(|e| PriceError::ParseError(e.to_string()))?;
+        let header = format!("# Top {} coins by market cap, refreshed by the tracker.", self.config.count);
+        ranking.write(&self.config.cache, &format!("{}\n\n{}", header, body))?;
+        Ok(changes)
+    }
+}
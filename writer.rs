@@ -0,0 +1,491 @@
+// Appends fetched records to a sink from a thread of its own, so a slow
+// disk or database holds up its queue rather than the next round of
+// fetching. What happens once a queue is full is set per sink:
+//
+//   [queues.sqlite]
+//   capacity = 1000
+//   overflow = "spill"   # or "block" (the default), "drop-oldest"
+//
+// Spilled records go to a file, `<sink>_queue.jsonl` in the storage
+// directory unless `spill_file` is set, and are written from it once the queue has drained, after a restart
+// too. Queued records are journaled, see journal.rs, unless `journal` is
+// false.
+//
+// While the sink fails, a full disk say, records are held in memory, up to
+// `hold` of them with the oldest making room, and written once it accepts
+// them again.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{self, Journal};
+use crate::storage::{PriceRecord, Storage, TIMESTAMP_FORMAT};
+use crate::display::NumberFormat;
+use crate::theme::Theme;
+use crate::{format_quotes, PriceError, Pricing};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueConfig {
+    #[serde(default = "default_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub overflow: Overflow,
+    pub spill_file: Option<PathBuf>,
+    #[serde(default = "default_journal")]
+    pub journal: bool,
+    pub journal_file: Option<PathBuf>,
+    #[serde(default = "default_hold")]
+    pub hold: usize,
+}
+
+// A few rounds of a long watchlist.
+fn default_capacity() -> usize {
+    1024
+}
+
+fn default_journal() -> bool {
+    true
+}
+
+// A day of a short watchlist every minute.
+fn default_hold() -> usize {
+    10_000
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig {
+            capacity: default_capacity(),
+            overflow: Overflow::default(),
+            spill_file: None,
+            journal: default_journal(),
+            journal_file: None,
+            hold: default_hold(),
+        }
+    }
+}
+
+impl QueueConfig {
+    pub fn spill_path(&self, sink: &str, directory: &Path) -> PathBuf {
+        self.spill_file.clone().unwrap_or_else(|| directory.join(format!("{}_queue.jsonl", sink)))
+    }
+
+    pub fn journal_path(&self, sink: &str, directory: &Path) -> PathBuf {
+        self.journal_file.clone().unwrap_or_else(|| directory.join(format!("{}_journal.jsonl", sink)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    // The round waits until the sink catches up.
+    #[default]
+    Block,
+    // The oldest queued record makes room and is lost.
+    DropOldest,
+    // Records go to the spill file until the queue has drained.
+    Spill,
+}
+
+pub struct Write {
+    pub asset: String,
+    pub name: String,
+    pub precision: usize,
+    pub record: PriceRecord,
+    // Its number in the journal, once journaled.
+    entry: Option<u64>,
+}
+
+impl Write {
+    pub fn new(asset: &dyn Pricing, record: PriceRecord) -> Self {
+        let (id, name, precision) = (asset.id().to_string(), asset.name().to_string(), asset.precision());
+        Write { asset: id, name, precision, record, entry: None }
+    }
+}
+
+// A queued write as one line of the spill file or the journal.
+#[derive(Serialize, Deserialize)]
+pub struct Spilled {
+    asset: String,
+    name: String,
+    precision: usize,
+    timestamp: String,
+    price: f64,
+    volume: Option<f64>,
+    market_cap: Option<f64>,
+    #[serde(default)]
+    columns: BTreeMap<String, f64>,
+}
+
+impl Spilled {
+    pub fn from_write(write: &Write) -> Self {
+        let record = &write.record;
+        Spilled {
+            asset: write.asset.clone(),
+            name: write.name.clone(),
+            precision: write.precision,
+            timestamp: record.timestamp.format(TIMESTAMP_FORMAT).to_string(),
+            price: record.price,
+            volume: record.volume,
+            market_cap: record.market_cap,
+            columns: record.extra_columns().into_iter().collect(),
+        }
+    }
+
+    pub fn into_write(self) -> Result<Write, String> {
+        let timestamp = NaiveDateTime::parse_from_str(&self.timestamp, TIMESTAMP_FORMAT)
+            .map_err(|e| format!("invalid timestamp '{}': {}", self.timestamp, e))?;
+        let mut record = PriceRecord::new(timestamp, self.price);
+        record.volume = self.volume;
+        record.market_cap = self.market_cap;
+        for (column, value) in &self.columns {
+            record.set_extra_column(column, *value);
+        }
+        Ok(Write { asset: self.asset, name: self.name, precision: self.precision, record, entry: None })
+    }
+}
+
+// A change in whether the sink takes records, as reported once per round.
+pub enum Health {
+    Failing { error: String, since: NaiveDateTime },
+    Recovered { down: Duration, written: usize },
+}
+
+struct Queue {
+    writes: VecDeque<Write>,
+    // Lines in the spill file not yet written to the sink.
+    spilled: usize,
+    closed: bool,
+    // Totals since the start, and as of the last report.
+    dropped: u64,
+    spilled_total: u64,
+    lost: u64,
+    reported: (u64, u64, u64),
+    // The error and when the sink started failing, while it fails.
+    failing: Option<(String, NaiveDateTime)>,
+    // Set once the sink takes records again: since when it failed and how
+    // many held records were written.
+    recovered: Option<(NaiveDateTime, usize)>,
+    failing_reported: bool,
+}
+
+struct Shared {
+    name: String,
+    queue: Mutex<Queue>,
+    changed: Condvar,
+    config: QueueConfig,
+    spill_path: PathBuf,
+    journal: Option<Mutex<Journal>>,
+    // For the console line of each record written; None under --watch,
+    // whose table shows them instead.
+    console: Option<(NumberFormat, Theme)>,
+}
+
+impl Shared {
+    fn spill(&self, queue: &mut Queue, write: &Write) -> Result<(), String> {
+        let line = serde_json::to_string(&Spilled::from_write(write)).map_err(|e| e.to_string())?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.spill_path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| format!("{}: {}", self.spill_path.display(), e))?;
+        queue.spilled += 1;
+        queue.spilled_total += 1;
+        Ok(())
+    }
+
+    // Done with `write` as far as the journal is concerned.
+    fn acknowledge(&self, write: &Write) {
+        if let Some((journal, entry)) = self.journal.as_ref().zip(write.entry) {
+            journal.lock().unwrap().acknowledge(entry);
+        }
+    }
+
+    // Writes the held records in order, stopping at the first the sink
+    // refuses so it and the rest wait for the next attempt.
+    fn flush(&self, storage: &mut dyn Storage, held: &mut VecDeque<Write>) {
+        let mut written = 0;
+        while let Some(write) = held.front() {
+            if let Err(e) = append(storage, write, self.console.as_ref()) {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.failing.is_none() {
+                    eprintln!(
+                        "Error saving prices to {}: {}; holding new records until it can be written",
+                        self.name, e
+                    );
+                    queue.failing = Some((e.to_string(), Local::now().naive_local()));
+                }
+                return;
+            }
+            self.acknowledge(write);
+            held.pop_front();
+            written += 1;
+        }
+        let mut queue = self.queue.lock().unwrap();
+        if let Some((_, since)) = queue.failing.take() {
+            println!("{} takes records again, wrote the {} held", self.name, written);
+            queue.recovered = Some((since, written));
+        }
+    }
+
+    // Makes room for a new record while the sink fails.
+    fn hold(&self, held: &mut VecDeque<Write>) {
+        while held.len() > self.config.hold {
+            if let Some(dropped) = held.pop_front() {
+                self.acknowledge(&dropped);
+            }
+            self.queue.lock().unwrap().lost += 1;
+        }
+    }
+
+    // Empties the spill file; the caller holds the queue's lock. Its records
+    // are journaled again first, as they were acknowledged once spilled, so
+    // a crash before the sink has them leaves them in one or the other.
+    fn take_spilled(&self, queue: &mut Queue) -> Vec<Write> {
+        let body = fs::read_to_string(&self.spill_path).unwrap_or_default();
+        let mut writes: Vec<Write> = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                let spilled = serde_json::from_str::<Spilled>(line).map_err(|e| e.to_string());
+                let write = spilled.and_then(Spilled::into_write);
+                write.inspect_err(|e| eprintln!("Error reading {}: {}", self.spill_path.display(), e)).ok()
+            })
+            .collect();
+        if let Some(journal) = &self.journal {
+            let mut journal = journal.lock().unwrap();
+            for write in &mut writes {
+                write.entry = journal.record(write);
+            }
+        }
+        if let Err(e) = fs::remove_file(&self.spill_path) {
+            eprintln!("Error clearing {}: {}", self.spill_path.display(), e);
+        }
+        queue.spilled = 0;
+        writes
+    }
+
+    // The next writes for the sink, or None once closed and drained. Spilled
+    // records are newer than the queued ones, and older than any queued
+    // after them, so they are taken once the queue has drained.
+    fn next(&self) -> Option<Vec<Write>> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(write) = queue.writes.pop_front() {
+                self.changed.notify_all();
+                return Some(vec![write]);
+            }
+            if queue.spilled > 0 {
+                return Some(self.take_spilled(&mut queue));
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = self.changed.wait(queue).unwrap();
+        }
+    }
+}
+
+pub struct Writer {
+    name: String,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Writer {
+    pub fn start(
+        name: &str,
+        mut storage: Box<dyn Storage>,
+        config: &QueueConfig,
+        directory: &Path,
+        console: Option<(NumberFormat, Theme)>,
+    ) -> Result<Self, PriceError> {
+        let spill_path = config.spill_path(name, directory);
+        // Left over from a run that stopped before writing them.
+        let spilled = spill_lines(&spill_path);
+        let queue = Queue {
+            writes: VecDeque::new(),
+            spilled,
+            closed: false,
+            dropped: 0,
+            spilled_total: 0,
+            lost: 0,
+            reported: (0, 0, 0),
+            failing: None,
+            recovered: None,
+            failing_reported: false,
+        };
+        // Written here, before the tracker reads the last stored records.
+        let journal = config.journal.then(|| {
+            let path = config.journal_path(name, directory);
+            let (mut journal, unwritten) = Journal::open(&path);
+            let failed = journal::replay(storage.as_mut(), unwritten);
+            if path.exists() {
+                journal.clear();
+            }
+            (journal, failed)
+        });
+        let (journal, failed) = journal.unzip();
+        let config = config.clone();
+        let journal = journal.map(Mutex::new);
+        let queue = Mutex::new(queue);
+        let changed = Condvar::new();
+        let shared = Arc::new(Shared {
+            name: name.to_string(),
+            queue,
+            changed,
+            config,
+            spill_path,
+            journal,
+            console,
+        });
+
+        let worker = shared.clone();
+        let thread = thread::Builder::new()
+            .name(format!("writer-{}", name))
+            .spawn(move || {
+                let mut held = VecDeque::new();
+                while let Some(writes) = worker.next() {
+                    held.extend(writes);
+                    worker.hold(&mut held);
+                    worker.flush(storage.as_mut(), &mut held);
+                }
+                // Still journaled, unless that failed too.
+                if !held.is_empty() {
+                    eprintln!("Error saving prices to {}: {} records were never written", worker.name, held.len());
+                }
+            })
+            .map_err(|e| PriceError::FileError(format!("starting the {} writer: {}", name, e)))?;
+        let writer = Writer { name: name.to_string(), shared, thread: Some(thread) };
+        // Journaled again, to be tried once more.
+        for write in failed.into_iter().flatten() {
+            writer.send(write);
+        }
+        Ok(writer)
+    }
+
+    pub fn send(&self, mut write: Write) {
+        if self.thread.as_ref().is_none_or(|thread| thread.is_finished()) {
+            eprintln!("Error saving price for {}: the {} writer has stopped", write.name, self.name);
+            return;
+        }
+        let shared = &self.shared;
+        if let Some(journal) = &shared.journal {
+            write.entry = journal.lock().unwrap().record(&write);
+        }
+        let mut queue = shared.queue.lock().unwrap();
+        // Once spilling, newer records follow the spilled ones.
+        let full = queue.writes.len() >= shared.config.capacity;
+        match shared.config.overflow {
+            Overflow::Spill if full || queue.spilled > 0 => {
+                // The spill file keeps it from then on.
+                match shared.spill(&mut queue, &write) {
+                    Ok(()) => shared.acknowledge(&write),
+                    Err(e) => eprintln!("Error spilling price for {}: {}", write.name, e),
+                }
+                shared.changed.notify_all();
+                return;
+            }
+            Overflow::DropOldest if full => {
+                if let Some(dropped) = queue.writes.pop_front() {
+                    shared.acknowledge(&dropped);
+                }
+                queue.dropped += 1;
+            }
+            Overflow::Block => {
+                while queue.writes.len() >= shared.config.capacity {
+                    queue = shared.changed.wait(queue).unwrap();
+                }
+            }
+            _ => {}
+        }
+        queue.writes.push_back(write);
+        shared.changed.notify_all();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Warns about records dropped or spilled since the last call, and
+    // returns whether the sink has started or stopped failing since.
+    pub fn report(&self) -> Option<Health> {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let (dropped, spilled, lost) = queue.reported;
+        if queue.dropped > dropped {
+            eprintln!(
+                "Warning: the {} queue is full, dropped {} of the oldest records ({} so far)",
+                self.name,
+                queue.dropped - dropped,
+                queue.dropped
+            );
+        }
+        if queue.spilled_total > spilled {
+            eprintln!(
+                "Warning: the {} queue is full, spilled {} records to {} ({} so far)",
+                self.name,
+                queue.spilled_total - spilled,
+                self.shared.spill_path.display(),
+                queue.spilled_total
+            );
+        }
+        if queue.lost > lost {
+            eprintln!(
+                "Warning: {} is still failing, dropped {} of the oldest held records ({} so far)",
+                self.name,
+                queue.lost - lost,
+                queue.lost
+            );
+        }
+        queue.reported = (queue.dropped, queue.spilled_total, queue.lost);
+
+        let recovered = queue.recovered.take();
+        if let Some((error, since)) = queue.failing.clone().filter(|_| !queue.failing_reported) {
+            queue.failing_reported = true;
+            return Some(Health::Failing { error, since });
+        }
+        let (since, written) = recovered.filter(|_| queue.failing_reported)?;
+        queue.failing_reported = false;
+        Some(Health::Recovered { down: Local::now().naive_local() - since, written })
+    }
+}
+
+fn append(storage: &mut dyn Storage, write: &Write, console: Option<&(NumberFormat, Theme)>) -> Result<(), PriceError> {
+    let record = &write.record;
+    storage.append(&write.asset, record)?;
+    let Some((format, theme)) = console else {
+        return Ok(());
+    };
+    println!(
+        "[{}] {}: {}{}",
+        theme.paint(theme.muted, &record.timestamp.format(TIMESTAMP_FORMAT).to_string()),
+        write.name,
+        format.money(record.price, write.precision, "usd"),
+        format_quotes(record, format, theme)
+    );
+    Ok(())
+}
+
+fn spill_lines(path: &Path) -> usize {
+    fs::read_to_string(path).map_or(0, |body| body.lines().filter(|line| !line.trim().is_empty()).count())
+}
+
+// Whatever is still queued is written before the writer goes away.
+impl Drop for Writer {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}